@@ -1,21 +1,59 @@
+use crate::chaos;
 use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
 use crate::config::Config;
+use crate::exit;
 use crate::git::StagedChanges;
+use crate::http;
+use crate::logging;
+use crate::server::{MAX_ATTEMPTS, retry_delay};
+use crate::template::Template;
+use regex::Regex;
 use reqwest::header::HeaderValue;
+use std::time::Instant;
 
-const SYSTEM_PROMPT: &str = r#"You are an AI assistant that helps developers write clear and meaningful git commit messages.
-Follow these rules:
-1. Use the conventional commit format: <type>(<scope>): <description>
-2. Keep the subject line under 72 characters
-3. Use the imperative mood ("add" not "added")
-4. Don't end the subject line with a period
-5. Focus on WHY and WHAT, not HOW
-6. If there are breaking changes, add BREAKING CHANGE: in the body
+/// Anthropic models that can be selected with `ai.model` or `--model`
+pub const ANTHROPIC_MODELS: &[&str] = &[
+    "claude-3-5-haiku-20241022",
+    "claude-3-5-haiku-latest",
+    "claude-haiku-4-5-20251001",
+    "claude-haiku-4-5",
+    "claude-3-5-sonnet-20241022",
+    "claude-3-5-sonnet-latest",
+    "claude-3-7-sonnet-20250219",
+    "claude-3-7-sonnet-latest",
+    "claude-sonnet-4-20250514",
+    "claude-sonnet-4-0",
+    "claude-sonnet-4-5-20250929",
+    "claude-sonnet-4-5",
+    "claude-opus-4-20250514",
+    "claude-opus-4-0",
+    "claude-opus-4-1-20250805",
+    "claude-opus-4-1",
+    "claude-3-haiku-20240307",
+    "claude-3-opus-20240229",
+];
 
-Types: feat, fix, docs, style, refactor, perf, test, chore, ci, build
+/// The models known for `provider`, or `None` for a provider gyst has no list for
+pub fn known_models(provider: &str) -> Option<&'static [&'static str]> {
+    match provider {
+        "anthropic" => Some(ANTHROPIC_MODELS),
+        _ => None,
+    }
+}
 
-Return ONLY the commit message, without any prefixes or explanations."#;
+/// Check that `model` is a model `provider` offers
+pub fn check_model(provider: &str, model: &str) -> Result<()> {
+    match known_models(provider) {
+        Some(models) if !models.contains(&model) => Err(anyhow!(
+            "Unknown {} model '{}'. Known models: {}",
+            provider,
+            model,
+            models.join(", ")
+        )),
+        _ => Ok(()),
+    }
+}
 
 #[derive(Debug, Serialize, Clone)]
 struct AnthropicRequest {
@@ -42,6 +80,14 @@ struct AnthropicContent {
 #[derive(Debug, Deserialize)]
 struct AnthropicResponse {
     content: Vec<AnthropicResponseContent>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u32,
+    output_tokens: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,133 +97,329 @@ struct AnthropicResponseContent {
     text: String,
 }
 
+/// Default temperature for a single commit message, which should be predictable
+const COMMIT_TEMPERATURE: f32 = 0.4;
+
+/// Default temperature for suggestions, which should differ from each other
+const SUGGEST_TEMPERATURE: f32 = 0.9;
+
 pub struct CommitMessageGenerator {
     config: Config,
     client: reqwest::Client,
+    /// The subcommand whose `[ai.commands.<name>]` settings apply
+    command: Option<&'static str>,
 }
 
 impl CommitMessageGenerator {
     pub fn new(config: Config) -> Self {
         Self {
+            client: http::builder_or_warn(&config).build().unwrap_or_default(),
             config,
-            client: reqwest::Client::new(),
+            command: None,
         }
     }
 
-    pub async fn generate_message(&self, changes: &StagedChanges, diff: &str) -> Result<String> {
-        let suggestions = self.generate_suggestions(changes, diff, 1).await?;
+    /// Use the reply length and temperature configured for `command`
+    pub fn for_command(mut self, command: &'static str) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    pub async fn generate_message(
+        &self,
+        changes: &StagedChanges,
+        diff: &str,
+        template: &Template,
+        hints: &[String],
+    ) -> Result<String> {
+        let suggestions = self
+            .generate_suggestions(changes, diff, template, hints, 1)
+            .await?;
         Ok(suggestions.into_iter().next().unwrap())
     }
 
-    fn clean_commit_message(message: &str) -> String {
-        // Remove any prefixes like "Based on the changes..."
-        if let Some(feat_idx) = message.find("feat") {
-            message[feat_idx..].trim().to_string()
-        } else if let Some(fix_idx) = message.find("fix") {
-            message[fix_idx..].trim().to_string()
-        } else if let Some(docs_idx) = message.find("docs") {
-            message[docs_idx..].trim().to_string()
-        } else if let Some(style_idx) = message.find("style") {
-            message[style_idx..].trim().to_string()
-        } else if let Some(refactor_idx) = message.find("refactor") {
-            message[refactor_idx..].trim().to_string()
-        } else if let Some(perf_idx) = message.find("perf") {
-            message[perf_idx..].trim().to_string()
-        } else if let Some(test_idx) = message.find("test") {
-            message[test_idx..].trim().to_string()
-        } else if let Some(chore_idx) = message.find("chore") {
-            message[chore_idx..].trim().to_string()
-        } else if let Some(ci_idx) = message.find("ci") {
-            message[ci_idx..].trim().to_string()
-        } else if let Some(build_idx) = message.find("build") {
-            message[build_idx..].trim().to_string()
+    pub async fn generate_suggestions(
+        &self,
+        changes: &StagedChanges,
+        diff: &str,
+        template: &Template,
+        hints: &[String],
+        count: u8,
+    ) -> Result<Vec<String>> {
+        let mut prompt = String::new();
+        prompt.push_str("Here are the changes to commit:\n\n");
+
+        // Add file changes summary; large changes are described by directory
+        if changes.is_large() {
+            prompt.push_str(&format!(
+                "{} files changed, too many to list. Changes by directory:\n",
+                changes.stats.files_changed
+            ));
+            prompt.push_str(&changes.directory_summary());
         } else {
-            message.trim().to_string()
+            prompt.push_str(&file_list(changes));
         }
-    }
 
-    pub async fn generate_suggestions(&self, changes: &StagedChanges, diff: &str, count: u8) -> Result<Vec<String>> {
-        let api_key = self.config.get_api_key()
-            .ok_or_else(|| anyhow!("API key not set. Use 'gyst config --api-key <key>' to set it."))?;
+        // Add the diff
+        prompt.push_str("\nHere's the detailed diff:\n");
+        prompt.push_str(diff);
 
-        let mut prompt = String::new();
-        prompt.push_str("Here are the changes to commit:\n\n");
-        
-        // Add file changes summary
-        if !changes.added.is_empty() {
-            prompt.push_str("Added files:\n");
-            for file in &changes.added {
-                prompt.push_str(&format!("  + {}\n", file));
+        // Add project-specific instructions (e.g. ticket references)
+        if !hints.is_empty() {
+            prompt.push_str("\nAdditional instructions:\n");
+            for hint in hints {
+                prompt.push_str(&format!("- {}\n", hint));
             }
         }
-        
-        if !changes.modified.is_empty() {
-            prompt.push_str("\nModified files:\n");
-            for file in &changes.modified {
-                prompt.push_str(&format!("  * {}\n", file));
+
+        prompt.push_str("\nPlease generate a commit message following the rules above.");
+
+        let system_prompt = template.system_prompt(self.config.commit.max_subject_length);
+
+        let (max_tokens, temperature) = match self.command {
+            Some(command) => self.config.generation(command, 200, COMMIT_TEMPERATURE),
+            None if count > 1 => self.config.generation("suggest", 200, SUGGEST_TEMPERATURE),
+            None => self.config.generation("commit", 200, COMMIT_TEMPERATURE),
+        };
+
+        // Ask for all suggestions at once, then one by one for any the reply lacks
+        let mut suggestions: Vec<String> = Vec::new();
+        if count > 1 {
+            let request = format!(
+                "{}\n\nInstead of one message, write {} clearly different ones, each taking another angle, scope or wording while still describing the same changes accurately. Start each with a line of the form \"=== MESSAGE <number> ===\" and write nothing else.",
+                prompt, count
+            );
+            let reply = self
+                .send(
+                    &system_prompt,
+                    &request,
+                    max_tokens.saturating_mul(u32::from(count)),
+                    temperature,
+                )
+                .await?;
+            for message in split_numbered(&reply) {
+                let message = template.clean(&message);
+                if !message.is_empty() && !suggestions.contains(&message) {
+                    suggestions.push(message);
+                }
             }
-        }
-        
-        if !changes.deleted.is_empty() {
-            prompt.push_str("\nDeleted files:\n");
-            for file in &changes.deleted {
-                prompt.push_str(&format!("  - {}\n", file));
+            suggestions.truncate(usize::from(count));
+            if suggestions.len() < usize::from(count) {
+                logging::log(|| {
+                    format!(
+                        "The reply held {} of {} suggestions; asking for the rest one by one",
+                        suggestions.len(),
+                        count
+                    )
+                });
             }
         }
-        
-        if !changes.renamed.is_empty() {
-            prompt.push_str("\nRenamed files:\n");
-            for (old, new) in &changes.renamed {
-                prompt.push_str(&format!("  {} -> {}\n", old, new));
+        while suggestions.len() < usize::from(count) {
+            // Show the earlier suggestions so the next one takes a different angle
+            let mut request = prompt.clone();
+            if !suggestions.is_empty() {
+                request.push_str("\n\nYou already suggested these messages:\n");
+                for suggestion in &suggestions {
+                    request.push_str(&format!("---\n{}\n", suggestion));
+                }
+                request.push_str("---\nWrite a clearly different one: pick another angle, scope or wording, while still describing the same changes accurately.");
             }
+            let message = self
+                .send(&system_prompt, &request, max_tokens, temperature)
+                .await?;
+            suggestions.push(template.clean(&message));
         }
 
-        // Add the diff
-        prompt.push_str("\nHere's the detailed diff:\n");
-        prompt.push_str(diff);
-        
-        prompt.push_str("\nPlease generate a commit message following the conventional commit format.");
-
-        let mut suggestions = Vec::new();
-        
-        for _ in 0..count {
-            let request = AnthropicRequest {
-                model: "claude-3-5-haiku-20241022".to_string(),
-                max_tokens: 200,
-                temperature: 0.7,  // Increased temperature for more varied suggestions
-                system: SYSTEM_PROMPT.to_string(),
-                messages: vec![AnthropicMessage {
-                    role: "user".to_string(),
-                    content: vec![AnthropicContent {
-                        content_type: "text".to_string(),
-                        text: prompt.clone(),
-                    }],
+        Ok(suggestions)
+    }
+
+    /// Check that the API key is accepted with a minimal request
+    pub async fn validate_key(&self) -> Result<()> {
+        self.complete("Reply with the single word OK.", "Are you there?", 5, 0.0)
+            .await
+            .map(|_| ())
+    }
+
+    /// Send a single prompt to the Anthropic API and return the text of the reply.
+    /// `max_tokens` and `temperature` are defaults the command's settings can override.
+    pub async fn complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Result<String> {
+        let (max_tokens, temperature) = match self.command {
+            Some(command) => self.config.generation(command, max_tokens, temperature),
+            None => (max_tokens, temperature),
+        };
+        self.send(system, prompt, max_tokens, temperature).await
+    }
+
+    /// Send the prompt as is. Timeouts, rate limits and overload errors are retried.
+    async fn send(
+        &self,
+        system: &str,
+        prompt: &str,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Result<String> {
+        logging::show_prompt(
+            &format!("System prompt for {}", self.config.ai.model),
+            &format!("{}\n\n# Prompt\n{}", system, prompt),
+        );
+        let api_key = self.config.get_api_key().ok_or_else(|| {
+            anyhow!(
+                "API key not set. Run 'gyst init', or set it with 'gyst config set ai.api_key <key>'."
+            )
+        })?;
+        logging::hide(api_key);
+        logging::log(|| {
+            format!(
+                "{} {}: max_tokens {}, temperature {}",
+                self.config.ai.provider, self.config.ai.model, max_tokens, temperature
+            )
+        });
+        logging::log(|| format!("System prompt:\n{}", system));
+        logging::log(|| format!("Prompt ({} characters):\n{}", prompt.len(), prompt));
+
+        let request = AnthropicRequest {
+            model: self.config.ai.model.clone(),
+            max_tokens,
+            temperature,
+            system: system.to_string(),
+            messages: vec![AnthropicMessage {
+                role: "user".to_string(),
+                content: vec![AnthropicContent {
+                    content_type: "text".to_string(),
+                    text: prompt.to_string(),
                 }],
-            };
+            }],
+        };
 
-            let response = self.client
-                .post("https://api.anthropic.com/v1/messages")
-                .header("x-api-key", HeaderValue::from_str(&api_key)?)
-                .header("anthropic-version", HeaderValue::from_static("2023-06-01"))
-                .header("Content-Type", HeaderValue::from_static("application/json"))
-                .json(&request)
-                .send()
-                .await
-                .context("Failed to send request to Anthropic")?;
+        let api_key = HeaderValue::from_str(api_key)?;
+        let mut attempt = 1;
+        let response = loop {
+            let started = Instant::now();
+            let result = chaos::send(
+                &self.client,
+                self.client
+                    .post("https://api.anthropic.com/v1/messages")
+                    .header("x-api-key", api_key.clone())
+                    .header("anthropic-version", HeaderValue::from_static("2023-06-01"))
+                    .header("Content-Type", HeaderValue::from_static("application/json"))
+                    .json(&request),
+            )
+            .await;
+            logging::attempt("Anthropic", attempt, &result, started);
 
-            let response_text = response.text().await?;
+            let delay = retry_delay(&result, attempt);
+            if delay.is_none() || attempt == MAX_ATTEMPTS {
+                break result
+                    .context("Failed to send request to Anthropic")
+                    .map_err(exit::provider)?;
+            }
+            tokio::time::sleep(delay.unwrap_or_default()).await;
+            attempt += 1;
+        };
 
-            let anthropic_response: AnthropicResponse = serde_json::from_str(&response_text)
-                .context("Failed to parse Anthropic response")?;
+        let status = response.status();
+        let response_text = response
+            .text()
+            .await
+            .context("Failed to read Anthropic response")
+            .map_err(exit::provider)?;
+        if !status.is_success() {
+            return Err(exit::provider(anyhow!(
+                "Anthropic API error ({}): {}",
+                status,
+                response_text
+            )));
+        }
 
-            let message = anthropic_response.content.into_iter()
-                .find(|c| c.content_type == "text")
-                .map(|c| c.text)
-                .ok_or_else(|| anyhow!("No text content in response"))?;
+        let anthropic_response: AnthropicResponse = serde_json::from_str(&response_text)
+            .context("Failed to parse Anthropic response")
+            .map_err(exit::provider)?;
+        if let Some(usage) = &anthropic_response.usage {
+            logging::log(|| {
+                format!(
+                    "Tokens: {} in, {} out",
+                    usage.input_tokens, usage.output_tokens
+                )
+            });
+        }
+
+        anthropic_response.content.into_iter()
+            .find(|c| c.content_type == "text")
+            .map(|c| c.text)
+            .ok_or_else(|| exit::provider(anyhow!("No text content in response")))
+    }
+}
 
-            suggestions.push(Self::clean_commit_message(&message));
+/// The changed files, one per line, grouped by kind of change
+fn file_list(changes: &StagedChanges) -> String {
+    let mut list = String::new();
+    if !changes.added.is_empty() {
+        list.push_str("Added files:\n");
+        for file in &changes.added {
+            list.push_str(&format!("  + {}\n", file));
         }
+    }
 
-        Ok(suggestions)
+    if !changes.modified.is_empty() {
+        list.push_str("\nModified files:\n");
+        for file in &changes.modified {
+            list.push_str(&format!("  * {}\n", file));
+        }
     }
-}
\ No newline at end of file
+
+    if !changes.deleted.is_empty() {
+        list.push_str("\nDeleted files:\n");
+        for file in &changes.deleted {
+            list.push_str(&format!("  - {}\n", file));
+        }
+    }
+
+    if !changes.renamed.is_empty() {
+        list.push_str("\nRenamed files:\n");
+        for (old, new) in &changes.renamed {
+            list.push_str(&format!("  {} -> {}\n", old, new));
+        }
+    }
+
+    if !changes.copied.is_empty() {
+        list.push_str("\nCopied files:\n");
+        for (source, new) in &changes.copied {
+            list.push_str(&format!("  {} -> {}\n", source, new));
+        }
+    }
+    list
+}
+
+/// The messages in a reply holding several, each under a `=== MESSAGE 2 ===`
+/// line. Numbered headings like `Message 2:` or `**Option 2**` are accepted
+/// too; text before the first heading is dropped.
+fn split_numbered(reply: &str) -> Vec<String> {
+    let Ok(heading) =
+        Regex::new(r"(?i)^[\s=#*]*(?:message|option|suggestion)\s*#?\d+\s*:?[\s=*]*$")
+    else {
+        return Vec::new();
+    };
+    let mut messages: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+    for line in reply.lines() {
+        if heading.is_match(line) {
+            messages.extend(current.take());
+            current = Some(String::new());
+        } else if let Some(message) = current.as_mut() {
+            message.push_str(line);
+            message.push('\n');
+        }
+    }
+    messages.extend(current);
+    messages
+        .into_iter()
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .collect()
+}
@@ -0,0 +1,84 @@
+//! Changes gyst made to a repository's branches, kept one JSON object per line
+//! in `.git/gyst/audit.jsonl` (or `gyst.db`, see `storage`) so they can be undone.
+
+use crate::git::GitRepo;
+use crate::storage;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+const AUDIT_LOG: &str = "audit";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum Event {
+    /// `oid` is the commit the branch pointed to
+    BranchDeleted {
+        branch: String,
+        oid: String,
+    },
+    BranchRestored {
+        branch: String,
+        oid: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix timestamp
+    pub time: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// A branch gyst deleted that has not been restored or recreated since
+#[derive(Debug)]
+pub struct DeletedBranch {
+    pub name: String,
+    pub oid: String,
+    pub time: i64,
+}
+
+/// Append `event` to the repository's audit log
+pub fn record(repo: &GitRepo, event: Event) -> Result<()> {
+    let entry = Entry {
+        time: chrono::Utc::now().timestamp(),
+        event,
+    };
+    storage::open(&repo.data_dir())?.append(AUDIT_LOG, &serde_json::to_string(&entry)?)
+}
+
+/// Everything in the audit log, oldest first. Records that do not parse are skipped.
+pub fn entries(repo: &GitRepo) -> Vec<Entry> {
+    let Ok(records) = storage::open(&repo.data_dir()).and_then(|store| store.records(AUDIT_LOG))
+    else {
+        return Vec::new();
+    };
+    records
+        .iter()
+        .filter_map(|record| serde_json::from_str(record).ok())
+        .collect()
+}
+
+/// Branches gyst deleted that do not exist again, most recently deleted first
+pub fn deleted_branches(repo: &GitRepo) -> Result<Vec<DeletedBranch>> {
+    let existing: HashSet<String> = repo.branch_names()?.into_iter().collect();
+    let mut seen = HashSet::new();
+    let mut deleted = Vec::new();
+    for entry in entries(repo).into_iter().rev() {
+        let (branch, oid, restored) = match entry.event {
+            Event::BranchDeleted { branch, oid } => (branch, oid, false),
+            Event::BranchRestored { branch, oid } => (branch, oid, true),
+        };
+        // Only the latest event for each name counts
+        if !seen.insert(branch.clone()) || restored || existing.contains(&branch) {
+            continue;
+        }
+        deleted.push(DeletedBranch {
+            name: branch,
+            oid,
+            time: entry.time,
+        });
+    }
+    Ok(deleted)
+}
@@ -1,55 +1,243 @@
-use anyhow::{Result, Context};
-use git2::{Repository, Branch, BranchType, Time};
-use serde::Serialize;
-use std::time::{SystemTime, UNIX_EPOCH};
-use chrono::Local;
+pub mod recommend;
 
-#[derive(Debug, Serialize)]
-pub struct TimeAgo {
-    pub days: u32,
-    pub hours: u32,
-    pub minutes: u32,
-}
-
-impl TimeAgo {
-    pub fn to_string(&self) -> String {
-        if self.days > 0 {
-            format!("{} days", self.days)
-        } else if self.hours > 0 {
-            format!("{} hours", self.hours)
-        } else {
-            format!("{} minutes", self.minutes)
-        }
-    }
-}
+use crate::config;
+use crate::dates;
+use crate::forge::PullRequest;
+use crate::output::Symbol;
+use crate::theme;
+use anyhow::{Context, Result};
+use chrono::Local;
+use git2::{Branch, BranchType, Commit, Mailmap, Repository, Signature};
+use rayon::prelude::*;
+use recommend::Recommendations;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
 
 #[derive(Debug, Serialize)]
 pub struct BranchHealth {
     pub name: String,
     pub status: BranchStatus,
+    /// Whole days since the last commit
+    #[serde(skip)]
+    pub idle_days: u32,
+    /// Unix timestamp of the last commit
+    #[serde(skip)]
+    pub last_activity: i64,
+    /// Unix timestamp of the first commit the branch adds
     #[serde(skip)]
-    pub last_activity: TimeAgo,
+    pub started: i64,
     #[serde(rename = "last_activity")]
     pub last_activity_display: String,
     #[serde(rename = "age")]
     pub age_display: String,
     pub author: String,
+    /// Commits reachable from the branch
     pub commit_count: u32,
     pub ahead_count: u32,
     pub behind_count: u32,
+    /// The branch this local branch tracks; `None` for one that has not been
+    /// pushed (and for remote branches)
+    pub upstream: Option<Upstream>,
+    /// The open pull request for this branch, when looked up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<PullRequest>,
+    #[serde(skip)]
+    is_remote: bool,
+    /// Whether this is the base branch, or its twin on a remote
+    #[serde(skip)]
+    is_base: bool,
 }
 
-#[derive(Debug, Serialize)]
+impl BranchHealth {
+    /// The branch name without the remote, e.g. `feature` for `origin/feature`
+    pub fn branch_name(&self) -> &str {
+        if self.is_remote {
+            self.name
+                .split_once('/')
+                .map_or(&self.name, |(_, name)| name)
+        } else {
+            &self.name
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.is_remote
+    }
+
+    /// Whether this is the branch the others are measured against
+    pub fn is_base(&self) -> bool {
+        self.is_base
+    }
+
+    /// Record the branch's open pull request. Branches under review are not
+    /// cleanup candidates, so they are never reported as stale.
+    pub fn attach_pull_request(&mut self, pull_request: PullRequest) {
+        if self.status == BranchStatus::Stale {
+            self.status = BranchStatus::NeedsAttention;
+        }
+        self.pull_request = Some(pull_request);
+    }
+}
+
+/// How far a local branch is from the branch it tracks
+#[derive(Debug, Clone, Serialize)]
+pub struct Upstream {
+    pub name: String,
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+impl Upstream {
+    /// e.g. `origin/feature (2 ahead, 1 behind)` or `origin/feature (up to date)`
+    pub fn describe(&self) -> String {
+        if self.ahead == 0 && self.behind == 0 {
+            format!("{} (up to date)", self.name)
+        } else {
+            format!(
+                "{} ({} ahead, {} behind)",
+                self.name, self.ahead, self.behind
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum BranchStatus {
     Healthy,
     NeedsAttention,
     Stale,
 }
 
+/// The current user as configured in git, with the mailmap applied
+struct Identity {
+    name: String,
+    email: String,
+}
+
+impl Identity {
+    fn is(&self, mailmap: &Mailmap, signature: &Signature) -> bool {
+        let Ok(signature) = mailmap.resolve_signature(signature) else {
+            return false;
+        };
+        match signature.email() {
+            Some(email) if !email.is_empty() && !self.email.is_empty() => {
+                email.eq_ignore_ascii_case(&self.email)
+            }
+            _ => signature.name() == Some(self.name.as_str()),
+        }
+    }
+
+    /// Whether a branch is theirs: its last commit is, or most of the
+    /// commits it adds to the main branch are
+    fn owns(&self, mailmap: &Mailmap, tip: &Commit, added: &[Commit]) -> bool {
+        if self.is(mailmap, &tip.committer()) {
+            return true;
+        }
+        let mine = added
+            .iter()
+            .filter(|commit| self.is(mailmap, &commit.committer()))
+            .count();
+        mine * 2 > added.len()
+    }
+}
+
+/// Names the default branch can have, in the order they are looked for
+const BASE_NAMES: &[&str] = &["main", "master", "develop", "trunk"];
+
+const NO_BASE: &str = "Could not find the main branch (main, master, develop, trunk, or the one origin/HEAD points at). Set it with 'gyst config set branch.base <branch>'";
+
+/// The branch the others are compared with: ahead and behind counts, and
+/// where each branch starts, are measured against it
+#[derive(Debug, Clone)]
+pub struct Base {
+    /// The branch as found, e.g. `develop` or `origin/develop`
+    pub name: String,
+    /// The name without the remote, e.g. `develop`
+    pub branch: String,
+    oid: git2::Oid,
+}
+
+impl Base {
+    /// `configured` when it is set, otherwise the branch `origin/HEAD` points
+    /// at, otherwise the first of `main`, `master`, `develop` and `trunk`.
+    /// Each is looked for locally first, then on `origin`, so a clone that
+    /// only has remote-tracking branches still works.
+    pub fn find(repo: &Repository, configured: Option<&str>) -> Result<Self> {
+        if let Some(name) = configured.map(str::trim).filter(|name| !name.is_empty()) {
+            return Self::named(repo, name).with_context(|| {
+                format!(
+                    "branch.base is '{}', which is not a branch of this repository",
+                    name
+                )
+            });
+        }
+
+        let origin_head = repo
+            .find_reference("refs/remotes/origin/HEAD")
+            .ok()
+            .and_then(|head| {
+                head.symbolic_target()?
+                    .strip_prefix("refs/remotes/origin/")
+                    .map(str::to_string)
+            });
+        origin_head
+            .into_iter()
+            .chain(BASE_NAMES.iter().map(|name| name.to_string()))
+            .find_map(|name| Self::named(repo, &name))
+            .context(NO_BASE)
+    }
+
+    /// The local branch `name`, else `origin/<name>`, else the remote-tracking
+    /// branch `name` (e.g. `upstream/main`)
+    fn named(repo: &Repository, name: &str) -> Option<Self> {
+        [
+            (name.to_string(), BranchType::Local),
+            (format!("origin/{}", name), BranchType::Remote),
+            (name.to_string(), BranchType::Remote),
+        ]
+        .into_iter()
+        .find_map(|(full, kind)| {
+            let oid = repo
+                .find_branch(&full, kind)
+                .ok()?
+                .get()
+                .peel_to_commit()
+                .ok()?
+                .id();
+            let branch = match kind {
+                BranchType::Remote => full.split_once('/').map_or(full.as_str(), |(_, b)| b),
+                BranchType::Local => full.as_str(),
+            }
+            .to_string();
+            Some(Self {
+                name: full,
+                branch,
+                oid,
+            })
+        })
+    }
+}
+
+/// Reports how many of the branches have been analyzed: `(done, total)`
+type ProgressFn = Box<dyn Fn(usize, usize) + Sync>;
+
+/// Branches are analyzed in parallel, each thread with its own `Repository`
+/// (they cannot be shared between threads), so the analyzer only keeps where
+/// the repository is
 pub struct BranchAnalyzer {
-    repo: Repository,
+    /// The repository's `.git` directory
+    git_dir: PathBuf,
+    /// The branch the others are measured against
+    base: Option<Base>,
     stale_days: u32,
     inactive_days: u32,
+    /// When set, only branches owned by this identity are analyzed
+    owner: Option<Identity>,
+    /// Branches left out of the analysis; entries ending in `*` match a prefix
+    ignored: Vec<String>,
+    progress: Option<ProgressFn>,
 }
 
 impl BranchAnalyzer {
@@ -58,69 +246,123 @@ impl BranchAnalyzer {
             .context("Failed to find git repository")?;
         
         Ok(Self {
-            repo,
+            git_dir: repo.path().to_path_buf(),
+            base: Base::find(&repo, None).ok(),
             stale_days: 30,
             inactive_days: 7,
+            owner: None,
+            ignored: Vec::new(),
+            progress: None,
         })
     }
 
-    fn calculate_time_ago(&self, git_time: Time) -> Result<TimeAgo> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .context("Failed to get current time")?
-            .as_secs() as i64;
-        
-        let diff_secs = now - git_time.seconds();
-        let days = diff_secs / (24 * 60 * 60);
-        let remaining_secs = diff_secs % (24 * 60 * 60);
-        let hours = remaining_secs / (60 * 60);
-        let minutes = (remaining_secs % (60 * 60)) / 60;
-        
-        Ok(TimeAgo {
-            days: days as u32,
-            hours: hours as u32,
-            minutes: minutes as u32,
-        })
+    fn open(&self) -> Result<Repository> {
+        Repository::open(&self.git_dir).context("Failed to open git repository")
+    }
+
+    /// Measure branches against `configured` (`branch.base`) instead of the
+    /// detected main branch. Fails if neither can be found.
+    pub fn with_base(mut self, configured: Option<&str>) -> Result<Self> {
+        self.base = Some(Base::find(&self.open()?, configured)?);
+        Ok(self)
     }
 
-    pub fn analyze_branch(&self, branch: &Branch) -> Result<BranchHealth> {
+    /// Call `progress` with the number of branches analyzed so far and the
+    /// total, after each branch
+    pub fn with_progress(mut self, progress: impl Fn(usize, usize) + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// The branch the others are measured against, if one was found
+    pub fn base(&self) -> Option<&Base> {
+        self.base.as_ref()
+    }
+
+    /// Leave out branches that match one of `patterns`. Remote branches are
+    /// matched without the remote, so `release/*` also covers `origin/release/1.0`.
+    pub fn ignoring(mut self, patterns: Vec<String>) -> Self {
+        self.ignored = patterns;
+        self
+    }
+
+    fn is_ignored(&self, branch: &Branch, branch_type: BranchType) -> bool {
+        let Ok(Some(name)) = branch.name() else {
+            return false;
+        };
+        let name = match branch_type {
+            BranchType::Remote => name.split_once('/').map_or(name, |(_, name)| name),
+            BranchType::Local => name,
+        };
+        config::matches_branch(&self.ignored, name)
+    }
+
+    /// Only analyze branches that belong to the current user (`user.name` and
+    /// `user.email`, resolved through `.mailmap`): the branch's last commit is
+    /// theirs, or they committed most of the commits it adds
+    pub fn only_mine(mut self) -> Result<Self> {
+        let repo = self.open()?;
+        let mailmap = repo.mailmap().context("Failed to read .mailmap")?;
+        let me = repo
+            .signature()
+            .context("Set user.name and user.email in git config to use --mine")?;
+        let me = mailmap.resolve_signature(&me)?;
+        self.owner = Some(Identity {
+            name: me.name().unwrap_or_default().to_string(),
+            email: me.email().unwrap_or_default().to_string(),
+        });
+        Ok(self)
+    }
+
+    fn analyze_branch(
+        &self,
+        repo: &Repository,
+        branch: &Branch,
+        commit: &Commit,
+        base: &Base,
+        history: &BaseHistory,
+        walk: &Walk,
+    ) -> Result<BranchHealth> {
         let branch_ref = branch.get();
         let branch_name = match branch.name()? {
             Some(name) => name.to_string(),
             None => "unknown".to_string(),
         };
-        
-        let commit = branch_ref.peel_to_commit()
-            .context("Failed to get branch commit")?;
-        
-        let last_activity = self.calculate_time_ago(commit.time())?;
-        
-        let mut revwalk = self.repo.revwalk()?;
-        revwalk.push(commit.id())?;
-        let commit_count = revwalk.count() as u32;
-        
-        let (ahead, behind) = self.get_distance_from_main(&branch)?;
-
-        let main_branch = self.repo.find_branch("master", BranchType::Local)
-            .or_else(|_| self.repo.find_branch("main", BranchType::Local))
-            .context("Failed to find main branch")?;
-        let main_commit = main_branch.get().peel_to_commit()?;
-        let merge_base = self.repo.merge_base(commit.id(), main_commit.id())?;
-
-        let mut revwalk = self.repo.revwalk()?;
-        revwalk.push(commit.id())?;
-        revwalk.hide(merge_base)?;
-        let age_time = if let Some(Ok(commit_id)) = revwalk.next() {
-            self.repo.find_commit(commit_id)?.time()
+
+        let last_activity = commit.time().seconds();
+        let idle_days = ((Local::now().timestamp() - last_activity).max(0) / (24 * 60 * 60)) as u32;
+
+        // `origin/develop` is the base too when `develop` is
+        let is_base = if branch_ref.is_remote() {
+            branch_name
+                .split_once('/')
+                .is_some_and(|(_, name)| name == base.branch)
         } else {
-            commit.time()
+            branch_name == base.branch
         };
+        let ahead = walk.added.len();
+        let behind = match walk.behind {
+            Some(behind) => behind,
+            None => {
+                repo.graph_ahead_behind(commit.id(), base.oid)
+                    .context("Failed to calculate ahead/behind counts")?
+                    .1
+            }
+        };
+        // The branch has the base's history, less what it is behind, plus
+        // what it adds: no need to walk all of it for every branch
+        let commit_count = (history.commits.len() + ahead).saturating_sub(behind) as u32;
+        // A branch starts with the oldest commit it adds
+        let started = walk
+            .added
+            .iter()
+            .map(|commit| commit.time().seconds())
+            .min()
+            .unwrap_or(last_activity);
 
-        let age = self.calculate_time_ago(age_time)?;
-
-        let status = if last_activity.days >= self.stale_days {
+        let status = if idle_days >= self.stale_days {
             BranchStatus::Stale
-        } else if last_activity.days >= self.inactive_days {
+        } else if idle_days >= self.inactive_days {
             BranchStatus::NeedsAttention
         } else {
             BranchStatus::Healthy
@@ -129,67 +371,198 @@ impl BranchAnalyzer {
         Ok(BranchHealth {
             name: branch_name,
             status,
-            age_display: age.to_string(),
-            last_activity_display: last_activity.to_string(),
+            age_display: dates::age(started),
+            last_activity_display: dates::when(last_activity),
+            idle_days,
             last_activity,
+            started,
             author: commit.author().name().unwrap_or("unknown").to_string(),
             commit_count,
             ahead_count: ahead as u32,
             behind_count: behind as u32,
+            upstream: upstream(repo, branch),
+            pull_request: None,
+            is_remote: branch_ref.is_remote(),
+            is_base,
         })
     }
 
-    fn get_distance_from_main(&self, branch: &Branch) -> Result<(usize, usize)> {
-        let main_branch = self.repo.find_branch("main", BranchType::Local)
-            .or_else(|_| self.repo.find_branch("master", BranchType::Local))
-            .context("Failed to find main or master branch")?;
-        
-        let branch_commit = branch.get().peel_to_commit()
-            .context("Failed to get branch commit")?;
-        let main_commit = main_branch.get().peel_to_commit()
-            .context("Failed to get main branch commit")?;
-        
-        self.repo.graph_ahead_behind(branch_commit.id(), main_commit.id())
-            .context("Failed to calculate ahead/behind counts")
-    }
-
     pub fn analyze_branches(&self, filter: BranchFilter, days: Option<u32>, author: Option<String>) -> Result<Vec<BranchHealth>> {
-        let mut results = Vec::new();
-        
+        let base = self.base.as_ref().context(NO_BASE)?;
+        let repo = self.open()?;
+
         let branch_types = match filter {
             BranchFilter::All => vec![BranchType::Local, BranchType::Remote],
             BranchFilter::Local => vec![BranchType::Local],
             BranchFilter::Remote => vec![BranchType::Remote],
         };
-        
+
+        let mut names: Vec<(String, BranchType)> = Vec::new();
         for branch_type in branch_types {
-            let branches = self.repo.branches(Some(branch_type))
+            let branches = repo
+                .branches(Some(branch_type))
                 .context("Failed to get repository branches")?;
             
             for branch_result in branches {
                 let (branch, _) = branch_result?;
-                if let Ok(health) = self.analyze_branch(&branch) {
-                    if let Some(max_days) = days {
-                        if health.last_activity.days > max_days {
-                            continue;
-                        }
-                    }
-                    
-                    if let Some(ref target_author) = author {
-                        if health.author != *target_author {
-                            continue;
+                if self.is_ignored(&branch, branch_type) {
+                    continue;
+                }
+                if let Ok(Some(name)) = branch.name() {
+                    names.push((name.to_string(), branch_type));
+                }
+            }
+        }
+
+        let history = BaseHistory::load(&repo, base.oid)?;
+
+        let total = names.len();
+        let done = Mutex::new(0);
+        let results: Vec<BranchHealth> = names
+            .par_iter()
+            .map_init(
+                || {
+                    let repo = self.open().ok()?;
+                    let mailmap = repo.mailmap().ok()?;
+                    Some((repo, mailmap))
+                },
+                |thread, (name, branch_type)| {
+                    let health = thread.as_ref().and_then(|(repo, mailmap)| {
+                        let branch = repo.find_branch(name, *branch_type).ok()?;
+                        let tip = branch.get().peel_to_commit().ok()?;
+                        let walk = history.walk(repo, &tip).ok()?;
+                        if let Some(owner) = &self.owner
+                            && !owner.owns(mailmap, &tip, &walk.added)
+                        {
+                            return None;
                         }
+                        self.analyze_branch(repo, &branch, &tip, base, &history, &walk)
+                            .ok()
+                    });
+                    // Reported one at a time, so the count only goes up
+                    if let Some(progress) = &self.progress
+                        && let Ok(mut done) = done.lock()
+                    {
+                        *done += 1;
+                        progress(*done, total);
                     }
-                    
-                    results.push(health);
+                    health
+                },
+            )
+            .flatten()
+            .collect();
+
+        Ok(results
+            .into_iter()
+            .filter(|health| days.is_none_or(|max_days| health.idle_days <= max_days))
+            .filter(|health| {
+                author
+                    .as_ref()
+                    .is_none_or(|target_author| health.author == *target_author)
+            })
+            .collect())
+    }
+}
+
+/// The history of the base branch, walked once and shared by every branch
+/// analyzed, so each branch only needs to walk the commits it adds
+struct BaseHistory {
+    /// Every commit reachable from the base
+    commits: HashSet<git2::Oid>,
+    /// The commits on the base's first-parent line, each with the number of
+    /// commits of the base's history it does not reach
+    behind: HashMap<git2::Oid, usize>,
+}
+
+impl BaseHistory {
+    fn load(repo: &Repository, base: git2::Oid) -> Result<Self> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(base)?;
+        let commits = revwalk
+            .collect::<Result<HashSet<_>, _>>()
+            .context("Failed to walk the main branch")?;
+
+        // Going down the first-parent line, each commit leaves behind itself
+        // and whatever it merged in
+        let mut behind = HashMap::new();
+        let mut missing = 0;
+        let mut current = repo.find_commit(base)?;
+        loop {
+            behind.insert(current.id(), missing);
+            let Ok(parent) = current.parent(0) else {
+                break;
+            };
+            missing += 1;
+            if current.parent_count() > 1 {
+                let mut merged = repo.revwalk()?;
+                for id in current.parent_ids().skip(1) {
+                    merged.push(id)?;
                 }
+                merged.hide(parent.id())?;
+                missing += merged.count();
             }
+            current = parent;
         }
-        
-        Ok(results)
+        Ok(Self { commits, behind })
+    }
+
+    /// Walk back from `tip` until the walk reaches the base's history
+    fn walk<'r>(&self, repo: &'r Repository, tip: &Commit<'r>) -> Result<Walk<'r>> {
+        let mut added = Vec::new();
+        let mut reached = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![tip.clone()];
+        while let Some(commit) = pending.pop() {
+            if !seen.insert(commit.id()) {
+                continue;
+            }
+            if self.commits.contains(&commit.id()) {
+                reached.insert(commit.id());
+                continue;
+            }
+            for id in commit.parent_ids() {
+                pending.push(repo.find_commit(id)?);
+            }
+            added.push(commit);
+        }
+        // Unrelated history has no merge base to compare with
+        if reached.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The branch has no history in common with the base branch"
+            ));
+        }
+
+        // Forked from a single commit on the first-parent line, as most
+        // branches are; otherwise libgit2 counts it
+        let behind = match reached.iter().collect::<Vec<_>>()[..] {
+            [fork] => self.behind.get(fork).copied(),
+            _ => None,
+        };
+        Ok(Walk { added, behind })
     }
 }
 
+/// The commits a branch adds to the base, and how many of the base's it lacks
+/// when the shared history can tell
+struct Walk<'r> {
+    added: Vec<Commit<'r>>,
+    behind: Option<usize>,
+}
+
+/// The upstream of a local branch and how far the branch is from it
+fn upstream(repo: &Repository, branch: &Branch) -> Option<Upstream> {
+    let upstream = branch.upstream().ok()?;
+    let name = upstream.name().ok()??.to_string();
+    let local = branch.get().target()?;
+    let remote = upstream.get().target()?;
+    let (ahead, behind) = repo.graph_ahead_behind(local, remote).ok()?;
+    Some(Upstream {
+        name,
+        ahead: ahead as u32,
+        behind: behind as u32,
+    })
+}
+
 #[derive(Debug)]
 pub enum BranchFilter {
     All,
@@ -197,10 +570,47 @@ pub enum BranchFilter {
     Remote,
 }
 
+/// The order branches are reported in
+pub enum SortKey {
+    /// Oldest branch first, by its first commit
+    Age,
+    /// Most recently active first
+    Activity,
+    /// Furthest behind the main branch first
+    Behind,
+    /// Furthest ahead of the main branch first
+    Ahead,
+}
+
+impl From<&str> for SortKey {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "activity" => SortKey::Activity,
+            "behind" => SortKey::Behind,
+            "ahead" => SortKey::Ahead,
+            _ => SortKey::Age,
+        }
+    }
+}
+
+/// Sort `results` by `key`, then by name
+pub fn sort(results: &mut [BranchHealth], key: SortKey) {
+    results.sort_by(|a, b| {
+        match key {
+            SortKey::Age => a.started.cmp(&b.started),
+            SortKey::Activity => b.last_activity.cmp(&a.last_activity),
+            SortKey::Behind => b.behind_count.cmp(&a.behind_count),
+            SortKey::Ahead => b.ahead_count.cmp(&a.ahead_count),
+        }
+        .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
 pub enum OutputFormat {
     Text,
     Json,
     Markdown,
+    Csv,
 }
 
 impl From<&str> for OutputFormat {
@@ -208,63 +618,519 @@ impl From<&str> for OutputFormat {
         match s.to_lowercase().as_str() {
             "json" => OutputFormat::Json,
             "markdown" => OutputFormat::Markdown,
+            "csv" => OutputFormat::Csv,
             _ => OutputFormat::Text,
         }
     }
 }
 
-pub fn format_output(results: &[BranchHealth], format: OutputFormat) -> Result<String> {
+/// The JSON report with recommendations
+#[derive(Serialize)]
+struct Report<'a> {
+    branches: &'a [BranchHealth],
+    recommendations: &'a Recommendations,
+}
+
+/// The report on `results`, with the `--recommend` recommendations if given.
+/// JSON output is then an object with `branches` and `recommendations`
+/// instead of the list of branches.
+pub fn format_output(
+    results: &[BranchHealth],
+    recommendations: Option<&Recommendations>,
+    format: OutputFormat,
+) -> Result<String> {
     match format {
-        OutputFormat::Json => Ok(serde_json::to_string_pretty(results)?),
-        OutputFormat::Markdown => format_markdown(results),
-        OutputFormat::Text => format_text(results),
+        OutputFormat::Json => Ok(match recommendations {
+            Some(recommendations) => serde_json::to_string_pretty(&Report {
+                branches: results,
+                recommendations,
+            })?,
+            None => serde_json::to_string_pretty(results)?,
+        }),
+        OutputFormat::Markdown => format_markdown(results, recommendations),
+        OutputFormat::Csv => Ok(format_csv(results, recommendations)),
+        OutputFormat::Text => format_text(results, recommendations),
+    }
+}
+
+/// A header row and one row per branch, for importing into a spreadsheet.
+/// Dates are written as `YYYY-MM-DD HH:MM` and counts as plain numbers, so they sort.
+/// Recommendations add columns, with the hygiene score repeated on each row.
+fn format_csv(results: &[BranchHealth], recommendations: Option<&Recommendations>) -> String {
+    let mut header = "branch,status,started,last_activity,idle_days,author,commits,ahead,behind,upstream,upstream_ahead,upstream_behind,pull_request".to_string();
+    if recommendations.is_some() {
+        header.push_str(",recommendation,recommendation_reason,hygiene_score");
+    }
+    let mut rows = vec![header];
+    let date = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    };
+    for health in results {
+        let upstream = health.upstream.as_ref();
+        let mut fields = vec![
+            health.name.clone(),
+            format!("{:?}", health.status),
+            date(health.started),
+            date(health.last_activity),
+            health.idle_days.to_string(),
+            health.author.clone(),
+            health.commit_count.to_string(),
+            health.ahead_count.to_string(),
+            health.behind_count.to_string(),
+            upstream.map(|u| u.name.clone()).unwrap_or_default(),
+            upstream.map(|u| u.ahead.to_string()).unwrap_or_default(),
+            upstream.map(|u| u.behind.to_string()).unwrap_or_default(),
+            health
+                .pull_request
+                .as_ref()
+                .map(|pull_request| pull_request.url.clone())
+                .unwrap_or_default(),
+        ];
+        if let Some(recommendations) = recommendations {
+            let recommendation = recommendations.for_branch(&health.name);
+            fields.push(
+                recommendation
+                    .map(|r| r.action.label().to_string())
+                    .unwrap_or_default(),
+            );
+            fields.push(recommendation.map(|r| r.reason.clone()).unwrap_or_default());
+            fields.push(recommendations.score.to_string());
+        }
+        let row: Vec<String> = fields.iter().map(|field| escape_csv_field(field)).collect();
+        rows.push(row.join(","));
     }
+    rows.join("\n")
 }
 
-fn format_text(results: &[BranchHealth]) -> Result<String> {
+/// Quote a CSV field that holds a comma, quote or line break (RFC 4180)
+fn escape_csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_text(
+    results: &[BranchHealth],
+    recommendations: Option<&Recommendations>,
+) -> Result<String> {
     let mut output = String::from("Branch Health Report\n");
-    output.push_str(&format!("Last updated: {}\n\n", Local::now().format("%Y-%m-%d %H:%M:%S")));
+    output.push_str(&format!(
+        "Last updated: {}\n\n",
+        dates::date_time(Local::now().timestamp())
+    ));
+    if let Some(recommendations) = recommendations {
+        let score = format!("{}/100", recommendations.score);
+        let score = match recommendations.score {
+            80.. => theme::added(score),
+            50..80 => theme::warning(score),
+            _ => theme::removed(score),
+        };
+        output.push_str(&format!(
+            "Branch hygiene: {}\n{}\n\n",
+            score.bold(),
+            recommendations.summary
+        ));
+    }
 
     for health in results {
-        let status_emoji = match health.status {
-            BranchStatus::Healthy => "🟢",
-            BranchStatus::NeedsAttention => "🟡",
-            BranchStatus::Stale => "🔴",
+        let label = format!("{:?}", health.status);
+        let (status_emoji, status) = match health.status {
+            BranchStatus::Healthy => (Symbol("🟢", "+"), theme::added(label)),
+            BranchStatus::NeedsAttention => (Symbol("🟡", "!"), theme::warning(label)),
+            BranchStatus::Stale => (Symbol("🔴", "x"), theme::removed(label)),
         };
 
         output.push_str(&format!("{}\n", health.name));
-        output.push_str(&format!("├── Status: {} {}\n", status_emoji, format!("{:?}", health.status)));
+        output.push_str(&format!("├── Status: {} {}\n", status_emoji, status));
         output.push_str(&format!("├── Age: {}\n", health.age_display));
         output.push_str(&format!("├── Last Activity: {}\n", health.last_activity_display));
         output.push_str(&format!("├── Author: {}\n", health.author));
         output.push_str(&format!("├── Commits: {}\n", health.commit_count));
+        if let Some(pull_request) = &health.pull_request {
+            output.push_str(&format!(
+                "├── Pull Request: #{} ({})\n",
+                pull_request.number, pull_request.review_state
+            ));
+        }
+        if !health.is_remote {
+            output.push_str(&format!(
+                "├── Upstream: {}\n",
+                health
+                    .upstream
+                    .as_ref()
+                    .map_or("none (not pushed yet)".to_string(), Upstream::describe)
+            ));
+        }
+        if let Some(recommendation) = recommendations.and_then(|r| r.for_branch(&health.name)) {
+            output.push_str(&format!(
+                "├── Recommendation: {} ({})\n",
+                theme::prompt(recommendation.action.label()).bold(),
+                recommendation.reason
+            ));
+        }
         output.push_str(&format!("└── Main Distance: {} ahead, {} behind\n\n", health.ahead_count, health.behind_count));
     }
 
     Ok(output)
 }
 
-fn format_markdown(results: &[BranchHealth]) -> Result<String> {
+fn format_markdown(
+    results: &[BranchHealth],
+    recommendations: Option<&Recommendations>,
+) -> Result<String> {
     let mut output = String::from("# Branch Health Report\n\n");
-    output.push_str(&format!("*Last updated: {}*\n\n", Local::now().format("%Y-%m-%d %H:%M:%S")));
+    output.push_str(&format!(
+        "*Last updated: {}*\n\n",
+        dates::date_time(Local::now().timestamp())
+    ));
+
+    let with_pull_requests = results.iter().any(|h| h.pull_request.is_some());
+    let with_upstreams = results.iter().any(|h| !h.is_remote);
 
+    // Problem groups first; they start expanded so they are visible in the issue
+    let groups = [
+        (BranchStatus::Stale, "🔴", "Stale", true),
+        (BranchStatus::NeedsAttention, "🟡", "Needs Attention", true),
+        (BranchStatus::Healthy, "🟢", "Healthy", false),
+    ];
+
+    output.push_str("| Status | Branches |\n");
+    output.push_str("|--------|----------|\n");
+    for (status, emoji, label, _) in &groups {
+        let count = results.iter().filter(|h| h.status == *status).count();
+        output.push_str(&format!("| {} {} | {} |\n", emoji, label, count));
+    }
+    output.push_str(&format!("| **Total** | **{}** |\n\n", results.len()));
+    if let Some(recommendations) = recommendations {
+        output.push_str(&format!(
+            "**Branch hygiene: {}/100.** {}\n\n",
+            recommendations.score, recommendations.summary
+        ));
+    }
+
+    for (status, emoji, label, open) in &groups {
+        let branches: Vec<&BranchHealth> = results.iter().filter(|h| h.status == *status).collect();
+        if branches.is_empty() {
+            continue;
+        }
+
+        output.push_str(if *open {
+            "<details open>\n"
+        } else {
+            "<details>\n"
+        });
+        output.push_str(&format!(
+            "<summary>{} {} ({})</summary>\n\n",
+            emoji,
+            label,
+            branches.len()
+        ));
+        output.push_str("| Branch | Age | Last Activity | Author | Commits | Main Distance |");
+        if with_upstreams {
+            output.push_str(" Upstream |");
+        }
+        if recommendations.is_some() {
+            output.push_str(" Recommendation |");
+        }
+        output.push_str(if with_pull_requests {
+            " Pull Request |\n"
+        } else {
+            "\n"
+        });
+        output.push_str("|--------|-----|---------------|--------|---------|---------------|");
+        if with_upstreams {
+            output.push_str("----------|");
+        }
+        if recommendations.is_some() {
+            output.push_str("----------------|");
+        }
+        output.push_str(if with_pull_requests {
+            "--------------|\n"
+        } else {
+            "\n"
+        });
+        for health in branches {
+            output.push_str(&format!(
+                "| `{}` | {} | {} | {} | {} | {} ahead, {} behind |",
+                health.name,
+                health.age_display,
+                health.last_activity_display,
+                escape_table_cell(&health.author),
+                health.commit_count,
+                health.ahead_count,
+                health.behind_count
+            ));
+            if with_upstreams {
+                match &health.upstream {
+                    Some(upstream) => output.push_str(&format!(
+                        " `{}` {} ahead, {} behind |",
+                        upstream.name, upstream.ahead, upstream.behind
+                    )),
+                    None if health.is_remote => output.push_str(" |"),
+                    None => output.push_str(" not pushed yet |"),
+                }
+            }
+            if let Some(recommendations) = recommendations {
+                match recommendations.for_branch(&health.name) {
+                    Some(recommendation) => output.push_str(&format!(
+                        " **{}**: {} |",
+                        recommendation.action.label(),
+                        escape_table_cell(&recommendation.reason)
+                    )),
+                    None => output.push_str(" |"),
+                }
+            }
+            if with_pull_requests {
+                match &health.pull_request {
+                    Some(pull_request) => output.push_str(&format!(
+                        " [#{}]({}) {} |",
+                        pull_request.number, pull_request.url, pull_request.review_state
+                    )),
+                    None => output.push_str(" |"),
+                }
+            }
+            output.push('\n');
+        }
+        output.push_str("\n</details>\n\n");
+    }
+
+    Ok(output)
+}
+
+/// One author's branches, for `gyst branch health --group-by author`
+#[derive(Debug, Serialize)]
+pub struct AuthorSummary {
+    pub author: String,
+    pub branches: usize,
+    pub healthy: usize,
+    pub needs_attention: usize,
+    pub stale: usize,
+    /// Whole days since the author's least recently active branch had a commit
+    pub longest_idle_days: u32,
+    /// Unix timestamp of that commit
+    #[serde(skip)]
+    pub least_recent_activity: i64,
+    pub stale_branches: Vec<String>,
+}
+
+/// Count each author's branches by status: the authors with the most stale
+/// branches first, then those with the most branches
+pub fn group_by_author(results: &[BranchHealth]) -> Vec<AuthorSummary> {
+    let mut summaries: Vec<AuthorSummary> = Vec::new();
     for health in results {
-        let status_emoji = match health.status {
-            BranchStatus::Healthy => "🟢",
-            BranchStatus::NeedsAttention => "🟡",
-            BranchStatus::Stale => "🔴",
+        let index = match summaries.iter().position(|s| s.author == health.author) {
+            Some(index) => index,
+            None => {
+                summaries.push(AuthorSummary {
+                    author: health.author.clone(),
+                    branches: 0,
+                    healthy: 0,
+                    needs_attention: 0,
+                    stale: 0,
+                    longest_idle_days: 0,
+                    least_recent_activity: health.last_activity,
+                    stale_branches: Vec::new(),
+                });
+                summaries.len() - 1
+            }
         };
+        let summary = &mut summaries[index];
+        summary.branches += 1;
+        match health.status {
+            BranchStatus::Healthy => summary.healthy += 1,
+            BranchStatus::NeedsAttention => summary.needs_attention += 1,
+            BranchStatus::Stale => {
+                summary.stale += 1;
+                summary.stale_branches.push(health.name.clone());
+            }
+        }
+        summary.longest_idle_days = summary.longest_idle_days.max(health.idle_days);
+        summary.least_recent_activity = summary.least_recent_activity.min(health.last_activity);
+    }
+    summaries.sort_by(|a, b| {
+        b.stale
+            .cmp(&a.stale)
+            .then_with(|| b.branches.cmp(&a.branches))
+            .then_with(|| a.author.cmp(&b.author))
+    });
+    summaries
+}
 
-        output.push_str(&format!("## {}\n\n", health.name));
-        output.push_str("| Metric | Value |\n");
-        output.push_str("|--------|-------|\n");
-        output.push_str(&format!("| Status | {} {} |\n", status_emoji, format!("{:?}", health.status)));
-        output.push_str(&format!("| Age | {} |\n", health.age_display));
-        output.push_str(&format!("| Last Activity | {} |\n", health.last_activity_display));
-        output.push_str(&format!("| Author | {} |\n", health.author));
-        output.push_str(&format!("| Commits | {} |\n", health.commit_count));
-        output.push_str(&format!("| Main Distance | {} ahead, {} behind |\n\n", health.ahead_count, health.behind_count));
+pub fn format_by_author(summaries: &[AuthorSummary], format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(summaries)?),
+        OutputFormat::Markdown => Ok(format_authors_markdown(summaries)),
+        OutputFormat::Csv => Ok(format_authors_csv(summaries)),
+        OutputFormat::Text => Ok(format_authors_text(summaries)),
     }
+}
 
-    Ok(output)
+fn format_authors_text(summaries: &[AuthorSummary]) -> String {
+    let mut output = String::from("Branch Ownership Report\n");
+    output.push_str(&format!(
+        "Last updated: {}\n\n",
+        dates::date_time(Local::now().timestamp())
+    ));
+
+    let width = summaries
+        .iter()
+        .map(|summary| summary.author.chars().count())
+        .chain(["Author".len(), "Total".len()])
+        .max()
+        .unwrap_or(0);
+    output.push_str(&format!(
+        "{:<width$}  Branches  Healthy  Needs Attention  Stale  Least Recent Activity\n",
+        "Author"
+    ));
+    for summary in summaries {
+        // Padded before coloring, as escape codes would count towards the width
+        let stale = format!("{:>5}", summary.stale);
+        output.push_str(&format!(
+            "{:<width$}  {:>8}  {:>7}  {:>15}  {}  {}\n",
+            summary.author,
+            summary.branches,
+            summary.healthy,
+            summary.needs_attention,
+            if summary.stale > 0 {
+                theme::removed(stale).to_string()
+            } else {
+                stale
+            },
+            dates::when(summary.least_recent_activity)
+        ));
+    }
+    let total = |count: fn(&AuthorSummary) -> usize| summaries.iter().map(count).sum::<usize>();
+    output.push_str(&format!(
+        "{:<width$}  {:>8}  {:>7}  {:>15}  {:>5}\n",
+        "Total",
+        total(|s| s.branches),
+        total(|s| s.healthy),
+        total(|s| s.needs_attention),
+        total(|s| s.stale)
+    ));
+
+    let with_stale: Vec<&AuthorSummary> = summaries.iter().filter(|s| s.stale > 0).collect();
+    if !with_stale.is_empty() {
+        output.push_str("\nStale branches:\n");
+        for summary in with_stale {
+            output.push_str(&format!(
+                "  {}: {}\n",
+                summary.author,
+                summary.stale_branches.join(", ")
+            ));
+        }
+    }
+    output
+}
+
+fn format_authors_markdown(summaries: &[AuthorSummary]) -> String {
+    let mut output = String::from("# Branch Ownership Report\n\n");
+    output.push_str(&format!(
+        "*Last updated: {}*\n\n",
+        dates::date_time(Local::now().timestamp())
+    ));
+
+    output.push_str(
+        "| Author | Branches | 🟢 Healthy | 🟡 Needs Attention | 🔴 Stale | Least Recent Activity | Stale Branches |\n",
+    );
+    output.push_str(
+        "|--------|----------|------------|--------------------|----------|-----------------------|----------------|\n",
+    );
+    for summary in summaries {
+        let stale_branches: Vec<String> = summary
+            .stale_branches
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect();
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} |\n",
+            escape_table_cell(&summary.author),
+            summary.branches,
+            summary.healthy,
+            summary.needs_attention,
+            summary.stale,
+            dates::when(summary.least_recent_activity),
+            stale_branches.join(", ")
+        ));
+    }
+    let total = |count: fn(&AuthorSummary) -> usize| summaries.iter().map(count).sum::<usize>();
+    output.push_str(&format!(
+        "| **Total** | **{}** | **{}** | **{}** | **{}** | | |\n",
+        total(|s| s.branches),
+        total(|s| s.healthy),
+        total(|s| s.needs_attention),
+        total(|s| s.stale)
+    ));
+    output
+}
+
+fn format_authors_csv(summaries: &[AuthorSummary]) -> String {
+    let mut rows = vec![
+        "author,branches,healthy,needs_attention,stale,longest_idle_days,stale_branches"
+            .to_string(),
+    ];
+    for summary in summaries {
+        let fields = [
+            summary.author.clone(),
+            summary.branches.to_string(),
+            summary.healthy.to_string(),
+            summary.needs_attention.to_string(),
+            summary.stale.to_string(),
+            summary.longest_idle_days.to_string(),
+            summary.stale_branches.join(" "),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| escape_csv_field(field)).collect();
+        rows.push(row.join(","));
+    }
+    rows.join("\n")
+}
+
+/// Escape characters that would break a markdown table cell
+fn escape_table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Derive a feature branch name such as `feat/add-help-topics` from a commit message
+pub fn suggest_branch_name(message: &str) -> String {
+    let subject = message.lines().next().unwrap_or("").trim();
+
+    let (prefix, description) = match subject.split_once(": ") {
+        Some((kind, description)) => {
+            let kind = kind.split('(').next().unwrap_or("").trim_end_matches('!');
+            if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic()) {
+                (kind.to_lowercase(), description)
+            } else {
+                ("feature".to_string(), subject)
+            }
+        }
+        None => ("feature".to_string(), subject),
+    };
+
+    let mut slug = String::new();
+    for word in description
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if slug.len() + word.len() + 1 > 40 {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_lowercase());
+    }
+
+    if slug.is_empty() {
+        slug.push_str("my-change");
+    }
+
+    format!("{}/{}", prefix, slug)
 }
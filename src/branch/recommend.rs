@@ -0,0 +1,308 @@
+use super::{BranchHealth, BranchStatus};
+use serde::Serialize;
+
+pub const SYSTEM_PROMPT: &str = r#"You review the branches of a git repository and tell the team what to do with them.
+Reply in exactly this format, with nothing before or after it:
+SCORE: <branch hygiene from 0 to 100> | <one sentence about the branches overall>
+BRANCH: <name exactly as listed> | <delete, rebase, merge or split> | <why, in a few words>
+
+Give a BRANCH line only for branches that need one of these actions; leave out the rest:
+- delete: stale, or everything on it is already in the main branch
+- rebase: far behind the main branch, so it should be rebased onto it
+- merge: ready or nearly ready, so it should be merged soon before it drifts
+- split: too many commits to review as one, so it should be split into smaller branches
+Never recommend an action for the main branch itself."#;
+
+/// Branches described to the AI; the most neglected ones are kept when there are more
+const MAX_PROMPT_BRANCHES: usize = 150;
+
+/// Commits behind the main branch that call for a rebase, without the AI
+const FAR_BEHIND: u32 = 20;
+
+/// Commits ahead of the main branch that call for a split, without the AI
+const TOO_BIG: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Delete,
+    Rebase,
+    Merge,
+    Split,
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Delete => "delete",
+            Action::Rebase => "rebase onto main",
+            Action::Merge => "merge soon",
+            Action::Split => "split",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        [
+            ("delete", Action::Delete),
+            ("rebase", Action::Rebase),
+            ("merge", Action::Merge),
+            ("split", Action::Split),
+        ]
+        .into_iter()
+        .find(|(word, _)| text.starts_with(word))
+        .map(|(_, action)| action)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recommendation {
+    pub branch: String,
+    pub action: Action,
+    pub reason: String,
+}
+
+/// What to do with the branches, from `gyst branch health --recommend`
+#[derive(Debug, Clone, Serialize)]
+pub struct Recommendations {
+    /// Branch hygiene from 0 to 100
+    pub score: u8,
+    pub summary: String,
+    pub branches: Vec<Recommendation>,
+}
+
+impl Recommendations {
+    /// The recommendation for the branch named `name`, if there is one
+    pub fn for_branch(&self, name: &str) -> Option<&Recommendation> {
+        self.branches.iter().find(|r| r.branch == name)
+    }
+
+    /// Recommendations worked out from the numbers alone, for when the AI is
+    /// not used. The score is the share of healthy branches, with those that
+    /// need attention counting half.
+    pub fn offline(results: &[BranchHealth]) -> Self {
+        let branches: Vec<&BranchHealth> = results.iter().filter(|h| !is_main(h)).collect();
+        let mut recommendations = Vec::new();
+        for health in &branches {
+            let (action, reason) = if health.ahead_count == 0 {
+                (
+                    Action::Delete,
+                    "everything on it is already in main".to_string(),
+                )
+            } else if health.status == BranchStatus::Stale {
+                (
+                    Action::Delete,
+                    format!("no commits for {} days", health.idle_days),
+                )
+            } else if health.behind_count >= FAR_BEHIND {
+                (
+                    Action::Rebase,
+                    format!("{} commits behind main", health.behind_count),
+                )
+            } else if health.ahead_count >= TOO_BIG {
+                (
+                    Action::Split,
+                    format!("{} commits are a lot to review at once", health.ahead_count),
+                )
+            } else if health.pull_request.is_some() && health.status == BranchStatus::Healthy {
+                (
+                    Action::Merge,
+                    "active, with an open pull request".to_string(),
+                )
+            } else {
+                continue;
+            };
+            recommendations.push(Recommendation {
+                branch: health.name.clone(),
+                action,
+                reason,
+            });
+        }
+
+        let weight: f64 = branches
+            .iter()
+            .map(|h| match h.status {
+                BranchStatus::Healthy => 1.0,
+                BranchStatus::NeedsAttention => 0.5,
+                BranchStatus::Stale => 0.0,
+            })
+            .sum();
+        let score = if branches.is_empty() {
+            100
+        } else {
+            (100.0 * weight / branches.len() as f64).round() as u8
+        };
+        let stale = branches
+            .iter()
+            .filter(|h| h.status == BranchStatus::Stale)
+            .count();
+        let summary = match (branches.len(), stale) {
+            (0, _) => "There are no branches besides the main branch.".to_string(),
+            (_, 0) => format!(
+                "None of the {} branches is stale; {} could use some work.",
+                branches.len(),
+                recommendations.len()
+            ),
+            (total, stale) => format!("{} of {} branches are stale.", stale, total),
+        };
+        Self {
+            score,
+            summary,
+            branches: recommendations,
+        }
+    }
+}
+
+/// The names in a prompt: the real ones, or placeholders such as `branch-3`
+/// and `author-1` that keep names and authors from being sent
+pub struct Names {
+    anonymous: bool,
+    branches: Vec<String>,
+    authors: Vec<String>,
+}
+
+impl Names {
+    fn branch(&mut self, name: &str) -> String {
+        if !self.anonymous {
+            return name.to_string();
+        }
+        format!("branch-{}", index_of(&mut self.branches, name))
+    }
+
+    fn author(&mut self, name: &str) -> String {
+        if !self.anonymous {
+            return name.to_string();
+        }
+        format!("author-{}", index_of(&mut self.authors, name))
+    }
+
+    /// The real name of a branch the AI named
+    fn real_branch(&self, name: &str) -> Option<String> {
+        if !self.anonymous {
+            return Some(name.to_string());
+        }
+        let index: usize = name.strip_prefix("branch-")?.parse().ok()?;
+        self.branches.get(index.checked_sub(1)?).cloned()
+    }
+}
+
+/// The 1-based position of `name` in `names`, added if it is new
+fn index_of(names: &mut Vec<String>, name: &str) -> usize {
+    match names.iter().position(|n| n == name) {
+        Some(index) => index + 1,
+        None => {
+            names.push(name.to_string());
+            names.len()
+        }
+    }
+}
+
+/// Describe the branches for the AI. With `anonymous`, branch and author
+/// names are replaced by placeholders; the returned `Names` maps them back.
+pub fn build_prompt(results: &[BranchHealth], anonymous: bool) -> (String, Names) {
+    let mut names = Names {
+        anonymous,
+        branches: Vec::new(),
+        authors: Vec::new(),
+    };
+
+    // The longest idle first, so the ones that need attention make the cut
+    let mut branches: Vec<&BranchHealth> = results.iter().collect();
+    branches.sort_by_key(|h| std::cmp::Reverse(h.idle_days));
+    branches.truncate(MAX_PROMPT_BRANCHES);
+
+    let mut prompt = format!(
+        "The repository has {} branches. Each line is: branch | status | days since the last commit | author | commits ahead, behind the main branch | upstream | pull request\n\n",
+        results.len()
+    );
+    for health in branches {
+        let main = if is_main(health) {
+            " (the main branch)"
+        } else {
+            ""
+        };
+        let upstream = match &health.upstream {
+            Some(upstream) => format!(
+                "{} ahead, {} behind its upstream",
+                upstream.ahead, upstream.behind
+            ),
+            None if health.is_remote => "remote branch".to_string(),
+            None => "not pushed".to_string(),
+        };
+        let pull_request = health
+            .pull_request
+            .as_ref()
+            .map_or("none".to_string(), |pr| {
+                format!("open, {}", pr.review_state)
+            });
+        prompt.push_str(&format!(
+            "{}{} | {:?} | {} | {} | {} ahead, {} behind | {} | {}\n",
+            names.branch(&health.name),
+            main,
+            health.status,
+            health.idle_days,
+            names.author(&health.author),
+            health.ahead_count,
+            health.behind_count,
+            upstream,
+            pull_request
+        ));
+    }
+    if results.len() > MAX_PROMPT_BRANCHES {
+        prompt.push_str(&format!(
+            "... and {} more branches with more recent activity\n",
+            results.len() - MAX_PROMPT_BRANCHES
+        ));
+    }
+    (prompt, names)
+}
+
+/// Read the score and recommendations from a reply in the SCORE/BRANCH
+/// format, or `None` if it has no score. Branches that are not in the report
+/// are dropped.
+pub fn parse_reply(
+    reply: &str,
+    names: &Names,
+    results: &[BranchHealth],
+) -> Option<Recommendations> {
+    let mut score = None;
+    let mut branches: Vec<Recommendation> = Vec::new();
+    for line in reply.lines().map(str::trim) {
+        if let Some(text) = line.strip_prefix("SCORE:") {
+            let (value, summary) = text.split_once('|').unwrap_or((text, ""));
+            let value: u8 = value.trim().trim_end_matches("/100").trim().parse().ok()?;
+            score = Some((value.min(100), summary.trim().to_string()));
+        } else if let Some(text) = line.strip_prefix("BRANCH:") {
+            let mut parts = text.splitn(3, '|').map(str::trim);
+            let (Some(name), Some(action), reason) = (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            let Some(branch) = names.real_branch(name.trim_matches('`')) else {
+                continue;
+            };
+            let Some(action) = Action::parse(action) else {
+                continue;
+            };
+            if results.iter().any(|h| h.name == branch)
+                && !branches.iter().any(|r| r.branch == branch)
+            {
+                branches.push(Recommendation {
+                    branch,
+                    action,
+                    reason: reason.unwrap_or("").to_string(),
+                });
+            }
+        }
+    }
+    let (score, summary) = score?;
+    Some(Recommendations {
+        score,
+        summary,
+        branches,
+    })
+}
+
+fn is_main(health: &BranchHealth) -> bool {
+    health.is_base() || health.branch_name() == "HEAD"
+}
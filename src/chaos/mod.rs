@@ -0,0 +1,300 @@
+//! Failure injection for requests to the AI provider and the gyst server.
+//!
+//! Setting `GYST_CHAOS` to a comma-separated list of faults sends the next
+//! requests to a local server that misbehaves in the listed ways, in order.
+//! `name` affects one request, `name:N` the next N, and `name:*` every
+//! remaining one. Once the list is used up, requests go out normally.
+//!
+//! ```text
+//! GYST_CHAOS=rate-limit,timeout:2,ok gyst commit
+//! ```
+
+use anyhow::{Result, anyhow};
+use reqwest::{Client, RequestBuilder, Response};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const ENV_VAR: &str = "GYST_CHAOS";
+
+/// How long a request to the `timeout` fault waits before giving up
+const TIMEOUT: Duration = Duration::from_millis(250);
+
+const JSON: &str = "application/json";
+
+/// The reply text of the `ok` fault
+const CANNED_MESSAGE: &str = "chore: canned response from GYST_CHAOS";
+
+/// The faults requests are answered with, oldest first. `None` when chaos mode is off.
+static PLAN: OnceLock<Option<Mutex<VecDeque<Step>>>> = OnceLock::new();
+
+/// Address of the misbehaving server, started on first use
+static SERVER: tokio::sync::OnceCell<SocketAddr> = tokio::sync::OnceCell::const_new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    /// The server accepts the request and never answers
+    Timeout,
+    /// 429 Too Many Requests with a `Retry-After` header
+    RateLimit,
+    /// 200 OK with a body that is not valid JSON
+    Malformed,
+    /// 200 OK, but the connection closes halfway through the body
+    Partial,
+    /// A well-formed successful reply with a canned message
+    Ok,
+}
+
+impl Fault {
+    const ALL: &[(&str, Fault)] = &[
+        ("timeout", Fault::Timeout),
+        ("rate-limit", Fault::RateLimit),
+        ("malformed", Fault::Malformed),
+        ("partial", Fault::Partial),
+        ("ok", Fault::Ok),
+    ];
+
+    fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, fault)| *fault == self)
+            .map_or("ok", |(name, _)| name)
+    }
+}
+
+#[derive(Debug)]
+struct Step {
+    fault: Fault,
+    /// `None` repeats the fault forever
+    remaining: Option<u32>,
+}
+
+/// Read `GYST_CHAOS`. Fails on fault names it does not know, so a typo does
+/// not silently turn chaos mode off.
+pub fn init() -> Result<()> {
+    let plan = match std::env::var(ENV_VAR) {
+        Ok(spec) if !spec.trim().is_empty() => Some(Mutex::new(parse(&spec)?)),
+        _ => None,
+    };
+    let _ = PLAN.set(plan);
+    Ok(())
+}
+
+fn parse(spec: &str) -> Result<VecDeque<Step>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (name, count) = item.split_once(':').unwrap_or((item, "1"));
+            let fault = Fault::ALL
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|(_, fault)| *fault)
+                .ok_or_else(|| {
+                    let names: Vec<_> = Fault::ALL.iter().map(|(name, _)| *name).collect();
+                    anyhow!(
+                        "Unknown {} fault '{}'. Use one of {}",
+                        ENV_VAR,
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+            let remaining = match count {
+                "*" => None,
+                count => Some(count.parse().map_err(|_| {
+                    anyhow!("{} count must be a number or '*', not '{}'", ENV_VAR, count)
+                })?),
+            };
+            Ok(Step { fault, remaining })
+        })
+        .collect()
+}
+
+/// The fault for the next request, if any are left
+fn next_fault() -> Option<Fault> {
+    let mut plan = PLAN.get()?.as_ref()?.lock().ok()?;
+    loop {
+        let step = plan.front_mut()?;
+        match &mut step.remaining {
+            None => return Some(step.fault),
+            Some(0) => {
+                plan.pop_front();
+            }
+            Some(remaining) => {
+                *remaining -= 1;
+                return Some(step.fault);
+            }
+        }
+    }
+}
+
+/// Send a request, or in chaos mode, send it to the misbehaving server for the
+/// next fault. The caller sees the same errors and responses a real server
+/// failing that way would produce.
+pub async fn send(client: &Client, request: RequestBuilder) -> reqwest::Result<Response> {
+    let mut request = request.build()?;
+    if let Some(fault) = next_fault()
+        && let Ok(addr) = SERVER.get_or_try_init(start_server).await
+    {
+        let url = format!("http://{}/{}{}", addr, fault.name(), request.url().path());
+        if let Ok(url) = url.parse() {
+            *request.url_mut() = url;
+        }
+        if fault == Fault::Timeout {
+            *request.timeout_mut() = Some(TIMEOUT);
+        }
+    }
+    client.execute(request).await
+}
+
+async fn start_server() -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream));
+        }
+    });
+    Ok(addr)
+}
+
+/// Answer one request according to the fault at the start of its path
+async fn serve(mut stream: TcpStream) -> std::io::Result<()> {
+    let path = read_request(&mut stream).await?;
+    let (fault, endpoint) = path
+        .trim_start_matches('/')
+        .split_once('/')
+        .unwrap_or((path.as_str(), ""));
+    let fault = Fault::ALL
+        .iter()
+        .find(|(name, _)| *name == fault)
+        .map_or(Fault::Ok, |(_, fault)| *fault);
+
+    let (content_type, body) = canned_body(endpoint);
+    match fault {
+        Fault::Timeout => {
+            // Hold the connection open until the client gives up
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        }
+        Fault::RateLimit => {
+            let body = r#"{"type":"error","error":{"type":"rate_limit_error","message":"Rate limited by GYST_CHAOS"}}"#;
+            write_response(
+                &mut stream,
+                "429 Too Many Requests",
+                "Retry-After: 1\r\n",
+                JSON,
+                body,
+                body.len(),
+            )
+            .await?;
+        }
+        Fault::Malformed => {
+            let body = "{\"content\": [{\"type\": \"text\", \"text\": <html>";
+            write_response(&mut stream, "200 OK", "", JSON, body, body.len()).await?;
+        }
+        Fault::Partial => {
+            // Promise the whole body, send half of it, then hang up
+            let half = &body[..body.len() / 2];
+            write_response(&mut stream, "200 OK", "", content_type, half, body.len()).await?;
+        }
+        Fault::Ok => {
+            write_response(&mut stream, "200 OK", "", content_type, &body, body.len()).await?;
+        }
+    }
+    stream.shutdown().await
+}
+
+/// A successful reply in the shape the endpoint returns, with its content
+/// type. Server endpoints are matched with or without their API version
+/// (`api/v1/commit`); streaming ones send the reply as two events.
+fn canned_body(endpoint: &str) -> (&'static str, String) {
+    let text = serde_json::Value::from(CANNED_MESSAGE);
+    let server_endpoint = endpoint.strip_prefix("api/").map(|path| {
+        path.split_once('/')
+            .filter(|(version, _)| {
+                version
+                    .strip_prefix('v')
+                    .is_some_and(|n| n.parse::<u32>().is_ok())
+            })
+            .map_or(path, |(_, rest)| rest)
+    });
+    let body = match server_endpoint.unwrap_or_default() {
+        "commit" => format!(r#"{{"message":{}}}"#, text),
+        "commit/suggestions" => format!(r#"{{"suggestions":[{}]}}"#, text),
+        "command" => format!(r#"{{"suggestion":{}}}"#, text),
+        "commit/stream" | "command/stream" => {
+            let (first, rest) = CANNED_MESSAGE.split_at(CANNED_MESSAGE.len() / 2);
+            let events = format!(
+                "event: delta\ndata: {}\n\nevent: delta\ndata: {}\n\nevent: done\ndata: {{}}\n\n",
+                serde_json::json!({ "text": first }),
+                serde_json::json!({ "text": rest })
+            );
+            return ("text/event-stream", events);
+        }
+        "health" => r#"{"status":"ok"}"#.to_string(),
+        "usage" => {
+            r#"{"requests":12,"input_tokens":34567,"output_tokens":2345,"estimated_cost":0.05}"#
+                .to_string()
+        }
+        _ => format!(r#"{{"content":[{{"type":"text","text":{}}}]}}"#, text),
+    };
+    (JSON, body)
+}
+
+/// Read the request head and body, returning the request path
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut data = Vec::new();
+    let mut buf = [0; 8192];
+    let head_end = loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(String::new());
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let content_length: usize = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse().ok())?
+        })
+        .unwrap_or(0);
+    while data.len() - head_end < content_length {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+
+    Ok(head
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string())
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &str,
+    content_type: &str,
+    body: &str,
+    content_length: usize,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status, content_type, content_length, headers, body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await
+}
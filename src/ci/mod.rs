@@ -0,0 +1,52 @@
+//! Machine mode for CI pipelines.
+//!
+//! On a CI runner gyst never prompts, prints no colors, writes `--verbose`
+//! logs as JSON lines, and exits with `exit::Status::Config` instead of
+//! asking for missing settings. `GYST_CI=0` turns detection off, `GYST_CI=1`
+//! forces it.
+
+use std::sync::OnceLock;
+
+pub const ENV_VAR: &str = "GYST_CI";
+
+/// Variables CI services set, with the service's name. `CI` comes last as the
+/// catch-all most other services set.
+const PROVIDERS: &[(&str, &str)] = &[
+    ("GITHUB_ACTIONS", "GitHub Actions"),
+    ("GITLAB_CI", "GitLab CI"),
+    ("BUILDKITE", "Buildkite"),
+    ("CIRCLECI", "CircleCI"),
+    ("TF_BUILD", "Azure Pipelines"),
+    ("JENKINS_URL", "Jenkins"),
+    ("TEAMCITY_VERSION", "TeamCity"),
+    ("CI", "CI"),
+];
+
+static PROVIDER: OnceLock<Option<&'static str>> = OnceLock::new();
+
+/// The CI service gyst is running on, if any. The environment is read once.
+pub fn provider() -> Option<&'static str> {
+    *PROVIDER.get_or_init(detect)
+}
+
+pub fn active() -> bool {
+    provider().is_some()
+}
+
+fn detect() -> Option<&'static str> {
+    if let Ok(value) = std::env::var(ENV_VAR) {
+        return match value.trim().to_lowercase().as_str() {
+            "" | "0" | "off" | "false" => None,
+            _ => Some("CI"),
+        };
+    }
+    PROVIDERS
+        .iter()
+        .find(|(var, _)| {
+            std::env::var(var).is_ok_and(|value| {
+                let value = value.trim().to_lowercase();
+                !matches!(value.as_str(), "" | "0" | "false")
+            })
+        })
+        .map(|(_, name)| *name)
+}
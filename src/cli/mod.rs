@@ -1,13 +1,50 @@
+use crate::completions;
 use clap::{Parser, Subcommand};
+use clap_complete::{ArgValueCandidates, Shell};
 
 #[derive(Parser)]
 #[command(name = "gyst")]
 #[command(author = "Varun V")]
 #[command(version = "0.1.2")]
+#[command(disable_help_subcommand = true)]
 #[command(
     about = "AI-powered Git assistant for commits, branch management, and more. Visit https://gyst-cli.vercel.app for more information."
 )]
 pub struct Cli {
+    /// Run as if gyst was started in <PATH> instead of the current directory
+    #[arg(
+        short = 'C',
+        long = "repo",
+        global = true,
+        value_name = "PATH",
+        default_value = "."
+    )]
+    pub repo: String,
+
+    /// Wait for another gyst operation on the same repository to finish instead of failing
+    #[arg(long, global = true)]
+    pub wait: bool,
+
+    /// Log prompts, the model, token counts, HTTP statuses and timings to stderr (or set GYST_LOG=1)
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Print the prompt that would be sent to the AI and exit without sending it
+    #[arg(long, global = true)]
+    pub show_prompt: bool,
+
+    /// Print only results, prompts and errors: no status lines, notices or spinners
+    #[arg(long, global = true)]
+    pub quiet: bool,
+
+    /// Answer yes to every confirmation, such as staging all changes or using the generated message
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// Print no colors or emoji (or set NO_COLOR)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -28,13 +65,108 @@ pub enum Commands {
         /// Push changes after committing
         #[arg(short, long)]
         push: bool,
+
+        /// Message template to use instead of commit.template (conventional, plain, gitmoji, angular, jira-prefixed, or a custom one)
+        #[arg(short, long, value_name = "NAME", add = ArgValueCandidates::new(completions::templates))]
+        template: Option<String>,
+
+        /// Add a Co-authored-by trailer, e.g. --co-author "Jane Doe <jane@example.com>" (repeatable)
+        #[arg(long = "co-author", value_name = "NAME <EMAIL>")]
+        co_authors: Vec<String>,
+
+        /// After pushing, open a pull request (merge request on GitLab) for the branch; the push and the request are confirmed first unless --quick
+        #[arg(long, requires = "push")]
+        create_pr: bool,
+
+        /// Remote to push to with --push (repeatable, or `all`); defaults to git.push_remotes
+        #[arg(long = "remote", value_name = "NAME", requires = "push", add = ArgValueCandidates::new(completions::remotes))]
+        remotes: Vec<String>,
+
+        /// Commit even if the current branch is protected (see git.protected_branches)
+        #[arg(short, long)]
+        force: bool,
+
+        /// Create a "fixup! <subject>" commit for REF; without REF, pick from recent commits touching the staged files
+        #[arg(long, value_name = "REF", num_args = 0..=1, default_missing_value = "auto", add = ArgValueCandidates::new(completions::refs))]
+        fixup: Option<String>,
+
+        /// Like --fixup, but create a "squash! <subject>" commit
+        #[arg(
+            long,
+            value_name = "REF",
+            num_args = 0..=1,
+            default_missing_value = "auto",
+            conflicts_with = "fixup",
+            add = ArgValueCandidates::new(completions::refs)
+        )]
+        squash: Option<String>,
+
+        /// Attach the commit to an issue: #42 on GitHub/GitLab, or a Jira or Linear key like ABC-123. Its title goes into the prompt and a Fixes/Closes/Refs footer is added.
+        #[arg(long, value_name = "ISSUE", conflicts_with_all = ["fixup", "squash"])]
+        link: Option<String>,
+
+        /// Split the staged changes into several commits: the AI proposes groups of files, which you can rearrange before they are committed
+        #[arg(long, conflicts_with_all = ["fixup", "squash", "link"])]
+        split: bool,
+
+        /// Pick which staged files go into this commit; the others are unstaged while it is made and staged again afterwards
+        #[arg(long, conflicts_with = "split")]
+        interactive_files: bool,
+
+        /// Commit only the working tree changes matching PATHSPEC, like 'git commit -- <pathspec>'; the rest of the index is left as it was
+        #[arg(long, value_name = "PATHSPEC", num_args = 1.., conflicts_with_all = ["split", "interactive_files"])]
+        only: Vec<String>,
+
+        /// Copy the commit message to the clipboard (or set ui.copy)
+        #[arg(long)]
+        copy: bool,
+
+        /// Model to use instead of ai.model (direct API mode)
+        #[arg(long, value_name = "MODEL", add = ArgValueCandidates::new(completions::models))]
+        model: Option<String>,
     },
 
     /// Get multiple commit message suggestions
     ///
     /// Generates three different commit message options for you to choose from.
     /// If no changes are staged, offers to stage all changes first.
-    Suggest,
+    Suggest {
+        /// Number of suggestions to generate
+        #[arg(short = 'n', long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=10))]
+        count: u8,
+
+        /// Print the suggestions one per line without colors or prompts and exit (for scripts and pickers like fzf)
+        #[arg(long)]
+        pipe: bool,
+
+        /// With --pipe, separate suggestions with NUL instead of newlines and keep multi-line messages intact
+        #[arg(short = 'z', long, requires = "pipe")]
+        null: bool,
+
+        /// Message template to use instead of commit.template (conventional, plain, gitmoji, angular, jira-prefixed, or a custom one)
+        #[arg(short, long, value_name = "NAME", add = ArgValueCandidates::new(completions::templates))]
+        template: Option<String>,
+
+        /// Add a Co-authored-by trailer, e.g. --co-author "Jane Doe <jane@example.com>" (repeatable)
+        #[arg(long = "co-author", value_name = "NAME <EMAIL>")]
+        co_authors: Vec<String>,
+
+        /// Commit even if the current branch is protected (see git.protected_branches)
+        #[arg(short, long)]
+        force: bool,
+
+        /// Copy the chosen message to the clipboard (or set ui.copy)
+        #[arg(long, conflicts_with = "pipe")]
+        copy: bool,
+
+        /// In a monorepo, when the staged changes span several packages, offer one commit per package, each with its own message, instead of one commit for all
+        #[arg(long, conflicts_with = "pipe")]
+        by_package: bool,
+
+        /// Model to use instead of ai.model (direct API mode)
+        #[arg(long, value_name = "MODEL", add = ArgValueCandidates::new(completions::models))]
+        model: Option<String>,
+    },
 
     /// Get AI-powered suggestions for Git commands
     ///
@@ -44,8 +176,68 @@ pub enum Commands {
         /// Description of what you want to do (e.g., "undo last commit")
         #[arg(value_name = "DESCRIPTION")]
         description: String,
+
+        /// Model to use instead of ai.model (direct API mode)
+        #[arg(long, value_name = "MODEL", add = ArgValueCandidates::new(completions::models))]
+        model: Option<String>,
+
+        /// Explain it for someone new to git, in plain words
+        #[arg(long)]
+        eli5: bool,
+
+        /// Offer to run each suggested command; destructive ones need their name typed first
+        #[arg(long)]
+        run: bool,
+    },
+
+    /// Set up gyst step by step
+    ///
+    /// Asks how to reach the AI (the gyst server or your own Anthropic API
+    /// key, which is checked with a test request), the commit message style
+    /// and language, and writes ~/.gyst/config.toml. Runs by itself the first
+    /// time an AI command is used without a configuration.
+    Init,
+
+    /// Show the usage statistics recorded on this machine
+    ///
+    /// Nothing is recorded unless you turn it on with 'gyst config set
+    /// telemetry.enabled true'. Only command names, how long they took and
+    /// whether they succeeded are kept, in ~/.gyst.
+    Stats {
+        /// Delete the recorded statistics
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Show or clear the generated messages you edited or rejected
+    ///
+    /// gyst commit records the messages you change in the editor or turn
+    /// down, in ~/.gyst/feedback (turn this off with 'gyst config set
+    /// feedback.record false'). Show your recent edits to the AI as examples
+    /// of your style with 'gyst config set feedback.examples 3'.
+    Feedback {
+        #[command(subcommand)]
+        command: FeedbackCommands,
+    },
+
+    /// Show what the gyst server has counted for your token this month
+    ///
+    /// Lists the requests, input and output tokens, and the estimated cost
+    /// the server recorded for server.token (or GYST_SERVER_TOKEN).
+    Usage {
+        /// The month to show, e.g. 2026-09 (default: this month)
+        #[arg(long)]
+        month: Option<String>,
     },
 
+    /// Check that gyst can work here and explain how to fix what is missing
+    ///
+    /// Checks the git repository, libgit2 and the git executable, that the
+    /// configuration can be read, that the AI provider or gyst server is
+    /// reachable (through HTTPS_PROXY if set) and accepts the API key, and that
+    /// EDITOR points at an installed editor. Exits with an error if a check fails.
+    Doctor,
+
     /// Configure gyst settings
     ///
     /// Manage configuration settings including API keys and server preferences.
@@ -54,10 +246,18 @@ pub enum Commands {
     /// Server mode: Uses the gyst cloud service for AI operations (default)
     /// Direct API mode: Uses your API key directly (requires --api-key)
     ///
-    /// Use --show to view current settings, --api-key to set API key,
-    /// --use-server to toggle between server and direct API modes.
+    /// `gyst config list` shows every setting, and `get`, `set` and `unset`
+    /// read and change one by name. The older --show, --api-key and
+    /// --use-server flags still work.
+    ///
+    /// `export` and `import` share conventions, templates, branch rules and
+    /// labels with a team; secrets such as API keys and tokens are never included.
+    #[command(args_conflicts_with_subcommands = true)]
     Config {
-        /// Set the OpenAI API key
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+
+        /// Set the API key (same as 'gyst config set ai.api_key <key>')
         #[arg(long)]
         api_key: Option<String>,
 
@@ -65,20 +265,81 @@ pub enum Commands {
         #[arg(short, long)]
         show: bool,
 
-        /// Enable or disable server mode (default: true)
+        /// Enable or disable server mode (default: true); same as 'gyst config set server.use_server <bool>'
         /// When enabled, uses gyst cloud service for AI operations (no API key needed)
         /// When disabled, uses your API key directly with the Anthropic API
         #[arg(long)]
         use_server: Option<bool>,
     },
 
+    /// Print the shell completion script
+    ///
+    /// Add `source <(gyst completions bash)` to ~/.bashrc (or the equivalent for
+    /// your shell). Branch, tag, remote and template names are completed from
+    /// the current repository.
+    Completions {
+        /// bash, zsh, fish, powershell or elvish
+        shell: Shell,
+    },
+
     /// Show detailed analysis of staged changes
     ///
     /// Displays a comprehensive diff view including:
     /// - Summary of changes (files, insertions, deletions)
     /// - List of added, modified, deleted, and renamed files
     /// - Detailed changes with syntax highlighting
-    Diff,
+    Diff {
+        /// Show the commit and author that last changed each removed line
+        #[arg(long)]
+        blame: bool,
+    },
+
+    /// Show where the repository stands and what to do next
+    ///
+    /// Combines the branch, its upstream, any merge or rebase in progress and
+    /// the staged, unstaged and untracked files with a one-line summary and
+    /// suggested next commands. The AI summary needs direct API mode;
+    /// otherwise both are worked out from the state itself.
+    #[command(alias = "st")]
+    Status {
+        /// Work out the summary and next steps without the AI
+        #[arg(long)]
+        no_ai: bool,
+    },
+
+    /// Summarize recent work into a standup update
+    ///
+    /// Collects your commits since the given time plus any uncommitted work
+    /// and writes a short Slack-friendly update. The AI summary needs direct
+    /// API mode; otherwise the raw activity is listed.
+    Today {
+        /// Start of the period: 24h, 3d, 1w, yesterday, monday, or a date like 2024-05-01
+        #[arg(long, default_value = "24h")]
+        since: String,
+
+        /// Only include commits by this author (name or email); defaults to you
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Include commits from all authors
+        #[arg(long, conflicts_with = "author")]
+        all: bool,
+
+        /// List the activity without an AI summary
+        #[arg(long)]
+        no_ai: bool,
+    },
+
+    /// Show workflow guides or help for a command
+    ///
+    /// Topics (daily, release, cleanup) walk through common workflows using
+    /// branch names and conventions detected in the current repository.
+    /// Any command name shows that command's usage instead.
+    Help {
+        /// Topic or command name (omit to list topics)
+        #[arg(value_name = "TOPIC")]
+        topic: Option<String>,
+    },
 
     /// Analyze and manage git branches
     ///
@@ -88,6 +349,278 @@ pub enum Commands {
         #[command(subcommand)]
         command: BranchCommands,
     },
+
+    /// Search and explore commit history
+    History {
+        #[command(subcommand)]
+        command: HistoryCommands,
+    },
+
+    /// Prepare releases: release notes and changelogs
+    Release {
+        #[command(subcommand)]
+        command: ReleaseCommands,
+    },
+
+    /// Work with semantic versions
+    Version {
+        #[command(subcommand)]
+        command: VersionCommands,
+    },
+
+    /// Open a GitHub pull request or GitLab merge request for the current branch
+    /// with a generated description
+    ///
+    /// The description comes from the branch's commits and diff, fills in the
+    /// repository's description template if it has one, closes the issue named in
+    /// the branch (e.g. `fix/123-crash`), and labels follow the commit types
+    /// (`forge.labels`).
+    Pr {
+        /// Branch to merge into (default: the repository's default branch)
+        #[arg(long, add = ArgValueCandidates::new(completions::branches))]
+        base: Option<String>,
+
+        /// Use this title instead of a generated one
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Open the pull request as a draft
+        #[arg(long)]
+        draft: bool,
+
+        /// Push the branch to origin first
+        #[arg(long)]
+        push: bool,
+
+        /// Description template to fill in, by file name without `.md`
+        /// (from `.github/` or `.gitlab/merge_request_templates/`)
+        #[arg(long)]
+        template: Option<String>,
+
+        /// Do not add labels
+        #[arg(long)]
+        no_labels: bool,
+
+        /// Do not use the AI for the description
+        #[arg(long)]
+        no_ai: bool,
+
+        /// Copy the description to the clipboard (or set ui.copy)
+        #[arg(long)]
+        copy: bool,
+    },
+
+    /// Create an annotated tag with a message summarizing the changes, or list tags
+    ///
+    /// Without a name (or with `list`), lists the tags newest first.
+    #[command(args_conflicts_with_subcommands = true)]
+    Tag {
+        #[command(subcommand)]
+        command: Option<TagCommands>,
+
+        /// Name of the tag to create, e.g. v1.4.0
+        name: Option<String>,
+
+        /// Commit to tag
+        #[arg(long, default_value = "HEAD", add = ArgValueCandidates::new(completions::refs))]
+        target: String,
+
+        /// Summarize the changes after this tag or commit (default: the previous tag)
+        #[arg(long, add = ArgValueCandidates::new(completions::refs))]
+        from: Option<String>,
+
+        /// Do not use the AI for the tag message
+        #[arg(long)]
+        no_ai: bool,
+    },
+
+    /// Check how staged changes affect the public API of a Rust library
+    ///
+    /// Lists the public items that were added, removed or changed, and the
+    /// semantic version bump they call for. Removed or changed items are breaking.
+    SemverCheck {
+        /// Compare HEAD with this tag or commit instead of the staged changes with HEAD
+        #[arg(long, add = ArgValueCandidates::new(completions::refs))]
+        since: Option<String>,
+    },
+
+    /// Review, describe and commit the staged changes on one screen
+    ///
+    /// Shows the staged files, the diff of the selected file, commit message
+    /// suggestions and the health of the local branches. Move between the panes
+    /// with Tab, generate suggestions with g and commit the selected one with Enter.
+    Ui {
+        /// Number of suggestions to generate (1-10)
+        #[arg(short = 'n', long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=10))]
+        count: u8,
+
+        /// Commit even if the current branch is protected (see git.protected_branches)
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Commit work in progress to a shadow branch as you change files
+    ///
+    /// Watches the working tree and, once nothing has changed for --interval
+    /// seconds, commits everything (untracked files included, ignored ones
+    /// left out) to gyst/wip/<branch> with a generated message. Your branch,
+    /// index and working tree are never touched. Press Enter to snapshot right
+    /// away and q or Ctrl-C to stop. Squash the snapshots later with
+    /// `git merge --squash gyst/wip/<branch>`.
+    Watch {
+        /// Seconds without changes before a snapshot is taken
+        #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        /// Commit the snapshots to this branch instead of gyst/wip/<branch>
+        #[arg(long)]
+        branch: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FeedbackCommands {
+    /// List the recorded edits and rejections, newest first
+    Show {
+        /// Maximum number of entries to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Delete everything recorded
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// List tags newest first, with their dates and whether the main branch contains them
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print the value of a setting, e.g. `gyst config get commit.template`
+    Get {
+        #[arg(add = ArgValueCandidates::new(completions::config_keys))]
+        key: String,
+    },
+
+    /// Change a setting in ~/.gyst/config.toml, e.g. `gyst config set git.protected_branches main,release/*`
+    Set {
+        #[arg(add = ArgValueCandidates::new(completions::config_keys))]
+        key: String,
+
+        /// New value; lists are comma-separated
+        value: String,
+    },
+
+    /// Reset a setting in ~/.gyst/config.toml to its default
+    Unset {
+        #[arg(add = ArgValueCandidates::new(completions::config_keys))]
+        key: String,
+    },
+
+    /// List the settings in effect here and their values
+    List,
+
+    /// Write the shareable settings in effect here to a bundle file, without secrets
+    Export {
+        /// File to write
+        file: String,
+    },
+
+    /// Apply the settings from a bundle file to your global configuration
+    Import {
+        /// Bundle written by 'gyst config export'
+        file: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum VersionCommands {
+    /// Recommend the next semantic version from the commits since the last tag
+    ///
+    /// Breaking changes (`!` or a BREAKING CHANGE footer) call for a major release,
+    /// new features for a minor one, anything else for a patch. Before 1.0.0,
+    /// breaking changes bump the minor version.
+    Suggest {
+        /// Also have the AI review the diff for breaking changes the commit
+        /// messages do not mention (direct API mode only)
+        #[arg(long, conflicts_with = "no_ai")]
+        diffs: bool,
+
+        /// Create an annotated tag for the recommended version on HEAD
+        #[arg(long)]
+        tag: bool,
+
+        /// Do not use the AI for the diff review or the tag message
+        #[arg(long)]
+        no_ai: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReleaseCommands {
+    /// Generate release notes for the commits between two tags
+    ///
+    /// Commits are grouped by conventional commit type and scope, pull request
+    /// numbers are taken from merge commits and squash-merge subjects, and the AI
+    /// polishes the wording (direct API mode only).
+    Notes {
+        /// Tag or commit to start after (default: the latest tag before --to)
+        #[arg(long, add = ArgValueCandidates::new(completions::refs))]
+        from: Option<String>,
+
+        /// Tag or commit to end at
+        #[arg(long, default_value = "HEAD", add = ArgValueCandidates::new(completions::refs))]
+        to: String,
+
+        /// Prepend the notes to a changelog file instead of printing them
+        #[arg(
+            long,
+            value_name = "FILE",
+            num_args = 0..=1,
+            default_missing_value = "CHANGELOG.md"
+        )]
+        changelog: Option<String>,
+
+        /// Skip the AI editing pass
+        #[arg(long)]
+        no_ai: bool,
+
+        /// Copy the notes to the clipboard (or set ui.copy)
+        #[arg(long)]
+        copy: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum HistoryCommands {
+    /// Answer a question about the history, e.g. "when did we change the retry logic?"
+    ///
+    /// Commits from all local branches are kept in a local index (.git/gyst) that is
+    /// updated incrementally. Keyword matches are re-ranked by the AI, which answers
+    /// the question citing commit hashes (direct API mode only).
+    Search {
+        /// What you are looking for, in plain language
+        query: String,
+
+        /// Maximum number of commits to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+
+        /// Also index the added and removed lines of each commit (slower on first run)
+        #[arg(long)]
+        diffs: bool,
+
+        /// Rebuild the index from scratch
+        #[arg(long)]
+        rebuild: bool,
+
+        /// Only show keyword matches, without an AI answer
+        #[arg(long)]
+        no_ai: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -120,8 +653,119 @@ pub enum BranchCommands {
         #[arg(long)]
         author: Option<String>,
 
-        /// Output format: text (default), json, or markdown
+        /// Only branches I own: my last commit, or most of the commits the
+        /// branch adds (identity from git config, resolved through .mailmap)
+        #[arg(long, conflicts_with = "author")]
+        mine: bool,
+
+        /// Output format: text (default), json, markdown, or csv
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Order of the report: age (oldest first), activity (most recent
+        /// first), behind or ahead (furthest from the main branch first)
+        #[arg(long, value_name = "KEY", value_parser = ["age", "activity", "behind", "ahead"])]
+        sort: Option<String>,
+
+        /// Report only the first N branches, after sorting
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Summarize the branches per author instead of listing them: how many
+        /// each has, how many are stale, and which
+        #[arg(long, value_name = "KEY", value_parser = ["author"], conflicts_with_all = ["sort", "limit"])]
+        group_by: Option<String>,
+
+        /// Ask the AI what to do with each branch (delete, rebase onto main,
+        /// merge soon or split) and for a branch hygiene score, added to the report
+        #[arg(long, conflicts_with = "group_by")]
+        recommend: bool,
+
+        /// With --recommend, send placeholders instead of branch and author names
+        #[arg(long, requires = "recommend")]
+        anonymize: bool,
+
+        /// Look up open pull requests (needs a GitHub token); branches with one
+        /// are annotated and never reported as stale
+        #[arg(long)]
+        exclude_with_open_pr: bool,
+
+        /// Also analyze branches that match `branch.ignore_patterns` or
+        /// `git.protected_branches`
+        #[arg(long)]
+        include_ignored: bool,
+
+        /// Offer to rebase the local branches far behind the main branch onto
+        /// it, one at a time, stopping at the first conflict
+        #[arg(long)]
+        rebase: bool,
+
+        /// Commits behind the main branch that count as far behind for --rebase
+        #[arg(
+            long,
+            value_name = "COMMITS",
+            default_value_t = 10,
+            requires = "rebase"
+        )]
+        behind: u32,
+    },
+
+    /// List stale branches that are candidates for cleanup
+    Stale {
+        /// Include all branches (local and remote)
+        #[arg(long)]
+        all: bool,
+
+        /// Only remote branches
+        #[arg(long)]
+        remote: bool,
+
+        /// Only local branches (default)
+        #[arg(long)]
+        local: bool,
+
+        /// Filter by author name
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Only branches I own: my last commit, or most of the commits the
+        /// branch adds (identity from git config, resolved through .mailmap)
+        #[arg(long, conflicts_with = "author")]
+        mine: bool,
+
+        /// Output format: text (default), json, markdown, or csv
         #[arg(long, default_value = "text")]
         format: String,
+
+        /// Keep branches with an open pull request off the list, even if inactive
+        /// (needs a GitHub token)
+        #[arg(long)]
+        exclude_with_open_pr: bool,
+
+        /// Also analyze branches that match `branch.ignore_patterns` or
+        /// `git.protected_branches`
+        #[arg(long)]
+        include_ignored: bool,
+    },
+
+    /// Delete local branches, remembering where they pointed so
+    /// 'gyst branch restore' can bring them back
+    Delete {
+        /// Branches to delete
+        #[arg(required = true, value_name = "BRANCH", add = ArgValueCandidates::new(completions::branches))]
+        names: Vec<String>,
+
+        /// Delete branches even if they have commits found on no other branch
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Recreate branches deleted with 'gyst branch delete'
+    ///
+    /// Without names, lists the deleted branches and lets you pick the ones to restore.
+    Restore {
+        /// Branches to restore
+        #[arg(value_name = "BRANCH")]
+        names: Vec<String>,
     },
 }
@@ -0,0 +1,12 @@
+//! Copying generated text to the system clipboard (`--copy`, `ui.copy`)
+
+use anyhow::{Context, Result};
+
+/// Put `text` on the clipboard. On Linux it stays there after gyst exits only
+/// if a clipboard manager takes it over, as most desktops have one.
+pub fn copy(text: &str) -> Result<()> {
+    let mut clipboard = arboard::Clipboard::new().context("No clipboard is available")?;
+    clipboard
+        .set_text(text)
+        .context("Failed to copy to the clipboard")
+}
@@ -0,0 +1,181 @@
+//! The repository's CODEOWNERS file: who owns which paths.
+
+use crate::config::Config;
+use crate::git::GitRepo;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// Where GitHub and GitLab look for the file, in the order they do
+const LOCATIONS: &[&str] = &[
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+/// Files listed per owner before the rest are counted
+const MAX_FILES_SHOWN: usize = 3;
+
+struct Rule {
+    pattern: Regex,
+    /// Empty for a rule that leaves its paths without owners
+    owners: Vec<String>,
+}
+
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+/// Code owned by someone else, with the changed files they own
+pub struct Area {
+    pub owner: String,
+    pub files: Vec<String>,
+}
+
+impl Area {
+    /// The files, shortened to the first few, e.g. `a.rs, b.rs, c.rs and 2 more`
+    pub fn file_list(&self) -> String {
+        let shown = self.files[..self.files.len().min(MAX_FILES_SHOWN)].join(", ");
+        match self.files.len().saturating_sub(MAX_FILES_SHOWN) {
+            0 => shown,
+            rest => format!("{} and {} more", shown, rest),
+        }
+    }
+}
+
+impl CodeOwners {
+    /// The first CODEOWNERS file in the repository at `workdir`, if there is one
+    pub fn load(workdir: &Path) -> Option<Self> {
+        LOCATIONS
+            .iter()
+            .find_map(|location| fs::read_to_string(workdir.join(location)).ok())
+            .map(|text| Self::parse(&text))
+    }
+
+    /// Read the rules in `text`. GitLab section headers (`[Docs]`) are skipped
+    /// and their rules read like any other.
+    pub fn parse(text: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with('[')
+                || line.starts_with("^[")
+            {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let Some(pattern) = words.next().and_then(pattern_regex) else {
+                continue;
+            };
+            let owners = words
+                .take_while(|word| !word.starts_with('#'))
+                .map(str::to_string)
+                .collect();
+            rules.push(Rule { pattern, owners });
+        }
+        Self { rules }
+    }
+
+    /// The owners of `path`, from the last rule matching it
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.pattern.is_match(path))
+            .map_or(&[], |rule| rule.owners.as_slice())
+    }
+
+    /// The owners of `paths` other than `mine`, in the order they first own a
+    /// file. Files that one of `mine` also owns are left out.
+    pub fn others(&self, paths: &[String], mine: &[String]) -> Vec<Area> {
+        let mut areas: Vec<Area> = Vec::new();
+        for path in paths {
+            let owners = self.owners_of(path);
+            if owners.iter().any(|owner| is_mine(owner, mine)) {
+                continue;
+            }
+            for owner in owners {
+                match areas.iter_mut().find(|area| &area.owner == owner) {
+                    Some(area) => area.files.push(path.clone()),
+                    None => areas.push(Area {
+                        owner: owner.clone(),
+                        files: vec![path.clone()],
+                    }),
+                }
+            }
+        }
+        areas
+    }
+}
+
+/// Your own CODEOWNERS entries: `commit.my_owners` and your git email
+pub fn mine(config: &Config, repo: &GitRepo) -> Vec<String> {
+    config
+        .commit
+        .my_owners
+        .iter()
+        .cloned()
+        .chain(repo.user_email())
+        .collect()
+}
+
+fn is_mine(owner: &str, mine: &[String]) -> bool {
+    let owner = owner.trim_start_matches('@');
+    mine.iter()
+        .any(|own| own.trim_start_matches('@').eq_ignore_ascii_case(owner))
+}
+
+/// A CODEOWNERS pattern as a regex over repository paths. Like in
+/// `.gitignore`, a pattern with a slash before its end is anchored to the
+/// root, and one naming a directory covers everything inside it. As on
+/// GitHub, `docs/*` covers the files in `docs` but not those further down.
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    let dir_only = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let suffix = if dir_only {
+        "/.*"
+    } else if pattern.ends_with("/*") {
+        ""
+    } else {
+        "(?:/.*)?"
+    };
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let mut body = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    body.push_str("(?:.*/)?");
+                } else {
+                    body.push_str(".*");
+                }
+            }
+            '*' => body.push_str("[^/]*"),
+            '?' => body.push_str("[^/]"),
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    body.push_str(&regex::escape(&next.to_string()));
+                }
+            }
+            _ => body.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    Regex::new(&format!(
+        "^{}{}{}$",
+        if anchored { "" } else { "(?:.*/)?" },
+        body,
+        suffix
+    ))
+    .ok()
+}
@@ -1,6 +1,10 @@
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
+mod risk;
+
+pub use risk::{Risk, confirmation_word};
+
+use crate::ai;
 use crate::config::Config;
+use anyhow::Result;
 
 const SYSTEM_PROMPT: &str = r#"You are a Git command suggestion assistant. Given a natural language description of what the user wants to do, suggest the appropriate Git command(s).
 
@@ -14,83 +18,73 @@ Rules:
 
 Format your response as:
 COMMAND: <the command>
+RISK: <safe, history or destructive>
 EXPLANATION: <brief explanation>
 NOTE: <optional notes/warnings>
-"#;
 
-#[derive(Debug, Serialize)]
-struct CommandRequest {
-    model: String,
-    max_tokens: u32,
-    temperature: f32,
-    system: String,
-    messages: Vec<CommandMessage>,
-}
-
-#[derive(Debug, Serialize)]
-struct CommandMessage {
-    role: String,
-    content: Vec<CommandContent>,
-}
-
-#[derive(Debug, Serialize)]
-struct CommandContent {
-    #[serde(rename = "type")]
-    content_type: String,
-    text: String,
-}
+RISK is "safe" for commands that only read or add to the history, "history" for commands that rewrite commits or move branches (rebase, commit --amend, reset --soft), and "destructive" for commands that can lose uncommitted work, commits or history on a remote (reset --hard, clean -f, push --force).
+"#;
 
-#[derive(Debug, Deserialize)]
-struct CommandResponse {
-    content: Vec<CommandResponseContent>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CommandResponseContent {
-    text: String,
-}
+/// Added to the request with `--eli5`
+pub const BEGINNER_INSTRUCTION: &str = "The user is new to git. Explain in short sentences and plain words, avoid jargon, and say what any git term you do use means.";
 
 pub struct CommandSuggester {
-    client: reqwest::Client,
     config: Config,
 }
 
 impl CommandSuggester {
     pub fn new(config: Config) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            config,
-        }
+        Self { config }
     }
 
-    pub async fn suggest(&self, description: &str) -> Result<String> {
-        let api_key = self.config.get_api_key()
-            .ok_or_else(|| anyhow::anyhow!("API key not found. Please set it using 'gyst config --api-key <key>'"))?;
-
-        let request = CommandRequest {
-            model: "claude-3-5-haiku-20241022".to_string(),
-            max_tokens: 500,
-            temperature: 0.2,  // Lower temperature for more focused suggestions
-            system: SYSTEM_PROMPT.to_string(),
-            messages: vec![CommandMessage {
-                role: "user".to_string(),
-                content: vec![CommandContent {
-                    content_type: "text".to_string(),
-                    text: description.to_string(),
-                }],
-            }],
+    /// Suggest commands for `description`; `beginner` asks for phrasing without jargon
+    pub async fn suggest(&self, description: &str, beginner: bool) -> Result<String> {
+        let system_prompt = if beginner {
+            format!("{}\n{}", SYSTEM_PROMPT, BEGINNER_INSTRUCTION)
+        } else {
+            SYSTEM_PROMPT.to_string()
         };
+        // Low temperature by default for focused suggestions
+        ai::CommitMessageGenerator::new(self.config.clone())
+            .for_command("explain")
+            .complete(&system_prompt, description, 500, 0.2)
+            .await
+    }
+}
 
-        let response = self.client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&request)
-            .send()
-            .await?
-            .json::<CommandResponse>()
-            .await?;
+/// One command from a suggestion
+pub struct SuggestedCommand {
+    pub command: String,
+    pub explanation: String,
+    pub note: Option<String>,
+    pub risk: Risk,
+}
 
-        Ok(response.content[0].text.clone())
-    }
+/// The introduction and commands of a reply in the COMMAND/RISK/EXPLANATION
+/// format, or `None` if it has no commands. The AI's risk level can raise the
+/// one the command itself shows, never lower it.
+pub fn parse_reply(reply: &str) -> Option<(String, Vec<SuggestedCommand>)> {
+    let reply = format!("\n{}", reply.trim_start());
+    let mut sections = reply.split("\nCOMMAND:");
+    let intro = sections.next().unwrap_or_default().trim().to_string();
+    let commands: Vec<SuggestedCommand> = sections
+        .filter_map(|section| {
+            let (head, rest) = section.split_once("\nEXPLANATION:")?;
+            let (command, stated) = match head.split_once("\nRISK:") {
+                Some((command, stated)) => (command, Risk::parse(stated)),
+                None => (head, None),
+            };
+            let (explanation, note) = match rest.split_once("\nNOTE:") {
+                Some((explanation, note)) => (explanation, Some(note.trim().to_string())),
+                None => (rest, None),
+            };
+            Some(SuggestedCommand {
+                risk: risk::classify(command).max(stated.unwrap_or(Risk::Safe)),
+                command: command.trim().to_string(),
+                explanation: explanation.trim().to_string(),
+                note: note.filter(|note| !note.is_empty()),
+            })
+        })
+        .collect();
+    (!commands.is_empty()).then_some((intro, commands))
 }
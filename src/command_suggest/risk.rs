@@ -0,0 +1,422 @@
+use crate::shell::split_words;
+
+/// How much harm a suggested command can do, from harmless to harmful so the
+/// higher of two levels is their maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Risk {
+    /// Only reads, or adds to the history
+    Safe,
+    /// Rewrites commits or moves branches; the reflog can still bring them back.
+    /// Also commands whose effect gyst cannot tell, which need a look first.
+    RewritesHistory,
+    /// Can lose uncommitted work, unreachable commits or history on a remote
+    Destructive,
+}
+
+impl Risk {
+    pub fn label(self) -> &'static str {
+        match self {
+            Risk::Safe => "safe",
+            Risk::RewritesHistory => "rewrites history",
+            Risk::Destructive => "destructive",
+        }
+    }
+
+    /// The level named in the AI's `RISK:` line
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        if text.starts_with("destructive") {
+            Some(Risk::Destructive)
+        } else if text.starts_with("history") || text.starts_with("rewrites") {
+            Some(Risk::RewritesHistory)
+        } else if text.starts_with("safe") {
+            Some(Risk::Safe)
+        } else {
+            None
+        }
+    }
+}
+
+/// Programs that only read, or only print
+const READ_ONLY_PROGRAMS: &[&str] = &[
+    "cat", "cd", "diff", "echo", "grep", "head", "less", "ls", "more", "printf", "pwd", "rg",
+    "tail", "tree", "wc", "which",
+];
+
+/// Programs that overwrite or delete files
+const DESTRUCTIVE_PROGRAMS: &[&str] = &["cp", "dd", "mv", "rm", "rmdir", "shred", "truncate"];
+
+/// Programs that run the command given after their options
+const WRAPPERS: &[&str] = &["command", "env", "nohup", "sudo", "time", "xargs"];
+
+/// The risk of `command`, worked out from the command itself. Several
+/// commands joined by `&&`, `;` or newlines count as their riskiest one, and
+/// pipes, command substitutions and redirections to files always need a look.
+pub fn classify(command: &str) -> Risk {
+    let (commands, opaque) = split_commands(command);
+    let risk = commands
+        .iter()
+        .map(|command| classify_one(command))
+        .max()
+        .unwrap_or(Risk::Safe);
+    if opaque {
+        risk.max(Risk::RewritesHistory)
+    } else {
+        risk
+    }
+}
+
+/// Split a command line into simple commands at `;`, `&`, `|` and newlines
+/// outside quotes. Also tells whether it pipes, substitutes commands or
+/// redirects to a file, so that what runs depends on more than its words.
+fn split_commands(command: &str) -> (Vec<String>, bool) {
+    let chars: Vec<char> = command.chars().collect();
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut opaque = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                current.push(c);
+                if let Some(next) = next {
+                    current.push(next);
+                }
+                i += 2;
+                continue;
+            }
+            (_, '`') => opaque = true,
+            (_, '$') if next == Some('(') => opaque = true,
+            (Some(_), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '\n' | ';' | '&' | '|') => {
+                if c == '|' && next != Some('|') {
+                    opaque = true;
+                }
+                if next == Some(c) {
+                    i += 1;
+                }
+                commands.push(std::mem::take(&mut current));
+                i += 1;
+                continue;
+            }
+            (None, '>' | '<') => {
+                // `2>&1` and `>/dev/null` leave files alone
+                let target: String = chars[i + 1..]
+                    .iter()
+                    .skip_while(|&&c| c == '>' || c == '&' || c.is_whitespace())
+                    .take_while(|&&c| !c.is_whitespace() && !matches!(c, ';' | '&' | '|'))
+                    .collect();
+                let duplicate = chars.get(i + 1) == Some(&'&');
+                if !(duplicate || target == "/dev/null") {
+                    opaque = true;
+                }
+                current.push(c);
+                if duplicate {
+                    current.push('&');
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        i += 1;
+    }
+    commands.push(current);
+    (commands, opaque)
+}
+
+/// The word to type before a destructive command runs: the git subcommand
+/// (`reset`, `clean`) or else the program (`rm`) of its riskiest part
+pub fn confirmation_word(command: &str) -> String {
+    let (commands, _) = split_commands(command);
+    let riskiest = commands
+        .iter()
+        .rev()
+        .max_by_key(|command| classify_one(command))
+        .map_or(command, String::as_str);
+    let words = split_words(riskiest);
+    match words.iter().position(|word| word == "git") {
+        Some(start) => subcommand(&words[start + 1..])
+            .map(|(subcommand, _)| subcommand.to_string())
+            .unwrap_or_else(|| "git".to_string()),
+        None => words.first().cloned().unwrap_or_default(),
+    }
+}
+
+fn classify_one(command: &str) -> Risk {
+    let words = split_words(command);
+    // A shell prompt copied along with the command
+    let words = match words.first().map(String::as_str) {
+        Some("$") => &words[1..],
+        _ => &words[..],
+    };
+    program(words)
+}
+
+/// The risk of running `words`, a program and its arguments
+fn program(words: &[String]) -> Risk {
+    // `NAME=value` assignments before the program
+    let start = words
+        .iter()
+        .position(|word| {
+            word.split_once('=')
+                .is_none_or(|(name, _)| name.contains('/'))
+        })
+        .unwrap_or(words.len());
+    let words = &words[start..];
+    let Some(name) = words.first().map(String::as_str) else {
+        return Risk::Safe;
+    };
+    let args = &words[1..];
+
+    match name {
+        "git" => git(args),
+        "find" if args.iter().any(|arg| arg == "-delete") => Risk::Destructive,
+        "find" => match args
+            .iter()
+            .position(|arg| matches!(arg.as_str(), "-exec" | "-execdir" | "-ok" | "-okdir"))
+        {
+            Some(exec) => program(&args[exec + 1..]).max(Risk::RewritesHistory),
+            None => Risk::Safe,
+        },
+        _ if WRAPPERS.contains(&name) => {
+            let command = args
+                .iter()
+                .position(|arg| !arg.starts_with('-'))
+                .unwrap_or(args.len());
+            program(&args[command..])
+        }
+        _ if READ_ONLY_PROGRAMS.contains(&name) => Risk::Safe,
+        _ if DESTRUCTIVE_PROGRAMS.contains(&name) => Risk::Destructive,
+        // Anything else may do anything
+        _ => Risk::RewritesHistory,
+    }
+}
+
+/// Whether a `git checkout` argument names files rather than a branch or tag:
+/// `.`, `./src`, `*.rs` or `src/main.rs`, but not `v1.2.0` or `release/2.0`
+fn is_pathspec(arg: &str) -> bool {
+    let last = arg.rsplit('/').next().unwrap_or(arg);
+    arg == "."
+        || arg.starts_with("./")
+        || arg.starts_with("../")
+        || arg.starts_with(":/")
+        || arg.contains('*')
+        || last.rsplit_once('.').is_some_and(|(stem, extension)| {
+            !stem.is_empty() && extension.chars().any(|c| c.is_ascii_alphabetic())
+        })
+}
+
+/// The git subcommand and its arguments, skipping global options like `-C <dir>`
+fn subcommand(args: &[String]) -> Option<(&str, &[String])> {
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        match arg.as_str() {
+            "-C" | "-c" | "--git-dir" | "--work-tree" => index += 2,
+            arg if arg.starts_with('-') => index += 1,
+            arg => return Some((arg, &args[index + 1..])),
+        }
+    }
+    None
+}
+
+fn git(args: &[String]) -> Risk {
+    let Some((subcommand, args)) = subcommand(args) else {
+        return Risk::Safe;
+    };
+    let has = |flag: &str| {
+        args.iter()
+            .any(|arg| arg == flag || arg.starts_with(&format!("{}=", flag)))
+    };
+    // A short option alone or combined, e.g. `-f` or `-fd`
+    let short = |option: char| {
+        args.iter().any(|arg| {
+            arg.len() > 1
+                && arg.starts_with('-')
+                && !arg.starts_with("--")
+                && arg[1..].contains(option)
+        })
+    };
+    let first = args.first().map(String::as_str);
+    // Another commit than HEAD, which `git reset` would move the branch to
+    let revision = |arg: &String| {
+        arg != "HEAD"
+            && (arg.starts_with("HEAD")
+                || arg.contains(['~', '^', '@'])
+                || arg.starts_with("origin/")
+                || (arg.len() >= 7 && arg.chars().all(|c| c.is_ascii_hexdigit())))
+    };
+    let before_paths = args.split(|arg| arg == "--").next().unwrap_or_default();
+    // `git checkout <rev> <paths>` or `git checkout <paths>` overwrite the files
+    let checkout_paths = {
+        let mut positional = Vec::new();
+        let mut iter = before_paths.iter();
+        while let Some(arg) = iter.next() {
+            if matches!(arg.as_str(), "-b" | "-B" | "--orphan") {
+                iter.next();
+            } else if !arg.starts_with('-') {
+                positional.push(arg.as_str());
+            }
+        }
+        let creates = short('b') || short('B') || has("--orphan");
+        !creates && (positional.len() > 1 || positional.iter().any(|arg| is_pathspec(arg)))
+    };
+
+    match subcommand {
+        "reset" if has("--hard") || has("--merge") => Risk::Destructive,
+        "reset" if has("--soft") || before_paths.iter().any(revision) => Risk::RewritesHistory,
+        "clean" if short('f') || has("--force") => Risk::Destructive,
+        "checkout"
+            if has("--")
+                || short('f')
+                || has("--force")
+                || short('p')
+                || has("--patch")
+                || checkout_paths =>
+        {
+            Risk::Destructive
+        }
+        "checkout" if short('B') => Risk::RewritesHistory,
+        "switch" if short('f') || has("--force") || has("--discard-changes") => Risk::Destructive,
+        "switch" if short('C') || has("--force-create") => Risk::RewritesHistory,
+        "restore" if (has("--staged") || short('S')) && !has("--worktree") && !short('W') => {
+            Risk::Safe
+        }
+        "restore" => Risk::Destructive,
+        "rm" if short('f') || has("--force") => Risk::Destructive,
+        "branch" if short('D') || (has("--delete") && has("--force")) => Risk::Destructive,
+        "branch" if short('d') || short('m') || short('M') || short('f') || has("--delete") => {
+            Risk::RewritesHistory
+        }
+        "tag" if short('d') || short('f') || has("--delete") || has("--force") => {
+            Risk::RewritesHistory
+        }
+        "push"
+            if short('f')
+                || has("--force")
+                || has("--mirror")
+                || has("--prune")
+                || args.iter().any(|arg| arg.starts_with('+')) =>
+        {
+            Risk::Destructive
+        }
+        "push"
+            if has("--force-with-lease")
+                || has("--delete")
+                || short('d')
+                || args.iter().any(|arg| arg.starts_with(':')) =>
+        {
+            Risk::RewritesHistory
+        }
+        "stash" if matches!(first, Some("drop" | "clear")) => Risk::Destructive,
+        "reflog" if matches!(first, Some("expire" | "delete")) => Risk::Destructive,
+        "gc" if has("--prune") => Risk::Destructive,
+        "update-ref" if short('d') => Risk::Destructive,
+        "worktree" if first == Some("remove") && (short('f') || has("--force")) => {
+            Risk::Destructive
+        }
+        "filter-branch" | "filter-repo" => Risk::Destructive,
+        "rebase" if has("--abort") || has("--quit") => Risk::Safe,
+        "rebase" | "replace" => Risk::RewritesHistory,
+        "commit" if has("--amend") => Risk::RewritesHistory,
+        _ => Risk::Safe,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_commands() {
+        assert_eq!(classify("git status"), Risk::Safe);
+        assert_eq!(classify("$ git log --oneline -5"), Risk::Safe);
+        assert_eq!(classify("git rebase -i HEAD~3"), Risk::RewritesHistory);
+        assert_eq!(classify("git reset --hard HEAD~2"), Risk::Destructive);
+        assert_eq!(classify("git clean -fd"), Risk::Destructive);
+    }
+
+    #[test]
+    fn checkout_of_paths_is_destructive() {
+        assert_eq!(classify("git checkout -- src/main.rs"), Risk::Destructive);
+        assert_eq!(classify("git checkout src/main.rs"), Risk::Destructive);
+        assert_eq!(classify("git checkout ."), Risk::Destructive);
+        assert_eq!(classify("git checkout HEAD~1 Makefile"), Risk::Destructive);
+        assert_eq!(classify("git checkout -p"), Risk::Destructive);
+        assert_eq!(classify("git checkout main"), Risk::Safe);
+        assert_eq!(classify("git checkout v1.2.0"), Risk::Safe);
+        assert_eq!(classify("git checkout release/2.0"), Risk::Safe);
+        assert_eq!(
+            classify("git checkout -b feature/x origin/main"),
+            Risk::Safe
+        );
+    }
+
+    #[test]
+    fn other_programs() {
+        assert_eq!(classify("ls -la"), Risk::Safe);
+        assert_eq!(classify("rm -rf target"), Risk::Destructive);
+        assert_eq!(classify("mv a.txt b.txt"), Risk::Destructive);
+        assert_eq!(classify("dd if=/dev/zero of=disk.img"), Risk::Destructive);
+        assert_eq!(classify("find . -name '*.orig' -delete"), Risk::Destructive);
+        assert_eq!(
+            classify("find . -name '*.orig' -exec rm {} \\;"),
+            Risk::Destructive
+        );
+        assert_eq!(classify("find . -name '*.orig'"), Risk::Safe);
+        assert_eq!(classify("sudo rm -rf /tmp/x"), Risk::Destructive);
+        assert_eq!(classify("make install"), Risk::RewritesHistory);
+    }
+
+    #[test]
+    fn joined_commands_count_as_the_riskiest() {
+        assert_eq!(classify("git fetch && git status"), Risk::Safe);
+        assert_eq!(classify("git stash; git stash drop"), Risk::Destructive);
+        assert_eq!(classify("git pull || rm -rf .git"), Risk::Destructive);
+        assert_eq!(classify("git status\nrm notes.txt"), Risk::Destructive);
+        assert_eq!(classify("git fetch & rm notes.txt"), Risk::Destructive);
+    }
+
+    #[test]
+    fn pipes_substitutions_and_redirections_need_a_look() {
+        assert_eq!(classify("git status | xargs rm"), Risk::Destructive);
+        assert_eq!(classify("git branch | grep main"), Risk::RewritesHistory);
+        assert_eq!(
+            classify("curl -fsSL https://example.com/install | sh"),
+            Risk::RewritesHistory
+        );
+        assert_eq!(classify("git log > history.txt"), Risk::RewritesHistory);
+        assert_eq!(classify("> notes.txt"), Risk::RewritesHistory);
+        assert_eq!(classify("echo $(cat list)"), Risk::RewritesHistory);
+        assert_eq!(classify("echo `cat list`"), Risk::RewritesHistory);
+        assert_eq!(
+            classify("git commit -m \"fix $(whoami)\""),
+            Risk::RewritesHistory
+        );
+        assert_eq!(classify("git branch 2>/dev/null"), Risk::Safe);
+        assert_eq!(classify("git log 2>&1"), Risk::Safe);
+    }
+
+    #[test]
+    fn confirmation_word_comes_from_the_riskiest_part() {
+        assert_eq!(confirmation_word("git reset --hard HEAD~1"), "reset");
+        assert_eq!(confirmation_word("git fetch && git clean -fd"), "clean");
+        assert_eq!(confirmation_word("git status | xargs rm"), "xargs");
+    }
+
+    #[test]
+    fn quoted_separators_do_not_split() {
+        assert_eq!(classify("git commit -m \"fix: a; b | c > d\""), Risk::Safe);
+        assert_eq!(classify("git commit -m 'echo $(rm -rf x)'"), Risk::Safe);
+    }
+}
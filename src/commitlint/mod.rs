@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Config files commitlint looks for, in its own lookup order
+const CONFIG_FILES: &[&str] = &[
+    ".commitlintrc",
+    ".commitlintrc.json",
+    ".commitlintrc.yaml",
+    ".commitlintrc.yml",
+    ".commitlintrc.js",
+    ".commitlintrc.cjs",
+    ".commitlintrc.mjs",
+    ".commitlintrc.ts",
+    "commitlint.config.js",
+    "commitlint.config.cjs",
+    "commitlint.config.mjs",
+    "commitlint.config.ts",
+];
+
+/// Types allowed by `@commitlint/config-conventional`
+const CONVENTIONAL_TYPES: &[&str] = &[
+    "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test",
+];
+
+/// The subset of a project's commitlint rules gyst understands
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    pub source: String,
+    pub types: Vec<String>,
+    pub scopes: Vec<String>,
+    pub header_max_length: Option<usize>,
+    pub body_max_line_length: Option<usize>,
+}
+
+impl Rules {
+    /// Read the commitlint config in `workdir`, if there is one.
+    ///
+    /// JavaScript configs are not executed; rules are read from their literal
+    /// `'rule-name': [level, 'always', value]` entries, which covers the usual
+    /// hand-written configs.
+    pub fn load(workdir: &Path) -> Result<Option<Self>> {
+        let Some(name) = CONFIG_FILES
+            .iter()
+            .find(|name| workdir.join(name).is_file())
+        else {
+            return Ok(None);
+        };
+
+        let path = workdir.join(name);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let mut rules = Self {
+            source: name.to_string(),
+            ..Self::default()
+        };
+
+        // Start from the shared conventional preset, then apply the project's own rules
+        if text.contains("config-conventional") {
+            rules.types = CONVENTIONAL_TYPES.iter().map(|t| t.to_string()).collect();
+            rules.header_max_length = Some(100);
+            rules.body_max_line_length = Some(100);
+        }
+
+        if let Some(value) = active_rule(&text, "type-enum") {
+            rules.types = parse_list(&value);
+        }
+        if let Some(value) = active_rule(&text, "scope-enum") {
+            rules.scopes = parse_list(&value);
+        }
+        if let Some(value) = active_rule(&text, "header-max-length") {
+            rules.header_max_length = value.trim().parse().ok();
+        }
+        if let Some(value) = active_rule(&text, "body-max-line-length") {
+            rules.body_max_line_length = value.trim().parse().ok();
+        }
+
+        Ok(Some(rules))
+    }
+
+    /// Describe the rules as instructions for the AI
+    pub fn prompt_rules(&self) -> Vec<String> {
+        let mut rules = Vec::new();
+        if !self.types.is_empty() {
+            rules.push(format!(
+                "Only use these commit types: {}",
+                self.types.join(", ")
+            ));
+        }
+        if !self.scopes.is_empty() {
+            rules.push(format!(
+                "Only use these scopes (or none): {}",
+                self.scopes.join(", ")
+            ));
+        }
+        if let Some(max) = self.header_max_length {
+            rules.push(format!(
+                "Keep the whole first line under {} characters",
+                max
+            ));
+        }
+        if let Some(max) = self.body_max_line_length {
+            rules.push(format!("Wrap body lines at {} characters", max));
+        }
+        rules
+    }
+
+    /// Check `message` against the rules and describe each violation
+    pub fn validate(&self, message: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let header = message.lines().next().unwrap_or("").trim();
+
+        if let Some(max) = self.header_max_length {
+            let length = header.chars().count();
+            if length > max {
+                problems.push(format!(
+                    "The header is {} characters long ({} allows {})",
+                    length, self.source, max
+                ));
+            }
+        }
+
+        if let Some((prefix, _)) = header.split_once(": ") {
+            let prefix = prefix.trim_end_matches('!');
+            let (kind, scope) = match prefix.split_once('(') {
+                Some((kind, rest)) => (kind, rest.strip_suffix(')')),
+                None => (prefix, None),
+            };
+
+            if !self.types.is_empty() && !self.types.iter().any(|t| t == kind) {
+                problems.push(format!(
+                    "Type '{}' is not allowed by {} (allowed: {})",
+                    kind,
+                    self.source,
+                    self.types.join(", ")
+                ));
+            }
+            if let Some(scope) = scope
+                && !self.scopes.is_empty()
+                && !scope
+                    .split([',', '/'])
+                    .all(|s| self.scopes.iter().any(|a| a == s.trim()))
+            {
+                problems.push(format!(
+                    "Scope '{}' is not allowed by {} (allowed: {})",
+                    scope,
+                    self.source,
+                    self.scopes.join(", ")
+                ));
+            }
+        } else if !self.types.is_empty() {
+            problems.push(format!(
+                "The header has no type, which {} requires",
+                self.source
+            ));
+        }
+
+        if let Some(max) = self.body_max_line_length
+            && let Some(line) = message
+                .lines()
+                .skip(1)
+                .find(|line| line.chars().count() > max)
+        {
+            problems.push(format!(
+                "Body line exceeds {} characters: '{}'",
+                max,
+                line.chars().take(30).collect::<String>()
+            ));
+        }
+
+        problems
+    }
+}
+
+/// Find `name: [level, 'always', value]` and return `value` if the rule is enabled
+fn active_rule(text: &str, name: &str) -> Option<String> {
+    let mut search = text;
+    while let Some(pos) = search.find(name) {
+        let after = &search[pos + name.len()..];
+        let rest = after.trim_start_matches(['\'', '"', '`']).trim_start();
+        if let Some(rest) = rest.strip_prefix(':') {
+            let rest = rest.trim_start();
+            if rest.starts_with('[') {
+                let elements = split_top_level(&rest[1..bracket_end(rest)?]);
+                let level: u8 = elements.first()?.trim().parse().ok()?;
+                let applicable = unquote(elements.get(1)?);
+                if level == 0 || applicable != "always" {
+                    return None;
+                }
+                return elements.get(2).map(|value| value.trim().to_string());
+            }
+        }
+        search = after;
+    }
+    None
+}
+
+/// Index of the `]` matching the `[` at the start of `text`
+fn bracket_end(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split `text` on commas that are not nested inside brackets or quotes
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Parse `['feat', 'fix']` (or YAML's `[feat, fix]`) into its items
+fn parse_list(value: &str) -> Vec<String> {
+    let inner = value
+        .trim()
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+    split_top_level(inner)
+        .iter()
+        .map(|item| unquote(item))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches(['\'', '"', '`'])
+        .trim()
+        .to_string()
+}
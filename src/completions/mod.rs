@@ -0,0 +1,138 @@
+use crate::ai;
+use crate::config::{Config, SETTINGS};
+use crate::git::GitRepo;
+use crate::template;
+use anyhow::Result;
+use clap_complete::env::{Bash, Elvish, EnvCompleter, Fish, Powershell, Zsh};
+use clap_complete::{CompletionCandidate, Shell};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Environment variable that switches gyst into completion mode; the scripts
+/// written by `write_registration` set it when they call back into gyst
+pub const ENV_VAR: &str = "COMPLETE";
+
+/// Write the script that registers gyst's completions with `shell`. The script
+/// calls back into gyst for every completion, so branch names and other values
+/// from the repository are always current.
+pub fn write_registration(shell: Shell, out: &mut dyn Write) -> Result<()> {
+    let completer: &dyn EnvCompleter = match shell {
+        Shell::Bash => &Bash,
+        Shell::Elvish => &Elvish,
+        Shell::Fish => &Fish,
+        Shell::PowerShell => &Powershell,
+        Shell::Zsh => &Zsh,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Completions are not supported for {}",
+                shell
+            ));
+        }
+    };
+    completer.write_registration(ENV_VAR, "gyst", "gyst", &completer_path(), out)?;
+    Ok(())
+}
+
+/// How the completion script should run gyst: by name when it was found on
+/// `PATH`, otherwise by the absolute path it was started with
+fn completer_path() -> String {
+    let Some(arg) = std::env::args_os().next() else {
+        return "gyst".to_string();
+    };
+    let path = PathBuf::from(arg);
+    if path.components().count() > 1 {
+        std::env::current_dir()
+            .map(|dir| dir.join(&path))
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        path.to_string_lossy().into_owned()
+    }
+}
+
+/// Local and remote-tracking branches of the repository in the current directory
+pub fn branches() -> Vec<CompletionCandidate> {
+    let Ok(repo) = GitRepo::open(".") else {
+        return Vec::new();
+    };
+    repo.branch_names()
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Branches and tags, for options that take any commit
+pub fn refs() -> Vec<CompletionCandidate> {
+    let Ok(repo) = GitRepo::open(".") else {
+        return Vec::new();
+    };
+    let tags = repo.tag_names().unwrap_or_default();
+    branches()
+        .into_iter()
+        .chain(
+            tags.into_iter()
+                .map(|tag| CompletionCandidate::new(tag).help(Some("tag".into()))),
+        )
+        .collect()
+}
+
+/// Configured remotes, plus `all`
+pub fn remotes() -> Vec<CompletionCandidate> {
+    let names = GitRepo::open(".")
+        .and_then(|repo| repo.remote_names())
+        .unwrap_or_default();
+    names
+        .into_iter()
+        .chain(["all".to_string()])
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The built-in message templates and the ones defined in the configuration
+pub fn templates() -> Vec<CompletionCandidate> {
+    let custom = Config::load_for_repo(".")
+        .map(|config| config.commit.templates.into_keys().collect::<Vec<_>>())
+        .unwrap_or_default();
+    template::PRESETS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(custom)
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The models of the configured AI provider
+pub fn models() -> Vec<CompletionCandidate> {
+    let provider = Config::load_for_repo(".")
+        .map(|config| config.ai.provider)
+        .unwrap_or_default();
+    ai::known_models(&provider)
+        .unwrap_or_default()
+        .iter()
+        .map(|model| CompletionCandidate::new(*model))
+        .collect()
+}
+
+/// Setting names for `gyst config get/set/unset`, with what each one does
+pub fn config_keys() -> Vec<CompletionCandidate> {
+    let config = Config::load_for_repo(".").unwrap_or_default();
+    let mut keys = Vec::new();
+    for setting in SETTINGS {
+        if setting.key.contains('*') {
+            // Offer the names that are already in use, e.g. `forge.labels.feat`
+            keys.extend(
+                config
+                    .list_settings()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(key, _)| setting.matches(key))
+                    .map(|(key, _)| CompletionCandidate::new(key).help(Some(setting.about.into()))),
+            );
+        } else {
+            keys.push(CompletionCandidate::new(setting.key).help(Some(setting.about.into())));
+        }
+    }
+    keys
+}
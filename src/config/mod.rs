@@ -1,53 +1,495 @@
+mod remote;
+mod settings;
+
+pub use settings::SETTINGS;
+
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Settings that are secret or personal to one machine. They are left out of
+/// exported bundles and ignored when a bundle is imported.
+const PERSONAL_SETTINGS: &[&str] = &[
+    "ai.api_key",
+    "commit.my_owners",
+    "server",
+    "forge.github_token",
+    "forge.gitlab_token",
+    "jira.email",
+    "jira.token",
+    "linear.api_key",
+    "telemetry",
+    "feedback",
+    "network",
+    "storage",
+];
+
+/// The settings a repository's `.gyst.toml` and team settings can change: the
+/// project's own conventions. Where requests go (the AI provider, Jira and
+/// forge URLs, team settings) only comes from your own settings, so a cloned
+/// repository or a team file cannot send your tokens somewhere else.
+const SHAREABLE_SETTINGS: &[&str] = &[
+    "ai.enabled",
+    "commit",
+    "git.protected_branches",
+    "branch",
+    "ui",
+    "forge.labels",
+    "jira.project",
+];
+
+/// The first line of an exported settings bundle
+const BUNDLE_HEADER: &str = "# gyst settings bundle. Import it with 'gyst config import <file>'.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub ai: AiConfig,
     #[serde(default)]
     pub git: GitConfig,
     #[serde(default)]
+    pub branch: BranchConfig,
+    #[serde(default)]
     pub commit: CommitConfig,
     #[serde(default)]
     pub server: ServerConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub forge: ForgeConfig,
+    #[serde(default)]
+    pub jira: JiraConfig,
+    #[serde(default, skip_serializing_if = "LinearConfig::is_unset")]
+    pub linear: LinearConfig,
+    /// The `[config]` table
+    #[serde(
+        default,
+        rename = "config",
+        skip_serializing_if = "RemoteConfig::is_unset"
+    )]
+    pub remote: RemoteConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub feedback: FeedbackConfig,
+    #[serde(default, skip_serializing_if = "NetworkConfig::is_unset")]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiConfig {
     pub provider: String,
     pub api_key: String,
     #[serde(default = "default_model")]
     pub model: String,
+    /// When false, gyst never calls an AI provider and uses deterministic fallbacks
+    #[serde(default = "default_ai_enabled")]
+    pub enabled: bool,
+    /// Temperature for commit messages and suggestions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Longest reply for commit messages and suggestions, in tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Settings for single commands, e.g. `[ai.commands.suggest]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, GenerationConfig>,
+    /// Replace the diff of large files with short cached summaries when the
+    /// staged diff is too long for the prompt
+    #[serde(default = "default_compress_prompts")]
+    pub compress_prompts: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// How one command asks the AI for replies. Unset values use the command's defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitConfig {
     #[serde(default = "default_max_diff_size")]
     pub max_diff_size: usize,
     #[serde(default = "default_protected_branches")]
     pub protected_branches: Vec<String>,
+    /// Minimum similarity (0-100) for a delete/add pair to be treated as a rename or copy
+    #[serde(default = "default_rename_threshold")]
+    pub rename_threshold: u16,
+    #[serde(default = "default_detect_copies")]
+    pub detect_copies: bool,
+    /// Remotes `--push` pushes to when `--remote` is not given; `all` means every remote
+    #[serde(default = "default_push_remotes")]
+    pub push_remotes: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Settings for `gyst branch health` and `gyst branch stale`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BranchConfig {
+    /// The branch others are measured against; detected when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Branches left out of the analysis; `release/*` matches a prefix
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            max_diff_size: default_max_diff_size(),
+            protected_branches: default_protected_branches(),
+            rename_threshold: default_rename_threshold(),
+            detect_copies: default_detect_copies(),
+            push_remotes: default_push_remotes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitConfig {
     #[serde(default = "default_commit_template")]
     pub template: String,
     #[serde(default = "default_max_subject_length")]
     pub max_subject_length: usize,
+    /// Column the body of generated messages is wrapped at; 0 leaves it alone
+    #[serde(default = "default_body_width")]
+    pub body_width: usize,
+    /// Regex that extracts a ticket ID (e.g. `[A-Z]+-\d+`) from the branch name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket_pattern: Option<String>,
+    #[serde(default)]
+    pub ticket_placement: TicketPlacement,
+    /// User-defined templates, selected by name with `template` or `--template`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, CustomTemplate>,
+    /// Trailers such as `Reviewed-by: Name <email>` added to every generated message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trailers: Vec<String>,
+    /// Language generated messages are written in, when not English
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// How long `gyst commit` waits for the AI before using a template-based message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wait_ms: Option<u64>,
+    /// Write messages for trivial changes (a version bump, a one-word typo fix,
+    /// new `.gitignore` entries) without asking the AI
+    #[serde(default = "default_fast_path")]
+    pub fast_path: bool,
+    /// Your own CODEOWNERS entries (handles, teams, emails); changes to code
+    /// owned by anyone else are pointed out before committing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub my_owners: Vec<String>,
+    /// Terms generated messages must spell a certain way, keyed by the variant
+    /// to replace, e.g. `postgres = "PostgreSQL"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub terminology: BTreeMap<String, String>,
+    /// Correct common misspellings in generated messages
+    #[serde(default = "default_spell_check")]
+    pub spell_check: bool,
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        Self {
+            template: default_commit_template(),
+            max_subject_length: default_max_subject_length(),
+            body_width: default_body_width(),
+            ticket_pattern: None,
+            ticket_placement: TicketPlacement::default(),
+            templates: BTreeMap::new(),
+            trailers: Vec::new(),
+            language: None,
+            max_wait_ms: None,
+            fast_path: default_fast_path(),
+            my_owners: Vec::new(),
+            terminology: BTreeMap::new(),
+            spell_check: default_spell_check(),
+        }
+    }
+}
+
+/// A commit message template defined in `[commit.templates.<name>]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomTemplate {
+    /// Rules for the AI, one per line
+    pub instructions: String,
+    /// Body sections every message must contain, e.g. `["Why", "Testing"]`
+    #[serde(default)]
+    pub required_sections: Vec<String>,
+    /// Regex the subject line must match
+    #[serde(default)]
+    pub subject_pattern: Option<String>,
+}
+
+/// Where a ticket ID taken from the branch name goes in the commit message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketPlacement {
+    /// A `Refs: JIRA-123` trailer at the end of the message
+    #[default]
+    Trailer,
+    /// A `[JIRA-123]` prefix on the subject line
+    Prefix,
+}
+
+/// Access to the code hosting service (pull requests, issues)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgeConfig {
+    /// GitHub token; `GITHUB_TOKEN`, `GH_TOKEN` or git's credential helper is used when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+    /// Base URL of a GitHub Enterprise Server instance, e.g. `https://github.example.com`.
+    /// Without it, only `github.com` remotes are treated as GitHub.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_url: Option<String>,
+    /// Base URL of a self-hosted GitLab instance, e.g. `https://git.example.com`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitlab_url: Option<String>,
+    /// GitLab token; `GITLAB_TOKEN` or git's credential helper is used when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitlab_token: Option<String>,
+    /// Add the title and description of the issue named in the branch (e.g.
+    /// `fix/123-crash`) to the commit prompt. Off by default, since it asks the
+    /// forge on every commit.
+    #[serde(default)]
+    pub link_issues: bool,
+    /// Pull request label for each conventional commit type, e.g. `feat = "enhancement"`
+    #[serde(default = "default_pr_labels")]
+    pub labels: BTreeMap<String, String>,
+    /// List the CODEOWNERS of the changed files as suggested reviewers in
+    /// generated pull request descriptions
+    #[serde(default)]
+    pub suggest_reviewers: bool,
+}
+
+impl Default for ForgeConfig {
+    fn default() -> Self {
+        Self {
+            github_token: None,
+            github_url: None,
+            gitlab_url: None,
+            gitlab_token: None,
+            link_issues: false,
+            labels: default_pr_labels(),
+            suggest_reviewers: false,
+        }
+    }
+}
+
+/// Team settings fetched from a shared URL. They fill in every setting you
+/// have not changed yourself; `.gyst.toml` still wins over both.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    /// URL of a TOML or JSON settings file, e.g. a raw file in a team repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>,
+    /// Fetch the file again once the cached copy is this many minutes old; 0 fetches on every run
+    #[serde(default = "default_refresh_minutes")]
+    pub refresh_minutes: u64,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            remote_url: None,
+            refresh_minutes: default_refresh_minutes(),
+        }
+    }
+}
+
+impl RemoteConfig {
+    fn is_unset(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Opt-in usage statistics. Only your own `~/.gyst/config.toml` can turn them
+/// on; `.gyst.toml`, team settings and bundles cannot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// Record command names, durations and success in `~/.gyst/stats.json`
+    #[serde(default)]
+    pub enabled: bool,
+    /// Also send daily totals to the gyst server
+    #[serde(default)]
+    pub upload: bool,
+}
+
+/// Generated messages you edit or reject, kept in `~/.gyst/feedback` so the
+/// AI can learn your style
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackConfig {
+    /// Record generated messages you edit or reject
+    #[serde(default = "default_feedback_record")]
+    pub record: bool,
+    /// How many of your recent edits to show the AI as examples; 0 shows none
+    #[serde(default)]
+    pub examples: usize,
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        Self {
+            record: default_feedback_record(),
+            examples: 0,
+        }
+    }
+}
+
+/// TLS settings for every HTTPS request gyst makes, for TLS-intercepting
+/// proxies and servers with a private CA. Only your own `~/.gyst/config.toml`
+/// can set them; `.gyst.toml`, team settings and bundles cannot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// PEM file with extra CA certificates to trust, on top of the system's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert_path: Option<String>,
+    /// Accept any certificate. Only for debugging: it allows interception.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure_skip_verify: bool,
+}
+
+impl NetworkConfig {
+    fn is_unset(&self) -> bool {
+        self.ca_cert_path.is_none() && !self.insecure_skip_verify
+    }
+}
+
+/// Where usage statistics, the history index and the audit log are kept.
+/// Only your own `~/.gyst/config.toml` can set this.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// `jsonl` for plain files, `sqlite` for a `gyst.db` database
+    #[serde(default = "default_storage_backend")]
+    pub backend: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_storage_backend(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Jira issues named in the branch (e.g. `feature/PROJ-123-login`) give the
+/// AI the requirement behind the change
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JiraConfig {
+    /// Base URL, e.g. `https://example.atlassian.net`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Account email for Jira Cloud; leave unset to use a Server/Data Center personal access token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// API token or personal access token; `JIRA_API_TOKEN` is used when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Project key, e.g. `PROJ`. Ticket IDs with this key are taken from branch
+    /// names even without `commit.ticket_pattern`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+/// Linear issues can be linked to commits with `gyst commit --link ENG-123`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinearConfig {
+    /// Personal API key; `LINEAR_API_KEY` is used when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Add the title and description of the Linear issue for the ticket ID in
+    /// the branch name to the commit prompt. Off by default, since it asks
+    /// Linear on every commit.
+    #[serde(default)]
+    pub link_issues: bool,
+}
+
+impl LinearConfig {
+    fn is_unset(&self) -> bool {
+        self.api_key.is_none() && !self.link_issues
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// `relative` ("3 weeks ago") or `absolute` dates in reports
+    #[serde(default = "default_date_style")]
+    pub dates: String,
+    /// strftime pattern for absolute dates; the locale's format when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    /// Copy generated messages, descriptions and release notes to the
+    /// clipboard, as with `--copy`
+    #[serde(default)]
+    pub copy: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            theme: ThemeConfig::default(),
+            dates: default_date_style(),
+            date_format: None,
+            copy: false,
+        }
+    }
+}
+
+/// Colors for each role in gyst's output, e.g. `added = "green.bold"` or `warning = "208"`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeConfig {
+    /// One of `default`, `solarized`, `high-contrast` or `monochrome`
+    #[serde(default = "default_theme_preset")]
+    pub preset: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            preset: default_theme_preset(),
+            added: None,
+            removed: None,
+            warning: None,
+            prompt: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     #[serde(default = "default_use_server")]
     pub use_server: bool,
+    /// API token for servers that require one; `GYST_SERVER_TOKEN` is used when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// The server's X25519 public key (base64); requests are encrypted to it when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             use_server: true,
+            token: None,
+            public_key: None,
         }
     }
 }
@@ -56,6 +498,10 @@ fn default_model() -> String {
     "claude-haiku".to_string()
 }
 
+fn default_ai_enabled() -> bool {
+    true
+}
+
 fn default_max_diff_size() -> usize {
     1000
 }
@@ -64,6 +510,50 @@ fn default_protected_branches() -> Vec<String> {
     vec!["main".to_string(), "master".to_string()]
 }
 
+fn default_fast_path() -> bool {
+    true
+}
+
+fn default_spell_check() -> bool {
+    true
+}
+
+fn default_feedback_record() -> bool {
+    true
+}
+
+fn default_storage_backend() -> String {
+    "jsonl".to_string()
+}
+
+fn default_compress_prompts() -> bool {
+    true
+}
+
+fn default_pr_labels() -> BTreeMap<String, String> {
+    [
+        ("feat", "enhancement"),
+        ("fix", "bug"),
+        ("docs", "documentation"),
+        ("perf", "performance"),
+    ]
+    .into_iter()
+    .map(|(kind, label)| (kind.to_string(), label.to_string()))
+    .collect()
+}
+
+fn default_rename_threshold() -> u16 {
+    50
+}
+
+fn default_detect_copies() -> bool {
+    true
+}
+
+fn default_push_remotes() -> Vec<String> {
+    vec!["origin".to_string()]
+}
+
 fn default_commit_template() -> String {
     "conventional".to_string()
 }
@@ -72,30 +562,126 @@ fn default_max_subject_length() -> usize {
     72
 }
 
+fn default_body_width() -> usize {
+    72
+}
+
 fn default_use_server() -> bool {
     true
 }
 
+fn default_refresh_minutes() -> u64 {
+    60
+}
+
+fn default_theme_preset() -> String {
+    "default".to_string()
+}
+
+fn default_date_style() -> String {
+    "relative".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            ai: AiConfig {
+                provider: "anthropic".to_string(),
+                api_key: String::new(),
+                model: "claude-3-5-haiku-20241022".to_string(),
+                enabled: true,
+                temperature: None,
+                max_tokens: None,
+                commands: BTreeMap::new(),
+                compress_prompts: default_compress_prompts(),
+            },
+            git: GitConfig::default(),
+            branch: BranchConfig::default(),
+            commit: CommitConfig::default(),
+            server: ServerConfig::default(),
+            ui: UiConfig::default(),
+            forge: ForgeConfig::default(),
+            jira: JiraConfig::default(),
+            linear: LinearConfig::default(),
+            remote: RemoteConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            feedback: FeedbackConfig::default(),
+            network: NetworkConfig::default(),
+            storage: StorageConfig::default(),
+        }
+    }
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let config_path = Config::get_config_path()?;
 
         if !config_path.exists() {
-            return Ok(Config {
-                ai: AiConfig {
-                    provider: "anthropic".to_string(),
-                    api_key: String::new(),
-                    model: "claude-3-5-haiku-20241022".to_string(),
-                },
-                git: GitConfig::default(),
-                commit: CommitConfig::default(),
-                server: ServerConfig::default(),
-            });
+            return Ok(Config::default());
         }
 
         let contents = fs::read_to_string(&config_path).context("Failed to read config file")?;
+        let mut value: toml::Value =
+            toml::from_str(&contents).context("Failed to parse config file")?;
+
+        if let Some(team) = remote::team_settings(&value) {
+            let defaults = toml::Value::try_from(Config::default())?;
+            remote::merge_below(&mut value, &team, Some(&defaults));
+        }
+        value.try_into().context("Failed to parse config file")
+    }
+
+    /// Load the global config with the repository's `.gyst.toml` (if any) layered on top.
+    /// Settings from the repository file win over the global ones, but only
+    /// those in `SHAREABLE_SETTINGS` are read from it, and it can turn AI off but
+    /// not back on.
+    pub fn load_for_repo(repo_path: &str) -> Result<Self> {
+        let config = Self::load()?;
+
+        let Some(repo_config_path) = Self::get_repo_config_path(repo_path) else {
+            return Ok(config);
+        };
+        if !repo_config_path.exists() {
+            return Ok(config);
+        }
+
+        let contents = fs::read_to_string(&repo_config_path)
+            .with_context(|| format!("Failed to read {}", repo_config_path.display()))?;
+        let overlay: toml::Value = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", repo_config_path.display()))?;
+        let overlay = shareable(&overlay);
 
-        toml::from_str(&contents).context("Failed to parse config file")
+        let mut merged = toml::Value::try_from(&config).context("Failed to serialize config")?;
+        merge_values(&mut merged, overlay);
+        let mut repo_config: Self = merged
+            .try_into()
+            .with_context(|| format!("Invalid settings in {}", repo_config_path.display()))?;
+        repo_config.ai.enabled &= config.ai.enabled;
+        Ok(repo_config)
+    }
+
+    /// The reply length and temperature for `command` (a subcommand name such
+    /// as `suggest`): its `[ai.commands.<command>]` settings, then `ai.max_tokens`
+    /// and `ai.temperature` for commit messages, then the given defaults
+    pub fn generation(&self, command: &str, max_tokens: u32, temperature: f32) -> (u32, f32) {
+        let own = self.ai.commands.get(command);
+        let (general_tokens, general_temperature) = match command {
+            "commit" | "suggest" => (self.ai.max_tokens, self.ai.temperature),
+            _ => (None, None),
+        };
+        (
+            own.and_then(|own| own.max_tokens)
+                .or(general_tokens)
+                .unwrap_or(max_tokens),
+            own.and_then(|own| own.temperature)
+                .or(general_temperature)
+                .map_or(temperature, |configured| configured as f32),
+        )
+    }
+
+    /// Whether AI features may be used
+    pub fn ai_enabled(&self) -> bool {
+        self.ai.enabled
     }
 
     pub fn save(&self) -> Result<()> {
@@ -106,9 +692,28 @@ impl Config {
             fs::create_dir_all(dir).context("Failed to create config directory")?;
         }
 
-        let contents = toml::to_string_pretty(self).context("Failed to serialize config")?;
+        // Team settings were merged in when the file on disk was loaded
+        let on_disk: Option<toml::Value> = fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok());
+        let mut value = toml::Value::try_from(self).context("Failed to serialize config")?;
+        if let Some(team) = on_disk.as_ref().and_then(remote::team_settings) {
+            let defaults = toml::Value::try_from(Config::default())?;
+            remote::strip(&mut value, &team, Some(&defaults));
+        }
+        let contents = toml::to_string_pretty(&value).context("Failed to serialize config")?;
 
-        fs::write(&config_path, contents).context("Failed to write config file")?;
+        // Write to a temporary file and rename it into place so concurrent gyst
+        // processes never observe a partially written config
+        let dir = config_path
+            .parent()
+            .context("Config path has no parent directory")?;
+        let mut temp =
+            tempfile::NamedTempFile::new_in(dir).context("Failed to write config file")?;
+        temp.write_all(contents.as_bytes())
+            .context("Failed to write config file")?;
+        temp.persist(&config_path)
+            .context("Failed to write config file")?;
 
         Ok(())
     }
@@ -135,17 +740,57 @@ impl Config {
         self.server.use_server
     }
 
-    fn get_config_path() -> Result<PathBuf> {
+    /// Check whether commits to `branch` need explicit confirmation.
+    /// Entries ending in `*` match any branch with that prefix.
+    pub fn is_protected_branch(&self, branch: &str) -> bool {
+        matches_branch(&self.git.protected_branches, branch)
+    }
+
+    fn get_repo_config_path(repo_path: &str) -> Option<PathBuf> {
+        let repo = git2::Repository::discover(repo_path).ok()?;
+        Some(repo.workdir()?.join(".gyst.toml"))
+    }
+
+    /// Whether `~/.gyst/config.toml` has been written yet
+    pub fn exists() -> bool {
+        Config::get_config_path().is_ok_and(|path| path.exists())
+    }
+
+    /// The language generated messages should be written in, if not English
+    pub fn message_language(&self) -> Option<&str> {
+        self.commit
+            .language
+            .as_deref()
+            .map(str::trim)
+            .filter(|language| !language.is_empty() && !language.eq_ignore_ascii_case("english"))
+    }
+
+    /// `~/.gyst/config.toml`. On Windows it is `%APPDATA%\gyst\config.toml`
+    /// unless a `~/.gyst` directory from an earlier version exists. Everything
+    /// else gyst keeps for you lives next to it.
+    pub fn get_config_path() -> Result<PathBuf> {
         let home = dirs::home_dir().context("Failed to determine home directory")?;
-        Ok(home.join(".gyst").join("config.toml"))
+        let legacy = home.join(".gyst");
+        if cfg!(windows)
+            && !legacy.exists()
+            && let Some(config_dir) = dirs::config_dir()
+        {
+            return Ok(config_dir.join("gyst").join("config.toml"));
+        }
+        Ok(legacy.join("config.toml"))
     }
 
     pub fn display(&self) -> String {
         let mut output = String::new();
 
         output.push_str("\nAI Configuration:\n");
+        output.push_str(&format!("  Enabled: {}\n", self.ai.enabled));
         output.push_str(&format!("  Provider: {}\n", self.ai.provider));
         output.push_str(&format!("  Model: {}\n", self.ai.model));
+        output.push_str(&format!(
+            "  Compress Prompts: {}\n",
+            self.ai.compress_prompts
+        ));
         output.push_str(&format!(
             "  API Key: {}\n",
             if self.ai.api_key.is_empty() {
@@ -160,21 +805,416 @@ impl Config {
             "  Max Diff Size: {} lines\n",
             self.git.max_diff_size
         ));
+        output.push_str(&format!(
+            "  Rename Threshold: {}%\n",
+            self.git.rename_threshold
+        ));
+        output.push_str(&format!("  Detect Copies: {}\n", self.git.detect_copies));
+        output.push_str(&format!(
+            "  Push Remotes: {}\n",
+            self.git.push_remotes.join(", ")
+        ));
         output.push_str("  Protected Branches:\n");
         for branch in &self.git.protected_branches {
             output.push_str(&format!("    - {}\n", branch));
         }
 
+        output.push_str("\nBranch Configuration:\n");
+        output.push_str(&format!(
+            "  Base: {}\n",
+            self.branch.base.as_deref().unwrap_or("<detected>")
+        ));
+        if self.branch.ignore_patterns.is_empty() {
+            output.push_str("  Ignore Patterns: <none>\n");
+        } else {
+            output.push_str("  Ignore Patterns:\n");
+            for pattern in &self.branch.ignore_patterns {
+                output.push_str(&format!("    - {}\n", pattern));
+            }
+        }
+
         output.push_str("\nCommit Configuration:\n");
         output.push_str(&format!("  Template: {}\n", self.commit.template));
+        if let Some(language) = &self.commit.language {
+            output.push_str(&format!("  Language: {}\n", language));
+        }
+        if let Some(max_wait) = self.commit.max_wait_ms {
+            output.push_str(&format!("  Max Wait: {} ms\n", max_wait));
+        }
+        output.push_str(&format!("  Fast Path: {}\n", self.commit.fast_path));
+        if !self.commit.my_owners.is_empty() {
+            output.push_str(&format!(
+                "  My Code Owners: {}\n",
+                self.commit.my_owners.join(", ")
+            ));
+        }
+        if !self.commit.templates.is_empty() {
+            output.push_str("  Custom Templates:\n");
+            for name in self.commit.templates.keys() {
+                output.push_str(&format!("    - {}\n", name));
+            }
+        }
         output.push_str(&format!(
             "  Max Subject Length: {} characters\n",
             self.commit.max_subject_length
         ));
+        output.push_str(&format!(
+            "  Body Width: {}\n",
+            match self.commit.body_width {
+                0 => "not wrapped".to_string(),
+                width => format!("{} characters", width),
+            }
+        ));
+        output.push_str(&format!(
+            "  Ticket Pattern: {}\n",
+            self.commit.ticket_pattern.as_deref().unwrap_or("<not set>")
+        ));
+        output.push_str(&format!(
+            "  Ticket Placement: {}\n",
+            match self.commit.ticket_placement {
+                TicketPlacement::Trailer => "trailer",
+                TicketPlacement::Prefix => "prefix",
+            }
+        ));
+        if !self.commit.trailers.is_empty() {
+            output.push_str("  Trailers:\n");
+            for trailer in &self.commit.trailers {
+                output.push_str(&format!("    - {}\n", trailer));
+            }
+        }
+        output.push_str(&format!("  Spell Check: {}\n", self.commit.spell_check));
+        if !self.commit.terminology.is_empty() {
+            output.push_str("  Terminology:\n");
+            for (variant, term) in &self.commit.terminology {
+                output.push_str(&format!("    - {} -> {}\n", variant, term));
+            }
+        }
 
         output.push_str("\nServer Configuration:\n");
         output.push_str(&format!("  Use Server: {}\n", self.server.use_server));
+        output.push_str(&format!(
+            "  Token: {}\n",
+            if self.server.token.as_deref().is_some_and(|t| !t.is_empty()) {
+                "********"
+            } else {
+                "<not set>"
+            }
+        ));
+        output.push_str(&format!(
+            "  Public Key: {}\n",
+            self.server
+                .public_key
+                .as_deref()
+                .filter(|key| !key.is_empty())
+                .unwrap_or("<not set>")
+        ));
+
+        output.push_str("\nForge Configuration:\n");
+        output.push_str(&format!(
+            "  GitHub Token: {}\n",
+            if self
+                .forge
+                .github_token
+                .as_deref()
+                .is_some_and(|t| !t.is_empty())
+            {
+                "********"
+            } else {
+                "<not set>"
+            }
+        ));
+        output.push_str(&format!(
+            "  GitHub URL: {}\n",
+            self.forge.github_url.as_deref().unwrap_or("<not set>")
+        ));
+        output.push_str(&format!(
+            "  GitLab URL: {}\n",
+            self.forge.gitlab_url.as_deref().unwrap_or("<not set>")
+        ));
+        output.push_str(&format!(
+            "  GitLab Token: {}\n",
+            if self
+                .forge
+                .gitlab_token
+                .as_deref()
+                .is_some_and(|t| !t.is_empty())
+            {
+                "********"
+            } else {
+                "<not set>"
+            }
+        ));
+        output.push_str(&format!("  Link Issues: {}\n", self.forge.link_issues));
+        output.push_str(&format!(
+            "  Suggest Reviewers: {}\n",
+            self.forge.suggest_reviewers
+        ));
+        if !self.forge.labels.is_empty() {
+            output.push_str("  Pull Request Labels:\n");
+            for (kind, label) in &self.forge.labels {
+                output.push_str(&format!("    - {}: {}\n", kind, label));
+            }
+        }
+
+        output.push_str("\nJira Configuration:\n");
+        output.push_str(&format!(
+            "  URL: {}\n",
+            self.jira.url.as_deref().unwrap_or("<not set>")
+        ));
+        output.push_str(&format!(
+            "  Email: {}\n",
+            self.jira.email.as_deref().unwrap_or("<not set>")
+        ));
+        output.push_str(&format!(
+            "  Token: {}\n",
+            if self.jira.token.as_deref().is_some_and(|t| !t.is_empty()) {
+                "********"
+            } else {
+                "<not set>"
+            }
+        ));
+        output.push_str(&format!(
+            "  Project: {}\n",
+            self.jira.project.as_deref().unwrap_or("<not set>")
+        ));
+
+        output.push_str("\nLinear Configuration:\n");
+        output.push_str(&format!(
+            "  API Key: {}\n",
+            if self
+                .linear
+                .api_key
+                .as_deref()
+                .is_some_and(|k| !k.is_empty())
+            {
+                "********"
+            } else {
+                "<not set>"
+            }
+        ));
+        output.push_str(&format!("  Link Issues: {}\n", self.linear.link_issues));
+
+        if let Some(url) = &self.remote.remote_url {
+            output.push_str("\nTeam Settings:\n");
+            output.push_str(&format!("  URL: {}\n", url));
+            output.push_str(&format!(
+                "  Refresh: every {} minutes\n",
+                self.remote.refresh_minutes
+            ));
+            let fetched = self
+                .team_settings_fetched()
+                .map(|fetched| {
+                    crate::dates::date_time(
+                        chrono::DateTime::<chrono::Local>::from(fetched).timestamp(),
+                    )
+                })
+                .unwrap_or_else(|| "<never>".to_string());
+            output.push_str(&format!("  Last Fetched: {}\n", fetched));
+        }
+
+        output.push_str("\nUsage Statistics:\n");
+        output.push_str(&format!("  Enabled: {}\n", self.telemetry.enabled));
+        output.push_str(&format!("  Upload: {}\n", self.telemetry.upload));
+
+        output.push_str("\nFeedback:\n");
+        output.push_str(&format!("  Record: {}\n", self.feedback.record));
+        output.push_str(&format!("  Examples: {}\n", self.feedback.examples));
+
+        output.push_str("\nNetwork:\n");
+        output.push_str(&format!(
+            "  CA certificates: {}\n",
+            self.network
+                .ca_cert_path
+                .as_deref()
+                .unwrap_or("<system only>")
+        ));
+        output.push_str(&format!(
+            "  Verify certificates: {}\n",
+            if self.network.insecure_skip_verify {
+                "no (insecure_skip_verify)"
+            } else {
+                "yes"
+            }
+        ));
+
+        output.push_str("\nStorage:\n");
+        output.push_str(&format!("  Backend: {}\n", self.storage.backend));
+
+        output.push_str("\nUI Configuration:\n");
+        output.push_str(&format!("  Theme: {}\n", self.ui.theme.preset));
+        for (role, value) in [
+            ("Added", &self.ui.theme.added),
+            ("Removed", &self.ui.theme.removed),
+            ("Warning", &self.ui.theme.warning),
+            ("Prompt", &self.ui.theme.prompt),
+        ] {
+            if let Some(value) = value {
+                output.push_str(&format!("  {} Color: {}\n", role, value));
+            }
+        }
+        output.push_str(&format!("  Dates: {}\n", self.ui.dates));
+        if let Some(format) = &self.ui.date_format {
+            output.push_str(&format!("  Date Format: {}\n", format));
+        }
+        output.push_str(&format!(
+            "  Copy to Clipboard: {}\n",
+            if self.ui.copy { "yes" } else { "no" }
+        ));
 
         output
     }
 }
+
+/// The result of reading a settings bundle, before it is saved
+pub struct BundleImport {
+    pub config: Config,
+    /// Dotted names of the settings the bundle changes, e.g. `commit.template`
+    pub changed: Vec<String>,
+    /// Settings found in the bundle that a bundle cannot change, left alone
+    pub ignored: Vec<String>,
+}
+
+impl Config {
+    /// The shareable settings (conventions, templates, branch rules, labels, theme)
+    /// as TOML, without secrets, per-machine settings or URLs
+    pub fn export_bundle(&self) -> Result<String> {
+        let value = shareable(&toml::Value::try_from(self).context("Failed to serialize config")?);
+        let contents = toml::to_string_pretty(&value).context("Failed to serialize config")?;
+        Ok(format!("{}\n\n{}", BUNDLE_HEADER, contents))
+    }
+
+    /// Layer the settings from a bundle on top of this config. Only the
+    /// project's conventions are read from it; secrets, personal settings and
+    /// where requests go (URLs, the AI provider) are ignored.
+    pub fn import_bundle(&self, contents: &str) -> Result<BundleImport> {
+        let bundle: toml::Value =
+            toml::from_str(contents).context("Failed to parse the settings bundle")?;
+        let overlay = shareable(&bundle);
+        let mut found = BTreeMap::new();
+        flatten_settings("", &bundle, &mut found);
+        let mut kept = BTreeMap::new();
+        flatten_settings("", &overlay, &mut kept);
+        let ignored: Vec<String> = found
+            .into_keys()
+            .filter(|setting| !kept.contains_key(setting))
+            .collect();
+
+        let current = toml::Value::try_from(self).context("Failed to serialize config")?;
+        let mut merged = current.clone();
+        merge_values(&mut merged, overlay);
+        let config: Config = merged
+            .clone()
+            .try_into()
+            .context("Invalid settings in the bundle")?;
+
+        let mut before = BTreeMap::new();
+        flatten_settings("", &current, &mut before);
+        let mut after = BTreeMap::new();
+        flatten_settings("", &merged, &mut after);
+        let changed = after
+            .into_iter()
+            .filter(|(key, value)| before.get(key) != Some(value))
+            .map(|(key, _)| key)
+            .collect();
+
+        Ok(BundleImport {
+            config,
+            changed,
+            ignored,
+        })
+    }
+}
+
+/// Whether `branch` matches one of `patterns`. Entries ending in `*` match
+/// any branch with that prefix.
+pub fn matches_branch(patterns: &[String], branch: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => branch.starts_with(prefix),
+            None => pattern == branch,
+        })
+}
+
+/// Remove the dotted `setting` from a table. Returns whether it was there.
+fn remove_setting(value: &mut toml::Value, setting: &str) -> bool {
+    let (parents, key) = match setting.rsplit_once('.') {
+        Some((parents, key)) => (Some(parents), key),
+        None => (None, setting),
+    };
+    let mut table = value;
+    for part in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        match table.get_mut(part) {
+            Some(next) => table = next,
+            None => return false,
+        }
+    }
+    table
+        .as_table_mut()
+        .is_some_and(|table| table.remove(key).is_some())
+}
+
+/// A copy of `value` with only the settings in `SHAREABLE_SETTINGS`, less the
+/// personal ones among them
+fn shareable(value: &toml::Value) -> toml::Value {
+    let mut kept = keep_settings(value, SHAREABLE_SETTINGS);
+    for setting in PERSONAL_SETTINGS {
+        remove_setting(&mut kept, setting);
+    }
+    kept
+}
+
+/// A copy of `value` with only the given settings, each a table or a key
+fn keep_settings(value: &toml::Value, settings: &[&str]) -> toml::Value {
+    let mut kept = toml::Value::Table(toml::map::Map::new());
+    for setting in settings {
+        let found = setting
+            .split('.')
+            .try_fold(value, |table, part| table.get(part));
+        let Some(found) = found else {
+            continue;
+        };
+        // Wrap the value back in its tables, e.g. `{ jira = { project = .. } }`
+        let nested = setting.rsplit('.').fold(found.clone(), |inner, part| {
+            toml::Value::Table(toml::map::Map::from_iter([(part.to_string(), inner)]))
+        });
+        merge_values(&mut kept, nested);
+    }
+    kept
+}
+
+/// Collect every leaf setting under its dotted name
+fn flatten_settings(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_settings(&name, value, out);
+            }
+        }
+        value => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Recursively merge `overlay` into `base`, with values from `overlay` taking precedence
+fn merge_values(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
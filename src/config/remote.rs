@@ -0,0 +1,165 @@
+use super::{Config, merge_values, shareable};
+use crate::http;
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Where the last fetched team settings are kept, next to `config.toml`
+const CACHE_FILE: &str = "team-config.toml";
+
+/// Give up on the team settings server after this long, so it cannot hold up a command
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Config {
+    /// Fetch the team settings from `config.remote_url` again if the cached
+    /// copy is older than `config.refresh_minutes`. Returns whether they were fetched.
+    pub async fn refresh_team_settings(&self) -> Result<bool> {
+        let Some(url) = self.remote.remote_url.as_deref() else {
+            return Ok(false);
+        };
+        let max_age = Duration::from_secs(self.remote.refresh_minutes * 60);
+        if let Some((_, fetched)) = read_cache(url)
+            && fetched.elapsed().is_ok_and(|age| age < max_age)
+        {
+            return Ok(false);
+        }
+
+        let response = http::client(self, FETCH_TIMEOUT)?
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", url))?
+            .error_for_status()
+            .with_context(|| format!("Failed to fetch {}", url))?;
+        let text = response
+            .text()
+            .await
+            .with_context(|| format!("Failed to read {}", url))?;
+
+        // The team file can share conventions, but not point requests or
+        // tokens somewhere else
+        let team = shareable(&parse(&text).with_context(|| format!("Failed to parse {}", url))?);
+
+        // Only cache settings that load
+        let mut check = toml::Value::try_from(Config::default())?;
+        merge_values(&mut check, team.clone());
+        check
+            .try_into::<Config>()
+            .with_context(|| format!("Invalid settings in {}", url))?;
+
+        write_cache(url, &team)?;
+        Ok(true)
+    }
+
+    /// When the team settings in use were fetched, if there are any
+    pub fn team_settings_fetched(&self) -> Option<SystemTime> {
+        read_cache(self.remote.remote_url.as_deref()?).map(|(_, fetched)| fetched)
+    }
+}
+
+/// The cached team settings for the `config.remote_url` in the global settings `global`
+pub(super) fn team_settings(global: &toml::Value) -> Option<toml::Value> {
+    let url = global.get("config")?.get("remote_url")?.as_str()?;
+    // Copies cached by earlier versions may hold more than conventions
+    read_cache(url).map(|(team, _)| shareable(&team))
+}
+
+/// Fill in the team's settings wherever `target` still has the default value,
+/// so settings you changed yourself win over the team's
+pub(super) fn merge_below(
+    target: &mut toml::Value,
+    team: &toml::Value,
+    default: Option<&toml::Value>,
+) {
+    let (Some(target), Some(team)) = (target.as_table_mut(), team.as_table()) else {
+        return;
+    };
+    for (key, team_value) in team {
+        let default = default.and_then(|default| default.get(key));
+        match target.get_mut(key) {
+            Some(existing) if existing.is_table() && team_value.is_table() => {
+                merge_below(existing, team_value, default)
+            }
+            Some(existing) if Some(&*existing) != default => {}
+            _ => {
+                target.insert(key.clone(), team_value.clone());
+            }
+        }
+    }
+}
+
+/// Undo `merge_below` before saving: settings that match the team's go back to
+/// their defaults, so later changes to the team settings still reach you
+pub(super) fn strip(target: &mut toml::Value, team: &toml::Value, default: Option<&toml::Value>) {
+    let (Some(target), Some(team)) = (target.as_table_mut(), team.as_table()) else {
+        return;
+    };
+    for (key, team_value) in team {
+        let default = default.and_then(|default| default.get(key));
+        let Some(existing) = target.get_mut(key) else {
+            continue;
+        };
+        if existing.is_table() && team_value.is_table() {
+            strip(existing, team_value, default);
+        } else if existing == team_value {
+            match default {
+                Some(default) => *existing = default.clone(),
+                None => {
+                    target.remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// Team settings may be TOML or JSON
+fn parse(text: &str) -> Result<toml::Value> {
+    if let Ok(value) = toml::from_str::<toml::Value>(text) {
+        return Ok(value);
+    }
+    let json: serde_json::Value =
+        serde_json::from_str(text).map_err(|_| anyhow!("Expected a TOML or JSON settings file"))?;
+    toml::Value::try_from(json).context("The JSON settings cannot be expressed as TOML")
+}
+
+fn cache_path() -> Result<PathBuf> {
+    Ok(Config::get_config_path()?.with_file_name(CACHE_FILE))
+}
+
+fn cache_header(url: &str) -> String {
+    format!("# Team settings fetched from {}", url)
+}
+
+/// The cached settings and when they were fetched, if they came from `url`
+fn read_cache(url: &str) -> Option<(toml::Value, SystemTime)> {
+    let path = cache_path().ok()?;
+    let contents = fs::read_to_string(&path).ok()?;
+    let (header, settings) = contents.split_once('\n')?;
+    if header != cache_header(url) {
+        return None;
+    }
+    let fetched = fs::metadata(&path).ok()?.modified().ok()?;
+    Some((toml::from_str(settings).ok()?, fetched))
+}
+
+fn write_cache(url: &str, team: &toml::Value) -> Result<()> {
+    let path = cache_path()?;
+    let dir = path
+        .parent()
+        .context("Config path has no parent directory")?;
+    fs::create_dir_all(dir).context("Failed to create config directory")?;
+
+    let contents = format!(
+        "{}\n{}",
+        cache_header(url),
+        toml::to_string_pretty(team).context("Failed to serialize team settings")?
+    );
+    let mut temp = tempfile::NamedTempFile::new_in(dir).context("Failed to cache team settings")?;
+    temp.write_all(contents.as_bytes())
+        .context("Failed to cache team settings")?;
+    temp.persist(&path)
+        .context("Failed to cache team settings")?;
+    Ok(())
+}
@@ -0,0 +1,521 @@
+use super::{Config, flatten_settings, merge_values};
+use crate::template::Template;
+use crate::theme::Theme;
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// What a setting holds, which decides how `gyst config set` parses its value
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Bool,
+    Number {
+        min: i64,
+        max: i64,
+    },
+    /// A number with a fractional part, e.g. a temperature
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Text,
+    /// Text that is never printed
+    Secret,
+    /// Comma-separated values, e.g. `main,master,release/*`
+    List,
+    Choice(&'static [&'static str]),
+    /// A regular expression
+    Pattern,
+    /// A base64-encoded X25519 public key
+    PublicKey,
+}
+
+/// A setting that `gyst config get/set/unset` accepts
+pub struct Setting {
+    /// Dotted name, e.g. `commit.template`. A `*` part stands for any name.
+    pub key: &'static str,
+    pub about: &'static str,
+    kind: Kind,
+}
+
+const fn setting(key: &'static str, kind: Kind, about: &'static str) -> Setting {
+    Setting { key, about, kind }
+}
+
+pub const SETTINGS: &[Setting] = &[
+    setting("ai.enabled", Kind::Bool, "Use an AI provider at all"),
+    setting(
+        "ai.provider",
+        Kind::Choice(&["anthropic"]),
+        "AI provider for direct API mode",
+    ),
+    setting("ai.api_key", Kind::Secret, "API key for direct API mode"),
+    setting(
+        "ai.model",
+        Kind::Choice(crate::ai::ANTHROPIC_MODELS),
+        "Model for direct API mode",
+    ),
+    setting(
+        "ai.temperature",
+        Kind::Float { min: 0.0, max: 1.0 },
+        "Temperature for commit messages and suggestions",
+    ),
+    setting(
+        "ai.max_tokens",
+        Kind::Number { min: 1, max: 8192 },
+        "Longest commit message reply, in tokens",
+    ),
+    setting(
+        "ai.compress_prompts",
+        Kind::Bool,
+        "Summarize large files when the diff is too long",
+    ),
+    setting(
+        "ai.commands.*.temperature",
+        Kind::Float { min: 0.0, max: 1.0 },
+        "Temperature for one command, e.g. suggest",
+    ),
+    setting(
+        "ai.commands.*.max_tokens",
+        Kind::Number { min: 1, max: 8192 },
+        "Longest reply for one command, in tokens",
+    ),
+    setting(
+        "server.use_server",
+        Kind::Bool,
+        "Use the gyst server instead of your API key",
+    ),
+    setting(
+        "server.token",
+        Kind::Secret,
+        "API token for servers that require one",
+    ),
+    setting(
+        "server.public_key",
+        Kind::PublicKey,
+        "Server key to encrypt requests to",
+    ),
+    setting(
+        "git.max_diff_size",
+        Kind::Number {
+            min: 1,
+            max: i64::MAX,
+        },
+        "Diff lines sent to the AI",
+    ),
+    setting(
+        "git.protected_branches",
+        Kind::List,
+        "Branches that need confirmation to commit to; `release/*` matches a prefix",
+    ),
+    setting(
+        "git.rename_threshold",
+        Kind::Number { min: 0, max: 100 },
+        "Similarity (0-100) for renames and copies",
+    ),
+    setting("git.detect_copies", Kind::Bool, "Detect copied files"),
+    setting(
+        "git.push_remotes",
+        Kind::List,
+        "Remotes --push pushes to, or `all`",
+    ),
+    setting(
+        "branch.base",
+        Kind::Text,
+        "Branch others are compared with, e.g. `develop`; detected when unset",
+    ),
+    setting(
+        "branch.ignore_patterns",
+        Kind::List,
+        "Branches branch health and stale leave out; `release/*` matches a prefix",
+    ),
+    setting(
+        "commit.template",
+        Kind::Text,
+        "Message template: a preset or a custom template",
+    ),
+    setting(
+        "commit.max_subject_length",
+        Kind::Number { min: 1, max: 500 },
+        "Longest allowed subject line",
+    ),
+    setting(
+        "commit.body_width",
+        Kind::Number { min: 0, max: 500 },
+        "Column message bodies are wrapped at (0 to not wrap)",
+    ),
+    setting(
+        "commit.ticket_pattern",
+        Kind::Pattern,
+        "Regex for ticket IDs in branch names",
+    ),
+    setting(
+        "commit.ticket_placement",
+        Kind::Choice(&["trailer", "prefix"]),
+        "Where the ticket ID goes in messages",
+    ),
+    setting(
+        "commit.trailers",
+        Kind::List,
+        "Trailers added to every message",
+    ),
+    setting(
+        "commit.language",
+        Kind::Text,
+        "Language messages are written in",
+    ),
+    setting(
+        "commit.max_wait_ms",
+        Kind::Number {
+            min: 1,
+            max: i64::MAX,
+        },
+        "Milliseconds to wait for the AI before using a template-based message",
+    ),
+    setting(
+        "commit.fast_path",
+        Kind::Bool,
+        "Skip the AI for trivial changes such as version bumps",
+    ),
+    setting(
+        "commit.my_owners",
+        Kind::List,
+        "Your CODEOWNERS handles and teams",
+    ),
+    setting(
+        "commit.terminology.*",
+        Kind::Text,
+        "How a term is spelled in messages, e.g. commit.terminology.postgres",
+    ),
+    setting(
+        "commit.spell_check",
+        Kind::Bool,
+        "Correct common misspellings in generated messages",
+    ),
+    setting("forge.github_token", Kind::Secret, "GitHub token"),
+    setting("forge.github_url", Kind::Text, "GitHub Enterprise URL"),
+    setting("forge.gitlab_url", Kind::Text, "Self-hosted GitLab URL"),
+    setting("forge.gitlab_token", Kind::Secret, "GitLab token"),
+    setting(
+        "forge.link_issues",
+        Kind::Bool,
+        "Give the AI the issue named in the branch",
+    ),
+    setting(
+        "forge.suggest_reviewers",
+        Kind::Bool,
+        "Suggest code owners as reviewers in pull requests",
+    ),
+    setting(
+        "forge.labels.*",
+        Kind::Text,
+        "Pull request label for a commit type",
+    ),
+    setting("jira.url", Kind::Text, "Jira site URL"),
+    setting("jira.email", Kind::Text, "Jira Cloud account email"),
+    setting("jira.token", Kind::Secret, "Jira API token"),
+    setting("jira.project", Kind::Text, "Jira project key"),
+    setting("linear.api_key", Kind::Secret, "Linear personal API key"),
+    setting(
+        "linear.link_issues",
+        Kind::Bool,
+        "Give the AI the Linear issue named in the branch",
+    ),
+    setting(
+        "config.remote_url",
+        Kind::Text,
+        "URL of shared team settings (TOML or JSON)",
+    ),
+    setting(
+        "config.refresh_minutes",
+        Kind::Number { min: 0, max: 10080 },
+        "How long fetched team settings are reused",
+    ),
+    setting(
+        "telemetry.enabled",
+        Kind::Bool,
+        "Record anonymous usage statistics locally",
+    ),
+    setting(
+        "telemetry.upload",
+        Kind::Bool,
+        "Send daily usage totals to the gyst server",
+    ),
+    setting(
+        "feedback.record",
+        Kind::Bool,
+        "Record generated messages you edit or reject",
+    ),
+    setting(
+        "feedback.examples",
+        Kind::Number { min: 0, max: 10 },
+        "Recent edits shown to the AI as examples of your style",
+    ),
+    setting(
+        "network.ca_cert_path",
+        Kind::Text,
+        "PEM file with extra CA certificates to trust",
+    ),
+    setting(
+        "network.insecure_skip_verify",
+        Kind::Bool,
+        "Skip TLS certificate checks (insecure)",
+    ),
+    setting(
+        "storage.backend",
+        Kind::Choice(crate::storage::BACKENDS),
+        "Keep statistics, history index and audit log in files or SQLite",
+    ),
+    setting(
+        "ui.theme.preset",
+        Kind::Choice(crate::theme::PRESETS),
+        "Color preset",
+    ),
+    setting(
+        "ui.theme.added",
+        Kind::Text,
+        "Style for additions, e.g. green.bold",
+    ),
+    setting(
+        "ui.theme.removed",
+        Kind::Text,
+        "Style for deletions and errors",
+    ),
+    setting("ui.theme.warning", Kind::Text, "Style for warnings"),
+    setting(
+        "ui.theme.prompt",
+        Kind::Text,
+        "Style for prompts and headings",
+    ),
+    setting(
+        "ui.dates",
+        Kind::Choice(crate::dates::STYLES),
+        "Relative (3 weeks ago) or absolute dates",
+    ),
+    setting(
+        "ui.date_format",
+        Kind::Text,
+        "strftime pattern for absolute dates, e.g. %d.%m.%Y",
+    ),
+    setting(
+        "ui.copy",
+        Kind::Bool,
+        "Copy generated messages, descriptions and notes to the clipboard",
+    ),
+];
+
+impl Setting {
+    /// The setting for `key`, if gyst knows it
+    pub fn find(key: &str) -> Result<&'static Setting> {
+        SETTINGS
+            .iter()
+            .find(|setting| setting.matches(key))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown setting '{}'. Run 'gyst config list' to see the settings",
+                    key
+                )
+            })
+    }
+
+    /// Whether `key` names this setting, e.g. `forge.labels.feat` for `forge.labels.*`
+    pub fn matches(&self, key: &str) -> bool {
+        let mut parts = key.split('.');
+        let mut pattern = self.key.split('.');
+        loop {
+            match (pattern.next(), parts.next()) {
+                (None, None) => return true,
+                (Some("*"), Some(part)) if !part.is_empty() => {}
+                (Some(expected), Some(part)) if expected == part => {}
+                _ => return false,
+            }
+        }
+    }
+
+    /// Turn the text given on the command line into a value of the right type
+    fn parse(&self, key: &str, text: &str) -> Result<toml::Value> {
+        let value = match self.kind {
+            Kind::Bool => match text.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => toml::Value::Boolean(true),
+                "false" | "no" | "off" | "0" => toml::Value::Boolean(false),
+                _ => return Err(anyhow!("{} must be true or false, not '{}'", key, text)),
+            },
+            Kind::Number { min, max } => {
+                let number: i64 = text
+                    .parse()
+                    .map_err(|_| anyhow!("{} must be a whole number, not '{}'", key, text))?;
+                if !(min..=max).contains(&number) {
+                    return Err(if max == i64::MAX {
+                        anyhow!("{} must be at least {}", key, min)
+                    } else {
+                        anyhow!("{} must be between {} and {}", key, min, max)
+                    });
+                }
+                toml::Value::Integer(number)
+            }
+            Kind::Float { min, max } => {
+                let number: f64 = text
+                    .parse()
+                    .map_err(|_| anyhow!("{} must be a number, not '{}'", key, text))?;
+                if !(min..=max).contains(&number) {
+                    return Err(anyhow!("{} must be between {} and {}", key, min, max));
+                }
+                toml::Value::Float(number)
+            }
+            Kind::Text | Kind::Secret => toml::Value::String(text.to_string()),
+            Kind::List => toml::Value::Array(
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| toml::Value::String(item.to_string()))
+                    .collect(),
+            ),
+            Kind::Choice(choices) => {
+                if !choices.contains(&text) {
+                    return Err(anyhow!(
+                        "{} must be one of {}, not '{}'",
+                        key,
+                        choices.join(", "),
+                        text
+                    ));
+                }
+                toml::Value::String(text.to_string())
+            }
+            Kind::Pattern => {
+                Regex::new(text).with_context(|| format!("Invalid regex for {}", key))?;
+                toml::Value::String(text.to_string())
+            }
+            Kind::PublicKey => {
+                crate::seal::parse_key(text).with_context(|| format!("Invalid {}", key))?;
+                toml::Value::String(text.trim().to_string())
+            }
+        };
+        Ok(value)
+    }
+}
+
+impl Config {
+    /// The value of `key` as text, or `None` if it is not set. Secrets are masked.
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let setting = Setting::find(key)?;
+        let value = toml::Value::try_from(self).context("Failed to serialize config")?;
+        Ok(lookup(&value, key).map(|value| format_value(setting, value)))
+    }
+
+    /// A copy of the config with `key` set to `value`, checked like a config file would be
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<Self> {
+        let value = Setting::find(key)?.parse(key, value)?;
+        let mut overlay = toml::Value::Table(toml::Table::new());
+        insert(&mut overlay, key, value);
+        let mut merged = toml::Value::try_from(self).context("Failed to serialize config")?;
+        merge_values(&mut merged, overlay);
+        finish(merged, key)
+    }
+
+    /// A copy of the config with `key` back at its default, or removed if it has none
+    pub fn unset_setting(&self, key: &str) -> Result<Self> {
+        Setting::find(key)?;
+        let mut value = toml::Value::try_from(self).context("Failed to serialize config")?;
+        let defaults =
+            toml::Value::try_from(Config::default()).context("Failed to serialize config")?;
+        match lookup(&defaults, key) {
+            Some(default) => insert(&mut value, key, default.clone()),
+            None => remove(&mut value, key),
+        }
+        finish(value, key)
+    }
+
+    /// Every setting that has a value, with secrets masked
+    pub fn list_settings(&self) -> Result<Vec<(String, String)>> {
+        let value = toml::Value::try_from(self).context("Failed to serialize config")?;
+        let mut leaves = BTreeMap::new();
+        flatten_settings("", &value, &mut leaves);
+
+        let mut settings = Vec::new();
+        for setting in SETTINGS {
+            if setting.key.contains('*') {
+                for (key, entry) in leaves.iter().filter(|(key, _)| setting.matches(key)) {
+                    settings.push((key.clone(), format_value(setting, entry)));
+                }
+            } else if let Some(entry) = lookup(&value, setting.key) {
+                settings.push((setting.key.to_string(), format_value(setting, entry)));
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Deserialize the changed settings and check what serde cannot: that the
+/// theme, date format, template and ticket pattern are usable
+fn finish(value: toml::Value, key: &str) -> Result<Config> {
+    let config: Config = value
+        .try_into()
+        .with_context(|| format!("Invalid value for {}", key))?;
+    Theme::from_config(&config.ui.theme)?;
+    crate::dates::check(&config.ui)?;
+    Template::resolve(&config)?;
+    Ok(config)
+}
+
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+fn insert(value: &mut toml::Value, key: &str, new: toml::Value) {
+    let (parents, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut table = value;
+    for part in parents.split('.').filter(|part| !part.is_empty()) {
+        let Some(map) = table.as_table_mut() else {
+            return;
+        };
+        table = map
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    }
+    if let Some(map) = table.as_table_mut() {
+        map.insert(name.to_string(), new);
+    }
+}
+
+/// Remove a setting, along with the tables that leaves empty
+fn remove(value: &mut toml::Value, key: &str) {
+    let Some(map) = value.as_table_mut() else {
+        return;
+    };
+    match key.split_once('.') {
+        Some((parent, rest)) => {
+            if let Some(child) = map.get_mut(parent) {
+                remove(child, rest);
+                if child.as_table().is_some_and(|table| table.is_empty()) {
+                    map.remove(parent);
+                }
+            }
+        }
+        None => {
+            map.remove(key);
+        }
+    }
+}
+
+fn format_value(setting: &Setting, value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) if matches!(setting.kind, Kind::Secret) => {
+            if text.is_empty() {
+                String::new()
+            } else {
+                "********".to_string()
+            }
+        }
+        toml::Value::String(text) => text.clone(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map_or_else(|| item.to_string(), str::to_string)
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,409 @@
+//! `gyst ui`: the staged files, their diff, commit message suggestions and
+//! branch health on one screen. This module holds the screen's state, draws
+//! it and turns keys into actions; running the actions is up to the caller.
+
+use crate::branch::{BranchHealth, BranchStatus};
+use crate::diff_view;
+use crate::git::{FilePatch, StagedChanges};
+use crate::theme;
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+
+/// Lines PageUp and PageDown scroll the diff by
+const PAGE: u16 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Files,
+    Diff,
+    Suggestions,
+    Branches,
+}
+
+impl Pane {
+    const ALL: [Pane; 4] = [Pane::Files, Pane::Diff, Pane::Suggestions, Pane::Branches];
+
+    fn title(self) -> &'static str {
+        match self {
+            Pane::Files => "Staged files",
+            Pane::Diff => "Diff",
+            Pane::Suggestions => "Suggestions",
+            Pane::Branches => "Branch health",
+        }
+    }
+
+    fn step(self, forward: bool) -> Pane {
+        let index = Pane::ALL.iter().position(|pane| *pane == self).unwrap_or(0);
+        let next = if forward {
+            index + 1
+        } else {
+            index + Pane::ALL.len() - 1
+        };
+        Pane::ALL[next % Pane::ALL.len()]
+    }
+}
+
+/// What the caller should do after a key press
+pub enum Action {
+    /// Generate suggestions for the staged changes
+    Generate,
+    /// Commit the staged changes with this message
+    Commit(String),
+    /// Read the staged changes again
+    Reload,
+    Quit,
+}
+
+/// A suggested message and what it does wrong according to the template
+pub struct Suggestion {
+    pub message: String,
+    pub problems: Vec<String>,
+}
+
+struct StagedFile {
+    /// A, M, D, R or C, as in `git status --short`
+    status: char,
+    path: String,
+    patch: String,
+}
+
+pub struct Dashboard {
+    branch: String,
+    files: Vec<StagedFile>,
+    file: usize,
+    scroll: u16,
+    suggestions: Vec<Suggestion>,
+    suggestion: usize,
+    branches: Vec<BranchHealth>,
+    branch_row: usize,
+    focus: Pane,
+    status: Line<'static>,
+    busy: bool,
+}
+
+impl Dashboard {
+    pub fn new(branch: String, branches: Vec<BranchHealth>) -> Self {
+        Self {
+            branch,
+            files: Vec::new(),
+            file: 0,
+            scroll: 0,
+            suggestions: Vec::new(),
+            suggestion: 0,
+            branches,
+            branch_row: 0,
+            focus: Pane::Files,
+            status: Line::default(),
+            busy: false,
+        }
+    }
+
+    /// Show these staged changes. Suggestions for the earlier ones are dropped.
+    pub fn set_changes(&mut self, changes: &StagedChanges, patches: Vec<FilePatch>) {
+        let mut files: Vec<(char, String)> = Vec::new();
+        files.extend(changes.added.iter().map(|path| ('A', path.clone())));
+        files.extend(changes.modified.iter().map(|path| ('M', path.clone())));
+        files.extend(changes.deleted.iter().map(|path| ('D', path.clone())));
+        files.extend(changes.renamed.iter().map(|(_, path)| ('R', path.clone())));
+        files.extend(changes.copied.iter().map(|(_, path)| ('C', path.clone())));
+        files.sort_by(|a, b| a.1.cmp(&b.1));
+
+        self.files = files
+            .into_iter()
+            .map(|(status, path)| StagedFile {
+                patch: patches
+                    .iter()
+                    .find(|patch| patch.path == path)
+                    .map(|patch| patch.text.clone())
+                    .unwrap_or_default(),
+                status,
+                path,
+            })
+            .collect();
+        self.file = self.file.min(self.files.len().saturating_sub(1));
+        self.scroll = 0;
+        self.suggestions.clear();
+        self.suggestion = 0;
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.files.is_empty()
+    }
+
+    pub fn set_suggestions(&mut self, suggestions: Vec<Suggestion>) {
+        self.suggestions = suggestions;
+        self.suggestion = 0;
+        self.busy = false;
+        self.focus = Pane::Suggestions;
+        self.set_status("Pick a message and press Enter to commit");
+    }
+
+    /// Show that suggestions are being generated
+    pub fn set_busy(&mut self) {
+        self.busy = true;
+        self.status = Line::styled(
+            "Generating suggestions...",
+            theme::screen().prompt.add_modifier(Modifier::BOLD),
+        );
+    }
+
+    pub fn set_status(&mut self, message: impl Into<String>) {
+        self.status = Line::raw(message.into());
+    }
+
+    pub fn set_success(&mut self, message: impl Into<String>) {
+        self.status = Line::styled(message.into(), theme::screen().added);
+    }
+
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        self.busy = false;
+        self.status = Line::styled(message.into(), theme::screen().removed);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        let down = |index: &mut usize, len: usize| *index = (*index + 1).min(len.saturating_sub(1));
+        let up = |index: &mut usize| *index = index.saturating_sub(1);
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(Action::Quit);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Tab => self.focus = self.focus.step(true),
+            KeyCode::BackTab => self.focus = self.focus.step(false),
+            KeyCode::Char('r') => return Some(Action::Reload),
+            KeyCode::Char('g') if !self.busy && self.has_changes() => {
+                return Some(Action::Generate);
+            }
+            KeyCode::Enter if !self.busy => {
+                if let Some(suggestion) = self.suggestions.get(self.suggestion) {
+                    return Some(Action::Commit(suggestion.message.clone()));
+                }
+                if self.has_changes() {
+                    return Some(Action::Generate);
+                }
+            }
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(PAGE),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE),
+            KeyCode::Down | KeyCode::Char('j') => match self.focus {
+                Pane::Files => {
+                    down(&mut self.file, self.files.len());
+                    self.scroll = 0;
+                }
+                Pane::Diff => self.scroll = self.scroll.saturating_add(1),
+                Pane::Suggestions => down(&mut self.suggestion, self.suggestions.len()),
+                Pane::Branches => down(&mut self.branch_row, self.branches.len()),
+            },
+            KeyCode::Up | KeyCode::Char('k') => match self.focus {
+                Pane::Files => {
+                    up(&mut self.file);
+                    self.scroll = 0;
+                }
+                Pane::Diff => self.scroll = self.scroll.saturating_sub(1),
+                Pane::Suggestions => up(&mut self.suggestion),
+                Pane::Branches => up(&mut self.branch_row),
+            },
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [main, status, keys] = Layout::vertical([
+            Constraint::Min(8),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let [files, branches] =
+            Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(left);
+        let [diff, suggestions] =
+            Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(right);
+
+        self.draw_files(frame, files);
+        self.draw_diff(frame, diff);
+        self.draw_suggestions(frame, suggestions);
+        self.draw_branches(frame, branches);
+        frame.render_widget(Paragraph::new(self.status.clone()), status);
+        frame.render_widget(
+            Paragraph::new(
+                "Tab switch pane  ↑↓ move  PgUp/PgDn scroll diff  g generate  Enter commit  r reload  q quit",
+            )
+            .style(Style::new().add_modifier(Modifier::DIM)),
+            keys,
+        );
+    }
+
+    fn block(&self, pane: Pane, title: String) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.focus == pane {
+            block.border_style(theme::screen().prompt.add_modifier(Modifier::BOLD))
+        } else {
+            block
+        }
+    }
+
+    fn draw_files(&self, frame: &mut Frame, area: Rect) {
+        let title = format!("{} on {}", Pane::Files.title(), self.branch);
+        if self.files.is_empty() {
+            frame.render_widget(
+                Paragraph::new("Nothing staged. Stage changes with 'git add' and press r.")
+                    .wrap(Wrap { trim: true })
+                    .block(self.block(Pane::Files, title)),
+                area,
+            );
+            return;
+        }
+        let styles = theme::screen();
+        let items: Vec<ListItem> = self
+            .files
+            .iter()
+            .map(|file| {
+                let style = match file.status {
+                    'A' => styles.added,
+                    'D' => styles.removed,
+                    _ => styles.warning,
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{} ", file.status), style),
+                    Span::raw(file.path.clone()),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default().with_selected(Some(self.file));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(self.block(Pane::Files, title))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut state,
+        );
+    }
+
+    fn draw_diff(&self, frame: &mut Frame, area: Rect) {
+        let Some(file) = self.files.get(self.file) else {
+            frame.render_widget(Block::bordered().title(Pane::Diff.title()), area);
+            return;
+        };
+        let lines: Vec<Line> = file.patch.lines().map(diff_view::styled_line).collect();
+        let text = if lines.is_empty() {
+            Text::raw("No diff to show (binary file or mode change)")
+        } else {
+            Text::from(lines)
+        };
+        frame.render_widget(
+            Paragraph::new(text)
+                .scroll((self.scroll, 0))
+                .block(self.block(
+                    Pane::Diff,
+                    format!("{} of {}", Pane::Diff.title(), file.path),
+                )),
+            area,
+        );
+    }
+
+    fn draw_suggestions(&self, frame: &mut Frame, area: Rect) {
+        let title = Pane::Suggestions.title().to_string();
+        if self.suggestions.is_empty() {
+            let hint = if self.busy {
+                "Generating..."
+            } else if self.has_changes() {
+                "Press g to generate commit message suggestions."
+            } else {
+                ""
+            };
+            frame.render_widget(
+                Paragraph::new(hint)
+                    .wrap(Wrap { trim: true })
+                    .block(self.block(Pane::Suggestions, title)),
+                area,
+            );
+            return;
+        }
+        let warning = theme::screen().warning;
+        let items: Vec<ListItem> = self
+            .suggestions
+            .iter()
+            .enumerate()
+            .map(|(index, suggestion)| {
+                let mut lines: Vec<Line> = suggestion
+                    .message
+                    .lines()
+                    .enumerate()
+                    .map(|(number, line)| {
+                        let prefix = if number == 0 {
+                            format!("{}. ", index + 1)
+                        } else {
+                            "   ".to_string()
+                        };
+                        Line::raw(format!("{}{}", prefix, line))
+                    })
+                    .collect();
+                lines.extend(
+                    suggestion
+                        .problems
+                        .iter()
+                        .map(|problem| Line::styled(format!("   ! {}", problem), warning)),
+                );
+                ListItem::new(lines)
+            })
+            .collect();
+        let mut state = ListState::default().with_selected(Some(self.suggestion));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(self.block(Pane::Suggestions, title))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut state,
+        );
+    }
+
+    fn draw_branches(&self, frame: &mut Frame, area: Rect) {
+        let styles = theme::screen();
+        let width = self
+            .branches
+            .iter()
+            .map(|branch| branch.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let items: Vec<ListItem> = self
+            .branches
+            .iter()
+            .map(|branch| {
+                let (label, style) = match branch.status {
+                    BranchStatus::Healthy => ("healthy", styles.added),
+                    BranchStatus::NeedsAttention => ("attention", styles.warning),
+                    BranchStatus::Stale => ("stale", styles.removed),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:width$} ", branch.name, width = width)),
+                    Span::styled(format!("{:9} ", label), style),
+                    Span::styled(
+                        format!(
+                            "↑{} ↓{} {}",
+                            branch.ahead_count, branch.behind_count, branch.last_activity_display
+                        ),
+                        Style::new().add_modifier(Modifier::DIM),
+                    ),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default().with_selected(Some(self.branch_row));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(self.block(Pane::Branches, Pane::Branches.title().to_string()))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut state,
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::config::UiConfig;
+use anyhow::{Result, anyhow};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use console::Style;
+use std::sync::OnceLock;
+
+/// How times are shown in reports, selectable with `ui.dates`
+pub const STYLES: &[&str] = &["relative", "absolute"];
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+static FORMAT: OnceLock<DateFormat> = OnceLock::new();
+
+/// How dates and times are written, from `ui.dates`, `ui.date_format` and the locale
+#[derive(Debug, Clone)]
+struct DateFormat {
+    relative: bool,
+    /// strftime pattern for dates
+    date: String,
+    /// strftime pattern for the time of day
+    time: String,
+}
+
+impl DateFormat {
+    fn from_config(config: &UiConfig) -> Result<Self> {
+        let relative = match config.dates.as_str() {
+            "relative" => true,
+            "absolute" => false,
+            other => {
+                return Err(anyhow!(
+                    "Unknown ui.dates '{}'. Use one of {}",
+                    other,
+                    STYLES.join(", ")
+                ));
+            }
+        };
+        let (date, time) = locale_patterns(&locale());
+        let date = match &config.date_format {
+            Some(pattern) => {
+                check_pattern(pattern)?;
+                pattern.clone()
+            }
+            None => date.to_string(),
+        };
+        Ok(Self {
+            relative,
+            date,
+            time: time.to_string(),
+        })
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        let (date, time) = locale_patterns(&locale());
+        Self {
+            relative: true,
+            date: date.to_string(),
+            time: time.to_string(),
+        }
+    }
+}
+
+/// Use the date settings from the configuration. Invalid settings fall back
+/// to the defaults with a warning.
+pub fn init(config: &UiConfig) {
+    let format = DateFormat::from_config(config).unwrap_or_else(|e| {
+        eprintln!("{}", Style::new().yellow().apply_to(e));
+        DateFormat::default()
+    });
+    let _ = FORMAT.set(format);
+}
+
+/// Check that the date settings are usable
+pub fn check(config: &UiConfig) -> Result<()> {
+    DateFormat::from_config(config).map(|_| ())
+}
+
+fn current() -> &'static DateFormat {
+    FORMAT.get_or_init(DateFormat::default)
+}
+
+/// When something happened, in the style chosen with `ui.dates`:
+/// "3 weeks ago" or the date
+pub fn when(time: i64) -> String {
+    if current().relative {
+        ago(time)
+    } else {
+        date(time)
+    }
+}
+
+/// How long something has existed, in the style chosen with `ui.dates`:
+/// "3 weeks" or "since" the date it started
+pub fn age(since: i64) -> String {
+    if current().relative {
+        duration(now() - since)
+    } else {
+        format!("since {}", date(since))
+    }
+}
+
+/// The date in the locale's format, e.g. `05/01/2024` for en_US
+pub fn date(time: i64) -> String {
+    local(time)
+        .map(|dt| dt.format(&current().date).to_string())
+        .unwrap_or_default()
+}
+
+/// The date and time of day in the locale's format
+pub fn date_time(time: i64) -> String {
+    let format = current();
+    local(time)
+        .map(|dt| format!("{} {}", dt.format(&format.date), dt.format(&format.time)))
+        .unwrap_or_default()
+}
+
+/// How long ago `time` was, e.g. "just now", "5 minutes ago" or "3 weeks ago"
+pub fn ago(time: i64) -> String {
+    let seconds = now() - time;
+    if seconds < MINUTE {
+        // Also covers times slightly in the future from clock skew
+        "just now".to_string()
+    } else {
+        format!("{} ago", duration(seconds))
+    }
+}
+
+/// A span of time in its largest whole unit, e.g. "2 months"
+pub fn duration(seconds: i64) -> String {
+    let days = seconds / DAY;
+    let (count, unit) = if seconds < MINUTE {
+        return "less than a minute".to_string();
+    } else if seconds < HOUR {
+        (seconds / MINUTE, "minute")
+    } else if seconds < DAY {
+        (seconds / HOUR, "hour")
+    } else if days < 7 {
+        (days, "day")
+    } else if days < 30 {
+        (days / 7, "week")
+    } else if days < 365 {
+        (days / 30, "month")
+    } else {
+        (days / 365, "year")
+    };
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+fn now() -> i64 {
+    Local::now().timestamp()
+}
+
+fn local(time: i64) -> Option<DateTime<Local>> {
+    DateTime::from_timestamp(time, 0).map(|dt| dt.with_timezone(&Local))
+}
+
+/// Reject strftime patterns chrono cannot format, which would otherwise panic when printed
+fn check_pattern(pattern: &str) -> Result<()> {
+    if pattern.trim().is_empty()
+        || StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error))
+    {
+        return Err(anyhow!(
+            "Invalid ui.date_format '{}'. Use strftime codes such as %Y-%m-%d or %d.%m.%Y",
+            pattern
+        ));
+    }
+    Ok(())
+}
+
+/// The locale for dates, e.g. `de_DE.UTF-8`, from the usual environment variables
+fn locale() -> String {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_default()
+}
+
+/// The date and time patterns customary for `locale`. Unknown locales get
+/// ISO 8601 dates and a 24-hour clock.
+fn locale_patterns(locale: &str) -> (&'static str, &'static str) {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, region) = name.split_once(['_', '-']).unwrap_or((name, ""));
+    match (language, region) {
+        (_, "US") | ("en", "PH") => ("%m/%d/%Y", "%-I:%M %p"),
+        ("en", "CA") => ("%Y-%m-%d", "%-I:%M %p"),
+        ("en", "AU" | "IN" | "NZ") => ("%d/%m/%Y", "%-I:%M %p"),
+        ("en" | "fr" | "es" | "it" | "pt" | "el" | "ca" | "vi" | "id", _) => ("%d/%m/%Y", "%H:%M"),
+        ("nl", _) => ("%d-%m-%Y", "%H:%M"),
+        (
+            "de" | "ru" | "pl" | "cs" | "sk" | "fi" | "nb" | "nn" | "no" | "da" | "tr" | "uk"
+            | "ro" | "hr" | "sl" | "et" | "lv" | "is" | "sr" | "bg",
+            _,
+        ) => ("%d.%m.%Y", "%H:%M"),
+        ("ja" | "zh", _) => ("%Y/%m/%d", "%H:%M"),
+        ("ko" | "hu", _) => ("%Y. %m. %d.", "%H:%M"),
+        _ => ("%Y-%m-%d", "%H:%M"),
+    }
+}
@@ -0,0 +1,131 @@
+//! A full-screen, scrollable view of the staged diff, opened from prompts
+//! such as the `gyst suggest` selector and closed to return to them
+
+use crate::git::FilePatch;
+use crate::theme;
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, Paragraph};
+
+/// How much of the diff is read for the view
+pub const MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// A line of a patch in the colors of the theme
+pub fn styled_line(line: &str) -> Line<'static> {
+    let styles = theme::screen();
+    let style = if line.starts_with("diff --git") {
+        styles.prompt.add_modifier(Modifier::BOLD)
+    } else if line.starts_with("+++") || line.starts_with("---") {
+        Style::new().add_modifier(Modifier::BOLD)
+    } else if line.starts_with('+') {
+        styles.added
+    } else if line.starts_with('-') {
+        styles.removed
+    } else if line.starts_with("@@") {
+        styles.prompt
+    } else {
+        Style::new()
+    };
+    Line::styled(line.to_string(), style)
+}
+
+/// Show `patches` until q or Esc is pressed. `unread` files that did not fit
+/// in `MAX_BYTES` are mentioned at the end.
+pub fn show(patches: &[FilePatch], unread: usize) -> Result<()> {
+    let mut lines: Vec<Line> = Vec::new();
+    // Where each file starts, for jumping between files
+    let mut starts: Vec<(usize, &str)> = Vec::new();
+    for patch in patches {
+        starts.push((lines.len(), patch.path.as_str()));
+        lines.extend(patch.text.lines().map(styled_line));
+    }
+    if lines.is_empty() {
+        lines.push(Line::raw(
+            "No diff to show (binary files or mode changes only)",
+        ));
+    }
+    if unread > 0 {
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            format!("{} more files are not shown", unread),
+            theme::screen().warning,
+        ));
+    }
+
+    let mut terminal = ratatui::init();
+    let mut scroll: usize = 0;
+    let result = loop {
+        let mut height = 0;
+        let drawn = terminal.draw(|frame| {
+            let [body, help] =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+            height = body.height.saturating_sub(2) as usize;
+            scroll = scroll.min(lines.len().saturating_sub(height));
+
+            let file = starts
+                .iter()
+                .rposition(|&(start, _)| start <= scroll)
+                .unwrap_or(0);
+            let title = match starts.get(file) {
+                Some((_, path)) => format!(
+                    " Staged changes: {} ({} of {}) ",
+                    path,
+                    file + 1,
+                    starts.len()
+                ),
+                None => " Staged changes ".to_string(),
+            };
+            frame.render_widget(
+                // Only the visible lines, as the diff can be long
+                Paragraph::new(Text::from(
+                    lines[scroll..(scroll + height).min(lines.len())].to_vec(),
+                ))
+                .block(Block::bordered().title(title)),
+                body,
+            );
+            frame.render_widget(
+                Paragraph::new(Line::styled(
+                    "↑↓ scroll  PgUp/PgDn page  n/p next/previous file  q back",
+                    Style::new().add_modifier(Modifier::DIM),
+                )),
+                help,
+            );
+        });
+        if let Err(e) = drawn {
+            break Err(e.into());
+        }
+
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e.into()),
+        };
+        let page = height.max(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Ok(()),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => scroll = scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => scroll += page,
+            KeyCode::PageUp | KeyCode::Char('b') => scroll = scroll.saturating_sub(page),
+            KeyCode::Home | KeyCode::Char('g') => scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => scroll = lines.len(),
+            KeyCode::Char('n') => {
+                if let Some(&(start, _)) = starts.iter().find(|&&(start, _)| start > scroll) {
+                    scroll = start;
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(&(start, _)) = starts.iter().rev().find(|&&(start, _)| start < scroll) {
+                    scroll = start;
+                }
+            }
+            _ => {}
+        }
+    };
+    ratatui::restore();
+    result
+}
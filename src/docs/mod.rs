@@ -0,0 +1,314 @@
+use crate::git::{FileVersions, GitRepo};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Show at most this many names of each kind in the prompt
+const MAX_NAMES: usize = 8;
+
+/// What a change that only touches documentation documents: Markdown
+/// (README, `docs/`, mdBook chapters) and Rust doc comments
+#[derive(Debug, Default)]
+pub struct DocsChange {
+    /// Headings and mdBook chapters that are new
+    pub added_sections: Vec<String>,
+    /// Headings whose section text changed
+    pub updated_sections: Vec<String>,
+    /// Headings that are gone
+    pub removed_sections: Vec<String>,
+    /// Rust items whose doc comments were added or changed, e.g. `Config::load`
+    pub items: Vec<String>,
+    pub files: Vec<String>,
+}
+
+impl DocsChange {
+    /// Look at the staged changes. Returns `None` unless every staged file is
+    /// documentation or a Rust file whose only changes are to doc comments.
+    pub fn staged(repo: &GitRepo) -> Result<Option<Self>> {
+        let from = repo.has_head().then_some("HEAD");
+        let files = repo.changed_file_versions(from, None, |_| true)?;
+        Ok(Self::compare(&files))
+    }
+
+    fn compare(files: &[FileVersions]) -> Option<Self> {
+        if files.is_empty() {
+            return None;
+        }
+
+        let mut change = Self::default();
+        for file in files {
+            let old = file.old.as_deref().unwrap_or("");
+            let new = file.new.as_deref().unwrap_or("");
+            if file.path.ends_with(".rs") {
+                // Deleted or new source files change more than documentation
+                if file.old.is_none()
+                    || file.new.is_none()
+                    || without_doc_comments(old) != without_doc_comments(new)
+                {
+                    return None;
+                }
+                change.compare_doc_comments(&file.path, old, new);
+            } else if is_docs_file(&file.path) {
+                change.compare_markdown(&file.path, old, new);
+            } else {
+                return None;
+            }
+            change.files.push(file.path.clone());
+        }
+        Some(change)
+    }
+
+    /// Instructions for the AI to describe the documentation rather than the diff
+    pub fn prompt_hint(&self) -> String {
+        let names = |names: &[String]| {
+            let mut list = names
+                .iter()
+                .take(MAX_NAMES)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            if names.len() > MAX_NAMES {
+                list.push_str(&format!(" and {} more", names.len() - MAX_NAMES));
+            }
+            list
+        };
+
+        let mut hint = String::from(
+            "These changes only touch documentation. Write a 'docs' commit whose subject says what was documented, added or clarified (name the items or sections), not which files changed.",
+        );
+        for (label, list) in [
+            ("Items with new or changed doc comments", &self.items),
+            ("New sections", &self.added_sections),
+            ("Updated sections", &self.updated_sections),
+            ("Removed sections", &self.removed_sections),
+        ] {
+            if !list.is_empty() {
+                hint.push_str(&format!("\n{}: {}", label, names(list)));
+            }
+        }
+        hint
+    }
+
+    /// Headings and mdBook chapters added, changed and removed
+    fn compare_markdown(&mut self, path: &str, old: &str, new: &str) {
+        let old_sections = sections(old);
+        let new_sections = sections(new);
+        for (heading, text) in &new_sections {
+            match old_sections.get(heading) {
+                None => push_unique(&mut self.added_sections, heading),
+                Some(old_text) if old_text != text => {
+                    push_unique(&mut self.updated_sections, heading)
+                }
+                Some(_) => {}
+            }
+        }
+        for heading in old_sections.keys() {
+            if !new_sections.contains_key(heading) {
+                push_unique(&mut self.removed_sections, heading);
+            }
+        }
+
+        if file_name(path).eq_ignore_ascii_case("SUMMARY.md") {
+            let old_chapters = chapters(old);
+            for chapter in chapters(new) {
+                if !old_chapters.contains(&chapter) {
+                    push_unique(&mut self.added_sections, &chapter);
+                }
+            }
+        }
+    }
+
+    /// Items whose doc comments are new or different
+    fn compare_doc_comments(&mut self, path: &str, old: &str, new: &str) {
+        let old_docs = doc_comments(path, old);
+        for (item, docs) in doc_comments(path, new) {
+            if old_docs.get(&item) != Some(&docs) {
+                push_unique(&mut self.items, &item);
+            }
+        }
+    }
+}
+
+/// Markdown and other prose, and mdBook's configuration
+fn is_docs_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    [".md", ".markdown", ".mdx", ".rst", ".adoc", ".txt"]
+        .iter()
+        .any(|ext| lower.ends_with(ext))
+        || file_name(&lower) == "book.toml"
+        || lower.starts_with("docs/")
+        || lower.contains("/docs/")
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn is_doc_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    (line.starts_with("///") && !line.starts_with("////"))
+        || line.starts_with("//!")
+        || line.starts_with("#[doc")
+        || line.starts_with("#![doc")
+}
+
+/// The code lines of a Rust file, without doc comments and blank lines
+fn without_doc_comments(source: &str) -> Vec<&str> {
+    source
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !is_doc_comment(line))
+        .collect()
+}
+
+/// The text of each Markdown section, keyed by its heading. Text before the
+/// first heading belongs to the empty heading, which is never reported.
+fn sections(text: &str) -> BTreeMap<String, String> {
+    let mut sections = BTreeMap::new();
+    let mut heading = String::new();
+    let mut body = String::new();
+    let mut fence: Option<&str> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+            }
+            None if is_heading(trimmed) => {
+                sections.insert(std::mem::take(&mut heading), std::mem::take(&mut body));
+                heading = trimmed
+                    .trim_start_matches('#')
+                    .trim()
+                    .trim_end_matches('#')
+                    .trim()
+                    .to_string();
+                continue;
+            }
+            None => {}
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    sections.insert(heading, body);
+    sections.remove("");
+    sections
+}
+
+fn is_heading(line: &str) -> bool {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&hashes) && line[hashes..].starts_with(' ')
+}
+
+/// Chapter titles listed in an mdBook `SUMMARY.md`, e.g. `Installation` for `- [Installation](install.md)`
+fn chapters(summary: &str) -> Vec<String> {
+    let Ok(link) = Regex::new(r"\[([^\]]+)\]\([^)]*\)") else {
+        return Vec::new();
+    };
+    summary
+        .lines()
+        .filter_map(|line| link.captures(line).map(|caps| caps[1].trim().to_string()))
+        .collect()
+}
+
+/// The doc comment of each documented item, keyed by the item's name. Inner
+/// `//!` comments document the module itself.
+fn doc_comments(path: &str, source: &str) -> BTreeMap<String, String> {
+    let mut docs = BTreeMap::new();
+    let mut pending = String::new();
+    let mut module_docs = String::new();
+    // The type whose fields, variants or methods are being documented
+    let mut container: Option<String> = None;
+
+    for line in source.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("//!") || trimmed.starts_with("#![doc") {
+            module_docs.push_str(trimmed);
+            module_docs.push('\n');
+            continue;
+        }
+        if is_doc_comment(trimmed) {
+            pending.push_str(trimmed);
+            pending.push('\n');
+            continue;
+        }
+        if trimmed.starts_with("#[") || trimmed.is_empty() {
+            continue;
+        }
+
+        let top_level = !line.starts_with(char::is_whitespace);
+        let name = item_name(trimmed);
+        if top_level {
+            container = container_name(trimmed);
+        }
+        if !pending.is_empty() {
+            if let Some(name) = name {
+                let name = match (&container, top_level) {
+                    (Some(container), false) => format!("{}::{}", container, name),
+                    _ => name,
+                };
+                docs.insert(name, std::mem::take(&mut pending));
+            }
+            pending.clear();
+        }
+    }
+
+    if !module_docs.is_empty() {
+        docs.insert(module_name(path), module_docs);
+    }
+    docs
+}
+
+/// The name declared on an item, field or variant line
+fn item_name(line: &str) -> Option<String> {
+    let item = Regex::new(
+        r"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+\S+)\s+)*(?:fn|struct|enum|union|trait|type|mod|const|static|macro_rules!)\s+([A-Za-z_][A-Za-z0-9_]*)",
+    )
+    .ok()?;
+    if let Some(caps) = item.captures(line) {
+        return Some(caps[1].to_string());
+    }
+    if line.starts_with("impl") {
+        return container_name(line);
+    }
+    // Struct fields (`pub name: Type`) and enum variants (`Name`, `Name(..)`, `Name { .. }`)
+    let member =
+        Regex::new(r"^(?:pub(?:\([^)]*\))?\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*[:,({=]?").ok()?;
+    member
+        .captures(line)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| !matches!(name.as_str(), "let" | "use" | "return" | "if" | "match"))
+}
+
+/// The type named by a top-level `impl`, `struct`, `enum` or `trait` line
+fn container_name(line: &str) -> Option<String> {
+    let container = Regex::new(
+        r"^(?:pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?(?:impl(?:<[^>]*>)?\s+(?:[\w:<>, ]+\s+for\s+)?|struct\s+|enum\s+|union\s+|trait\s+)([A-Za-z_][A-Za-z0-9_]*)",
+    )
+    .ok()?;
+    container.captures(line).map(|caps| caps[1].to_string())
+}
+
+/// `crate` for the crate root, otherwise the module's name, e.g. `git` for `src/git/mod.rs`
+fn module_name(path: &str) -> String {
+    let path = Path::new(path);
+    match path.file_stem().and_then(|stem| stem.to_str()) {
+        Some("lib" | "main") => "crate".to_string(),
+        Some("mod") => path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .and_then(|dir| dir.to_str())
+            .unwrap_or("crate")
+            .to_string(),
+        Some(stem) => stem.to_string(),
+        None => "crate".to_string(),
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, name: &str) {
+    if !list.iter().any(|existing| existing == name) {
+        list.push(name.to_string());
+    }
+}
@@ -0,0 +1,436 @@
+//! Environment checks for `gyst doctor`.
+//!
+//! Each check says what it found and, when something is wrong, what to do
+//! about it. Later checks still run after a failure so one run shows every
+//! problem.
+
+use crate::ai::CommitMessageGenerator;
+use crate::chaos;
+use crate::config::Config;
+use crate::dates;
+use crate::editor::{self, Editor};
+use crate::git::GitRepo;
+use crate::http;
+use crate::server::ServerClient;
+use crate::template::Template;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The Anthropic API, checked for reachability in direct API mode
+const ANTHROPIC_URL: &str = "https://api.anthropic.com";
+
+/// How long the reachability check waits for an answer
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Works, but something may get in the way
+    Warning,
+    Failed,
+    /// Not relevant for the current configuration
+    Skipped,
+}
+
+/// The result of one check
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Skipped,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warning,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failed(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Failed,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check for the repository at `repo_path`, handing each result to
+/// `report` as soon as it is known
+pub async fn run(repo_path: &str, mut report: impl FnMut(&Check)) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let mut add = |check: Check| {
+        report(&check);
+        checks.push(check);
+    };
+
+    add(repository(repo_path));
+    add(libgit2());
+    add(git_executable());
+
+    let config = match Config::load_for_repo(repo_path) {
+        Ok(config) => {
+            add(configuration(&config));
+            config
+        }
+        Err(e) => {
+            add(Check::failed(
+                "Configuration",
+                format!("{:#}", e),
+                "Fix the file named above, or start over with 'gyst init'",
+            ));
+            Config::default()
+        }
+    };
+
+    add(tls(&config));
+    let online = network(&config).await;
+    let reachable = online.status == Status::Ok;
+    add(online);
+    add(ai_access(&config, reachable).await);
+    add(editor(repo_path));
+
+    checks
+}
+
+fn repository(repo_path: &str) -> Check {
+    const NAME: &str = "Git repository";
+    match GitRepo::open(repo_path) {
+        Ok(repo) => match repo.workdir() {
+            Ok(workdir) => Check::ok(NAME, workdir.display().to_string().trim_end_matches('/')),
+            Err(e) => Check::failed(
+                NAME,
+                e.to_string(),
+                "Run gyst in a checkout, or point -C at one",
+            ),
+        },
+        Err(e) => Check::failed(
+            NAME,
+            format!("{:#}", e),
+            "Run gyst inside a git repository, or point -C at one",
+        ),
+    }
+}
+
+fn libgit2() -> Check {
+    let version = git2::Version::get();
+    let (major, minor, patch) = version.libgit2_version();
+    let detail = format!(
+        "{}.{}.{} ({})",
+        major,
+        minor,
+        patch,
+        if version.vendored() {
+            "bundled"
+        } else {
+            "system library"
+        }
+    );
+    if !version.https() {
+        return Check::warning(
+            "libgit2",
+            format!("{}, built without HTTPS support", detail),
+            "Remote checks may fail. Reinstall gyst with the bundled libgit2 (the default build)",
+        );
+    }
+    Check::ok("libgit2", detail)
+}
+
+fn git_executable() -> Check {
+    const NAME: &str = "git executable";
+    match std::process::Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => Check::ok(
+            NAME,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        _ => Check::warning(
+            NAME,
+            "git was not found on PATH",
+            "Install git: pushing and credential lookups use it",
+        ),
+    }
+}
+
+/// Settings that parse but cannot be used
+fn configuration(config: &Config) -> Check {
+    const NAME: &str = "Configuration";
+    if let Err(e) = Template::resolve(config) {
+        return Check::failed(
+            NAME,
+            format!("{:#}", e),
+            "Pick another template with 'gyst config set commit.template <name>'",
+        );
+    }
+    if let Err(e) = dates::check(&config.ui) {
+        return Check::failed(
+            NAME,
+            e.to_string(),
+            "Change it with 'gyst config set', or remove it with 'gyst config unset'",
+        );
+    }
+    let path = Config::get_config_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    if Path::new(&path).exists() {
+        Check::ok(NAME, path)
+    } else {
+        Check::warning(
+            NAME,
+            format!("{} does not exist, so the defaults are used", path),
+            "Run 'gyst init' to set gyst up",
+        )
+    }
+}
+
+/// Extra CA certificates load, and certificate checks are on
+fn tls(config: &Config) -> Check {
+    const NAME: &str = "TLS";
+    let certificates = match http::ca_certificates(&config.network) {
+        Ok(certificates) => certificates,
+        Err(e) => {
+            return Check::failed(
+                NAME,
+                format!("{:#}", e),
+                "Point network.ca_cert_path at a PEM file, or remove it with 'gyst config unset network.ca_cert_path'",
+            );
+        }
+    };
+    if config.network.insecure_skip_verify {
+        return Check::warning(
+            NAME,
+            "Certificate checks are off (network.insecure_skip_verify)",
+            "Trust your proxy's CA with network.ca_cert_path instead, then 'gyst config unset network.insecure_skip_verify'",
+        );
+    }
+    if certificates.is_empty() {
+        Check::ok(NAME, "System certificates")
+    } else {
+        Check::ok(
+            NAME,
+            format!(
+                "System certificates and {} from network.ca_cert_path",
+                certificates.len()
+            ),
+        )
+    }
+}
+
+/// Whether the AI provider or gyst server answers at all, through the proxy if one is set
+async fn network(config: &Config) -> Check {
+    const NAME: &str = "Network";
+    if !config.ai_enabled() {
+        return Check::skipped(NAME, "AI is turned off (ai.enabled = false)");
+    }
+    let url = if config.use_server() {
+        ServerClient::new(config.clone()).get_server_url()
+    } else {
+        ANTHROPIC_URL.to_string()
+    };
+    let proxy = proxy();
+    let via = proxy
+        .as_deref()
+        .map(|proxy| format!(" via proxy {}", proxy))
+        .unwrap_or_default();
+
+    let client = match http::client(config, NETWORK_TIMEOUT) {
+        Ok(client) => client,
+        Err(e) => {
+            return Check::failed(
+                NAME,
+                format!("Could not create an HTTP client: {:#}", e),
+                "Check the proxy settings in HTTPS_PROXY and ALL_PROXY, and network.ca_cert_path",
+            );
+        }
+    };
+    // Any answer, even an error status, shows the host is reachable
+    match chaos::send(&client, client.get(&url)).await {
+        Ok(_) => Check::ok(NAME, format!("{} is reachable{}", url, via)),
+        Err(e) => {
+            let error = error_chain(&e);
+            let fix = if error.contains("certificate") {
+                "A proxy or server presented a certificate that is not trusted. Point network.ca_cert_path at its CA certificate (PEM)"
+            } else if proxy.is_some() {
+                "Check that the proxy in HTTPS_PROXY (or ALL_PROXY) is running and allows this host"
+            } else {
+                "Check your internet connection. Behind a corporate proxy, set HTTPS_PROXY"
+            };
+            Check::failed(
+                NAME,
+                format!("Could not reach {}{}: {}", url, via, error),
+                fix,
+            )
+        }
+    }
+}
+
+/// The API key (direct mode) or the server's health endpoint (server mode)
+async fn ai_access(config: &Config, reachable: bool) -> Check {
+    if !config.ai_enabled() {
+        return Check::skipped(
+            "AI provider",
+            "AI is turned off; template-based messages are used",
+        );
+    }
+
+    if config.use_server() {
+        const NAME: &str = "gyst server";
+        if !reachable {
+            return Check::skipped(NAME, "Not checked: the server cannot be reached");
+        }
+        return match ServerClient::new(config.clone()).health_check().await {
+            Ok(health) if health.draining => Check::failed(
+                NAME,
+                "The server is starting up or shutting down",
+                "Try again in a minute",
+            ),
+            Ok(health) if !health.ok => Check::failed(
+                NAME,
+                "The health check reported a problem",
+                "Try again later, or use your own API key with 'gyst init'",
+            ),
+            Ok(health) if health.anthropic_reachable == Some(false) => Check::failed(
+                NAME,
+                "The server is up but cannot reach the Anthropic API",
+                "Try again later, or use your own API key with 'gyst init'",
+            ),
+            Ok(health) => match health.summary() {
+                Some(summary) => Check::ok(NAME, format!("The health check passed ({})", summary)),
+                None => Check::ok(NAME, "The health check passed"),
+            },
+            Err(e) => Check::failed(
+                NAME,
+                format!("{:#}", e),
+                "Try again later, or use your own API key with 'gyst init'",
+            ),
+        };
+    }
+
+    const NAME: &str = "API key";
+    if config.get_api_key().is_none() {
+        return Check::failed(
+            NAME,
+            "No Anthropic API key is set",
+            "Run 'gyst init', or 'gyst config set ai.api_key <key>'",
+        );
+    }
+    if !reachable {
+        return Check::skipped(NAME, "Set, but not checked: the API cannot be reached");
+    }
+    match CommitMessageGenerator::new(config.clone())
+        .validate_key()
+        .await
+    {
+        Ok(()) => Check::ok(
+            NAME,
+            format!("Accepted by Anthropic (model {})", config.ai.model),
+        ),
+        Err(e) => {
+            let error = format!("{:#}", e);
+            let fix = if error.contains("401") || error.contains("authentication") {
+                "The key was rejected. Create a new one at https://console.anthropic.com and run 'gyst init'"
+            } else if error.contains("model") {
+                "Pick a model your key can use with 'gyst config set ai.model <model>'"
+            } else {
+                "Run 'gyst --verbose doctor' to see the request and response"
+            };
+            Check::failed(NAME, error, fix)
+        }
+    }
+}
+
+/// The editor for `e(edit)`, found like git finds its own, must be installed
+fn editor(repo_path: &str) -> Check {
+    const NAME: &str = "Editor";
+    let editor = Editor::resolve(repo_path);
+    let program = editor.program();
+
+    // A program named through a variable is up to the shell to find
+    if !program.contains('$') && find_program(&program).is_none() {
+        let detail = match editor.source {
+            Some(source) => format!(
+                "{} is '{}', but {} was not found",
+                source, editor.command, program
+            ),
+            None => format!(
+                "No editor is configured and {} was not found",
+                editor::DEFAULT
+            ),
+        };
+        return Check::failed(
+            NAME,
+            detail,
+            "Set an installed editor with 'git config --global core.editor \"code --wait\"' or EDITOR in your shell profile",
+        );
+    }
+    match editor.source {
+        Some(source) => Check::ok(NAME, format!("{} (from {})", editor.command, source)),
+        None => Check::warning(
+            NAME,
+            format!("No editor is configured, so {} is used", editor::DEFAULT),
+            "Set one with 'git config --global core.editor nano' or EDITOR in your shell profile",
+        ),
+    }
+}
+
+/// Where `program` would be started from: the path itself if it has a
+/// directory, otherwise the first match on `PATH`
+fn find_program(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The proxy requests go through, from the environment variables reqwest
+/// reads, with any password masked
+fn proxy() -> Option<String> {
+    let proxy = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))?;
+    Some(match reqwest::Url::parse(&proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("****"));
+            url.to_string()
+        }
+        _ => proxy,
+    })
+}
+
+/// The error and its causes, e.g. "error sending request: connection refused"
+fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,119 @@
+//! The editor messages are opened in, found the way git finds its own:
+//! `GIT_EDITOR`, then `core.editor`, then `VISUAL` (unless the terminal is
+//! dumb), then `EDITOR`, then a default
+
+use crate::shell::split_words;
+use std::path::Path;
+use std::process::Command;
+
+/// The editor when none is configured
+#[cfg(windows)]
+pub const DEFAULT: &str = "notepad";
+#[cfg(not(windows))]
+pub const DEFAULT: &str = "vi";
+
+pub struct Editor {
+    /// The editor command as configured, possibly with arguments, e.g. `code --wait`
+    pub command: String,
+    /// Where the command came from, e.g. `core.editor`; `None` for the default
+    pub source: Option<&'static str>,
+}
+
+impl Editor {
+    /// The editor for the repository at `repo_path`, whose `core.editor` wins
+    /// over the global one
+    pub fn resolve(repo_path: &str) -> Self {
+        let env = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+
+        let found = env("GIT_EDITOR")
+            .map(|command| (command, "GIT_EDITOR"))
+            .or_else(|| core_editor(repo_path).map(|command| (command, "core.editor")))
+            .or_else(|| {
+                env("VISUAL")
+                    .filter(|_| !dumb)
+                    .map(|command| (command, "VISUAL"))
+            })
+            .or_else(|| env("EDITOR").map(|command| (command, "EDITOR")));
+        match found {
+            Some((command, source)) => Self {
+                command,
+                source: Some(source),
+            },
+            None => Self {
+                command: DEFAULT.to_string(),
+                source: None,
+            },
+        }
+    }
+
+    /// The program the command starts, without its arguments
+    pub fn program(&self) -> String {
+        split_words(&self.command)
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// A command that opens `path`. Like git, an editor with arguments or
+    /// shell syntax is run by the shell: `sh -c` on Unix and `cmd /C` on
+    /// Windows, where batch files such as `code.cmd` also need it. Other
+    /// editors are started directly.
+    pub fn command(&self, path: &Path) -> Command {
+        #[cfg(windows)]
+        if self
+            .command
+            .contains(|c: char| c.is_whitespace() || "|&<>^%\"".contains(c))
+            || [".cmd", ".bat"]
+                .iter()
+                .any(|extension| self.command.to_lowercase().ends_with(extension))
+        {
+            use std::os::windows::process::CommandExt;
+            let mut command = Command::new("cmd");
+            // cmd has its own quoting rules, so the line is passed as written;
+            // with /S only the outer quotes are removed from it
+            command.args(["/S", "/C"]).raw_arg(format!(
+                "\"{} \"{}\"\"",
+                self.command,
+                path.display()
+            ));
+            return command;
+        }
+
+        #[cfg(not(windows))]
+        if self
+            .command
+            .contains(|c: char| c.is_whitespace() || "|&;<>()$`\\\"'*?[#~=%".contains(c))
+        {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(format!("{} \"$@\"", self.command))
+                .arg(&self.command)
+                .arg(path);
+            return command;
+        }
+
+        let mut words = split_words(&self.command).into_iter();
+        let mut command = Command::new(words.next().unwrap_or_else(|| DEFAULT.to_string()));
+        command.args(words).arg(path);
+        command
+    }
+}
+
+/// `core.editor` from the repository's configuration, or the global one
+/// outside a repository
+fn core_editor(repo_path: &str) -> Option<String> {
+    let config = match git2::Repository::discover(repo_path) {
+        Ok(repo) => repo.config().ok()?,
+        Err(_) => git2::Config::open_default().ok()?,
+    };
+    config
+        .get_string("core.editor")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+}
@@ -0,0 +1,117 @@
+//! The exit statuses scripts and editor plugins can rely on.
+//!
+//! | Status | Meaning |
+//! |--------|---------|
+//! | 0 | Success |
+//! | 1 | Any other error |
+//! | 2 | Nothing to commit: no staged changes |
+//! | 3 | Aborted at a prompt |
+//! | 4 | The AI provider or gyst server failed |
+//! | 5 | A git operation failed |
+//! | 6 | Configuration or input gyst does not accept, including a setting missing in CI |
+//!
+//! Errors carry their status by being wrapped in a `Failure`, which prints
+//! exactly like the error inside it. Unmarked errors from libgit2 count as
+//! git errors and unreadable TOML as configuration errors.
+
+use std::fmt;
+
+/// Any error not marked with a more specific status
+pub const ERROR: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    NothingStaged = 2,
+    Aborted = 3,
+    Provider = 4,
+    Git = 5,
+    Config = 6,
+}
+
+impl Status {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Outcomes that are reported as a warning rather than an error
+    pub fn is_expected(self) -> bool {
+        matches!(self, Status::NothingStaged | Status::Aborted)
+    }
+}
+
+/// An error that ends gyst with `status`
+#[derive(Debug)]
+pub struct Failure {
+    pub status: Status,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for Failure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+fn mark(status: Status, error: anyhow::Error) -> anyhow::Error {
+    // Keep the status of an error that already has one
+    if self::status(&error).is_some() {
+        return error;
+    }
+    Failure { status, error }.into()
+}
+
+/// There is nothing staged to commit
+pub fn nothing_staged(message: &str) -> anyhow::Error {
+    mark(Status::NothingStaged, anyhow::anyhow!("{}", message))
+}
+
+/// The AI provider or gyst server failed or refused the request
+pub fn provider(error: anyhow::Error) -> anyhow::Error {
+    mark(Status::Provider, error)
+}
+
+/// A git operation failed or the repository is not in a state gyst can work with
+pub fn git(error: anyhow::Error) -> anyhow::Error {
+    mark(Status::Git, error)
+}
+
+/// An option, setting or value gyst does not accept
+pub fn invalid(error: anyhow::Error) -> anyhow::Error {
+    mark(Status::Config, error)
+}
+
+/// The user said no at a prompt
+pub fn aborted(message: &str) -> anyhow::Error {
+    mark(Status::Aborted, anyhow::anyhow!("{}", message))
+}
+
+/// The status `error` was marked with, even under added context, or the one
+/// its cause implies
+pub fn status(error: &anyhow::Error) -> Option<Status> {
+    let marked = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<Failure>())
+        .map(|failure| failure.status);
+    marked.or_else(|| {
+        error.chain().find_map(|cause| {
+            if cause.is::<git2::Error>() {
+                Some(Status::Git)
+            } else if cause.is::<toml::de::Error>() {
+                Some(Status::Config)
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// The process exit status for `error`
+pub fn code(error: &anyhow::Error) -> i32 {
+    status(error).map_or(ERROR, Status::code)
+}
@@ -0,0 +1,121 @@
+//! Generated commit messages you edited or rejected, kept in
+//! `~/.gyst/feedback/corrections.json` (or `gyst.db`, see `storage`). With
+//! `feedback.examples`, your most recent edits are shown to the AI as examples
+//! of how you like messages written.
+
+use crate::config::Config;
+use crate::storage::{self, Store};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const CORRECTIONS_DOCUMENT: &str = "corrections";
+
+/// Older corrections are dropped once there are this many
+const MAX_CORRECTIONS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// The message was changed in the editor before committing
+    Edited,
+    /// The message was turned down at the prompt
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Correction {
+    /// Unix timestamp
+    pub time: i64,
+    pub outcome: Outcome,
+    /// The message as generated
+    pub original: String,
+    /// What it was changed to, for an edit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited: Option<String>,
+}
+
+/// Record that the generated `original` was committed as `edited`. Nothing is
+/// recorded when it was left as it was or `feedback.record` is off.
+pub fn record_edit(config: &Config, original: &str, edited: &str) -> Result<()> {
+    if original.trim() == edited.trim() {
+        return Ok(());
+    }
+    record(config, Outcome::Edited, original, Some(edited))
+}
+
+/// Record that the generated `original` was rejected
+pub fn record_rejection(config: &Config, original: &str) -> Result<()> {
+    record(config, Outcome::Rejected, original, None)
+}
+
+fn record(config: &Config, outcome: Outcome, original: &str, edited: Option<&str>) -> Result<()> {
+    if !config.feedback.record {
+        return Ok(());
+    }
+    let mut corrections = corrections()?;
+    corrections.push(Correction {
+        time: chrono::Utc::now().timestamp(),
+        outcome,
+        original: original.trim().to_string(),
+        edited: edited.map(|edited| edited.trim().to_string()),
+    });
+    let excess = corrections.len().saturating_sub(MAX_CORRECTIONS);
+    corrections.drain(..excess);
+
+    let contents =
+        serde_json::to_string_pretty(&corrections).context("Failed to serialize feedback")?;
+    feedback_store()?
+        .write(CORRECTIONS_DOCUMENT, &contents)
+        .context("Failed to save feedback")
+}
+
+/// Every recorded correction, oldest first
+pub fn corrections() -> Result<Vec<Correction>> {
+    let Some(contents) = feedback_store()?.read(CORRECTIONS_DOCUMENT)? else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(&contents).context("Failed to parse feedback")
+}
+
+/// Delete all recorded corrections. Returns whether there were any.
+pub fn clear() -> Result<bool> {
+    feedback_store()?.remove(CORRECTIONS_DOCUMENT)
+}
+
+/// Your `feedback.examples` most recent edits as examples for the AI, if any
+pub fn prompt_hint(config: &Config) -> Option<String> {
+    if config.feedback.examples == 0 {
+        return None;
+    }
+    let corrections = corrections().ok()?;
+    let mut examples: Vec<(&str, &str)> = corrections
+        .iter()
+        .rev()
+        .filter_map(|correction| {
+            Some((correction.original.as_str(), correction.edited.as_deref()?))
+        })
+        .take(config.feedback.examples)
+        .collect();
+    if examples.is_empty() {
+        return None;
+    }
+    examples.reverse();
+
+    let mut hint = "The user rewrote these generated messages before committing them. Write the new message the way the user would, following their rewritten versions.".to_string();
+    for (original, edited) in examples {
+        hint.push_str(&format!(
+            "\n\nGenerated:\n{}\nRewritten by the user:\n{}",
+            original, edited
+        ));
+    }
+    Some(hint)
+}
+
+/// The store in `~/.gyst/feedback`
+fn feedback_store() -> Result<Box<dyn Store>> {
+    let path = Config::get_config_path()?;
+    let dir = path
+        .parent()
+        .context("Config path has no parent directory")?;
+    storage::open(&dir.join("feedback"))
+}
@@ -0,0 +1,103 @@
+use crate::git::{CommitInfo, StagedChanges};
+
+/// Value of `--fixup`/`--squash` when no ref is given
+pub const AUTO_TARGET: &str = "auto";
+
+/// How many recent commits are considered when no target is given
+pub const HISTORY_DEPTH: usize = 30;
+
+/// How many candidates are offered to the AI and the user
+pub const MAX_CANDIDATES: usize = 5;
+
+pub const SYSTEM_PROMPT: &str = r#"You are an assistant that helps developers create fixup commits for git autosquash rebases.
+Given staged changes and a numbered list of recent commits, decide which commit the changes most likely fix or extend.
+Reply with ONLY the number of the chosen commit, nothing else."#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixupKind {
+    Fixup,
+    Squash,
+}
+
+impl FixupKind {
+    /// Build the commit message git's `--autosquash` recognizes for `target`.
+    /// For squash commits git asks for the combined message during the rebase.
+    pub fn message(&self, target: &CommitInfo) -> String {
+        match self {
+            FixupKind::Fixup => format!("fixup! {}", target.summary),
+            FixupKind::Squash => format!("squash! {}", target.summary),
+        }
+    }
+}
+
+/// A commit that might be the target of a fixup, with the staged files it also touched
+pub struct Candidate {
+    pub commit: CommitInfo,
+    pub overlap: Vec<String>,
+}
+
+/// Rank recent commits by how many of the staged files they touched.
+/// Commits that touched none of them are dropped.
+pub fn rank_candidates(
+    changes: &StagedChanges,
+    history: Vec<(CommitInfo, Vec<String>)>,
+) -> Vec<Candidate> {
+    let staged: Vec<&String> = changes
+        .added
+        .iter()
+        .chain(changes.modified.iter())
+        .chain(changes.deleted.iter())
+        .chain(changes.renamed.iter().flat_map(|(old, new)| [old, new]))
+        .chain(changes.copied.iter().map(|(_, new)| new))
+        .collect();
+
+    let mut candidates: Vec<Candidate> = history
+        .into_iter()
+        // Fixups of fixups are squashed into the original anyway
+        .filter(|(commit, _)| {
+            !commit.summary.starts_with("fixup! ") && !commit.summary.starts_with("squash! ")
+        })
+        .map(|(commit, files)| {
+            let overlap = files
+                .into_iter()
+                .filter(|file| staged.contains(&file))
+                .collect();
+            Candidate { commit, overlap }
+        })
+        .filter(|candidate: &Candidate| !candidate.overlap.is_empty())
+        .collect();
+
+    // Stable sort keeps newer commits first among equal overlaps
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.overlap.len()));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+/// Build the prompt asking the AI to choose among `candidates`
+pub fn build_prompt(candidates: &[Candidate], diff: &str) -> String {
+    let mut prompt = String::from("Recent commits:\n");
+    for (i, candidate) in candidates.iter().enumerate() {
+        prompt.push_str(&format!(
+            "{}. {} {} (files: {})\n",
+            i + 1,
+            candidate.commit.id,
+            candidate.commit.summary,
+            candidate.overlap.join(", ")
+        ));
+    }
+
+    prompt.push_str("\nStaged changes:\n");
+    prompt.push_str(diff);
+    prompt.push_str("\nWhich commit do these changes belong to?");
+    prompt
+}
+
+/// Parse the AI's reply into an index into the candidate list
+pub fn parse_choice(reply: &str, candidates: usize) -> Option<usize> {
+    reply
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|part| !part.is_empty())
+        .and_then(|number| number.parse::<usize>().ok())
+        .filter(|n| (1..=candidates).contains(n))
+        .map(|n| n - 1)
+}
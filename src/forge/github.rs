@@ -0,0 +1,297 @@
+use super::{Issue, MAX_PAGES, PAGE_SIZE, PullRequest, find_token, http_client, instance_of};
+use crate::config::Config;
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Environment variables checked for a GitHub token when `forge.github_token` is not set
+const TOKEN_VARS: &[&str] = &["GITHUB_TOKEN", "GH_TOKEN"];
+
+#[derive(Debug, Deserialize)]
+struct RepositoryResponse {
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullResponse {
+    number: u64,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    head: HeadResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeadResponse {
+    #[serde(rename = "ref")]
+    name: String,
+    repo: Option<RepoResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepoResponse {
+    full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewResponse {
+    state: String,
+    user: Option<UserResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserResponse {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+/// A GitHub repository, accessed with the configured token
+pub struct GitHub {
+    client: Client,
+    api_url: String,
+    token: String,
+    /// `owner/name`
+    repository: String,
+}
+
+impl GitHub {
+    /// Connect to the repository at `path` on `host`. Returns `None` if there
+    /// is no token, or the host is neither `github.com` nor the one in
+    /// `forge.github_url`: the token is only sent to a GitHub you named.
+    pub fn from_remote(config: &Config, host: &str, path: &str) -> Result<Option<Self>> {
+        if path.split('/').count() != 2 {
+            return Ok(None);
+        }
+        let enterprise = config
+            .forge
+            .github_url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .filter(|url| !url.is_empty())
+            .filter(|url| {
+                instance_of(url).is_some_and(|(instance, _)| instance.eq_ignore_ascii_case(host))
+            });
+        let api_url = match enterprise {
+            // GitHub Enterprise Server
+            Some(url) => format!("{}/api/v3", url),
+            None if host.eq_ignore_ascii_case("github.com") => "https://api.github.com".to_string(),
+            None => return Ok(None),
+        };
+        let Some(token) = find_token(config.forge.github_token.as_deref(), TOKEN_VARS, host) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            client: http_client(config)?,
+            api_url,
+            token,
+            repository: path.to_string(),
+        }))
+    }
+
+    /// The open pull requests whose branches live in this repository (not in forks),
+    /// keyed by branch name
+    pub async fn open_pull_requests(&self) -> Result<HashMap<String, PullRequest>> {
+        let mut pulls = HashMap::new();
+
+        for page in 1..=MAX_PAGES {
+            let path = format!(
+                "/repos/{}/pulls?state=open&per_page={}&page={}",
+                self.repository, PAGE_SIZE, page
+            );
+            let batch: Vec<PullResponse> = self.get(&path).await?;
+            let done = batch.len() < PAGE_SIZE;
+
+            for pull in batch {
+                let same_repo = pull
+                    .head
+                    .repo
+                    .as_ref()
+                    .is_some_and(|repo| repo.full_name.eq_ignore_ascii_case(&self.repository));
+                if !same_repo {
+                    continue;
+                }
+                pulls.insert(
+                    pull.head.name,
+                    PullRequest {
+                        number: pull.number,
+                        url: pull.html_url,
+                        review_state: String::new(),
+                        draft: pull.draft,
+                    },
+                );
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(pulls)
+    }
+
+    /// Fill in `review_state` from the latest review of each reviewer
+    pub async fn load_review_state(&self, pull: &mut PullRequest) -> Result<()> {
+        let path = format!(
+            "/repos/{}/pulls/{}/reviews?per_page={}",
+            self.repository, pull.number, PAGE_SIZE
+        );
+        let reviews: Vec<ReviewResponse> = self.get(&path).await?;
+
+        // Reviews are returned oldest first, so later ones replace earlier ones
+        let mut latest: HashMap<String, String> = HashMap::new();
+        for review in reviews {
+            if matches!(review.state.as_str(), "APPROVED" | "CHANGES_REQUESTED")
+                && let Some(user) = review.user
+            {
+                latest.insert(user.login, review.state);
+            }
+        }
+
+        pull.review_state = if latest.values().any(|s| s == "CHANGES_REQUESTED") {
+            "changes requested"
+        } else if latest.values().any(|s| s == "APPROVED") {
+            "approved"
+        } else if pull.draft {
+            "draft"
+        } else {
+            "review required"
+        }
+        .to_string();
+        Ok(())
+    }
+
+    /// The branch pull requests go to by default
+    pub async fn default_branch(&self) -> Result<String> {
+        let repository: RepositoryResponse =
+            self.get(&format!("/repos/{}", self.repository)).await?;
+        Ok(repository.default_branch)
+    }
+
+    pub async fn issue(&self, number: u64) -> Result<Issue> {
+        let issue: IssueResponse = self
+            .get(&format!("/repos/{}/issues/{}", self.repository, number))
+            .await?;
+        Ok(Issue {
+            number: issue.number,
+            title: issue.title,
+            body: issue.body.unwrap_or_default(),
+            forge: "GitHub",
+            kind: "issue",
+        })
+    }
+
+    /// The open pull request whose head is `branch` of this repository
+    pub async fn pull_request_for_branch(&self, branch: &str) -> Result<Option<Issue>> {
+        let owner = self.repository.split('/').next().unwrap_or_default();
+        let head = format!("{}:{}", owner, branch);
+        let pulls: Vec<IssueResponse> = self
+            .get_query(
+                &format!("/repos/{}/pulls", self.repository),
+                &[("state", "open"), ("head", &head)],
+            )
+            .await?;
+        Ok(pulls.into_iter().next().map(|pull| Issue {
+            number: pull.number,
+            title: pull.title,
+            body: pull.body.unwrap_or_default(),
+            forge: "GitHub",
+            kind: "pull request",
+        }))
+    }
+
+    /// Open a pull request from `head` (a branch of this repository) into `base`
+    pub async fn create_pull_request(
+        &self,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> Result<PullRequest> {
+        let pull: PullResponse = self
+            .post(
+                &format!("/repos/{}/pulls", self.repository),
+                &json!({
+                    "head": head,
+                    "base": base,
+                    "title": title,
+                    "body": body,
+                    "draft": draft,
+                }),
+            )
+            .await?;
+        Ok(PullRequest {
+            number: pull.number,
+            url: pull.html_url,
+            review_state: if pull.draft {
+                "draft"
+            } else {
+                "review required"
+            }
+            .to_string(),
+            draft: pull.draft,
+        })
+    }
+
+    /// Add labels to a pull request or issue. GitHub creates labels that do not exist yet.
+    pub async fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        let _: serde_json::Value = self
+            .post(
+                &format!("/repos/{}/issues/{}/labels", self.repository, number),
+                &json!({ "labels": labels }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = format!("{}{}", self.api_url, path);
+        self.send(self.client.get(&url)).await
+    }
+
+    async fn get_query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        let url = format!("{}{}", self.api_url, path);
+        self.send(self.client.get(&url).query(query)).await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> Result<T> {
+        let url = format!("{}{}", self.api_url, path);
+        self.send(self.client.post(&url).json(body)).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "gyst")
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .context("Failed to reach GitHub")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("GitHub API error ({}): {}", status, error_text));
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse GitHub response")
+    }
+}
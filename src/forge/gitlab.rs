@@ -0,0 +1,276 @@
+use super::{Issue, MAX_PAGES, PAGE_SIZE, PullRequest, find_token, http_client, instance_of};
+use crate::config::Config;
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Environment variables checked for a GitLab token when `forge.gitlab_token` is not set
+const TOKEN_VARS: &[&str] = &["GITLAB_TOKEN"];
+
+#[derive(Debug, Deserialize)]
+struct ProjectResponse {
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeRequestResponse {
+    iid: u64,
+    web_url: String,
+    #[serde(default)]
+    draft: bool,
+    source_branch: String,
+    source_project_id: u64,
+    project_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApprovalsResponse {
+    #[serde(default)]
+    approved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+    iid: u64,
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// A GitLab project on gitlab.com or a self-hosted instance, accessed with the configured token
+pub struct GitLab {
+    client: Client,
+    api_url: String,
+    token: String,
+    /// The URL-encoded `group/name` path, as the API expects it
+    project: String,
+}
+
+impl GitLab {
+    /// Connect to the project at `path` on `host`. Returns `None` if there is
+    /// no token, or the host is neither `gitlab.com` nor the one in
+    /// `forge.gitlab_url`: the token is only sent to a GitLab you named.
+    pub fn from_remote(config: &Config, host: &str, path: &str) -> Result<Option<Self>> {
+        let configured = config
+            .forge
+            .gitlab_url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .filter(|url| !url.is_empty());
+
+        let (base_url, path) = match configured.and_then(|url| Some((url, instance_of(url)?))) {
+            Some((url, (instance_host, prefix))) if instance_host.eq_ignore_ascii_case(host) => {
+                // Instances served under a path, e.g. `https://example.com/gitlab`, put
+                // the prefix in HTTPS remote URLs but not in SSH ones
+                let path = path
+                    .strip_prefix(&format!("{}/", prefix))
+                    .filter(|_| !prefix.is_empty())
+                    .unwrap_or(path);
+                (url.to_string(), path)
+            }
+            _ if host.eq_ignore_ascii_case("gitlab.com") => {
+                ("https://gitlab.com".to_string(), path)
+            }
+            _ => return Ok(None),
+        };
+
+        let Some(token) = find_token(config.forge.gitlab_token.as_deref(), TOKEN_VARS, host) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            client: http_client(config)?,
+            api_url: format!("{}/api/v4", base_url),
+            token,
+            project: path.replace('/', "%2F"),
+        }))
+    }
+
+    /// The open merge requests whose branches live in this project (not in forks),
+    /// keyed by branch name
+    pub async fn open_merge_requests(&self) -> Result<HashMap<String, PullRequest>> {
+        let mut requests = HashMap::new();
+
+        for page in 1..=MAX_PAGES {
+            let path = format!(
+                "/projects/{}/merge_requests?state=opened&per_page={}&page={}",
+                self.project, PAGE_SIZE, page
+            );
+            let batch: Vec<MergeRequestResponse> = self.get(&path).await?;
+            let done = batch.len() < PAGE_SIZE;
+
+            for request in batch {
+                if request.source_project_id != request.project_id {
+                    continue;
+                }
+                requests.insert(
+                    request.source_branch,
+                    PullRequest {
+                        number: request.iid,
+                        url: request.web_url,
+                        review_state: String::new(),
+                        draft: request.draft,
+                    },
+                );
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(requests)
+    }
+
+    /// Fill in `review_state` from the merge request's approval rules
+    pub async fn load_review_state(&self, request: &mut PullRequest) -> Result<()> {
+        let path = format!(
+            "/projects/{}/merge_requests/{}/approvals",
+            self.project, request.number
+        );
+        let approvals: ApprovalsResponse = self.get(&path).await?;
+
+        request.review_state = if approvals.approved {
+            "approved"
+        } else if request.draft {
+            "draft"
+        } else {
+            "review required"
+        }
+        .to_string();
+        Ok(())
+    }
+
+    /// The branch merge requests go to by default
+    pub async fn default_branch(&self) -> Result<String> {
+        let project: ProjectResponse = self.get(&format!("/projects/{}", self.project)).await?;
+        Ok(project.default_branch)
+    }
+
+    pub async fn issue(&self, number: u64) -> Result<Issue> {
+        let issue: IssueResponse = self
+            .get(&format!("/projects/{}/issues/{}", self.project, number))
+            .await?;
+        Ok(Issue {
+            number: issue.iid,
+            title: issue.title,
+            body: issue.description.unwrap_or_default(),
+            forge: "GitLab",
+            kind: "issue",
+        })
+    }
+
+    /// The open merge request whose source is `branch` of this project
+    pub async fn merge_request_for_branch(&self, branch: &str) -> Result<Option<Issue>> {
+        let requests: Vec<IssueResponse> = self
+            .get_query(
+                &format!("/projects/{}/merge_requests", self.project),
+                &[("state", "opened"), ("source_branch", branch)],
+            )
+            .await?;
+        Ok(requests.into_iter().next().map(|request| Issue {
+            number: request.iid,
+            title: request.title,
+            body: request.description.unwrap_or_default(),
+            forge: "GitLab",
+            kind: "merge request",
+        }))
+    }
+
+    /// Open a merge request from `source` (a branch of this project) into `target`.
+    /// GitLab marks merge requests as drafts by their title.
+    pub async fn create_merge_request(
+        &self,
+        source: &str,
+        target: &str,
+        title: &str,
+        description: &str,
+        draft: bool,
+    ) -> Result<PullRequest> {
+        let title = if draft {
+            format!("Draft: {}", title)
+        } else {
+            title.to_string()
+        };
+        let request: MergeRequestResponse = self
+            .send(
+                self.client
+                    .post(self.url(&format!("/projects/{}/merge_requests", self.project)))
+                    .json(&json!({
+                        "source_branch": source,
+                        "target_branch": target,
+                        "title": title,
+                        "description": description,
+                        "remove_source_branch": true,
+                    })),
+            )
+            .await?;
+        Ok(PullRequest {
+            number: request.iid,
+            url: request.web_url,
+            review_state: if request.draft {
+                "draft"
+            } else {
+                "review required"
+            }
+            .to_string(),
+            draft: request.draft,
+        })
+    }
+
+    /// Add labels to a merge request. GitLab creates labels that do not exist yet.
+    pub async fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        let url = self.url(&format!(
+            "/projects/{}/merge_requests/{}",
+            self.project, number
+        ));
+        let _: serde_json::Value = self
+            .send(
+                self.client
+                    .put(url)
+                    .json(&json!({ "add_labels": labels.join(",") })),
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_url, path)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.client.get(self.url(path))).await
+    }
+
+    async fn get_query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        self.send(self.client.get(self.url(path)).query(query))
+            .await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request
+            .header("User-Agent", "gyst")
+            .header("PRIVATE-TOKEN", &self.token)
+            .send()
+            .await
+            .context("Failed to reach GitLab")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("GitLab API error ({}): {}", status, error_text));
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse GitLab response")
+    }
+}
@@ -0,0 +1,299 @@
+mod github;
+mod gitlab;
+
+use crate::config::Config;
+use crate::git::GitRepo;
+use crate::http;
+use anyhow::{Result, anyhow};
+use github::GitHub;
+use gitlab::GitLab;
+use regex::Regex;
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Give up on the forge after this long, so an unreachable server cannot hold up a commit
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Issue bodies are cut off after this many characters before they go into a prompt
+const MAX_ISSUE_BODY: usize = 1500;
+
+/// GitHub and GitLab return at most 100 items per page
+const PAGE_SIZE: usize = 100;
+
+/// Stop paging after this many pages of open pull requests
+const MAX_PAGES: usize = 10;
+
+/// An open pull request (or GitLab merge request), as shown in branch reports
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: String,
+    /// `approved`, `changes requested`, `review required` or `draft`
+    pub review_state: String,
+    #[serde(skip)]
+    draft: bool,
+}
+
+/// An issue referenced by a branch name, or the open pull request for a branch
+#[derive(Debug)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    /// Name of the forge the issue lives on
+    pub forge: &'static str,
+    /// `issue`, `pull request` or `merge request`
+    pub kind: &'static str,
+}
+
+impl Issue {
+    /// How the forge refers to the item: `#12`, or `!12` for merge requests
+    pub fn reference(&self) -> String {
+        if self.kind == "merge request" {
+            format!("!{}", self.number)
+        } else {
+            format!("#{}", self.number)
+        }
+    }
+
+    /// Context for the AI about the issue the change addresses
+    pub fn prompt_hint(&self) -> String {
+        let verb = if self.kind == "issue" {
+            "addresses"
+        } else {
+            "is part of"
+        };
+        let mut hint = format!(
+            "This work {} {} {} {}: {}",
+            verb,
+            self.forge,
+            self.kind,
+            self.reference(),
+            self.title
+        );
+        let body = self.body.trim();
+        if !body.is_empty() {
+            let body: String = body.chars().take(MAX_ISSUE_BODY).collect();
+            hint.push_str(&format!("\nDescription:\n{}", body));
+        }
+        hint
+    }
+}
+
+/// The code hosting service behind the `origin` remote
+pub enum Forge {
+    GitHub(GitHub),
+    GitLab(GitLab),
+}
+
+impl Forge {
+    /// Connect to the forge hosting the `origin` remote: GitLab for gitlab.com
+    /// or the host in `forge.gitlab_url`, GitHub for github.com or the host in
+    /// `forge.github_url`. Returns `None` for other hosts or without a token;
+    /// tokens, including those from git's credential helper, are only looked
+    /// up for these hosts.
+    pub fn from_repo(config: &Config, repo: &GitRepo) -> Result<Option<Self>> {
+        let Some((host, path)) = repo
+            .remote_url("origin")
+            .as_deref()
+            .and_then(parse_remote_url)
+        else {
+            return Ok(None);
+        };
+
+        if let Some(gitlab) = GitLab::from_remote(config, &host, &path)? {
+            return Ok(Some(Self::GitLab(gitlab)));
+        }
+        Ok(GitHub::from_remote(config, &host, &path)?.map(Self::GitHub))
+    }
+
+    /// Like `from_repo`, but explains what is missing instead of returning `None`
+    pub fn connect(config: &Config, repo: &GitRepo) -> Result<Self> {
+        Self::from_repo(config, repo)?.ok_or_else(|| {
+            anyhow!(
+                "This needs an 'origin' remote on GitHub or GitLab and a token. Set forge.github_token or forge.gitlab_token in ~/.gyst/config.toml, the GITHUB_TOKEN or GITLAB_TOKEN environment variable, or store a token with git's credential helper. For GitHub Enterprise or self-hosted GitLab, also set forge.github_url or forge.gitlab_url"
+            )
+        })
+    }
+
+    /// What the forge calls a pull request
+    pub fn request_name(&self) -> &'static str {
+        match self {
+            Self::GitHub(_) => "pull request",
+            Self::GitLab(_) => "merge request",
+        }
+    }
+
+    /// How the forge refers to pull request `number`: `#12` or `!12`
+    pub fn reference(&self, number: u64) -> String {
+        match self {
+            Self::GitHub(_) => format!("#{}", number),
+            Self::GitLab(_) => format!("!{}", number),
+        }
+    }
+
+    /// The branch pull requests go to by default
+    pub async fn default_branch(&self) -> Result<String> {
+        match self {
+            Self::GitHub(github) => github.default_branch().await,
+            Self::GitLab(gitlab) => gitlab.default_branch().await,
+        }
+    }
+
+    /// The open pull requests whose branches live in this repository (not in forks),
+    /// keyed by branch name
+    pub async fn open_pull_requests(&self) -> Result<HashMap<String, PullRequest>> {
+        match self {
+            Self::GitHub(github) => github.open_pull_requests().await,
+            Self::GitLab(gitlab) => gitlab.open_merge_requests().await,
+        }
+    }
+
+    /// Fill in the pull request's `review_state`
+    pub async fn load_review_state(&self, pull: &mut PullRequest) -> Result<()> {
+        match self {
+            Self::GitHub(github) => github.load_review_state(pull).await,
+            Self::GitLab(gitlab) => gitlab.load_review_state(pull).await,
+        }
+    }
+
+    pub async fn issue(&self, number: u64) -> Result<Issue> {
+        match self {
+            Self::GitHub(github) => github.issue(number).await,
+            Self::GitLab(gitlab) => gitlab.issue(number).await,
+        }
+    }
+
+    /// The open pull request for `branch` of this repository, with its title and description
+    pub async fn pull_request_for_branch(&self, branch: &str) -> Result<Option<Issue>> {
+        match self {
+            Self::GitHub(github) => github.pull_request_for_branch(branch).await,
+            Self::GitLab(gitlab) => gitlab.merge_request_for_branch(branch).await,
+        }
+    }
+
+    /// Open a pull request from `head` (a branch of this repository) into `base`
+    pub async fn create_pull_request(
+        &self,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> Result<PullRequest> {
+        match self {
+            Self::GitHub(github) => {
+                github
+                    .create_pull_request(head, base, title, body, draft)
+                    .await
+            }
+            Self::GitLab(gitlab) => {
+                gitlab
+                    .create_merge_request(head, base, title, body, draft)
+                    .await
+            }
+        }
+    }
+
+    /// Add labels to a pull request. Labels that do not exist yet are created.
+    pub async fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        match self {
+            Self::GitHub(github) => github.add_labels(number, labels).await,
+            Self::GitLab(gitlab) => gitlab.add_labels(number, labels).await,
+        }
+    }
+}
+
+/// The issue number in a branch name such as `fix/123-crash`, `issue-42` or `gh-7-docs`
+pub fn issue_number_from_branch(branch: &str) -> Option<u64> {
+    let pattern = Regex::new(r"(?:^|/)(?:issue[-_]?|gh-|#)?(\d+)(?:[-_]|$)").ok()?;
+    pattern
+        .captures(branch)
+        .and_then(|caps| caps[1].parse().ok())
+}
+
+/// Pick the token from the configuration, then the environment, then git's
+/// credential helper for `host`
+fn find_token(configured: Option<&str>, vars: &[&str], host: &str) -> Option<String> {
+    configured
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            vars.iter()
+                .find_map(|var| std::env::var(var).ok().filter(|token| !token.is_empty()))
+        })
+        .or_else(|| credential_helper_token(host))
+}
+
+fn http_client(config: &Config) -> Result<Client> {
+    http::client(config, REQUEST_TIMEOUT)
+}
+
+/// Ask git's credential helper, which may keep the token in the system keychain,
+/// for the password stored for `host`. Never prompts.
+fn credential_helper_token(host: &str) -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child
+        .stdin
+        .take()?
+        .write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())
+        .ok()?;
+
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+}
+
+/// Split a remote URL such as `git@github.com:owner/repo.git` or
+/// `https://gitlab.example.com/group/sub/repo` into the host and the repository path
+fn parse_remote_url(url: &str) -> Option<(String, String)> {
+    let (host, path) = match url.split_once("://") {
+        // URL form, possibly with a user and port: `ssh://git@host:22/owner/repo`
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?.split(':').next()?;
+            (host, path)
+        }
+        // scp-like form: `git@host:owner/repo`
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            (authority.rsplit('@').next()?, path)
+        }
+    };
+    let path = path
+        .trim_start_matches('/')
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+    Some((host.to_string(), path.to_string()))
+}
+
+/// The host of a GitHub or GitLab instance URL and the path it is served under, e.g.
+/// `("example.com", "gitlab")` for `https://example.com/gitlab`
+fn instance_of(url: &str) -> Option<(String, String)> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (authority, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| (host.to_string(), prefix.trim_matches('/').to_string()))
+}
@@ -1,7 +1,23 @@
+use crate::exit;
 use anyhow::{Context, Result};
-use git2::{Delta, Repository, StatusOptions};
+use git2::{Delta, ErrorCode, Repository, StatusOptions};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// Above this many files, prompts describe the changes by directory instead
+/// of listing every file
+const LARGE_CHANGE_FILES: usize = 150;
+
+/// Directories shown in a directory summary; the rest are counted together
+const MAX_SUMMARY_DIRECTORIES: usize = 40;
+
+/// Path components a directory summary groups by, e.g. `vendor/serde`
+const SUMMARY_DEPTH: usize = 2;
+
+/// Files of each kind still named when a large change is condensed
+const MAX_LISTED_FILES: usize = 25;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StagedChanges {
@@ -9,9 +25,183 @@ pub struct StagedChanges {
     pub modified: Vec<String>,
     pub deleted: Vec<String>,
     pub renamed: Vec<(String, String)>, // (old_path, new_path)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub copied: Vec<(String, String)>, // (source_path, new_path)
     pub stats: DiffStats,
 }
 
+/// How many files in one directory were added, modified, deleted or renamed
+#[derive(Debug, Default)]
+struct DirectoryCounts {
+    added: usize,
+    modified: usize,
+    deleted: usize,
+    renamed: usize,
+}
+
+impl DirectoryCounts {
+    fn total(&self) -> usize {
+        self.added + self.modified + self.deleted + self.renamed
+    }
+}
+
+impl StagedChanges {
+    /// Whether there are too many files to list one by one in a prompt
+    pub fn is_large(&self) -> bool {
+        self.stats.files_changed > LARGE_CHANGE_FILES
+    }
+
+    /// The changed files counted per directory, busiest first, e.g.
+    /// `vendor/serde/: 212 added, 3 modified`
+    pub fn directory_summary(&self) -> String {
+        let mut directories: BTreeMap<String, DirectoryCounts> = BTreeMap::new();
+        for path in &self.added {
+            directory_entry(&mut directories, path).added += 1;
+        }
+        for path in &self.modified {
+            directory_entry(&mut directories, path).modified += 1;
+        }
+        for path in &self.deleted {
+            directory_entry(&mut directories, path).deleted += 1;
+        }
+        for (_, path) in self.renamed.iter().chain(&self.copied) {
+            directory_entry(&mut directories, path).renamed += 1;
+        }
+
+        let mut directories: Vec<(String, DirectoryCounts)> = directories.into_iter().collect();
+        directories.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then(a.0.cmp(&b.0)));
+
+        let mut summary = String::new();
+        for (directory, counts) in directories.iter().take(MAX_SUMMARY_DIRECTORIES) {
+            let parts: Vec<String> = [
+                (counts.added, "added"),
+                (counts.modified, "modified"),
+                (counts.deleted, "deleted"),
+                (counts.renamed, "renamed or copied"),
+            ]
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, kind)| format!("{} {}", n, kind))
+            .collect();
+            summary.push_str(&format!("  {}: {}\n", directory, parts.join(", ")));
+        }
+        if directories.len() > MAX_SUMMARY_DIRECTORIES {
+            let rest = &directories[MAX_SUMMARY_DIRECTORIES..];
+            summary.push_str(&format!(
+                "  ...and {} files in {} other directories\n",
+                rest.iter().map(|(_, counts)| counts.total()).sum::<usize>(),
+                rest.len()
+            ));
+        }
+        summary
+    }
+
+    /// Every changed path, with renamed and copied files under their new name
+    pub fn paths(&self) -> Vec<String> {
+        self.added
+            .iter()
+            .chain(&self.modified)
+            .chain(&self.deleted)
+            .cloned()
+            .chain(self.renamed.iter().map(|(_, new)| new.clone()))
+            .chain(self.copied.iter().map(|(_, new)| new.clone()))
+            .collect()
+    }
+
+    /// `files` (new names for renames) plus the old names of the renamed ones,
+    /// which have to be staged or committed together with them
+    pub fn with_old_names(&self, files: &[String]) -> Vec<String> {
+        let mut paths = files.to_vec();
+        for (old, new) in &self.renamed {
+            if files.contains(new) {
+                paths.push(old.clone());
+            }
+        }
+        paths
+    }
+
+    /// A copy with only the changes to `paths` (new names for renames). Line
+    /// counts are not known per file, so only `files_changed` is kept.
+    pub fn only(&self, paths: &[String]) -> Self {
+        let keep = |path: &String| paths.contains(path);
+        let changes = Self {
+            added: self.added.iter().filter(|p| keep(p)).cloned().collect(),
+            modified: self.modified.iter().filter(|p| keep(p)).cloned().collect(),
+            deleted: self.deleted.iter().filter(|p| keep(p)).cloned().collect(),
+            renamed: self
+                .renamed
+                .iter()
+                .filter(|(_, new)| keep(new))
+                .cloned()
+                .collect(),
+            copied: self
+                .copied
+                .iter()
+                .filter(|(_, new)| keep(new))
+                .cloned()
+                .collect(),
+            stats: DiffStats::default(),
+        };
+        Self {
+            stats: DiffStats {
+                files_changed: changes.paths().len(),
+                ..DiffStats::default()
+            },
+            ..changes
+        }
+    }
+
+    /// A copy naming only the first few files of each kind, for sending a
+    /// large change to the server. The stats still count every file.
+    pub fn condensed(&self) -> Self {
+        Self {
+            added: self.added.iter().take(MAX_LISTED_FILES).cloned().collect(),
+            modified: self
+                .modified
+                .iter()
+                .take(MAX_LISTED_FILES)
+                .cloned()
+                .collect(),
+            deleted: self
+                .deleted
+                .iter()
+                .take(MAX_LISTED_FILES)
+                .cloned()
+                .collect(),
+            renamed: self
+                .renamed
+                .iter()
+                .take(MAX_LISTED_FILES)
+                .cloned()
+                .collect(),
+            copied: self.copied.iter().take(MAX_LISTED_FILES).cloned().collect(),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// The counts for the directory `path` is summarized under
+fn directory_entry<'a>(
+    directories: &'a mut BTreeMap<String, DirectoryCounts>,
+    path: &str,
+) -> &'a mut DirectoryCounts {
+    directories.entry(summary_directory(path)).or_default()
+}
+
+/// The directory `path` is counted under in a directory summary: its first
+/// `SUMMARY_DEPTH` directories, or `./` for files at the top level
+fn summary_directory(path: &str) -> String {
+    let directories: Vec<&str> = path.split('/').collect();
+    let directories = &directories[..directories.len() - 1];
+    if directories.is_empty() {
+        return "./".to_string();
+    }
+    format!(
+        "{}/",
+        directories[..directories.len().min(SUMMARY_DEPTH)].join("/")
+    )
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct DiffStats {
     pub files_changed: usize,
@@ -30,21 +220,237 @@ pub struct DiffHunk {
     pub lines: Vec<DiffLine>,
 }
 
+/// A commit summarized for reports
+#[derive(Debug, Serialize, Clone)]
+pub struct CommitInfo {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    pub email: String,
+    /// Commit time in seconds since the Unix epoch
+    pub time: i64,
+}
+
+/// A commit with its full message and changed files
+pub struct CommitDetails {
+    pub full_id: String,
+    pub info: CommitInfo,
+    pub message: String,
+    pub files: Vec<String>,
+    /// Added and removed lines, when requested
+    pub patch: Option<String>,
+}
+
+/// A commit with its full message and parents, as listed by `commits_in_range`
+pub struct RangeCommit {
+    pub full_id: String,
+    pub info: CommitInfo,
+    pub message: String,
+    pub parent_ids: Vec<String>,
+}
+
+/// A tag, as listed by `gyst tag list`
+pub struct TagInfo {
+    pub name: String,
+    /// The commit the tag points to
+    pub commit: CommitInfo,
+    /// First line of the annotation, `None` for lightweight tags
+    pub subject: Option<String>,
+    /// When the tag was created (the commit time for lightweight tags), in seconds since the Unix epoch
+    pub time: i64,
+    /// Whether the tagged commit is reachable from the main branch, `None` if there is no main branch
+    pub on_main: Option<bool>,
+}
+
+/// The contents of a changed file before and after, `None` where it does not exist
+pub struct FileVersions {
+    pub path: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// How rebasing a branch ended
+pub enum RebaseOutcome {
+    Done,
+    /// Stopped on conflicts in these files; the rebase waits for them to be resolved
+    Conflicts(Vec<String>),
+}
+
+/// Changed files as `git status` groups them, each tracked one with a short
+/// status such as `modified`
+#[derive(Debug, Default)]
+pub struct WorkingTreeStatus {
+    pub staged: Vec<(String, &'static str)>,
+    pub unstaged: Vec<(String, &'static str)>,
+    pub untracked: Vec<String>,
+    /// Files with merge conflicts, which are neither staged nor unstaged
+    pub conflicted: Vec<String>,
+}
+
+impl WorkingTreeStatus {
+    pub fn is_clean(&self) -> bool {
+        self.staged.is_empty()
+            && self.unstaged.is_empty()
+            && self.untracked.is_empty()
+            && self.conflicted.is_empty()
+    }
+}
+
+/// How far the current branch is from the branch it tracks
+#[derive(Debug)]
+pub struct Upstream {
+    /// e.g. `origin/main`
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// The staged patch of one file
+pub struct FilePatch {
+    pub path: String,
+    /// The old and new blob IDs joined by `..`: the same change staged again
+    /// has the same key
+    pub key: String,
+    pub text: String,
+}
+
 #[derive(Debug)]
 pub struct DiffLine {
     pub origin: char,
     pub content: String,
+    /// The file the line is in, by its old name for renamed files
+    pub path: String,
+    /// Line number before the change, for removed and unchanged lines
+    pub old_lineno: Option<u32>,
 }
 
 pub struct GitRepo {
     repo: Repository,
+    rename_threshold: u16,
+    detect_copies: bool,
+}
+
+/// Staged files taken out of the index by `GitRepo::set_aside` or
+/// `GitRepo::stage_only`
+pub struct SetAside<'a> {
+    repo: &'a GitRepo,
+    /// The staged tree the files are restored from
+    staged: git2::Oid,
+    paths: Vec<String>,
+    /// Files staged for this commit only, put back as they were if it is not made
+    taken: Vec<String>,
+    restored: bool,
+}
+
+impl SetAside<'_> {
+    /// The files staged for this commit only
+    pub fn taken(&self) -> &[String] {
+        &self.taken
+    }
+
+    /// Stage the files again as they were staged before
+    pub fn restore(mut self) -> Result<()> {
+        self.restored = true;
+        self.stage_again(&self.paths)
+    }
+
+    fn stage_again(&self, paths: &[String]) -> Result<()> {
+        let staged = self
+            .repo
+            .repo
+            .find_tree(self.staged)
+            .context("Failed to find the staged tree")?;
+        let mut index = self.repo.repo.index().context("Failed to read the index")?;
+        for path in paths {
+            match staged.get_path(Path::new(path)) {
+                Ok(entry) => index
+                    .add(&index_entry(path, &entry))
+                    .with_context(|| format!("Failed to stage {} again", path))?,
+                // Staged as deleted
+                Err(_) => {
+                    index.remove_path(Path::new(path)).ok();
+                }
+            }
+        }
+        index.write().context("Failed to write the index")
+    }
+}
+
+impl Drop for SetAside<'_> {
+    /// Errors and early returns still get the index back as it was
+    fn drop(&mut self) {
+        if self.restored {
+            return;
+        }
+        let paths: Vec<String> = self.paths.iter().chain(&self.taken).cloned().collect();
+        if let Err(e) = self.stage_again(&paths) {
+            eprintln!("Failed to stage {} again: {:#}", self.paths.join(", "), e);
+        }
+    }
+}
+
+/// Advisory lock held while gyst mutates the index or creates commits.
+/// The lock is released when the guard is dropped.
+pub struct RepoLock {
+    _file: File,
 }
 
 impl GitRepo {
     /// Open a git repository at the given path or search parent directories
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let repo = Repository::discover(path).context("Failed to find git repository")?;
-        Ok(Self { repo })
+        let path = path.as_ref();
+        let repo = Repository::discover(path)
+            .with_context(|| format!("Failed to find git repository at {}", path.display()))?;
+
+        // Linked worktrees are discovered like normal checkouts, but bare
+        // repositories have no index or working tree to commit from
+        if repo.is_bare() {
+            return Err(exit::git(anyhow::anyhow!(
+                "{} is a bare repository. gyst needs a working tree; use -C to point at a checkout or worktree",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            repo,
+            rename_threshold: 50,
+            detect_copies: true,
+        })
+    }
+
+    /// Configure how renames and copies are detected in staged changes and diffs
+    pub fn with_similarity(mut self, rename_threshold: u16, detect_copies: bool) -> Self {
+        self.rename_threshold = rename_threshold.min(100);
+        self.detect_copies = detect_copies;
+        self
+    }
+
+    /// Acquire the per-repository gyst lock, optionally waiting for another holder to finish
+    pub fn lock(&self, wait: bool) -> Result<RepoLock> {
+        let lock_path = self.repo.path().join("gyst.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) if wait => {
+                file.lock().context("Failed to acquire repository lock")?;
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(exit::git(anyhow::anyhow!(
+                    "Another gyst operation is in progress in this repository. Re-run with --wait to wait for it to finish."
+                )));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).context("Failed to acquire repository lock");
+            }
+        }
+
+        Ok(RepoLock { _file: file })
     }
 
     /// Stage all changes in the repository
@@ -99,18 +505,10 @@ impl GitRepo {
         Ok(!statuses.is_empty())
     }
 
-    /// Get a summary of staged changes
-    pub fn get_staged_changes(&self) -> Result<StagedChanges> {
-        let mut changes = StagedChanges {
-            added: Vec::new(),
-            modified: Vec::new(),
-            deleted: Vec::new(),
-            renamed: Vec::new(),
-            stats: DiffStats::default(),
-        };
-
+    /// Get paths with changes in the working tree that are not staged yet
+    pub fn get_unstaged_files(&self) -> Result<Vec<String>> {
         let mut opts = StatusOptions::new();
-        opts.include_untracked(false)
+        opts.include_untracked(true)
             .include_ignored(false)
             .include_unmodified(false)
             .exclude_submodules(true);
@@ -120,66 +518,506 @@ impl GitRepo {
             .statuses(Some(&mut opts))
             .context("Failed to get repository status")?;
 
-        for entry in statuses.iter() {
-            let status = entry.status();
-            let path = entry.path().unwrap_or("").to_string();
-
-            if status.is_index_new() {
-                changes.added.push(path);
-                changes.stats.files_changed += 1;
-            } else if status.is_index_modified() {
-                changes.modified.push(path);
-                changes.stats.files_changed += 1;
-            } else if status.is_index_deleted() {
-                changes.deleted.push(path);
-                changes.stats.files_changed += 1;
-            } else if status.is_index_renamed() {
-                if let Some(head_to_index) = entry.head_to_index() {
-                    let old_path = head_to_index
-                        .old_file()
-                        .path()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_else(|| "unknown".to_string());
-                    let new_path = head_to_index
-                        .new_file()
-                        .path()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_else(|| "unknown".to_string());
-                    changes.renamed.push((old_path, new_path));
-                    changes.stats.files_changed += 1;
+        Ok(statuses
+            .iter()
+            .filter(|entry| {
+                let status = entry.status();
+                status.is_wt_new()
+                    || status.is_wt_modified()
+                    || status.is_wt_deleted()
+                    || status.is_wt_renamed()
+                    || status.is_wt_typechange()
+            })
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect())
+    }
+
+    /// Get commits on local branches made after `since` (seconds since the Unix epoch),
+    /// newest first. When `author` is given, only commits whose author name or
+    /// email contains it (case-insensitively) are returned.
+    pub fn commits_since(&self, since: i64, author: Option<&str>) -> Result<Vec<CommitInfo>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        if revwalk.push_glob("refs/heads/*").is_err() || self.repo.head().is_err() {
+            return Ok(Vec::new());
+        }
+
+        let author = author.map(str::to_lowercase);
+        let mut commits = Vec::new();
+        for id in revwalk {
+            let commit = self.repo.find_commit(id?)?;
+            if commit.time().seconds() < since {
+                break;
+            }
+
+            let info = commit_info(&commit);
+            if let Some(ref author) = author
+                && !info.author.to_lowercase().contains(author)
+                && !info.email.to_lowercase().contains(author)
+            {
+                continue;
+            }
+            commits.push(info);
+        }
+
+        Ok(commits)
+    }
+
+    /// Resolve a revision such as `HEAD~2`, a branch name, or a hash to a commit
+    pub fn resolve_commit(&self, rev: &str) -> Result<CommitInfo> {
+        Ok(commit_info(&self.peel_to_commit(rev)?))
+    }
+
+    /// Commits reachable from `to` but not from `from` (like `git log from..to`), newest first
+    pub fn commits_in_range(&self, from: Option<&str>, to: &str) -> Result<Vec<RangeCommit>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push(self.peel_to_commit(to)?.id())?;
+        if let Some(from) = from {
+            revwalk.hide(self.peel_to_commit(from)?.id())?;
+        }
+
+        let mut commits = Vec::new();
+        for id in revwalk {
+            let commit = self.repo.find_commit(id?)?;
+            commits.push(RangeCommit {
+                full_id: commit.id().to_string(),
+                info: commit_info(&commit),
+                message: commit.message().unwrap_or("").to_string(),
+                parent_ids: commit.parent_ids().map(|id| id.to_string()).collect(),
+            });
+        }
+
+        Ok(commits)
+    }
+
+    /// The most recent tag reachable from `rev` (like `git describe --tags --abbrev=0`)
+    pub fn latest_tag(&self, rev: &str) -> Result<Option<String>> {
+        let object = self.peel_to_commit(rev)?.into_object();
+        let mut options = git2::DescribeOptions::new();
+        options.describe_tags();
+
+        let describe = match object.describe(&options) {
+            Ok(describe) => describe,
+            // Raised when no tag is reachable from `rev`
+            Err(e) if e.class() == git2::ErrorClass::Describe => return Ok(None),
+            Err(e) => return Err(e).context("Failed to look up tags"),
+        };
+        let mut format = git2::DescribeFormatOptions::new();
+        format.abbreviated_size(0);
+        Ok(Some(describe.format(Some(&format))?))
+    }
+
+    /// The patch between `from` (or the empty tree) and `to`, cut off after `max_lines` lines
+    pub fn range_patch(&self, from: Option<&str>, to: &str, max_lines: usize) -> Result<String> {
+        let old_tree = match from {
+            Some(from) => Some(self.peel_to_commit(from)?.tree()?),
+            None => None,
+        };
+        let new_tree = self.peel_to_commit(to)?.tree()?;
+        let diff = self
+            .repo
+            .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)
+            .context("Failed to generate diff")?;
+
+        let mut patch = String::new();
+        let mut lines = 0;
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            lines += 1;
+            if lines > max_lines {
+                return false;
+            }
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })
+        // Stopping early is reported as an error by libgit2
+        .or_else(|e| if lines > max_lines { Ok(()) } else { Err(e) })?;
+
+        Ok(patch)
+    }
+
+    /// The paths changed between `from` and `to`, renamed files under both names
+    pub fn range_paths(&self, from: Option<&str>, to: &str) -> Result<Vec<String>> {
+        let old_tree = match from {
+            Some(from) => Some(self.peel_to_commit(from)?.tree()?),
+            None => None,
+        };
+        let new_tree = self.peel_to_commit(to)?.tree()?;
+        let diff = self
+            .repo
+            .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)
+            .context("Failed to generate diff")?;
+
+        let mut paths: Vec<String> = Vec::new();
+        for delta in diff.deltas() {
+            for file in [delta.old_file(), delta.new_file()] {
+                if let Some(path) = file.path().and_then(|p| p.to_str())
+                    && !paths.iter().any(|known| known == path)
+                {
+                    paths.push(path.to_string());
                 }
             }
         }
+        Ok(paths)
+    }
+
+    /// Old and new contents of the files matching `filter` that changed between
+    /// `from` and `to`. Without `to` the index is compared, i.e. the staged changes.
+    pub fn changed_file_versions(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        filter: impl Fn(&str) -> bool,
+    ) -> Result<Vec<FileVersions>> {
+        let old_tree = match from {
+            Some(from) => Some(self.peel_to_commit(from)?.tree()?),
+            None => None,
+        };
+        let diff = match to {
+            Some(to) => {
+                let new_tree = self.peel_to_commit(to)?.tree()?;
+                self.repo
+                    .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)
+            }
+            None => {
+                let index = self.repo.index()?;
+                self.repo
+                    .diff_tree_to_index(old_tree.as_ref(), Some(&index), None)
+            }
+        }
+        .context("Failed to generate diff")?;
 
-        // Get detailed stats from the diff
-        if let Ok(diff) = self.get_diff() {
-            let stats = diff.stats()?;
-            changes.stats.insertions = stats.insertions();
-            changes.stats.deletions = stats.deletions();
+        let read = |id: git2::Oid| -> Option<String> {
+            if id.is_zero() {
+                return None;
+            }
+            let blob = self.repo.find_blob(id).ok()?;
+            Some(String::from_utf8_lossy(blob.content()).into_owned())
+        };
+
+        let mut files = Vec::new();
+        for delta in diff.deltas() {
+            let Some(path) = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .and_then(|p| p.to_str())
+            else {
+                continue;
+            };
+            if !filter(path) {
+                continue;
+            }
+            files.push(FileVersions {
+                path: path.to_string(),
+                old: read(delta.old_file().id()),
+                new: read(delta.new_file().id()),
+            });
+        }
+        Ok(files)
+    }
+
+    /// Contents of every file matching `filter` at `rev`, keyed by path. Without
+    /// `rev` the index is read, i.e. the files as they would be committed.
+    pub fn file_contents(
+        &self,
+        rev: Option<&str>,
+        filter: impl Fn(&str) -> bool,
+    ) -> Result<BTreeMap<String, String>> {
+        let read = |id: git2::Oid| -> Option<String> {
+            let blob = self.repo.find_blob(id).ok()?;
+            Some(String::from_utf8_lossy(blob.content()).into_owned())
+        };
+
+        let mut files = BTreeMap::new();
+        match rev {
+            Some(rev) => {
+                let tree = self.peel_to_commit(rev)?.tree()?;
+                tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+                    if entry.kind() == Some(git2::ObjectType::Blob)
+                        && let Some(name) = entry.name()
+                    {
+                        let path = format!("{}{}", dir, name);
+                        if filter(&path)
+                            && let Some(contents) = read(entry.id())
+                        {
+                            files.insert(path, contents);
+                        }
+                    }
+                    git2::TreeWalkResult::Ok
+                })
+                .context("Failed to read the tree")?;
+            }
+            None => {
+                let index = self.repo.index().context("Failed to read the index")?;
+                for entry in index.iter() {
+                    let path = String::from_utf8_lossy(&entry.path).into_owned();
+                    if filter(&path)
+                        && let Some(contents) = read(entry.id)
+                    {
+                        files.insert(path, contents);
+                    }
+                }
+            }
         }
+        Ok(files)
+    }
+
+    /// Whether the repository has a commit checked out (false right after `git init`)
+    pub fn has_head(&self) -> bool {
+        self.repo.head().is_ok()
+    }
 
-        Ok(changes)
+    /// Create an annotated tag `name` on `target` and return the tag object's ID
+    pub fn create_tag(&self, name: &str, target: &str, message: &str) -> Result<git2::Oid> {
+        let signature = self.repo.signature().context("Failed to get signature")?;
+        let target = self.peel_to_commit(target)?.into_object();
+        self.repo
+            .tag(name, &target, &signature, message, false)
+            .with_context(|| format!("Failed to create tag '{}'", name))
+    }
+
+    /// Whether `name` is a tag in this repository
+    pub fn is_tag(&self, name: &str) -> bool {
+        self.repo
+            .find_reference(&format!("refs/tags/{}", name))
+            .is_ok()
+    }
+
+    /// Whether `name` can be used as a branch name
+    pub fn is_valid_branch_name(name: &str) -> bool {
+        git2::Reference::is_valid_name(&format!("refs/heads/{}", name))
+    }
+
+    /// Whether `name` can be used as a tag name
+    pub fn is_valid_tag_name(name: &str) -> bool {
+        git2::Reference::is_valid_name(&format!("refs/tags/{}", name))
+    }
+
+    /// All tags that point to commits, newest first
+    pub fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        let main = self
+            .main_branch()
+            .and_then(|name| self.peel_to_commit(&name).ok())
+            .map(|commit| commit.id());
+
+        let mut tags = Vec::new();
+        for name in self.repo.tag_names(None)?.iter().flatten() {
+            let object = self.repo.revparse_single(&format!("refs/tags/{}", name))?;
+            // Tags can also point to trees and blobs, which are not releases
+            let Ok(commit) = object.peel_to_commit() else {
+                continue;
+            };
+            let (subject, time) = match object.as_tag() {
+                Some(tag) => (
+                    tag.message()
+                        .and_then(|message| message.lines().next())
+                        .map(str::to_string),
+                    tag.tagger()
+                        .map_or(commit.time().seconds(), |tagger| tagger.when().seconds()),
+                ),
+                None => (None, commit.time().seconds()),
+            };
+            let on_main = main.map(|main| {
+                main == commit.id()
+                    || self
+                        .repo
+                        .graph_descendant_of(main, commit.id())
+                        .unwrap_or(false)
+            });
+
+            tags.push(TagInfo {
+                name: name.to_string(),
+                commit: commit_info(&commit),
+                subject,
+                time,
+                on_main,
+            });
+        }
+
+        tags.sort_by_key(|tag| std::cmp::Reverse(tag.time));
+        Ok(tags)
+    }
+
+    /// The first of `main`, `master`, `develop` and `trunk` that exists locally
+    pub fn main_branch(&self) -> Option<String> {
+        ["main", "master", "develop", "trunk"]
+            .iter()
+            .find(|name| self.repo.find_branch(name, git2::BranchType::Local).is_ok())
+            .map(|name| name.to_string())
+    }
+
+    fn peel_to_commit(&self, rev: &str) -> Result<git2::Commit<'_>> {
+        self.repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Failed to resolve '{}' to a commit", rev))
+    }
+
+    /// Get up to `limit` commits reachable from HEAD, newest first, together with
+    /// the paths each one changed relative to its first parent
+    pub fn recent_commits_with_files(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<(CommitInfo, Vec<String>)>> {
+        let mut revwalk = self.repo.revwalk()?;
+        if revwalk.push_head().is_err() {
+            return Ok(Vec::new());
+        }
+
+        let mut commits = Vec::new();
+        for id in revwalk.take(limit) {
+            let commit = self.repo.find_commit(id?)?;
+            let files = changed_files(&self.commit_diff(&commit)?);
+            commits.push((commit_info(&commit), files));
+        }
+
+        Ok(commits)
+    }
+
+    /// Full IDs of all commits reachable from local branches, newest first
+    pub fn all_commit_ids(&self) -> Result<Vec<String>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        if revwalk.push_glob("refs/heads/*").is_err() || self.repo.head().is_err() {
+            return Ok(Vec::new());
+        }
+
+        revwalk
+            .map(|id| Ok(id?.to_string()))
+            .collect::<Result<Vec<_>>>()
+    }
+
+    /// Load a commit's full message and changed files, and its patch when `with_diff` is set
+    pub fn commit_details(&self, id: &str, with_diff: bool) -> Result<CommitDetails> {
+        let oid = git2::Oid::from_str(id).with_context(|| format!("Invalid commit id '{}'", id))?;
+        let commit = self.repo.find_commit(oid)?;
+        let diff = self.commit_diff(&commit)?;
+
+        let patch = if with_diff {
+            let mut patch = String::new();
+            diff.print(git2::DiffFormat::Patch, |_, _, line| {
+                if matches!(line.origin(), '+' | '-') {
+                    patch.push_str(&String::from_utf8_lossy(line.content()));
+                }
+                true
+            })?;
+            Some(patch)
+        } else {
+            None
+        };
+
+        Ok(CommitDetails {
+            full_id: id.to_string(),
+            info: commit_info(&commit),
+            message: commit.message().unwrap_or("").to_string(),
+            files: changed_files(&diff),
+            patch,
+        })
+    }
+
+    /// Where gyst keeps per-repository data such as the history index (`.git/gyst`).
+    /// Linked worktrees share the directory of the main repository.
+    pub fn data_dir(&self) -> PathBuf {
+        self.repo.commondir().join("gyst")
+    }
+
+    /// Diff of a commit against its first parent (or the empty tree for root commits)
+    fn commit_diff(&self, commit: &git2::Commit) -> Result<git2::Diff<'_>> {
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        Ok(self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?)
+    }
+
+    /// Get the email configured for commits in this repository
+    pub fn user_email(&self) -> Option<String> {
+        self.repo.config().ok()?.get_string("user.email").ok()
+    }
+
+    /// The description set with `git branch --edit-description`, if any
+    pub fn branch_description(&self, branch: &str) -> Option<String> {
+        self.repo
+            .config()
+            .ok()?
+            .get_string(&format!("branch.{}.description", branch))
+            .ok()
+            .map(|description| description.trim().to_string())
+            .filter(|description| !description.is_empty())
+    }
+
+    /// Identify the staged content. The ID changes whenever the index does,
+    /// including when another process re-stages files.
+    pub fn staged_snapshot(&self) -> Result<git2::Oid> {
+        let mut index = self.repo.index().context("Failed to read the index")?;
+        index.read(false).context("Failed to read the index")?;
+        index
+            .write_tree()
+            .context("Failed to snapshot the staged changes")
+    }
+
+    /// Get a summary of staged changes
+    pub fn get_staged_changes(&self) -> Result<StagedChanges> {
+        // One pass over the index diff: a status scan would also walk the
+        // working tree, which is slow in large checkouts
+        changes_in(&self.get_diff()?)
+    }
+
+    /// The staged changes as patch text for a prompt, at most about `max_bytes`
+    /// long. Only files that fit are included; the others are counted at the end
+    /// so large imports do not have to be diffed in full.
+    pub fn staged_patch(&self, max_bytes: usize) -> Result<String> {
+        let (patches, unread) = self.staged_file_patches(max_bytes)?;
+        Ok(join_patches(&patches, unread, max_bytes, |_| None))
+    }
+
+    /// The staged patch of each file, in diff order, until `max_bytes` have
+    /// been read. Also returns how many files were left unread.
+    pub fn staged_file_patches(&self, max_bytes: usize) -> Result<(Vec<FilePatch>, usize)> {
+        file_patches(&self.get_diff()?, max_bytes)
     }
 
     /// Get the raw diff object for staged changes
-    fn get_diff(&self) -> Result<git2::Diff> {
+    fn get_diff(&self) -> Result<git2::Diff<'_>> {
         let mut diff_opts = git2::DiffOptions::new();
 
         // Get the current index (staged changes)
         let index = self.repo.index()?;
 
         // Get the diff between HEAD and index (staged changes)
-        if let Ok(head) = self.repo.head() {
+        let mut diff = if let Ok(head) = self.repo.head() {
             let tree = head.peel_to_tree()?;
             self.repo
                 .diff_tree_to_index(Some(&tree), Some(&index), Some(&mut diff_opts))
         } else {
             // If there's no HEAD (initial commit), diff against an empty tree
-            let empty_tree = self.repo.find_tree(git2::Oid::zero())?;
             self.repo
-                .diff_tree_to_index(Some(&empty_tree), Some(&index), Some(&mut diff_opts))
+                .diff_tree_to_index(None, Some(&index), Some(&mut diff_opts))
         }
-        .context("Failed to generate diff")
+        .context("Failed to generate diff")?;
+
+        self.detect_renames(&mut diff)?;
+        Ok(diff)
+    }
+
+    /// Collapse delete/add pairs in `diff` into renames and copies so they are
+    /// represented compactly
+    fn detect_renames(&self, diff: &mut git2::Diff) -> Result<()> {
+        let mut find_opts = git2::DiffFindOptions::new();
+        find_opts
+            .renames(true)
+            .copies(self.detect_copies)
+            .rename_threshold(self.rename_threshold)
+            .copy_threshold(self.rename_threshold);
+        diff.find_similar(Some(&mut find_opts))
+            .context("Failed to detect renames")
     }
 
     /// Get structured diff information
@@ -204,7 +1042,18 @@ impl GitRepo {
                 if let Some(hunk) = &mut current_hunk {
                     let origin = line.origin();
                     let content = String::from_utf8_lossy(line.content()).to_string();
-                    hunk.lines.push(DiffLine { origin, content });
+                    let path = delta
+                        .old_file()
+                        .path()
+                        .or(delta.new_file().path())
+                        .map(|path| path.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    hunk.lines.push(DiffLine {
+                        origin,
+                        content,
+                        path,
+                        old_lineno: line.old_lineno(),
+                    });
                 }
             } else if delta.status() == Delta::Renamed {
                 // Handle renamed files
@@ -222,6 +1071,23 @@ impl GitRepo {
         Ok(hunks)
     }
 
+    /// The commit that last changed each of the given lines of `path` as of HEAD
+    pub fn blame_lines(&self, path: &str, lines: &[u32]) -> Result<HashMap<u32, CommitInfo>> {
+        let blame = self
+            .repo
+            .blame_file(Path::new(path), None)
+            .with_context(|| format!("Failed to blame {}", path))?;
+        let mut commits = HashMap::new();
+        for &line in lines {
+            if let Some(hunk) = blame.get_line(line as usize)
+                && let Ok(commit) = self.repo.find_commit(hunk.final_commit_id())
+            {
+                commits.insert(line, commit_info(&commit));
+            }
+        }
+        Ok(commits)
+    }
+
     /// Create a commit with the given message
     pub fn create_commit(&self, message: &str) -> Result<git2::Oid> {
         let signature = self.repo.signature().context("Failed to get signature")?;
@@ -259,9 +1125,269 @@ impl GitRepo {
             .context("Failed to create commit")
     }
 
+    /// Commit the changes to `paths` from the staged tree `staged` (see
+    /// `staged_snapshot`) on top of HEAD. The index is left alone, so the
+    /// other staged changes stay staged for the next commit.
+    pub fn commit_staged_paths(
+        &self,
+        staged: git2::Oid,
+        paths: &[String],
+        message: &str,
+    ) -> Result<git2::Oid> {
+        let signature = self.repo.signature().context("Failed to get signature")?;
+        let staged = self
+            .repo
+            .find_tree(staged)
+            .context("Failed to find the staged tree")?;
+        let parent = match self.repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(_) => None,
+        };
+
+        let mut index = git2::Index::new()?;
+        if let Some(parent) = &parent {
+            index.read_tree(&parent.tree()?)?;
+        }
+        for path in paths {
+            let Ok(entry) = staged.get_path(Path::new(path)) else {
+                // Deleted in the staged tree; HEAD may not have it either
+                index.remove_path(Path::new(path)).ok();
+                continue;
+            };
+            index
+                .add(&index_entry(path, &entry))
+                .with_context(|| format!("Failed to add {}", path))?;
+        }
+        let tree_id = index
+            .write_tree_to(&self.repo)
+            .context("Failed to write tree")?;
+        let tree = self
+            .repo
+            .find_tree(tree_id)
+            .context("Failed to find tree")?;
+
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .context("Failed to create commit")
+    }
+
+    /// Unstage `paths` until the returned guard is restored or dropped, which
+    /// stages them again as they are staged now. Lets a commit take only some
+    /// of the staged files and leave the rest staged.
+    pub fn set_aside(&self, paths: &[String]) -> Result<SetAside<'_>> {
+        let staged = self.staged_snapshot()?;
+        let head = match self.repo.head() {
+            Ok(head) => Some(head.peel(git2::ObjectType::Commit)?),
+            Err(_) => None,
+        };
+        self.repo
+            .reset_default(head.as_ref(), paths)
+            .context("Failed to unstage the files left out")?;
+        Ok(SetAside {
+            repo: self,
+            staged,
+            paths: paths.to_vec(),
+            taken: Vec::new(),
+            restored: false,
+        })
+    }
+
+    /// Stage the working tree changes matching `pathspecs` (relative to the
+    /// repository root) and set every other staged change aside, like
+    /// `git commit -- <pathspec>`. Nothing is taken if nothing matched.
+    pub fn stage_only(&self, pathspecs: &[String]) -> Result<SetAside<'_>> {
+        let staged = self.staged_snapshot()?;
+        let mut index = self.repo.index().context("Failed to read the index")?;
+        index
+            .add_all(pathspecs, git2::IndexAddOption::DEFAULT, None)
+            .context("Failed to stage the matching files")?;
+        index
+            .update_all(pathspecs, None)
+            .context("Failed to stage the matching files")?;
+        index.write().context("Failed to write the index")?;
+
+        // A rename goes with the pathspec if either of its names matches
+        let spec = git2::Pathspec::new(pathspecs).context("Invalid pathspec")?;
+        let mut taken = Vec::new();
+        let mut others = Vec::new();
+        let diff = self.get_diff()?;
+        for delta in diff.deltas() {
+            let mut paths: Vec<&Path> = delta
+                .old_file()
+                .path()
+                .into_iter()
+                .chain(delta.new_file().path())
+                .collect();
+            paths.dedup();
+            let matched = paths
+                .iter()
+                .any(|path| spec.matches_path(path, git2::PathspecFlags::DEFAULT));
+            let group = if matched { &mut taken } else { &mut others };
+            group.extend(paths.iter().map(|path| path.to_string_lossy().into_owned()));
+        }
+
+        let mut set_aside = SetAside {
+            repo: self,
+            staged,
+            paths: Vec::new(),
+            taken,
+            restored: false,
+        };
+        if !others.is_empty() {
+            let head = match self.repo.head() {
+                Ok(head) => Some(head.peel(git2::ObjectType::Commit)?),
+                Err(_) => None,
+            };
+            self.repo
+                .reset_default(head.as_ref(), &others)
+                .context("Failed to unstage the other files")?;
+            set_aside.paths = others;
+        }
+        Ok(set_aside)
+    }
+
+    /// `pathspec`, given relative to `dir`, relative to the repository root
+    pub fn root_pathspec(&self, dir: &Path, pathspec: &str) -> String {
+        let prefix = self
+            .repo
+            .workdir()
+            .and_then(|workdir| {
+                let workdir = workdir.canonicalize().ok()?;
+                let dir = dir.canonicalize().ok()?;
+                dir.strip_prefix(workdir).ok().map(Path::to_path_buf)
+            })
+            .unwrap_or_default();
+        // Magic pathspecs like `:(glob)**/*.rs` are left to git
+        if pathspec.starts_with(':') {
+            return pathspec.to_string();
+        }
+        let mut parts: Vec<String> = Vec::new();
+        for component in prefix.join(pathspec).components() {
+            match component {
+                std::path::Component::ParentDir => {
+                    parts.pop();
+                }
+                std::path::Component::Normal(part) => parts.push(part.to_string_lossy().into()),
+                _ => {}
+            }
+        }
+        if parts.is_empty() {
+            return "*".to_string();
+        }
+        parts.join("/")
+    }
+
+    /// Write the working tree as a tree, untracked files included and ignored
+    /// ones left out, without touching the index
+    pub fn worktree_snapshot(&self) -> Result<git2::Oid> {
+        // A second handle reads its own copy of the index, which is never written
+        let repo = Repository::open(self.repo.path()).context("Failed to open repository")?;
+        let mut index = repo.index().context("Failed to read the index")?;
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .and_then(|()| index.update_all(["*"], None))
+            .context("Failed to snapshot the working tree")?;
+        index
+            .write_tree()
+            .context("Failed to snapshot the working tree")
+    }
+
+    /// The commit the next snapshot on `branch` is compared with: the last
+    /// snapshot, or HEAD when there is none yet or HEAD has moved on since
+    pub fn wip_base(&self, branch: &str) -> Result<Option<git2::Oid>> {
+        let tip = self.branch_tip(branch);
+        Ok(self.head_outside(tip)?.or(tip))
+    }
+
+    /// The changes from the commit `base` to `tree`, and their patch text of at
+    /// most about `max_bytes`
+    pub fn tree_changes(
+        &self,
+        base: Option<git2::Oid>,
+        tree: git2::Oid,
+        max_bytes: usize,
+    ) -> Result<(StagedChanges, String)> {
+        let old = match base {
+            Some(id) => Some(self.repo.find_commit(id)?.tree()?),
+            None => None,
+        };
+        let new = self.repo.find_tree(tree).context("Failed to find tree")?;
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(old.as_ref(), Some(&new), None)
+            .context("Failed to generate diff")?;
+        self.detect_renames(&mut diff)?;
+        let (patches, unread) = file_patches(&diff, max_bytes)?;
+        Ok((
+            changes_in(&diff)?,
+            join_patches(&patches, unread, max_bytes, |_| None),
+        ))
+    }
+
+    /// Commit `tree` on `branch` without checking it out. The branch starts
+    /// at HEAD; when HEAD has moved on, it becomes a second parent so the
+    /// snapshots can still be squashed onto it.
+    pub fn commit_wip(&self, branch: &str, tree: git2::Oid, message: &str) -> Result<git2::Oid> {
+        let signature = self.repo.signature().context("Failed to get signature")?;
+        let tree = self.repo.find_tree(tree).context("Failed to find tree")?;
+        let tip = self.branch_tip(branch);
+        let parents = tip
+            .into_iter()
+            .chain(self.head_outside(tip)?)
+            .map(|id| self.repo.find_commit(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        self.repo
+            .commit(
+                Some(&format!("refs/heads/{}", branch)),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .context("Failed to create commit")
+    }
+
+    /// The commit the local `branch` points at, if it exists
+    fn branch_tip(&self, branch: &str) -> Option<git2::Oid> {
+        self.repo
+            .refname_to_id(&format!("refs/heads/{}", branch))
+            .ok()
+    }
+
+    /// HEAD, unless there is no commit yet or `tip` already contains it
+    fn head_outside(&self, tip: Option<git2::Oid>) -> Result<Option<git2::Oid>> {
+        let Some(head) = self.repo.head().ok().and_then(|head| head.target()) else {
+            return Ok(None);
+        };
+        match tip {
+            Some(tip) if tip == head || self.repo.graph_descendant_of(tip, head)? => Ok(None),
+            _ => Ok(Some(head)),
+        }
+    }
+
+    /// Whether git ignores `path` (relative to the repository root)
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.repo.is_path_ignored(path).unwrap_or(false)
+    }
+
+    /// The directory holding the repository's data (`.git`)
+    pub fn git_dir(&self) -> &Path {
+        self.repo.path()
+    }
+
     /// Get a list of all local branches in the repository
     #[allow(dead_code)]
-    pub fn get_local_branches(&self) -> Result<Vec<git2::Branch>> {
+    pub fn get_local_branches(&self) -> Result<Vec<git2::Branch<'_>>> {
         let branches = self
             .repo
             .branches(Some(git2::BranchType::Local))?
@@ -273,7 +1399,7 @@ impl GitRepo {
 
     /// Get a list of all remote branches in the repository
     #[allow(dead_code)]
-    pub fn get_remote_branches(&self) -> Result<Vec<git2::Branch>> {
+    pub fn get_remote_branches(&self) -> Result<Vec<git2::Branch<'_>>> {
         let branches = self
             .repo
             .branches(Some(git2::BranchType::Remote))?
@@ -283,34 +1409,643 @@ impl GitRepo {
         Ok(branches)
     }
 
-    /// Get the current branch name
-    #[allow(dead_code)]
+    /// Get the current branch name, failing when HEAD is detached
     pub fn get_current_branch(&self) -> Result<String> {
-        let head = self.repo.head()?;
-        let branch_name = head
-            .shorthand()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get branch name"))?;
-        Ok(branch_name.to_string())
+        if self.repo.head_detached().unwrap_or(false) {
+            return Err(exit::git(anyhow::anyhow!(
+                "HEAD is detached. Check out a branch first (e.g. 'git switch -c <name>')"
+            )));
+        }
+
+        match self.repo.head() {
+            Ok(head) => {
+                let branch_name = head
+                    .shorthand()
+                    .ok_or_else(|| anyhow::anyhow!("Failed to get branch name"))?;
+                Ok(branch_name.to_string())
+            }
+            Err(e) if e.code() == ErrorCode::UnbornBranch => {
+                // No commits yet: HEAD still names the branch it will create
+                let head = self.repo.find_reference("HEAD")?;
+                head.symbolic_target()
+                    .and_then(|target| target.strip_prefix("refs/heads/"))
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow::anyhow!("Failed to get branch name"))
+            }
+            Err(e) => Err(e).context("Failed to read HEAD"),
+        }
+    }
+
+    /// Whether `branch` exists on `remote` (as of the last fetch or push), and if
+    /// so whether it points at HEAD
+    pub fn remote_branch_at_head(&self, remote: &str, branch: &str) -> Option<bool> {
+        let remote_id = self
+            .repo
+            .refname_to_id(&format!("refs/remotes/{}/{}", remote, branch))
+            .ok()?;
+        let head_id = self.repo.head().ok()?.target()?;
+        Some(remote_id == head_id)
+    }
+
+    /// Tracked files with uncommitted changes under `pathspecs` (everything when
+    /// empty), with a short status. With `worktree_only`, only changes that are
+    /// not staged count.
+    pub fn uncommitted_files(
+        &self,
+        pathspecs: &[String],
+        worktree_only: bool,
+    ) -> Result<Vec<(String, &'static str)>> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).exclude_submodules(true);
+        for pathspec in pathspecs {
+            opts.pathspec(pathspec);
+        }
+
+        let statuses = self
+            .repo
+            .statuses(Some(&mut opts))
+            .context("Failed to get repository status")?;
+
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| {
+                let status = entry.status();
+                let label = if status.is_index_new() && !worktree_only {
+                    "new file"
+                } else if status.is_wt_deleted() || (status.is_index_deleted() && !worktree_only) {
+                    "deleted"
+                } else if status.is_wt_modified()
+                    || status.is_wt_typechange()
+                    || status.is_wt_renamed()
+                    || (!worktree_only
+                        && (status.is_index_modified()
+                            || status.is_index_typechange()
+                            || status.is_index_renamed()))
+                {
+                    "modified"
+                } else {
+                    return None;
+                };
+                Some((entry.path()?.to_string(), label))
+            })
+            .collect())
+    }
+
+    /// Untracked files `git clean` would delete. Untracked directories are
+    /// listed as `dir/` and only included with `directories`.
+    pub fn clean_candidates(
+        &self,
+        directories: bool,
+        ignored: bool,
+        only_ignored: bool,
+    ) -> Result<Vec<String>> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(!only_ignored)
+            .include_ignored(ignored || only_ignored)
+            .recurse_untracked_dirs(false)
+            .recurse_ignored_dirs(false)
+            .exclude_submodules(true);
+
+        let statuses = self
+            .repo
+            .statuses(Some(&mut opts))
+            .context("Failed to get repository status")?;
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| {
+                let status = entry.status();
+                (status.is_wt_new() && !only_ignored)
+                    || (status.is_ignored() && (ignored || only_ignored))
+            })
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .filter(|path| directories || !path.ends_with('/'))
+            .collect())
+    }
+
+    /// Commits reachable from `tip` that would no longer be reachable from any
+    /// reference if `moved_ref` (e.g. `refs/heads/topic`) stopped pointing at
+    /// them, keeping whatever is reachable from `keep`. Newest first.
+    pub fn commits_lost(
+        &self,
+        tip: &str,
+        keep: Option<&str>,
+        moved_ref: Option<&str>,
+    ) -> Result<Vec<CommitInfo>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push(self.peel_to_commit(tip)?.id())?;
+        if let Some(keep) = keep {
+            revwalk.hide(self.peel_to_commit(keep)?.id())?;
+        }
+
+        for reference in self.repo.references()?.flatten() {
+            let Some(name) = reference.name() else {
+                continue;
+            };
+            if Some(name) == moved_ref {
+                continue;
+            }
+            if let Ok(commit) = reference.peel_to_commit() {
+                revwalk.hide(commit.id())?;
+            }
+        }
+        // A detached HEAD keeps its commits as long as it is not the one moving
+        if self.is_head_detached()
+            && moved_ref != Some("HEAD")
+            && let Ok(head) = self.repo.head().and_then(|head| head.peel_to_commit())
+        {
+            revwalk.hide(head.id())?;
+        }
+
+        let mut commits = Vec::new();
+        for id in revwalk {
+            let commit = self.repo.find_commit(id?)?;
+            commits.push(commit_info(&commit));
+        }
+        Ok(commits)
+    }
+
+    /// The full name of the reference HEAD points to, or `HEAD` when detached
+    pub fn head_ref_name(&self) -> String {
+        self.repo
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(str::to_string))
+            .unwrap_or_else(|| "HEAD".to_string())
+    }
+
+    /// Stash entries, newest first, as `stash@{n}: message`
+    pub fn stash_entries(&self) -> Vec<String> {
+        let Ok(reflog) = self.repo.reflog("refs/stash") else {
+            return Vec::new();
+        };
+        reflog
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| format!("stash@{{{}}}: {}", index, entry.message().unwrap_or("")))
+            .collect()
+    }
+
+    /// Create a branch at HEAD and switch to it, leaving the index and working tree untouched
+    pub fn create_and_checkout_branch(&self, name: &str) -> Result<()> {
+        if !git2::Branch::name_is_valid(name)? {
+            return Err(anyhow::anyhow!("'{}' is not a valid branch name", name));
+        }
+
+        // On an unborn branch there is no commit to branch from; pointing HEAD
+        // at the new name is enough for the first commit to create it
+        if let Ok(head) = self.repo.head() {
+            let commit = head.peel_to_commit()?;
+            self.repo
+                .branch(name, &commit, false)
+                .with_context(|| format!("Failed to create branch '{}'", name))?;
+        }
+
+        self.repo
+            .set_head(&format!("refs/heads/{}", name))
+            .with_context(|| format!("Failed to switch to branch '{}'", name))
+    }
+
+    /// Delete the local branch `name` and return the commit it pointed to.
+    /// Unless `force` is set, branches with commits found on no other branch
+    /// or tag are kept, like `git branch -d`.
+    pub fn delete_branch(&self, name: &str, force: bool) -> Result<git2::Oid> {
+        let mut branch = self
+            .repo
+            .find_branch(name, git2::BranchType::Local)
+            .with_context(|| format!("No local branch named '{}'", name))?;
+        if branch.is_head() {
+            return Err(anyhow::anyhow!(
+                "'{}' is checked out. Switch to another branch first",
+                name
+            ));
+        }
+        let tip = branch.get().peel_to_commit()?.id();
+        if !force {
+            let lost = self.commits_lost(
+                &tip.to_string(),
+                None,
+                Some(&format!("refs/heads/{}", name)),
+            )?;
+            if !lost.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "'{}' has {} commit{} found on no other branch. Use --force to delete it anyway",
+                    name,
+                    lost.len(),
+                    if lost.len() == 1 { "" } else { "s" }
+                ));
+            }
+        }
+        branch
+            .delete()
+            .with_context(|| format!("Failed to delete branch '{}'", name))?;
+        Ok(tip)
+    }
+
+    /// Create the local branch `name` at `oid` again, e.g. after `delete_branch`
+    pub fn restore_branch(&self, name: &str, oid: &str) -> Result<()> {
+        if self.repo.find_branch(name, git2::BranchType::Local).is_ok() {
+            return Err(anyhow::anyhow!("A branch named '{}' already exists", name));
+        }
+        let commit = git2::Oid::from_str(oid)
+            .and_then(|oid| self.repo.find_commit(oid))
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Commit {} of '{}' no longer exists; git has cleaned it up",
+                    &oid[..7.min(oid.len())],
+                    name
+                )
+            })?;
+        self.repo
+            .branch(name, &commit, false)
+            .with_context(|| format!("Failed to create branch '{}'", name))?;
+        Ok(())
+    }
+
+    /// Staged, unstaged, untracked and conflicted files, like `git status`
+    pub fn working_tree_status(&self) -> Result<WorkingTreeStatus> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(false)
+            .include_ignored(false)
+            .exclude_submodules(true)
+            .renames_head_to_index(true);
+
+        let statuses = self
+            .repo
+            .statuses(Some(&mut opts))
+            .context("Failed to get repository status")?;
+
+        let mut tree = WorkingTreeStatus::default();
+        for entry in statuses.iter() {
+            let Some(path) = entry.path().map(str::to_string) else {
+                continue;
+            };
+            let status = entry.status();
+            if status.is_conflicted() {
+                tree.conflicted.push(path);
+                continue;
+            }
+            if status.is_wt_new() {
+                tree.untracked.push(path.clone());
+            }
+            let staged = if status.is_index_new() {
+                Some("new file")
+            } else if status.is_index_deleted() {
+                Some("deleted")
+            } else if status.is_index_renamed() {
+                Some("renamed")
+            } else if status.is_index_modified() || status.is_index_typechange() {
+                Some("modified")
+            } else {
+                None
+            };
+            if let Some(label) = staged {
+                tree.staged.push((path.clone(), label));
+            }
+            let unstaged = if status.is_wt_deleted() {
+                Some("deleted")
+            } else if status.is_wt_modified() || status.is_wt_typechange() || status.is_wt_renamed()
+            {
+                Some("modified")
+            } else {
+                None
+            };
+            if let Some(label) = unstaged {
+                tree.unstaged.push((path, label));
+            }
+        }
+        Ok(tree)
     }
 
-    /// Push the current branch to the remote repository
-    pub fn push_changes(&self) -> Result<()> {
+    /// The branch the current branch tracks, with how many commits each side
+    /// has that the other does not. `None` without an upstream.
+    pub fn upstream(&self) -> Option<Upstream> {
+        let head = self.repo.head().ok()?;
+        if !head.is_branch() {
+            return None;
+        }
+        let upstream = git2::Branch::wrap(head).upstream().ok()?;
+        let name = upstream.name().ok()??.to_string();
+        let local = self.repo.head().ok()?.target()?;
+        let remote = upstream.get().target()?;
+        let (ahead, behind) = self.repo.graph_ahead_behind(local, remote).ok()?;
+        Some(Upstream {
+            name,
+            ahead,
+            behind,
+        })
+    }
+
+    /// The operation git is in the middle of, e.g. `rebase` or `merge`
+    pub fn operation_in_progress(&self) -> Option<&'static str> {
+        use git2::RepositoryState;
+        match self.repo.state() {
+            RepositoryState::Clean => None,
+            RepositoryState::Merge => Some("merge"),
+            RepositoryState::Revert | RepositoryState::RevertSequence => Some("revert"),
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                Some("cherry-pick")
+            }
+            RepositoryState::Bisect => Some("bisect"),
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge => Some("rebase"),
+            RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => Some("am"),
+        }
+    }
+
+    /// Check whether HEAD points directly at a commit instead of a branch
+    pub fn is_head_detached(&self) -> bool {
+        self.repo.head_detached().unwrap_or(false)
+    }
+
+    /// Get the working directory of the repository (or linked worktree)
+    pub fn workdir(&self) -> Result<&Path> {
+        self.repo
+            .workdir()
+            .context("Repository has no working directory")
+    }
+
+    /// Names of the local branches, then the remote-tracking ones (e.g. `origin/main`)
+    pub fn branch_names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for kind in [git2::BranchType::Local, git2::BranchType::Remote] {
+            for (branch, _) in self.repo.branches(Some(kind))?.flatten() {
+                if let Ok(Some(name)) = branch.name()
+                    && !name.ends_with("/HEAD")
+                {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Names of all tags
+    pub fn tag_names(&self) -> Result<Vec<String>> {
+        let tags = self.repo.tag_names(None).context("Failed to list tags")?;
+        Ok(tags.iter().flatten().map(str::to_string).collect())
+    }
+
+    /// Names of the configured remotes (e.g. `origin`, `upstream`)
+    pub fn remote_names(&self) -> Result<Vec<String>> {
+        let remotes = self.repo.remotes().context("Failed to list remotes")?;
+        Ok(remotes.iter().flatten().map(str::to_string).collect())
+    }
+
+    /// The fetch URL of `remote`, if it exists
+    pub fn remote_url(&self, remote: &str) -> Option<String> {
+        self.repo
+            .find_remote(remote)
+            .ok()?
+            .url()
+            .map(str::to_string)
+    }
+
+    /// Rebase `branch` onto `onto` with the git executable, which checks
+    /// `branch` out. A rebase that stops on conflicts is left in progress.
+    pub fn rebase_branch(&self, branch: &str, onto: &str) -> Result<RebaseOutcome> {
+        let output = std::process::Command::new("git")
+            .current_dir(self.workdir()?)
+            .args(["rebase", onto, branch])
+            .output()
+            .context("Failed to execute git rebase command")?;
+        if output.status.success() {
+            return Ok(RebaseOutcome::Done);
+        }
+
+        if matches!(
+            self.repo.state(),
+            git2::RepositoryState::Rebase
+                | git2::RepositoryState::RebaseMerge
+                | git2::RepositoryState::RebaseInteractive
+        ) {
+            let mut index = self.repo.index()?;
+            index.read(true)?;
+            let mut files: Vec<String> = Vec::new();
+            for conflict in index.conflicts()? {
+                let conflict = conflict?;
+                let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) else {
+                    continue;
+                };
+                let path = String::from_utf8_lossy(&entry.path).into_owned();
+                if !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+            return Ok(RebaseOutcome::Conflicts(files));
+        }
+        Err(exit::git(anyhow::anyhow!(
+            "Failed to rebase '{}' onto '{}': {}",
+            branch,
+            onto,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+
+    /// Switch to `branch` with the git executable
+    pub fn switch_branch(&self, branch: &str) -> Result<()> {
+        let output = std::process::Command::new("git")
+            .current_dir(self.workdir()?)
+            .args(["switch", branch])
+            .output()
+            .context("Failed to execute git switch command")?;
+        if !output.status.success() {
+            return Err(exit::git(anyhow::anyhow!(
+                "Failed to switch to '{}': {}",
+                branch,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Push the current branch to `remote`
+    pub fn push_changes(&self, remote: &str) -> Result<()> {
         // Get the current branch name
         let branch_name = self.get_current_branch()?;
-        
+
+        // The first push of a new branch makes it track the remote one
+        let set_upstream = self.upstream().is_none();
+
         // Execute git push using std::process::Command
         // This is simpler than using libgit2 for pushing
-        let status = std::process::Command::new("git")
+        let output = std::process::Command::new("git")
+            .current_dir(self.workdir()?)
             .arg("push")
-            .arg("origin")
+            .args(set_upstream.then_some("--set-upstream"))
+            .arg(remote)
             .arg(&branch_name)
-            .status()
+            .output()
             .context("Failed to execute git push command")?;
-            
-        if !status.success() {
-            return Err(anyhow::anyhow!("Failed to push changes to remote repository"));
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(exit::git(anyhow::anyhow!(
+                "Failed to push changes to '{}': {}",
+                remote,
+                stderr.trim()
+            )));
         }
-        
+
         Ok(())
     }
 }
+
+fn changed_files(diff: &git2::Diff) -> Vec<String> {
+    diff.deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+fn commit_info(commit: &git2::Commit) -> CommitInfo {
+    let id = commit.id().to_string();
+    CommitInfo {
+        id: id[..7.min(id.len())].to_string(),
+        summary: commit.summary().unwrap_or("").to_string(),
+        author: commit.author().name().unwrap_or("unknown").to_string(),
+        email: commit.author().email().unwrap_or("").to_string(),
+        time: commit.time().seconds(),
+    }
+}
+
+/// An index entry for the blob `entry` at `path`. The file's stat data is left
+/// empty, so git compares its content the next time it looks.
+fn index_entry(path: &str, entry: &git2::TreeEntry) -> git2::IndexEntry {
+    git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: entry.filemode() as u32,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: entry.id(),
+        flags: path.len().min(0xfff) as u16,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+/// Summarize the files `diff` adds, modifies, deletes, renames and copies
+fn changes_in(diff: &git2::Diff) -> Result<StagedChanges> {
+    let mut changes = StagedChanges {
+        added: Vec::new(),
+        modified: Vec::new(),
+        deleted: Vec::new(),
+        renamed: Vec::new(),
+        copied: Vec::new(),
+        stats: DiffStats::default(),
+    };
+
+    for delta in diff.deltas() {
+        let path = |file: git2::DiffFile| {
+            file.path()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        };
+        match delta.status() {
+            Delta::Added => changes.added.push(path(delta.new_file())),
+            Delta::Modified | Delta::Typechange => changes.modified.push(path(delta.new_file())),
+            Delta::Deleted => changes.deleted.push(path(delta.old_file())),
+            Delta::Renamed => changes
+                .renamed
+                .push((path(delta.old_file()), path(delta.new_file()))),
+            Delta::Copied => changes
+                .copied
+                .push((path(delta.old_file()), path(delta.new_file()))),
+            _ => continue,
+        }
+        changes.stats.files_changed += 1;
+    }
+
+    let stats = diff.stats()?;
+    changes.stats.insertions = stats.insertions();
+    changes.stats.deletions = stats.deletions();
+
+    Ok(changes)
+}
+
+/// The patch of each file in `diff`, until about `max_bytes` are read, and how
+/// many files were left unread
+fn file_patches(diff: &git2::Diff, max_bytes: usize) -> Result<(Vec<FilePatch>, usize)> {
+    let mut patches = Vec::new();
+    let mut read = 0;
+    for (index, delta) in diff.deltas().enumerate() {
+        if read >= max_bytes {
+            return Ok((patches, diff.deltas().len() - index));
+        }
+        let Some(mut patch) = git2::Patch::from_diff(diff, index)? else {
+            continue;
+        };
+        let buf = patch.to_buf()?;
+        let text = String::from_utf8_lossy(&buf).into_owned();
+        read += text.len();
+        patches.push(FilePatch {
+            path: delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            key: format!("{}..{}", delta.old_file().id(), delta.new_file().id()),
+            text,
+        });
+    }
+    Ok((patches, 0))
+}
+
+/// Join file patches into prompt text of at most about `max_bytes`. A patch
+/// `summary` has text for is replaced by it. The first patch that does not fit
+/// is cut at a line break; it and the rest are counted with the `unread` ones.
+pub fn join_patches(
+    patches: &[FilePatch],
+    unread: usize,
+    max_bytes: usize,
+    summary: impl Fn(&FilePatch) -> Option<String>,
+) -> String {
+    let mut text = String::new();
+    let mut left_out = unread;
+    for (index, file) in patches.iter().enumerate() {
+        let patch = match summary(file) {
+            Some(summary) => format!(
+                "diff --git a/{0} b/{0}\n[summary of this file's diff: {1}]\n",
+                file.path,
+                summary.trim()
+            ),
+            None => file.text.clone(),
+        };
+        let remaining = max_bytes.saturating_sub(text.len());
+        if patch.len() <= remaining {
+            text.push_str(&patch);
+            continue;
+        }
+
+        // Keep the start of the file that does not fit, cut at a line break
+        left_out += patches.len() - index - 1;
+        let mut end = remaining;
+        while !patch.is_char_boundary(end) {
+            end -= 1;
+        }
+        match patch[..end].rfind('\n') {
+            Some(cut) => {
+                text.push_str(&patch[..=cut]);
+                text.push_str("[rest of this file's diff left out]\n");
+            }
+            None => left_out += 1,
+        }
+        break;
+    }
+    if left_out > 0 {
+        text.push_str(&format!(
+            "[diff of {} more file{} left out to keep the prompt short]\n",
+            left_out,
+            if left_out == 1 { "" } else { "s" }
+        ));
+    }
+    text
+}
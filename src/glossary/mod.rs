@@ -0,0 +1,154 @@
+use regex::Regex;
+
+/// Most terms listed under one answer, so the glossary stays shorter than the answer
+const MAX_TERMS: usize = 6;
+
+/// A piece of git jargon and what it means in plain words
+pub struct Term {
+    pub name: &'static str,
+    /// Regular expression matching the term and its common spellings
+    pattern: &'static str,
+    pub definition: &'static str,
+}
+
+const TERMS: &[Term] = &[
+    Term {
+        name: "detached HEAD",
+        pattern: r"detached[- ]head",
+        definition: "You are looking at a commit directly instead of a branch. New commits here belong to no branch and are easy to lose; create a branch to keep them.",
+    },
+    Term {
+        name: "HEAD",
+        // Only jargon in capitals
+        pattern: r"(?-i:\bHEAD\b)",
+        definition: "The commit you currently have checked out, usually the latest commit of your branch. HEAD~1 is the commit before it.",
+    },
+    Term {
+        name: "fast-forward",
+        pattern: r"fast[- ]forward",
+        definition: "Moving a branch ahead to a newer commit without a merge commit, possible when the branch has no commits of its own.",
+    },
+    Term {
+        name: "reflog",
+        pattern: r"\breflog\b",
+        definition: "Git's local history of where HEAD and your branches have pointed. It can bring back commits after a bad reset or rebase.",
+    },
+    Term {
+        name: "rebase",
+        pattern: r"\brebas(e|ed|ing)\b",
+        definition: "Replaying your commits on top of another commit, as if you had started from there. It rewrites those commits.",
+    },
+    Term {
+        name: "staging area",
+        pattern: r"\b(staging area|staged|unstaged?|stage|index)\b",
+        definition: "The list of changes that go into the next commit. 'git add' puts changes there; it is also called the index.",
+    },
+    Term {
+        name: "working tree",
+        pattern: r"\bworking (tree|directory|copy)\b",
+        definition: "The files you see and edit on disk, including changes you have not staged yet.",
+    },
+    Term {
+        name: "upstream",
+        pattern: r"\b(upstream|tracking branch|remote-tracking)\b",
+        definition: "The branch on a remote that your local branch pushes to and pulls from, e.g. origin/main.",
+    },
+    Term {
+        name: "remote",
+        pattern: r"\b(remotes?|origin)\b",
+        definition: "A copy of the repository on another machine, such as GitHub. 'origin' is the usual name of the one you cloned from.",
+    },
+    Term {
+        name: "force push",
+        pattern: r"force[- ]push|push (-f|--force)",
+        definition: "Replacing the branch on the remote with yours, discarding commits there that you do not have. Others working on it lose their base.",
+    },
+    Term {
+        name: "stash",
+        pattern: r"\bstash(ed|ing)?\b",
+        definition: "A shelf for unfinished changes: 'git stash' puts them aside and cleans your files, 'git stash pop' brings them back.",
+    },
+    Term {
+        name: "cherry-pick",
+        pattern: r"cherry[- ]pick",
+        definition: "Copying one commit from somewhere else onto your current branch.",
+    },
+    Term {
+        name: "squash",
+        pattern: r"\bsquash(ed|ing)?\b",
+        definition: "Combining several commits into one.",
+    },
+    Term {
+        name: "amend",
+        pattern: r"\bamend(ed|ing)?\b",
+        definition: "Replacing the last commit with a corrected one, e.g. to fix its message or add a forgotten file.",
+    },
+    Term {
+        name: "merge conflict",
+        pattern: r"\bconflicts?\b",
+        definition: "Two changes touch the same lines and git cannot decide which to keep. You edit the file to choose, then stage it.",
+    },
+    Term {
+        name: "hard reset",
+        pattern: r"reset --hard|hard reset",
+        definition: "Moving the branch to another commit and throwing away all uncommitted changes. They cannot be recovered.",
+    },
+    Term {
+        name: "soft reset",
+        pattern: r"reset --soft|soft reset",
+        definition: "Moving the branch to another commit while keeping the changes of the undone commits staged.",
+    },
+    Term {
+        name: "revert",
+        pattern: r"\brevert(ed|ing|s)?\b",
+        definition: "Making a new commit that undoes an earlier one, without rewriting history. Safe on shared branches.",
+    },
+    Term {
+        name: "commit hash",
+        pattern: r"\b(hash|sha(-1)?|commit id)\b",
+        definition: "The unique ID of a commit, like a1b2c3d. The first 7 characters are usually enough.",
+    },
+    Term {
+        name: "tag",
+        pattern: r"\btags?\b",
+        definition: "A fixed name for one commit, usually a release version such as v1.2.0.",
+    },
+    Term {
+        name: "bisect",
+        pattern: r"\bbisect\b",
+        definition: "Finding the commit that introduced a bug by testing commits in a binary search.",
+    },
+    Term {
+        name: "submodule",
+        pattern: r"\bsubmodules?\b",
+        definition: "Another repository included in this one at a fixed commit.",
+    },
+];
+
+/// The terms used in `text`, in the order they first appear, at most `MAX_TERMS`.
+/// A term inside a longer one (HEAD in "detached HEAD") is only listed once.
+pub fn terms_in(text: &str) -> Vec<&'static Term> {
+    let mut found: Vec<(usize, usize, &Term)> = Vec::new();
+    for term in TERMS {
+        let Ok(pattern) = Regex::new(&format!("(?i){}", term.pattern)) else {
+            continue;
+        };
+        let covered = |start: usize, end: usize| {
+            found
+                .iter()
+                .any(|(s, e, _)| *s <= start && end <= *e && (*s, *e) != (start, end))
+        };
+        if let Some(m) = pattern
+            .find_iter(text)
+            .find(|m| !covered(m.start(), m.end()))
+        {
+            found.push((m.start(), m.end(), term));
+        }
+    }
+    found.sort_by_key(|(start, _, _)| *start);
+    found
+        .into_iter()
+        .map(|(_, _, term)| term)
+        .take(MAX_TERMS)
+        .collect()
+}
@@ -0,0 +1,215 @@
+use crate::git::GitRepo;
+use crate::theme;
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+/// A workflow help page rendered by `gyst help <topic>`
+pub struct Topic {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub summary: &'static str,
+    template: &'static str,
+}
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "daily",
+        aliases: &["daily-commit", "commit-flow"],
+        summary: "Daily commit flow: stage, review, and commit with gyst",
+        template: include_str!("topics/daily.md"),
+    },
+    Topic {
+        name: "release",
+        aliases: &["release-flow"],
+        summary: "Release flow: review changes since the last tag and publish",
+        template: include_str!("topics/release.md"),
+    },
+    Topic {
+        name: "cleanup",
+        aliases: &["cleanup-flow", "branches"],
+        summary: "Cleanup flow: find and remove stale branches",
+        template: include_str!("topics/cleanup.md"),
+    },
+];
+
+/// Find a help topic by name or alias
+pub fn find_topic(name: &str) -> Option<&'static Topic> {
+    let name = name.to_lowercase();
+    TOPICS
+        .iter()
+        .find(|topic| topic.name == name || topic.aliases.contains(&name.as_str()))
+}
+
+/// Repository details used to make help examples concrete
+struct RepoContext {
+    current_branch: String,
+    main_branch: String,
+    example_branch: String,
+    latest_tag: String,
+    commit_style: String,
+    recent_commit: String,
+}
+
+impl RepoContext {
+    fn detect(path: &str) -> Self {
+        let mut context = RepoContext {
+            current_branch: "feature/my-change".to_string(),
+            main_branch: "main".to_string(),
+            example_branch: "feature/my-change".to_string(),
+            latest_tag: "v1.0.0".to_string(),
+            commit_style: "conventional commits".to_string(),
+            recent_commit: "feat(cli): add help topics".to_string(),
+        };
+
+        let Ok(repo) = Repository::discover(path) else {
+            return context;
+        };
+
+        if let Ok(head) = repo.head()
+            && let Some(name) = head.shorthand()
+        {
+            context.current_branch = name.to_string();
+        }
+
+        let branches: Vec<String> = repo
+            .branches(Some(BranchType::Local))
+            .map(|branches| {
+                branches
+                    .filter_map(|b| b.ok())
+                    .filter_map(|(b, _)| b.name().ok().flatten().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if let Some(main) = ["main", "master", "develop", "trunk"]
+            .iter()
+            .find(|name| branches.iter().any(|b| b == *name))
+        {
+            context.main_branch = main.to_string();
+        }
+
+        if let Some(branch) = branches.iter().find(|b| **b != context.main_branch) {
+            context.example_branch = branch.clone();
+        }
+
+        // The nearest tag behind HEAD, not the last in name order, where
+        // v1.9.0 would sort after v1.10.0
+        if let Ok(Some(tag)) = GitRepo::open(path).and_then(|git| git.latest_tag("HEAD")) {
+            context.latest_tag = tag;
+        }
+
+        let subjects = recent_subjects(&repo, 20);
+        if let Some(subject) = subjects.first() {
+            context.recent_commit = subject.clone();
+        }
+        if !subjects.is_empty() {
+            let conventional = subjects.iter().filter(|s| is_conventional(s)).count();
+            if conventional * 2 < subjects.len() {
+                context.commit_style = "free-form commit messages".to_string();
+            }
+        }
+
+        context
+    }
+
+    fn apply(&self, template: &str) -> String {
+        template
+            .replace("{{current_branch}}", &self.current_branch)
+            .replace("{{main_branch}}", &self.main_branch)
+            .replace("{{example_branch}}", &self.example_branch)
+            .replace("{{latest_tag}}", &self.latest_tag)
+            .replace("{{commit_style}}", &self.commit_style)
+            .replace("{{recent_commit}}", &self.recent_commit)
+    }
+}
+
+fn recent_subjects(repo: &Repository, limit: usize) -> Vec<String> {
+    let Ok(mut revwalk) = repo.revwalk() else {
+        return Vec::new();
+    };
+    if revwalk.push_head().is_err() {
+        return Vec::new();
+    }
+
+    revwalk
+        .filter_map(|id| id.ok())
+        .filter_map(|id| repo.find_commit(id).ok())
+        .filter_map(|commit| commit.summary().map(str::to_string))
+        .take(limit)
+        .collect()
+}
+
+fn is_conventional(subject: &str) -> bool {
+    let Some((prefix, _)) = subject.split_once(": ") else {
+        return false;
+    };
+    let kind = prefix.split('(').next().unwrap_or("").trim_end_matches('!');
+    [
+        "feat", "fix", "docs", "style", "refactor", "perf", "test", "chore", "ci", "build",
+    ]
+    .contains(&kind)
+}
+
+/// Render a help topic with examples taken from the repository at `path`
+pub fn render_topic(topic: &Topic, path: &str) -> Result<String> {
+    let context = RepoContext::detect(path);
+    Ok(render_markdown(&context.apply(topic.template)))
+}
+
+/// List the available help topics
+pub fn render_topic_list() -> String {
+    let mut output = format!("\n{}\n\n", theme::prompt("Help topics").bold().underlined());
+    for topic in TOPICS {
+        output.push_str(&format!(
+            "  {:<10} {}\n",
+            theme::added(topic.name).bold(),
+            topic.summary
+        ));
+    }
+    output.push_str("\nRun 'gyst help <topic>' for a workflow guide or 'gyst help <command>' for command usage.\n");
+    output
+}
+
+/// Render a small subset of markdown (headings, lists, code) for the terminal
+fn render_markdown(markdown: &str) -> String {
+    let mut output = String::new();
+    let mut in_code = false;
+
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+
+        if in_code {
+            output.push_str(&format!("    {}\n", theme::added(line)));
+        } else if let Some(heading) = line.strip_prefix("# ") {
+            output.push_str(&format!(
+                "\n{}\n",
+                theme::prompt(heading).bold().underlined()
+            ));
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            output.push_str(&format!("{}\n", theme::prompt(heading).bold()));
+        } else if let Some(item) = line.strip_prefix("- ") {
+            output.push_str(&format!("  • {}\n", render_inline(item)));
+        } else {
+            output.push_str(&format!("{}\n", render_inline(line)));
+        }
+    }
+
+    output
+}
+
+/// Highlight `inline code` spans
+fn render_inline(line: &str) -> String {
+    line.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                theme::warning(part).to_string()
+            } else {
+                part.to_string()
+            }
+        })
+        .collect()
+}
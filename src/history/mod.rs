@@ -0,0 +1,292 @@
+use crate::git::GitRepo;
+use crate::storage::{self, Store};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+pub const SYSTEM_PROMPT: &str = r#"You are an assistant that answers questions about the history of a git repository.
+You are given a question and a numbered list of candidate commits found by keyword search.
+Follow these rules:
+1. Answer in a few sentences, citing the short hash of every commit you rely on, e.g. (a1b2c3d)
+2. Only use the commits provided; if none of them answer the question, say so
+3. Mention dates when the question asks "when"
+
+After the answer, add a final line of the form:
+RANKING: <hash>, <hash>, ...
+listing the relevant commits, most relevant first. Leave out commits that are not relevant."#;
+
+const INDEX_DOCUMENT: &str = "history-index";
+const INDEX_VERSION: u32 = 1;
+
+/// How many distinct terms from a commit's diff are kept in the index
+const MAX_DIFF_TERMS: usize = 400;
+
+/// How many keyword matches are shown to the AI for re-ranking
+pub const AI_CANDIDATES: usize = 20;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "did", "do", "does", "for", "from", "how", "i", "in", "is", "it",
+    "of", "on", "or", "that", "the", "this", "to", "was", "we", "were", "what", "when", "where",
+    "which", "who", "why", "with",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub body: String,
+    pub author: String,
+    pub time: i64,
+    pub files: Vec<String>,
+    /// Terms from added/removed lines; `None` if the diff was not indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_terms: Option<Vec<String>>,
+}
+
+/// A local index of commit messages (and optionally diffs), stored in `.git/gyst`
+/// with the configured `storage` backend
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+/// A commit that matched a search, with its keyword score
+pub struct Hit<'a> {
+    pub entry: &'a Entry,
+    pub score: u32,
+}
+
+impl Index {
+    /// Load the index and bring it up to date with the repository's branches.
+    /// Only commits that are not indexed yet are read. Returns the index and
+    /// the number of commits that were added.
+    pub fn update(repo: &GitRepo, with_diffs: bool, rebuild: bool) -> Result<(Self, usize)> {
+        let store = storage::open(&repo.data_dir())?;
+
+        let mut index = if rebuild {
+            Self::default()
+        } else {
+            store
+                .read(INDEX_DOCUMENT)
+                .ok()
+                .flatten()
+                .and_then(|contents| serde_json::from_str::<Self>(&contents).ok())
+                .filter(|index| index.version == INDEX_VERSION)
+                .unwrap_or_default()
+        };
+        index.version = INDEX_VERSION;
+
+        let ids = repo.all_commit_ids()?;
+        let reachable: HashSet<&String> = ids.iter().collect();
+        let before = index.entries.len();
+
+        // Drop commits that were rewritten or whose branches were deleted
+        index.entries.retain(|entry| reachable.contains(&entry.id));
+        let mut changed = index.entries.len() != before;
+
+        let mut existing: HashMap<String, Entry> = index
+            .entries
+            .drain(..)
+            .map(|entry| (entry.id.clone(), entry))
+            .collect();
+
+        let mut added = 0;
+        for id in &ids {
+            let entry = match existing.remove(id) {
+                Some(entry) if !with_diffs || entry.diff_terms.is_some() => entry,
+                _ => {
+                    added += 1;
+                    changed = true;
+                    Self::entry_for(repo, id, with_diffs)?
+                }
+            };
+            index.entries.push(entry);
+        }
+
+        if changed {
+            index.save(store.as_ref())?;
+        }
+
+        Ok((index, added))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Rank commits by how many query terms they contain. Matches in the
+    /// subject count most, then file paths, then the body and the diff.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit<'_>> {
+        let terms = tokenize(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<Hit> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let summary = tokenize(&entry.summary);
+                let body = tokenize(&entry.body);
+                let files = tokenize(&entry.files.join(" "));
+                let diff = entry.diff_terms.as_deref().unwrap_or_default();
+
+                let mut score = 0;
+                for term in &terms {
+                    if summary.contains(term) {
+                        score += 5;
+                    }
+                    if files.contains(term) {
+                        score += 3;
+                    }
+                    if body.contains(term) {
+                        score += 2;
+                    }
+                    if diff.contains(term) {
+                        score += 1;
+                    }
+                }
+
+                (score > 0).then_some(Hit { entry, score })
+            })
+            .collect();
+
+        // Stable sort keeps newer commits first among equal scores
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits.truncate(limit);
+        hits
+    }
+
+    fn entry_for(repo: &GitRepo, id: &str, with_diffs: bool) -> Result<Entry> {
+        let details = repo.commit_details(id, with_diffs)?;
+        let body = details
+            .message
+            .split_once('\n')
+            .map(|(_, body)| body.trim().to_string())
+            .unwrap_or_default();
+
+        let diff_terms = details.patch.map(|patch| {
+            let mut seen = HashSet::new();
+            tokenize(&patch)
+                .into_iter()
+                .filter(|term| seen.insert(term.clone()))
+                .take(MAX_DIFF_TERMS)
+                .collect()
+        });
+
+        Ok(Entry {
+            id: details.full_id,
+            short_id: details.info.id,
+            summary: details.info.summary,
+            body,
+            author: details.info.author,
+            time: details.info.time,
+            files: details.files,
+            diff_terms,
+        })
+    }
+
+    fn save(&self, store: &dyn Store) -> Result<()> {
+        let contents = serde_json::to_string(self).context("Failed to serialize history index")?;
+        store
+            .write(INDEX_DOCUMENT, &contents)
+            .context("Failed to write history index")
+    }
+}
+
+/// Build the prompt asking the AI to answer `query` from `hits`
+pub fn build_prompt(query: &str, hits: &[Hit]) -> String {
+    let mut prompt = format!("Question: {}\n\nCandidate commits:\n", query);
+    for (i, hit) in hits.iter().enumerate() {
+        let entry = hit.entry;
+        let date = chrono::DateTime::from_timestamp(entry.time, 0)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        prompt.push_str(&format!(
+            "{}. {} {} by {} on {}\n",
+            i + 1,
+            entry.short_id,
+            entry.summary,
+            entry.author,
+            date
+        ));
+        if !entry.body.is_empty() {
+            let body: String = entry.body.chars().take(300).collect();
+            prompt.push_str(&format!("   {}\n", body.replace('\n', "\n   ")));
+        }
+        if !entry.files.is_empty() {
+            let files: Vec<&str> = entry.files.iter().take(10).map(String::as_str).collect();
+            prompt.push_str(&format!("   Files: {}\n", files.join(", ")));
+        }
+    }
+    prompt
+}
+
+/// Split the AI reply into the answer and the commit ranking it gave
+pub fn parse_answer(reply: &str) -> (String, Vec<String>) {
+    let mut answer = Vec::new();
+    let mut ranking = Vec::new();
+
+    for line in reply.lines() {
+        match line.trim().strip_prefix("RANKING:") {
+            Some(rest) => ranking.extend(
+                rest.split([',', ' '])
+                    .map(|hash| hash.trim().trim_matches(['(', ')', '`']))
+                    .filter(|hash| !hash.is_empty())
+                    .map(str::to_string),
+            ),
+            None => answer.push(line),
+        }
+    }
+
+    (answer.join("\n").trim().to_string(), ranking)
+}
+
+/// Reorder `hits` by the AI's ranking; commits it did not rank keep their keyword order after the ranked ones
+pub fn apply_ranking<'a>(hits: Vec<Hit<'a>>, ranking: &[String]) -> Vec<Hit<'a>> {
+    if ranking.is_empty() {
+        return hits;
+    }
+
+    let position = |hit: &Hit| {
+        ranking
+            .iter()
+            .position(|hash| hit.entry.id.starts_with(hash.as_str()))
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut hits = hits;
+    hits.sort_by_key(|hit| position(hit));
+    hits
+}
+
+/// Lowercase words with stop words removed and simple suffixes stripped,
+/// so "retries" matches "retry" and "changed" matches "change"
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 1)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| stem(&word))
+        .collect()
+}
+
+fn stem(word: &str) -> String {
+    for (suffix, replacement) in [
+        ("ies", "y"),
+        ("ing", ""),
+        ("ed", ""),
+        ("es", ""),
+        ("s", ""),
+        ("e", ""),
+    ] {
+        if let Some(root) = word.strip_suffix(suffix)
+            && root.len() >= 3
+        {
+            return format!("{}{}", root, replacement);
+        }
+    }
+    word.to_string()
+}
@@ -0,0 +1,94 @@
+//! HTTP clients for every service gyst talks to, set up from `[network]`.
+
+use crate::config::{Config, NetworkConfig};
+use anyhow::{Context, Result, anyhow};
+use console::Style;
+use reqwest::{Certificate, Client, ClientBuilder};
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("gyst/", env!("CARGO_PKG_VERSION"));
+
+static INSECURE_WARNING: Once = Once::new();
+static INVALID_WARNING: Once = Once::new();
+
+/// A client builder that trusts `network.ca_cert_path` and, with
+/// `network.insecure_skip_verify`, any certificate at all
+pub fn builder(config: &Config) -> Result<ClientBuilder> {
+    let mut builder = Client::builder().user_agent(USER_AGENT);
+    for certificate in ca_certificates(&config.network)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    if config.network.insecure_skip_verify {
+        INSECURE_WARNING.call_once(|| {
+            eprintln!(
+                "{}",
+                Style::new().yellow().apply_to(
+                    "TLS certificate checks are off (network.insecure_skip_verify). Connections can be intercepted."
+                )
+            );
+        });
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// A client that gives up on requests after `timeout`
+pub fn client(config: &Config, timeout: Duration) -> Result<Client> {
+    builder(config)?
+        .timeout(timeout)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// Like `builder`, for callers that cannot fail: unusable `[network]`
+/// settings are reported once and left out
+pub fn builder_or_warn(config: &Config) -> ClientBuilder {
+    builder(config).unwrap_or_else(|e| {
+        INVALID_WARNING.call_once(|| {
+            eprintln!(
+                "{}",
+                Style::new()
+                    .yellow()
+                    .apply_to(format!("{:#}. Using the system's certificates only.", e))
+            );
+        });
+        Client::builder().user_agent(USER_AGENT)
+    })
+}
+
+/// The certificates in `network.ca_cert_path`, if it is set
+pub fn ca_certificates(network: &NetworkConfig) -> Result<Vec<Certificate>> {
+    let Some(path) = network
+        .ca_cert_path
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return Ok(Vec::new());
+    };
+    let path = expand_home(path);
+    let pem = std::fs::read(&path)
+        .with_context(|| format!("Failed to read network.ca_cert_path {}", path.display()))?;
+    let certificates = Certificate::from_pem_bundle(&pem).with_context(|| {
+        format!(
+            "network.ca_cert_path {} is not a PEM certificate file",
+            path.display()
+        )
+    })?;
+    if certificates.is_empty() {
+        return Err(anyhow!(
+            "network.ca_cert_path {} contains no PEM certificates",
+            path.display()
+        ));
+    }
+    Ok(certificates)
+}
+
+/// `path` with a leading `~/` replaced by the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
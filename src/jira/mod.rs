@@ -0,0 +1,135 @@
+use crate::config::Config;
+use crate::http;
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Environment variable checked for a Jira token when `jira.token` is not set
+const TOKEN_VAR: &str = "JIRA_API_TOKEN";
+
+/// Give up on Jira after this long, so an unreachable server cannot hold up a commit
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Issue descriptions are cut off after this many characters before they go into a prompt
+const MAX_DESCRIPTION: usize = 1500;
+
+/// A Jira issue, e.g. the story a branch implements
+#[derive(Debug)]
+pub struct JiraIssue {
+    pub key: String,
+    pub summary: String,
+    pub description: String,
+    /// Link to the issue in the Jira web interface
+    pub url: String,
+}
+
+impl JiraIssue {
+    /// Context for the AI about the requirement the change implements
+    pub fn prompt_hint(&self) -> String {
+        let mut hint = format!(
+            "This work implements Jira issue {}: {}",
+            self.key, self.summary
+        );
+        let description = self.description.trim();
+        if !description.is_empty() {
+            let description: String = description.chars().take(MAX_DESCRIPTION).collect();
+            hint.push_str(&format!("\nIssue description:\n{}", description));
+        }
+        hint
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+    key: String,
+    fields: FieldsResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct FieldsResponse {
+    summary: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// A Jira Cloud site or Jira Server/Data Center instance
+pub struct Jira {
+    client: Client,
+    url: String,
+    /// Jira Cloud authenticates with the account email and an API token;
+    /// Server and Data Center take a personal access token on its own
+    email: Option<String>,
+    token: String,
+}
+
+impl Jira {
+    /// Connect to the instance in `jira.url`. Returns `None` when no URL or token is set.
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let Some(url) = config
+            .jira
+            .url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/'))
+            .filter(|url| !url.is_empty())
+        else {
+            return Ok(None);
+        };
+        let Some(token) = config
+            .jira
+            .token
+            .clone()
+            .filter(|token| !token.is_empty())
+            .or_else(|| {
+                std::env::var(TOKEN_VAR)
+                    .ok()
+                    .filter(|token| !token.is_empty())
+            })
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            client: http::client(config, REQUEST_TIMEOUT)?,
+            url: url.to_string(),
+            email: config.jira.email.clone().filter(|email| !email.is_empty()),
+            token,
+        }))
+    }
+
+    /// Fetch the summary and description of the issue `key`, e.g. `PROJ-123`
+    pub async fn issue(&self, key: &str) -> Result<JiraIssue> {
+        // Version 2 of the API returns the description as text on both Cloud and Server
+        let url = format!(
+            "{}/rest/api/2/issue/{}?fields=summary,description",
+            self.url, key
+        );
+        let request = self
+            .client
+            .get(&url)
+            .header("Accept", "application/json")
+            .header("User-Agent", "gyst");
+        let request = match &self.email {
+            Some(email) => request.basic_auth(email, Some(&self.token)),
+            None => request.bearer_auth(&self.token),
+        };
+
+        let response = request.send().await.context("Failed to reach Jira")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Jira API error ({}): {}", status, error_text));
+        }
+
+        let issue: IssueResponse = response
+            .json()
+            .await
+            .context("Failed to parse Jira response")?;
+        Ok(JiraIssue {
+            url: format!("{}/browse/{}", self.url, issue.key),
+            key: issue.key,
+            summary: issue.fields.summary,
+            description: issue.fields.description.unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,177 @@
+//! Lays out a finished commit message the way `git log` and most projects
+//! expect: a blank line after the subject, a body wrapped at a fixed width
+//! and one block of footers at the end in a predictable order
+
+use crate::trailer;
+
+/// Tokens of footers that point at an issue or ticket
+const REFERENCE_TOKENS: &[&str] = &[
+    "close",
+    "closes",
+    "closed",
+    "fix",
+    "fixes",
+    "fixed",
+    "resolve",
+    "resolves",
+    "resolved",
+    "ref",
+    "refs",
+    "references",
+    "part-of",
+    "related-to",
+    "see-also",
+];
+
+/// Lay out `message`: separate the subject from the body, wrap body lines
+/// longer than `width` (0 leaves them alone), and gather the footers at the end
+/// in the order breaking changes, issue references, other trailers,
+/// co-authors, sign-offs. The subject line is never wrapped.
+pub fn apply(message: &str, width: usize) -> String {
+    let lines: Vec<&str> = message.trim().lines().map(str::trim_end).collect();
+    let Some((subject, rest)) = lines.split_first() else {
+        return String::new();
+    };
+
+    let mut paragraphs = paragraphs(rest);
+    let mut footers: Vec<&str> = Vec::new();
+    while let Some(last) = paragraphs.last()
+        && last.iter().all(|line| is_footer(line))
+    {
+        let mut paragraph = paragraphs.pop().unwrap_or_default();
+        paragraph.append(&mut footers);
+        footers = paragraph;
+    }
+
+    let mut out = vec![subject.trim().to_string()];
+    for paragraph in paragraphs {
+        out.push(String::new());
+        let mut in_code = false;
+        for line in paragraph {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+            }
+            if in_code || line.starts_with("    ") || line.starts_with('\t') {
+                out.push(line.to_string());
+            } else {
+                out.extend(wrap(line, width));
+            }
+        }
+    }
+    if !footers.is_empty() {
+        out.push(String::new());
+        out.extend(sort_footers(footers));
+    }
+    out.join("\n")
+}
+
+/// Group `lines` into paragraphs separated by blank lines. Blank lines inside
+/// a fenced code block are kept.
+fn paragraphs<'a>(lines: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut paragraphs: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut in_code = false;
+    for &line in lines {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if line.trim().is_empty() && !in_code {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs
+}
+
+/// A `Token: value` trailer, a `Token #value` issue reference or a breaking change note
+fn is_footer(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("BREAKING CHANGE: ")
+        || line.starts_with("BREAKING-CHANGE: ")
+        || trailer::is_trailer_line(line)
+        || line.split_once(" #").is_some_and(|(token, value)| {
+            !token.is_empty()
+                && token.chars().all(|c| c.is_alphanumeric() || c == '-')
+                && !value.trim().is_empty()
+        })
+}
+
+/// Order footers by kind, keeping their order within a kind, without duplicates
+fn sort_footers(footers: Vec<&str>) -> Vec<String> {
+    let mut footers: Vec<String> = footers.iter().map(|line| line.trim().to_string()).collect();
+    let mut seen = std::collections::HashSet::new();
+    footers.retain(|line| seen.insert(line.to_lowercase()));
+    footers.sort_by_key(|line| footer_rank(line));
+    footers
+}
+
+fn footer_rank(line: &str) -> u8 {
+    if line.starts_with("BREAKING") {
+        return 0;
+    }
+    let token = line
+        .split([':', ' '])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    match token.as_str() {
+        token if REFERENCE_TOKENS.contains(&token) => 1,
+        "co-authored-by" => 3,
+        "signed-off-by" => 4,
+        _ => 2,
+    }
+}
+
+/// Break `line` into lines of at most `width` characters at spaces. A list
+/// item's continuation lines are indented under its text, and a word longer
+/// than `width`, such as a URL, gets a line of its own.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    if width == 0 || line.chars().count() <= width {
+        return vec![line.to_string()];
+    }
+    let text = line.trim_start();
+    let indent = line.len() - text.len();
+    let hanging = indent + list_marker(text).map_or(0, str::len);
+    let first_indent = &line[..indent];
+    let continuation = " ".repeat(hanging);
+
+    let mut lines = Vec::new();
+    let mut current = first_indent.to_string();
+    let mut current_width = first_indent.chars().count();
+    let mut empty = true;
+    for word in text.split(' ').filter(|word| !word.is_empty()) {
+        let word_width = word.chars().count();
+        if !empty && current_width + 1 + word_width > width {
+            lines.push(std::mem::replace(&mut current, continuation.clone()));
+            current_width = hanging;
+            empty = true;
+        }
+        if !empty {
+            current.push(' ');
+            current_width += 1;
+        }
+        current.push_str(word);
+        current_width += word_width;
+        empty = false;
+    }
+    lines.push(current);
+    lines
+}
+
+/// The bullet or number starting a list item, with the space after it: `- `, `* `, `1. `
+fn list_marker(text: &str) -> Option<&str> {
+    let (marker, _) = text.split_once(' ')?;
+    let bullet = matches!(marker, "-" | "*" | "+");
+    let numbered = marker.len() > 1
+        && marker.ends_with(['.', ')'])
+        && marker[..marker.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_digit());
+    (bullet || numbered).then(|| &text[..marker.len() + 1])
+}
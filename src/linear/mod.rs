@@ -0,0 +1,132 @@
+use crate::config::Config;
+use crate::http;
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// Environment variable checked for a Linear API key when `linear.api_key` is not set
+const TOKEN_VAR: &str = "LINEAR_API_KEY";
+
+const API_URL: &str = "https://api.linear.app/graphql";
+
+/// Give up on Linear after this long, so an unreachable server cannot hold up a commit
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Issue descriptions are cut off after this many characters before they go into a prompt
+const MAX_DESCRIPTION: usize = 1500;
+
+const ISSUE_QUERY: &str =
+    "query Issue($id: String!) { issue(id: $id) { identifier title description } }";
+
+/// A Linear issue, e.g. `ENG-123`
+#[derive(Debug)]
+pub struct LinearIssue {
+    pub identifier: String,
+    pub title: String,
+    pub description: String,
+}
+
+impl LinearIssue {
+    /// Context for the AI about the work the change does
+    pub fn prompt_hint(&self) -> String {
+        let mut hint = format!(
+            "This work addresses Linear issue {}: {}",
+            self.identifier, self.title
+        );
+        let description = self.description.trim();
+        if !description.is_empty() {
+            let description: String = description.chars().take(MAX_DESCRIPTION).collect();
+            hint.push_str(&format!("\nIssue description:\n{}", description));
+        }
+        hint
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlResponse {
+    data: Option<IssueData>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueData {
+    issue: Option<IssueResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+    identifier: String,
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+/// The Linear workspace the API key belongs to
+pub struct Linear {
+    client: Client,
+    api_key: String,
+}
+
+impl Linear {
+    /// Connect with `linear.api_key` or `LINEAR_API_KEY`. Returns `None` when neither is set.
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let Some(api_key) = config
+            .linear
+            .api_key
+            .clone()
+            .filter(|key| !key.is_empty())
+            .or_else(|| std::env::var(TOKEN_VAR).ok().filter(|key| !key.is_empty()))
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            client: http::client(config, REQUEST_TIMEOUT)?,
+            api_key,
+        }))
+    }
+
+    /// Fetch the title and description of the issue `identifier`, e.g. `ENG-123`
+    pub async fn issue(&self, identifier: &str) -> Result<LinearIssue> {
+        let response = self
+            .client
+            .post(API_URL)
+            // Personal API keys are sent as they are, without "Bearer"
+            .header("Authorization", &self.api_key)
+            .header("User-Agent", "gyst")
+            .json(&json!({ "query": ISSUE_QUERY, "variables": { "id": identifier } }))
+            .send()
+            .await
+            .context("Failed to reach Linear")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Linear API error ({}): {}", status, error_text));
+        }
+
+        let response: GraphqlResponse = response
+            .json()
+            .await
+            .context("Failed to parse Linear response")?;
+        if let Some(error) = response.errors.first() {
+            return Err(anyhow!("Linear API error: {}", error.message));
+        }
+        let issue = response
+            .data
+            .and_then(|data| data.issue)
+            .ok_or_else(|| anyhow!("Linear issue {} not found", identifier))?;
+        Ok(LinearIssue {
+            identifier: issue.identifier,
+            title: issue.title,
+            description: issue.description.unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use crate::config::Config;
+use crate::forge::Forge;
+use crate::git::GitRepo;
+use crate::jira::Jira;
+use crate::linear::Linear;
+use crate::trailer;
+use anyhow::{Result, anyhow};
+use regex::Regex;
+
+/// Where a linked work item lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tracker {
+    GitHub,
+    GitLab,
+    Jira,
+    Linear,
+}
+
+impl Tracker {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::GitHub => "GitHub",
+            Self::GitLab => "GitLab",
+            Self::Jira => "Jira",
+            Self::Linear => "Linear",
+        }
+    }
+}
+
+/// The issue a commit is attached to with `gyst commit --link`
+#[derive(Debug)]
+pub struct WorkItem {
+    pub tracker: Tracker,
+    /// How the tracker refers to the item in commit messages: `#42` or `ABC-123`
+    pub reference: String,
+    pub title: String,
+    hint: String,
+}
+
+impl WorkItem {
+    /// Look up `reference` in the tracker it belongs to: issue numbers (`#42`)
+    /// on the forge behind `origin`, keys (`ABC-123`) in Jira, then Linear.
+    /// Fails when no tracker is configured or the item does not exist.
+    pub async fn fetch(config: &Config, repo: &GitRepo, reference: &str) -> Result<Self> {
+        let reference = reference.trim();
+        if let Ok(number) = reference.trim_start_matches('#').parse::<u64>() {
+            let forge = Forge::connect(config, repo)?;
+            let issue = forge
+                .issue(number)
+                .await
+                .map_err(|e| anyhow!("Could not find issue #{}: {}", number, e))?;
+            return Ok(Self {
+                tracker: if issue.forge == "GitLab" {
+                    Tracker::GitLab
+                } else {
+                    Tracker::GitHub
+                },
+                reference: format!("#{}", issue.number),
+                hint: issue.prompt_hint(),
+                title: issue.title,
+            });
+        }
+
+        let key_pattern = Regex::new(r"^[A-Za-z][A-Za-z0-9]*-\d+$")?;
+        if !key_pattern.is_match(reference) {
+            return Err(anyhow!(
+                "'{}' is not a work item. Use an issue number like #42 or a key like ABC-123",
+                reference
+            ));
+        }
+        let key = reference.to_uppercase();
+
+        let mut errors = Vec::new();
+        // A configured project key tells Jira keys apart from Linear ones
+        let jira_project = config
+            .jira
+            .project
+            .as_deref()
+            .filter(|project| !project.is_empty());
+        let jira_owns_key = jira_project.is_none_or(|project| {
+            key.split_once('-')
+                .is_some_and(|(prefix, _)| prefix.eq_ignore_ascii_case(project))
+        });
+        if let Some(jira) = Jira::from_config(config)?.filter(|_| jira_owns_key) {
+            match jira.issue(&key).await {
+                Ok(issue) => {
+                    return Ok(Self {
+                        tracker: Tracker::Jira,
+                        hint: issue.prompt_hint(),
+                        reference: issue.key,
+                        title: issue.summary,
+                    });
+                }
+                Err(e) => errors.push(format!("Jira: {:#}", e)),
+            }
+        }
+        if let Some(linear) = Linear::from_config(config)? {
+            match linear.issue(&key).await {
+                Ok(issue) => {
+                    return Ok(Self {
+                        tracker: Tracker::Linear,
+                        hint: issue.prompt_hint(),
+                        reference: issue.identifier,
+                        title: issue.title,
+                    });
+                }
+                Err(e) => errors.push(format!("Linear: {:#}", e)),
+            }
+        }
+
+        if errors.is_empty() {
+            return Err(anyhow!(
+                "No issue tracker is set up for {}. Set jira.url and jira.token, or linear.api_key (or the JIRA_API_TOKEN or LINEAR_API_KEY environment variable)",
+                key
+            ));
+        }
+        Err(anyhow!(
+            "Could not find {}.\n  {}",
+            key,
+            errors.join("\n  ")
+        ))
+    }
+
+    /// Context for the AI about the work item. The footer is added afterwards,
+    /// so the AI should leave the reference out.
+    pub fn prompt_hint(&self) -> String {
+        format!(
+            "{}\nDo not mention {} in the message; a reference to it is added automatically.",
+            self.hint, self.reference
+        )
+    }
+
+    /// The footer line for `message`. Forges and Linear close the item when a
+    /// fix or feature lands (`Fixes #42`, `Closes ENG-7`) and only mention it
+    /// otherwise; Jira keys are linked with a `Refs:` trailer.
+    fn footer(&self, message: &str) -> String {
+        if self.tracker == Tracker::Jira {
+            return format!("Refs: {}", self.reference);
+        }
+        let keyword = match commit_type(message).as_deref() {
+            Some("fix") => "Fixes",
+            Some("feat") => "Closes",
+            _ => "Refs",
+        };
+        format!("{} {}", keyword, self.reference)
+    }
+
+    /// Add the footer to `message` unless it already has one for this item
+    pub fn apply(&self, message: &str) -> String {
+        let message = message.trim();
+        let footer = self.footer(message);
+        if self.tracker == Tracker::Jira {
+            return trailer::append(message, &[footer]);
+        }
+
+        let already_linked = message.lines().skip(1).any(|line| {
+            let line = line.trim().to_lowercase();
+            line.split_once(' ').is_some_and(|(keyword, rest)| {
+                rest.trim() == self.reference.to_lowercase()
+                    && CLOSING_KEYWORDS.contains(&keyword.trim_end_matches(':'))
+            })
+        });
+        if already_linked {
+            return message.to_string();
+        }
+        format!("{}\n\n{}", message, footer)
+    }
+}
+
+/// Keywords forges and Linear recognize in front of an issue reference
+const CLOSING_KEYWORDS: &[&str] = &[
+    "close",
+    "closes",
+    "closed",
+    "fix",
+    "fixes",
+    "fixed",
+    "resolve",
+    "resolves",
+    "resolved",
+    "refs",
+    "ref",
+    "references",
+];
+
+/// The conventional commit type of `message`'s subject, e.g. `fix` for `fix(api): ...`
+fn commit_type(message: &str) -> Option<String> {
+    let subject = message.lines().next()?;
+    let pattern = Regex::new(r"^\s*([A-Za-z]+)(?:\([^)]*\))?!?:").ok()?;
+    pattern.captures(subject).map(|caps| caps[1].to_lowercase())
+}
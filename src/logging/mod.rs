@@ -0,0 +1,129 @@
+//! Request tracing for `--verbose` (or `GYST_LOG=1`) and `--show-prompt`.
+//!
+//! Log lines go to stderr so they never mix with output meant for pipes.
+//! Anything that looks like an API key or token is masked first. In CI they
+//! are JSON objects, one per line, for log collectors.
+
+use console::{Style, Term};
+use regex::Regex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+pub const ENV_VAR: &str = "GYST_LOG";
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static SHOW_PROMPT: AtomicBool = AtomicBool::new(false);
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Secrets seen during this run, e.g. the configured API key
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Patterns of well-known token formats
+static TOKEN_PATTERN: OnceLock<Option<Regex>> = OnceLock::new();
+
+/// Turn logging on for `--verbose` or a `GYST_LOG` value other than `0`, `off` or `false`.
+/// `json` writes each line as `{"source":"gyst","message":...}`.
+pub fn init(verbose: bool, show_prompt: bool, json: bool) {
+    let from_env = std::env::var(ENV_VAR).is_ok_and(|value| {
+        let value = value.trim().to_lowercase();
+        !matches!(value.as_str(), "" | "0" | "off" | "false")
+    });
+    VERBOSE.store(verbose || from_env, Ordering::Relaxed);
+    SHOW_PROMPT.store(show_prompt, Ordering::Relaxed);
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Write a line to stderr when logging is on. The message is only built then.
+pub fn log(message: impl FnOnce() -> String) {
+    if !enabled() {
+        return;
+    }
+    if JSON.load(Ordering::Relaxed) {
+        eprintln!(
+            "{}",
+            serde_json::json!({ "source": "gyst", "message": redact(&message()) })
+        );
+        return;
+    }
+    // Start on a fresh line in case a spinner is drawing
+    let _ = Term::stderr().clear_line();
+    eprintln!(
+        "{} {}",
+        Style::new().dim().apply_to("[gyst]"),
+        redact(&message())
+    );
+}
+
+/// Log how one attempt at a request went and how long it took
+pub fn attempt(
+    service: &str,
+    attempt: u32,
+    result: &reqwest::Result<reqwest::Response>,
+    started: Instant,
+) {
+    log(|| {
+        let outcome = match result {
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+        format!(
+            "{} attempt {}: {} in {:.2}s",
+            service,
+            attempt,
+            outcome,
+            started.elapsed().as_secs_f64()
+        )
+    });
+}
+
+/// Never log `secret`, even where it does not look like a token
+pub fn hide(secret: &str) {
+    if secret.len() < 8 {
+        return;
+    }
+    if let Ok(mut secrets) = SECRETS.lock()
+        && !secrets.iter().any(|known| known == secret)
+    {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Mask known secrets and anything shaped like an API key or token
+pub fn redact(text: &str) -> String {
+    let mut text = text.to_string();
+    if let Ok(secrets) = SECRETS.lock() {
+        for secret in secrets.iter() {
+            text = text.replace(secret.as_str(), "[REDACTED]");
+        }
+    }
+    let pattern = TOKEN_PATTERN.get_or_init(|| {
+        Regex::new(
+            r"sk-ant-[A-Za-z0-9_\-]+|sk-[A-Za-z0-9_\-]{20,}|gh[pousr]_[A-Za-z0-9]{20,}|github_pat_[A-Za-z0-9_]{20,}|glpat-[A-Za-z0-9_\-]{20,}|xox[abpr]-[A-Za-z0-9\-]{10,}|AKIA[0-9A-Z]{16}|(?i:bearer)\s+[A-Za-z0-9._\-]{16,}",
+        )
+        .ok()
+    });
+    match pattern {
+        Some(pattern) => pattern.replace_all(&text, "[REDACTED]").to_string(),
+        None => text,
+    }
+}
+
+pub fn showing_prompt() -> bool {
+    SHOW_PROMPT.load(Ordering::Relaxed)
+}
+
+/// With `--show-prompt`, print what would be sent and exit instead of sending it
+pub fn show_prompt(what: &str, prompt: &str) {
+    if !showing_prompt() {
+        return;
+    }
+    let _ = Term::stdout().clear_line();
+    println!("{}", Style::new().bold().apply_to(format!("# {}", what)));
+    println!("{}", redact(prompt));
+    std::process::exit(0);
+}
@@ -1,358 +1,891 @@
 mod ai;
+mod audit;
 mod branch;
+mod chaos;
+mod ci;
 mod cli;
+mod clipboard;
+mod codeowners;
 mod command_suggest;
+mod commitlint;
+mod completions;
 mod config;
+mod dashboard;
+mod dates;
+mod diff_view;
+mod docs;
+mod doctor;
+mod editor;
+mod exit;
+mod feedback;
+mod fixup;
+mod forge;
 mod git;
+mod glossary;
+mod help;
+mod history;
+mod http;
+mod jira;
+mod layout;
+mod linear;
+mod link;
+mod logging;
+mod offline;
+mod output;
+mod pr;
+mod preview;
+mod release;
+mod seal;
+mod semver;
 mod server;
+mod shell;
+mod split;
+mod standup;
+mod status;
+mod storage;
+mod summaries;
+mod telemetry;
+mod template;
+mod testing;
+mod theme;
+mod ticket;
+mod trailer;
+mod version;
+mod watch;
+mod wording;
+mod workspace;
 
 use crate::branch::{BranchAnalyzer, BranchFilter, format_output};
-use clap::Parser;
-use cli::{Cli, Commands};
-use colored::*;
-use console::{Emoji, style};
-use dialoguer::{Select, theme::ColorfulTheme};
+use anyhow::Context;
+use clap::{CommandFactory, FromArgMatches};
+use cli::{
+    Cli, Commands, ConfigCommands, FeedbackCommands, HistoryCommands, ReleaseCommands, TagCommands,
+    VersionCommands,
+};
+use console::{Term, style};
+use dialoguer::{Confirm, Input, MultiSelect, Password, Select};
 use spinners::{Spinner, Spinners};
-use std::io::{self, Write};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-static CHECKMARK: Emoji<'_, '_> = Emoji("✓", "√");
-static CROSS: Emoji<'_, '_> = Emoji("✗", "x");
-static SPARKLE: Emoji<'_, '_> = Emoji("✨", "*");
-static PENCIL: Emoji<'_, '_> = Emoji("✏️ ", ">");
+static CHECKMARK: output::Symbol = output::Symbol("✓", "√");
+static CROSS: output::Symbol = output::Symbol("✗", "x");
+static SPARKLE: output::Symbol = output::Symbol("✨", "*");
+static PENCIL: output::Symbol = output::Symbol("✏️ ", ">");
+static CAUTION: output::Symbol = output::Symbol("⚠", "!");
+static STOP: output::Symbol = output::Symbol("⛔", "!!");
+
+/// `println!` for status lines and notices, which `--quiet` leaves out
+macro_rules! notice {
+    ($($arg:tt)*) => {
+        if !output::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `eprintln!` for notices, which `--quiet` leaves out
+macro_rules! enotice {
+    ($($arg:tt)*) => {
+        if !output::quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// `gyst diff --blame` highlights removed lines last changed within this many days
+const RECENT_DAYS: i64 = 14;
+
+/// Longest staged diff put into a prompt, in bytes. Large imports are cut
+/// down to the first files that fit.
+const MAX_PROMPT_DIFF: usize = 60_000;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    // Answer completion requests from the scripts written by 'gyst completions'
+    clap_complete::CompleteEnv::with_factory(Cli::command)
+        .var(completions::ENV_VAR)
+        .complete();
+
+    let matches = Cli::command()
+        .try_get_matches()
+        .unwrap_or_else(exit_with_usage_error);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(exit_with_usage_error);
+    chaos::init()?;
+    logging::init(cli.verbose, cli.show_prompt, ci::active());
+    output::init(cli.quiet, cli.yes, cli.no_color);
+    if ci::active() {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+    if let Ok(config) = config::Config::load()
+        && let Err(e) = config.refresh_team_settings().await
+    {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!(
+                "Could not refresh team settings: {:#}. Using the last copy.",
+                e
+            ))
+            .dim()
+        );
+    }
+    if let Ok(config) = config::Config::load_for_repo(&cli.repo) {
+        theme::init(&config.ui.theme);
+        dates::init(&config.ui);
+    }
+
+    if let Some(problem) = missing_ci_setting(&cli.repo, &cli.command) {
+        eprintln!("Error: {}", problem);
+        std::process::exit(exit::Status::Config.code());
+    }
+    if needs_setup(&cli.repo, &cli.command) {
+        println!(
+            "{} {}",
+            PENCIL,
+            theme::prompt("gyst is not set up yet. This takes a minute.")
+        );
+        if run_setup(config::Config::load()?).await?.is_none() {
+            println!(
+                "\n{} {}\n",
+                CROSS,
+                theme::warning("Setup skipped. Run 'gyst init' any time.")
+            );
+        }
+    }
+
+    let command = command_name(&matches);
+    let started = Instant::now();
+    let result = run(cli).await;
+    telemetry::record(&command, started.elapsed(), result.is_ok()).await;
+    if let Err(e) = result {
+        match exit::status(&e) {
+            Some(status) if status.is_expected() => {
+                notice!("\n{} {}", CROSS, theme::warning(e.to_string()));
+            }
+            _ => eprintln!("Error: {:?}", e),
+        }
+        std::process::exit(exit::code(&e));
+    }
+    Ok(())
+}
 
+/// Print a command line clap could not read and exit with `exit::Status::Config`.
+/// `--help` and `--version` are printed the same way and exit with 0.
+fn exit_with_usage_error<T>(e: clap::Error) -> T {
+    if !e.use_stderr() {
+        e.exit();
+    }
+    let _ = e.print();
+    std::process::exit(exit::Status::Config.code());
+}
+
+/// Run the chosen subcommand
+async fn run(cli: Cli) -> anyhow::Result<()> {
     match cli.command {
-        Commands::Commit { quick, push } => {
-            let repo = git::GitRepo::open(".")?;
+        Commands::Commit {
+            quick,
+            push,
+            create_pr,
+            template,
+            co_authors,
+            remotes,
+            force,
+            fixup,
+            squash,
+            link,
+            split,
+            interactive_files,
+            only,
+            copy,
+            model,
+        } => {
+            // Nobody can answer prompts in CI
+            let quick = quick || ci::active();
+            let mut config = config::Config::load_for_repo(&cli.repo)?;
+            if let Some(name) = template {
+                config.commit.template = name;
+            }
+            override_model(&mut config, model).map_err(exit::invalid)?;
+            let copy = copy || config.ui.copy;
+            let repo = git::GitRepo::open(&cli.repo)?
+                .with_similarity(config.git.rename_threshold, config.git.detect_copies);
+            let template = template::Template::resolve(&config)
+                .map_err(exit::invalid)?
+                .with_commitlint(commitlint::Rules::load(repo.workdir()?)?)
+                .with_workspace(workspace::Workspace::load(repo.workdir()?));
+            // Find out before committing whether the pull request can be opened
+            if create_pr {
+                forge::Forge::connect(&config, &repo)?;
+            }
+            let _lock = repo.lock(cli.wait)?;
 
-            // Check if there are any changes at all
-            if !repo.has_any_changes()? {
+            if repo.is_head_detached() {
+                if push {
+                    return Err(exit::git(anyhow::anyhow!(
+                        "HEAD is detached, so there is no branch to push. Check out a branch first (e.g. 'git switch -c <name>')"
+                    )));
+                }
                 println!(
                     "\n{} {}",
                     CROSS,
-                    style("No changes found in the repository.").yellow()
+                    theme::warning(
+                        "HEAD is detached: the new commit will not belong to any branch."
+                    )
                 );
-                return Ok(());
             }
 
-            // Check if there are any staged changes
-            if !repo.has_staged_changes()? {
-                println!("\n{} {}", CROSS, style("No staged changes found.").yellow());
-                print!("\n{} Would you like to stage all changes? [y/N] ", PENCIL);
-                io::stdout().flush()?;
+            // Check if there are any changes at all
+            if !repo.has_any_changes()? {
+                return Err(exit::nothing_staged("No changes found in the repository."));
+            }
 
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
+            // Check if there are any staged changes; --only stages its own
+            if only.is_empty() && !repo.has_staged_changes()? {
+                if ci::active() && !output::assume_yes() {
+                    return Err(exit::nothing_staged(
+                        "No staged changes. Stage your changes using 'git add' first.",
+                    ));
+                }
+                println!("\n{} {}", CROSS, theme::warning("No staged changes found."));
+                print!("\n{} Would you like to stage all changes? [y/N] ", PENCIL);
+                let input = read_answer()?;
 
-                if input.trim().to_lowercase() == "y" {
-                    let mut sp = Spinner::new(Spinners::Dots9, "Staging all changes...".into());
+                if input == "y" {
+                    let mut sp = Progress::new(Spinners::Dots9, "Staging all changes...".into());
                     repo.stage_all()?;
                     sp.stop_with_message(format!(
                         "{} {} {}\n",
                         CHECKMARK,
-                        style("All changes have been staged").green(),
+                        theme::added("All changes have been staged"),
                         SPARKLE
                     ));
                 } else {
-                    println!(
-                        "\n{} {}",
-                        CROSS,
-                        style("No changes to commit. Stage your changes using 'git add' first.")
-                            .yellow()
-                    );
-                    return Ok(());
-                }
-            }
-
-            let changes = repo.get_staged_changes()?;
-            let hunks = repo.get_structured_diff()?;
-
-            // Convert hunks to a single diff string
-            let mut diff = String::new();
-            for hunk in &hunks {
-                diff.push_str(&hunk.header);
-                for line in &hunk.lines {
-                    diff.push_str(&line.content);
+                    return Err(exit::nothing_staged(
+                        "No changes to commit. Stage your changes using 'git add' first.",
+                    ));
                 }
             }
 
-            // Load config
-            let config = config::Config::load()?;
-
-            let mut sp = Spinner::new(
-                Spinners::Dots12,
-                "Analyzing changes and generating commit message...".into(),
-            );
-
-            let message = if config.use_server() {
-                // Use server client
-                let server_client = server::ServerClient::new(config);
-
-                // Optional: Check server health
-                if let Err(e) = server_client.health_check().await {
-                    sp.stop_with_message(format!(
-                        "{} {}\n",
-                        CROSS,
-                        style("Failed to connect to server").red()
-                    ));
-                    println!(
-                        "Error: {}. Check server URL or use direct API mode with 'gyst config --use-server false'",
-                        e
-                    );
-                    return Ok(());
+            // Staged again after the commit, or on the way out if it fails
+            let set_aside = if !only.is_empty() {
+                let pathspecs: Vec<String> = only
+                    .iter()
+                    .map(|pathspec| repo.root_pathspec(Path::new(&cli.repo), pathspec))
+                    .collect();
+                let set_aside = repo.stage_only(&pathspecs)?;
+                if set_aside.taken().is_empty() {
+                    return Err(exit::nothing_staged(&format!(
+                        "No changes match {}",
+                        only.join(" ")
+                    )));
                 }
-
-                server_client.generate_message(&changes, &diff).await?
+                Some(set_aside)
+            } else if interactive_files {
+                choose_files(&repo)?
             } else {
-                // Use direct API client
-                let generator = ai::CommitMessageGenerator::new(config);
-                generator.generate_message(&changes, &diff).await?
+                None
             };
 
-            sp.stop_with_message(format!(
-                "{} {}\n",
-                CHECKMARK,
-                style("Commit message generated!").green()
-            ));
+            let changes = repo.get_staged_changes()?;
+            let diff = diff_text(&repo, &config).await?;
+            print_large_change_notice(&changes);
+
+            if let Some((kind, target)) = fixup
+                .map(|target| (fixup::FixupKind::Fixup, target))
+                .or(squash.map(|target| (fixup::FixupKind::Squash, target)))
+            {
+                let target =
+                    select_fixup_target(&repo, &config, &changes, &diff, &target, !quick).await?;
 
-            if quick {
-                // Use the message directly in quick mode
-                let mut sp = Spinner::new(Spinners::Dots9, "Creating commit...".into());
+                let message = kind.message(&target);
+                confirm_protected_branch(&repo, &config, &message, force, !quick)?;
+
+                let mut sp = Progress::new(Spinners::Dots9, "Creating commit...".into());
                 repo.create_commit(&message)?;
                 sp.stop_with_message(format!(
                     "{} {} {}\n",
                     CHECKMARK,
-                    style("Commit created successfully!").green().bold(),
+                    theme::added("Commit created successfully!").bold(),
                     SPARKLE
                 ));
-                println!(
+                restage(set_aside)?;
+                notice!(
                     "\n{} {}\n{}\n",
                     PENCIL,
-                    style("Commit Message:").cyan().bold(),
+                    theme::prompt("Commit Message:").bold(),
                     message
                 );
-            } else {
-                // Show the message and ask for confirmation
-                println!(
-                    "\n{} {}",
-                    SPARKLE,
-                    style("Proposed commit message:").cyan().bold()
+                notice!(
+                    "Squash it into {} later with 'git rebase -i --autosquash {}~1'",
+                    target.id,
+                    target.id
                 );
-                println!("{}\n", style(message.as_str()).green());
-                print!("\n{} Use this message? [Y/n/e(edit)] ", PENCIL);
-                io::stdout().flush()?;
 
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
+                if push {
+                    publish(&cli.repo, &repo, &config, &remotes, create_pr, !quick).await?;
+                }
+                return Ok(());
+            }
 
-                let message = match input.trim().to_lowercase().as_str() {
-                    "n" | "no" => {
-                        println!("\n{} {}", CROSS, style("Commit aborted").yellow());
-                        return Ok(());
+            if split {
+                if !config.ai_enabled() {
+                    print_ai_disabled_notice("grouping files by directory");
+                }
+                let trailers = trailer::collect(&config.commit.trailers, &co_authors)?;
+                let ticket = detect_ticket(&repo, &config)?;
+                let hints = prompt_hints(&repo, &config, &template, ticket.as_ref(), None).await?;
+                let snapshot = repo.staged_snapshot()?;
+
+                let mut groups = propose_split(&config, &changes, &diff, &hints).await;
+                for group in &mut groups {
+                    let message = template.convert_conventional(&group.message);
+                    group.message = wording::fix(&config.commit, &message).0;
+                }
+                if !quick {
+                    let Some(edited) = edit_split(groups, &changes, &template)? else {
+                        return Err(exit::aborted("Commit aborted"));
+                    };
+                    groups = edited;
+                    if repo.staged_snapshot()? != snapshot {
+                        return Err(anyhow::anyhow!(
+                            "The staged changes changed while you were arranging the commits. Run 'gyst commit --split' again."
+                        ));
                     }
-                    "e" | "edit" => {
-                        println!("\n{} {}", PENCIL, style("Opening in editor...").cyan());
-                        // Create a temporary file with the message
-                        let mut temp = tempfile::NamedTempFile::new()?;
-                        writeln!(temp, "{}", message)?;
+                }
+                confirm_protected_branch(&repo, &config, &groups[0].message, force, !quick)?;
 
-                        // Get the path before the file is closed
-                        let temp_path = temp.path().to_path_buf();
+                let messages = commit_groups(
+                    &repo,
+                    &config,
+                    &groups,
+                    &changes,
+                    snapshot,
+                    ticket.as_ref(),
+                    &trailers,
+                )?;
+                if copy {
+                    copy_to_clipboard(&messages.join("\n\n"), "commit messages");
+                }
 
-                        // Open in the default editor
-                        let status = std::process::Command::new(
-                            std::env::var("EDITOR").unwrap_or_else(|_| "vim".to_string()),
-                        )
-                        .arg(&temp_path)
-                        .status()?;
+                if push {
+                    publish(&cli.repo, &repo, &config, &remotes, create_pr, !quick).await?;
+                }
+                return Ok(());
+            }
+
+            if !config.ai_enabled() {
+                print_ai_disabled_notice("using a template-based commit message");
+            }
+
+            let trailers = trailer::collect(&config.commit.trailers, &co_authors)?;
+            let ticket = detect_ticket(&repo, &config)?;
+            let link = match link {
+                Some(reference) => Some(fetch_work_item(&repo, &config, &reference).await?),
+                None => None,
+            };
+            let hints =
+                prompt_hints(&repo, &config, &template, ticket.as_ref(), link.as_ref()).await?;
+
+            let mut changes = changes;
+            let mut diff = diff;
+            let message = loop {
+                let snapshot = repo.staged_snapshot()?;
 
-                        if !status.success() {
-                            println!("{} {}", CROSS, style("Editor returned with error").red());
-                            return Ok(());
+                let mut sp = StreamingSpinner::new(
+                    Spinners::Dots12,
+                    "Analyzing changes and generating commit message...".into(),
+                );
+
+                let mut waited_too_long = false;
+                let trivial = config
+                    .commit
+                    .fast_path
+                    .then(|| offline::trivial_message(&changes, &diff))
+                    .flatten();
+                let message = if let Some(message) = &trivial {
+                    template.convert_conventional(message)
+                } else if !config.ai_enabled() {
+                    template.convert_conventional(&offline::generate_message(&changes))
+                } else {
+                    let generate = async {
+                        if !config.use_server() {
+                            // Use direct API client
+                            let generator = ai::CommitMessageGenerator::new(config.clone());
+                            return generator
+                                .generate_message(&changes, &diff, &template, &hints)
+                                .await;
+                        }
+
+                        // Use server client
+                        let server_client = server::ServerClient::new(config.clone());
+
+                        // Optional: Check server health
+                        if let Err(e) = server_client.health_check().await {
+                            sp.stop_with_message(format!(
+                                "{} {}\n",
+                                CROSS,
+                                theme::removed("Failed to connect to server")
+                            ));
+                            return Err(exit::provider(anyhow::anyhow!(
+                                "{}. Check server URL or use direct API mode with 'gyst config --use-server false'",
+                                e
+                            )));
                         }
 
-                        // Read back the edited message
-                        let edited = std::fs::read_to_string(&temp_path)?;
-                        edited.trim().to_string()
+                        server_client
+                            .generate_message(&changes, &diff, &hints, &mut |text| sp.show(text))
+                            .await
+                    };
+                    let generated = match config.commit.max_wait_ms {
+                        Some(max_wait) => {
+                            tokio::time::timeout(Duration::from_millis(max_wait), generate)
+                                .await
+                                .ok()
+                        }
+                        None => Some(generate.await),
+                    };
+                    match generated {
+                        Some(Ok(message)) => message,
+                        Some(Err(e)) => return Err(e),
+                        None => {
+                            waited_too_long = true;
+                            template.convert_conventional(&offline::generate_message(&changes))
+                        }
                     }
-                    _ => message,
                 };
+                let (message, corrections) = wording::fix(&config.commit, &message);
+                let message = finish_message(
+                    &config.commit,
+                    &message,
+                    ticket.as_ref(),
+                    link.as_ref(),
+                    &trailers,
+                );
 
-                // Create the commit
-                let mut sp = Spinner::new(Spinners::Dots9, "Creating commit...".into());
-                repo.create_commit(&message)?;
                 sp.stop_with_message(format!(
-                    "{} {} {}\n",
+                    "{} {}\n",
                     CHECKMARK,
-                    style("Commit created successfully!").green().bold(),
-                    SPARKLE
+                    theme::added("Commit message generated!")
                 ));
+                if trivial.is_some() && config.ai_enabled() {
+                    notice!(
+                        "{} {}",
+                        PENCIL,
+                        style("A trivial change, so this message was written without the AI (turn this off with 'gyst config set commit.fast_path false').").dim()
+                    );
+                }
+                if waited_too_long {
+                    notice!(
+                        "{} {}",
+                        PENCIL,
+                        style(format!(
+                            "The AI did not answer within commit.max_wait_ms ({} ms), so this message was built from a template. Improve it later with 'git commit --amend'.",
+                            config.commit.max_wait_ms.unwrap_or_default()
+                        ))
+                        .dim()
+                    );
+                }
+                print_corrections(&corrections);
+                print_template_problems(&template, &message, &config);
+                print_code_owners(&repo, &config, &changes);
+
+                if quick {
+                    // Use the message directly in quick mode
+                    break message;
+                }
+
+                // Show the message and ask for confirmation
                 println!(
-                    "\n{} {}\n{}\n",
-                    PENCIL,
-                    style("Final Commit Message:").cyan().bold(),
-                    message
+                    "\n{} {}",
+                    SPARKLE,
+                    theme::prompt("Proposed commit message:").bold()
                 );
+                println!("{}\n", theme::added(message.as_str()));
+                print!("\n{} Use this message? [Y/n/e(edit)] ", PENCIL);
+                let input = read_answer()?;
+
+                let message = match input.as_str() {
+                    "n" | "no" => {
+                        if let Err(e) = feedback::record_rejection(&config, &message) {
+                            logging::log(|| format!("Could not record feedback: {:#}", e));
+                        }
+                        return Err(exit::aborted("Commit aborted"));
+                    }
+                    "e" | "edit" => {
+                        let Some(edited) = edit_commit_message(
+                            &cli.repo, &config, &message, &template, &changes, &diff, &hints,
+                        )
+                        .await?
+                        else {
+                            return Err(exit::aborted("Commit aborted"));
+                        };
+                        if let Err(e) = feedback::record_edit(&config, &message, &edited) {
+                            logging::log(|| format!("Could not record feedback: {:#}", e));
+                        }
+                        print_template_problems(&template, &edited, &config);
+                        edited
+                    }
+                    _ => message,
+                };
+
+                // The user may have re-staged files while the prompt was open
+                if repo.staged_snapshot()? == snapshot {
+                    break message;
+                }
+                match ask_about_stale_message()? {
+                    StaleMessage::Regenerate => {
+                        if !repo.has_staged_changes()? {
+                            return Err(exit::nothing_staged(
+                                "Nothing is staged anymore. Commit aborted",
+                            ));
+                        }
+                        changes = repo.get_staged_changes()?;
+                        diff = diff_text(&repo, &config).await?;
+                    }
+                    StaleMessage::Keep => break message,
+                    StaleMessage::Abort => {
+                        return Err(exit::aborted("Commit aborted"));
+                    }
+                }
+            };
+
+            confirm_protected_branch(&repo, &config, &message, force, !quick)?;
+
+            // Create the commit
+            let mut sp = Progress::new(Spinners::Dots9, "Creating commit...".into());
+            repo.create_commit(&message)?;
+            sp.stop_with_message(format!(
+                "{} {} {}\n",
+                CHECKMARK,
+                theme::added("Commit created successfully!").bold(),
+                SPARKLE
+            ));
+            restage(set_aside)?;
+            notice!(
+                "\n{} {}\n{}\n",
+                PENCIL,
+                theme::prompt(if quick {
+                    "Commit Message:"
+                } else {
+                    "Final Commit Message:"
+                })
+                .bold(),
+                message
+            );
+            if copy {
+                copy_to_clipboard(&message, "commit message");
             }
 
             if push {
-                let mut sp = Spinner::new(Spinners::Dots9, "Pushing changes...".into());
-                repo.push_changes()?;
-                sp.stop_with_message(format!(
-                    "{} {} {}\n",
-                    CHECKMARK,
-                    style("Changes pushed successfully!").green().bold(),
-                    SPARKLE
-                ));
+                publish(&cli.repo, &repo, &config, &remotes, create_pr, !quick).await?;
             }
         }
-        Commands::Suggest => {
-            let repo = git::GitRepo::open(".")?;
+        Commands::Suggest {
+            count,
+            pipe,
+            null,
+            template,
+            co_authors,
+            force,
+            copy,
+            by_package,
+            model,
+        } => {
+            // In CI the messages are printed instead of offered for selection
+            let pipe = pipe || ci::active();
+            if pipe && by_package {
+                return Err(exit::invalid(anyhow::anyhow!(
+                    "--by-package asks how to commit, so it needs a terminal and cannot run in CI"
+                )));
+            }
+            let mut config = config::Config::load_for_repo(&cli.repo)?;
+            if let Some(name) = template {
+                config.commit.template = name;
+            }
+            override_model(&mut config, model).map_err(exit::invalid)?;
+            let copy = copy || config.ui.copy;
+            let repo = git::GitRepo::open(&cli.repo)?
+                .with_similarity(config.git.rename_threshold, config.git.detect_copies);
+            let template = template::Template::resolve(&config)
+                .map_err(exit::invalid)?
+                .with_commitlint(commitlint::Rules::load(repo.workdir()?)?)
+                .with_workspace(workspace::Workspace::load(repo.workdir()?));
+
+            if pipe {
+                // Read-only and non-interactive: no lock, no staging prompt, no colors
+                if !repo.has_staged_changes()? {
+                    return Err(exit::nothing_staged(
+                        "No staged changes. Stage your changes using 'git add' first.",
+                    ));
+                }
+
+                let changes = repo.get_staged_changes()?;
+                let diff = diff_text(&repo, &config).await?;
+                print_large_change_notice(&changes);
+                let trailers = trailer::collect(&config.commit.trailers, &co_authors)?;
+                let ticket = detect_ticket(&repo, &config)?;
+                let hints = prompt_hints(&repo, &config, &template, ticket.as_ref(), None).await?;
+
+                let suggestions =
+                    generate_suggestions(&config, &template, &changes, &diff, &hints, count)
+                        .await?;
+                for message in suggestions {
+                    let message = wording::fix(&config.commit, &message).0;
+                    let message =
+                        finish_message(&config.commit, &message, ticket.as_ref(), None, &trailers);
+                    if null {
+                        print!("{}\0", message);
+                    } else {
+                        println!("{}", message.replace('\n', "\\n"));
+                    }
+                }
+                io::stdout().flush()?;
+                return Ok(());
+            }
+
+            let _lock = repo.lock(cli.wait)?;
 
             // Check if there are any changes at all
             if !repo.has_any_changes()? {
-                println!(
-                    "\n{} {}",
-                    CROSS,
-                    style("No changes found in the repository.").yellow()
-                );
-                return Ok(());
+                return Err(exit::nothing_staged("No changes found in the repository."));
             }
 
             // Check if there are any staged changes
             if !repo.has_staged_changes()? {
-                println!("\n{} {}", CROSS, style("No staged changes found.").yellow());
+                println!("\n{} {}", CROSS, theme::warning("No staged changes found."));
                 print!("\n{} Would you like to stage all changes? [y/N] ", PENCIL);
-                io::stdout().flush()?;
-
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
+                let input = read_answer()?;
 
-                if input.trim().to_lowercase() == "y" {
-                    let mut sp = Spinner::new(Spinners::Dots9, "Staging all changes...".into());
+                if input == "y" {
+                    let mut sp = Progress::new(Spinners::Dots9, "Staging all changes...".into());
                     repo.stage_all()?;
                     sp.stop_with_message(format!(
                         "{} {} {}\n",
                         CHECKMARK,
-                        style("All changes have been staged").green(),
+                        theme::added("All changes have been staged"),
                         SPARKLE
                     ));
                 } else {
-                    println!(
-                        "\n{} {}",
-                        CROSS,
-                        style("No changes to commit. Stage your changes using 'git add' first.")
-                            .yellow()
-                    );
-                    return Ok(());
+                    return Err(exit::nothing_staged(
+                        "No changes to commit. Stage your changes using 'git add' first.",
+                    ));
                 }
             }
 
             let changes = repo.get_staged_changes()?;
-            let hunks = repo.get_structured_diff()?;
+            let diff = diff_text(&repo, &config).await?;
+            print_large_change_notice(&changes);
+
+            if !config.ai_enabled() {
+                print_ai_disabled_notice("using template-based suggestions");
+            }
+
+            let trailers = trailer::collect(&config.commit.trailers, &co_authors)?;
+            let ticket = detect_ticket(&repo, &config)?;
+            let hints = prompt_hints(&repo, &config, &template, ticket.as_ref(), None).await?;
 
-            // Convert hunks to a single diff string
-            let mut diff = String::new();
-            for hunk in &hunks {
-                diff.push_str(&hunk.header);
-                for line in &hunk.lines {
-                    diff.push_str(&line.content);
+            if by_package
+                && let Some(messages) = commit_by_package(
+                    &repo,
+                    &config,
+                    &template,
+                    &hints,
+                    ticket.as_ref(),
+                    &trailers,
+                    force,
+                )
+                .await?
+            {
+                if copy {
+                    copy_to_clipboard(&messages.join("\n\n"), "commit messages");
                 }
+                return Ok(());
             }
 
-            let config = config::Config::load()?;
+            let mut changes = changes;
+            let mut diff = diff;
+            loop {
+                let snapshot = repo.staged_snapshot()?;
 
-            let mut sp = Spinner::new(
-                Spinners::Dots12,
-                "Generating commit message suggestions...".into(),
-            );
+                let mut sp = Progress::new(
+                    Spinners::Dots12,
+                    "Generating commit message suggestions...".into(),
+                );
 
-            let suggestions = if config.use_server() {
-                // Use server client
-                let server_client = server::ServerClient::new(config);
+                let suggestions =
+                    match generate_suggestions(&config, &template, &changes, &diff, &hints, count)
+                        .await
+                    {
+                        Ok(suggestions) => suggestions,
+                        Err(e) => {
+                            sp.stop_with_message(format!(
+                                "{} {}\n",
+                                CROSS,
+                                theme::removed("Failed to generate suggestions")
+                            ));
+                            return Err(e);
+                        }
+                    };
+                let suggestions: Vec<String> = suggestions
+                    .iter()
+                    .map(|message| {
+                        let message = wording::fix(&config.commit, message).0;
+                        finish_message(&config.commit, &message, ticket.as_ref(), None, &trailers)
+                    })
+                    .collect();
 
-                // Optional: Check server health
-                if let Err(e) = server_client.health_check().await {
-                    sp.stop_with_message(format!(
-                        "{} {}\n",
-                        CROSS,
-                        style("Failed to connect to server").red()
-                    ));
-                    println!(
-                        "Error: {}. Check server URL or use direct API mode with 'gyst config --use-server false'",
-                        e
-                    );
-                    return Ok(());
-                }
+                sp.stop_with_message(format!(
+                    "{} {} {}\n",
+                    CHECKMARK,
+                    theme::added("Suggestions generated!"),
+                    SPARKLE
+                ));
 
-                server_client
-                    .generate_suggestions(&changes, &diff, 3)
-                    .await?
-            } else {
-                // Use direct API client
-                let generator = ai::CommitMessageGenerator::new(config);
-                generator.generate_suggestions(&changes, &diff, 3).await?
-            };
+                // The last entry opens the diff and comes back to the selector
+                let mut items: Vec<String> = suggestions.clone();
+                items.push(style("Show the staged diff").dim().to_string());
+                let message = loop {
+                    let selection = Select::with_theme(&theme::dialog())
+                        .with_prompt("Select a commit message")
+                        .default(0)
+                        .items(&items)
+                        .interact_opt()?;
 
-            sp.stop_with_message(format!(
-                "{} {} {}\n",
-                CHECKMARK,
-                style("Suggestions generated!").green(),
-                SPARKLE
-            ));
+                    let Some(index) = selection else {
+                        return Err(exit::aborted(
+                            "No message selected. You can still create a commit manually.",
+                        ));
+                    };
+                    if index == suggestions.len() {
+                        let (patches, unread) = repo.staged_file_patches(diff_view::MAX_BYTES)?;
+                        diff_view::show(&patches, unread)?;
+                        continue;
+                    }
 
-            // Create selection items with numbers
-            let selection = Select::with_theme(&ColorfulTheme::default())
-                .with_prompt("Select a commit message")
-                .default(0)
-                .items(&suggestions)
-                .interact_opt()?;
-
-            match selection {
-                Some(index) => {
-                    let message = &suggestions[index];
-                    let mut sp = Spinner::new(Spinners::Dots9, "Creating commit...".into());
-                    repo.create_commit(message)?;
-                    sp.stop_with_message(format!(
-                        "{} {} {}\n",
-                        CHECKMARK,
-                        style("Commit created successfully!").green().bold(),
-                        SPARKLE
-                    ));
-                    println!(
-                        "\n{} {}\n{}\n",
-                        PENCIL,
-                        style("Final Commit Message:").cyan().bold(),
-                        message
-                    );
-                }
-                None => {
-                    println!(
-                        "\n{} {}",
-                        CROSS,
-                        style("No message selected. You can still create a commit manually.")
-                            .yellow()
-                    );
-                }
-            }
-        }
-        Commands::Explain { description } => {
-            let mut sp = Spinner::new(
-                Spinners::Dots12,
+                    let selected = &suggestions[index];
+                    let action = Select::with_theme(&theme::dialog())
+                        .with_prompt("Use this message?")
+                        .default(0)
+                        .items(&["Accept", "Edit in editor", "Back to the list"])
+                        .interact_opt()?;
+                    match action {
+                        Some(0) => break selected.clone(),
+                        Some(1) => {
+                            let others: Vec<String> = suggestions
+                                .iter()
+                                .filter(|other| *other != selected)
+                                .cloned()
+                                .collect();
+                            let Some(edited) = edit_message_with(
+                                &cli.repo,
+                                &config,
+                                selected,
+                                &template,
+                                Ok(others),
+                            )?
+                            else {
+                                continue;
+                            };
+                            if edited.is_empty() {
+                                return Err(exit::aborted("The message is empty. Commit aborted"));
+                            }
+                            if let Err(e) = feedback::record_edit(&config, selected, &edited) {
+                                logging::log(|| format!("Could not record feedback: {:#}", e));
+                            }
+                            break edited;
+                        }
+                        // Esc goes back to the list too
+                        _ => continue,
+                    }
+                };
+                let message = &message;
+
+                // The user may have re-staged files while the selector was open
+                if repo.staged_snapshot()? != snapshot {
+                    match ask_about_stale_message()? {
+                        StaleMessage::Regenerate => {
+                            if !repo.has_staged_changes()? {
+                                return Err(exit::nothing_staged(
+                                    "Nothing is staged anymore. Commit aborted",
+                                ));
+                            }
+                            changes = repo.get_staged_changes()?;
+                            diff = diff_text(&repo, &config).await?;
+                            continue;
+                        }
+                        StaleMessage::Keep => {}
+                        StaleMessage::Abort => {
+                            return Err(exit::aborted("Commit aborted"));
+                        }
+                    }
+                }
+
+                print_template_problems(&template, message, &config);
+                confirm_protected_branch(&repo, &config, message, force, true)?;
+
+                let mut sp = Progress::new(Spinners::Dots9, "Creating commit...".into());
+                repo.create_commit(message)?;
+                sp.stop_with_message(format!(
+                    "{} {} {}\n",
+                    CHECKMARK,
+                    theme::added("Commit created successfully!").bold(),
+                    SPARKLE
+                ));
+                notice!(
+                    "\n{} {}\n{}\n",
+                    PENCIL,
+                    theme::prompt("Final Commit Message:").bold(),
+                    message
+                );
+                if copy {
+                    copy_to_clipboard(message, "commit message");
+                }
+                break;
+            }
+        }
+        Commands::Explain {
+            description,
+            model,
+            eli5,
+            run,
+        } => {
+            if description.trim().is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Describe what you want to do, e.g. gyst explain \"undo my last commit\""
+                ));
+            }
+            let mut config = config::Config::load_for_repo(&cli.repo)?;
+            override_model(&mut config, model).map_err(exit::invalid)?;
+            // Previews need a repository, but explanations do not
+            let repo = git::GitRepo::open(&cli.repo).ok();
+            if description.trim_start().starts_with("git ") {
+                print_preview(repo.as_ref(), &description, true);
+            }
+            if !config.ai_enabled() {
+                print_ai_disabled_notice("answering from the offline knowledge base");
+            }
+
+            let mut sp = StreamingSpinner::new(
+                Spinners::Dots12,
                 format!(
                     "{} {}",
                     SPARKLE,
-                    style("Analyzing your request...").cyan().bold()
-                )
-                .into(),
+                    theme::prompt("Analyzing your request...").bold()
+                ),
             );
 
-            let config = config::Config::load()?;
-
-            let suggestion = if config.use_server() {
+            let suggestion = if !config.ai_enabled() {
+                sp.stop_with_message(format!(
+                    "{} {}\n",
+                    CHECKMARK,
+                    theme::added("Analysis complete!")
+                ));
+                Ok(offline::explain(&description))
+            } else if config.use_server() {
                 // Use server client
                 let server_client = server::ServerClient::new(config);
 
@@ -361,21 +894,32 @@ async fn main() -> anyhow::Result<()> {
                     sp.stop_with_message(format!(
                         "{} {}\n",
                         CROSS,
-                        style("Failed to connect to server").red()
+                        theme::removed("Failed to connect to server")
                     ));
-                    println!(
-                        "Error: {}. Check server URL or use direct API mode with 'gyst config --use-server false'",
+                    return Err(exit::provider(anyhow::anyhow!(
+                        "{}. Check server URL or use direct API mode with 'gyst config --use-server false'",
                         e
-                    );
-                    return Ok(());
+                    )));
                 }
 
-                match server_client.suggest_command(&description).await {
+                let request = if eli5 {
+                    format!(
+                        "{}\n\n{}",
+                        description,
+                        command_suggest::BEGINNER_INSTRUCTION
+                    )
+                } else {
+                    description.clone()
+                };
+                match server_client
+                    .suggest_command(&request, &mut |text| sp.show(text))
+                    .await
+                {
                     Ok(suggestion) => {
                         sp.stop_with_message(format!(
                             "{} {}\n",
                             CHECKMARK,
-                            style("Analysis complete!").green()
+                            theme::added("Analysis complete!")
                         ));
                         Ok(suggestion)
                     }
@@ -383,7 +927,7 @@ async fn main() -> anyhow::Result<()> {
                         sp.stop_with_message(format!(
                             "{} {}\n",
                             CROSS,
-                            style("Analysis failed").red()
+                            theme::removed("Analysis failed")
                         ));
                         Err(e)
                     }
@@ -391,12 +935,12 @@ async fn main() -> anyhow::Result<()> {
             } else {
                 // Use direct API client
                 let suggester = command_suggest::CommandSuggester::new(config);
-                match suggester.suggest(&description).await {
+                match suggester.suggest(&description, eli5).await {
                     Ok(suggestion) => {
                         sp.stop_with_message(format!(
                             "{} {}\n",
                             CHECKMARK,
-                            style("Analysis complete!").green()
+                            theme::added("Analysis complete!")
                         ));
                         Ok(suggestion)
                     }
@@ -404,76 +948,432 @@ async fn main() -> anyhow::Result<()> {
                         sp.stop_with_message(format!(
                             "{} {}\n",
                             CROSS,
-                            style("Analysis failed").red()
+                            theme::removed("Analysis failed")
                         ));
                         Err(e)
                     }
                 }
             };
 
-            match suggestion {
-                Ok(suggestion) => {
-                    // Parse the suggestion into sections
-                    let sections: Vec<&str> = suggestion.split("\nCOMMAND:").collect();
+            let suggestion = suggestion?;
+            let Some((intro, commands)) = command_suggest::parse_reply(&suggestion) else {
+                // Simple output for single-line suggestions
+                println!("\n{} {}", PENCIL, theme::added(&suggestion));
+                print_glossary(&suggestion);
+                if run {
+                    notice!(
+                        "\n{} {}",
+                        PENCIL,
+                        style("The answer has no commands to run.").dim()
+                    );
+                }
+                return Ok(());
+            };
+
+            // First section is the introduction
+            if !intro.is_empty() {
+                println!("\n{}", style(&intro).white());
+            }
+            for suggested in &commands {
+                println!(
+                    "\n{} {} {}",
+                    PENCIL,
+                    theme::added(&suggested.command).bold(),
+                    risk_badge(suggested.risk)
+                );
+                println!("   {}", style(&suggested.explanation).white());
 
-                    if sections.len() > 1 {
-                        // First section is the introduction
-                        if !sections[0].trim().is_empty() {
-                            println!("\n{}", style(sections[0].trim()).white());
-                        }
+                // Print the note only if it's important
+                if let Some(note) = &suggested.note
+                    && (note.contains("CAREFUL")
+                        || note.contains("WARNING")
+                        || note.contains("IMPORTANT")
+                        || note.contains("DO NOT"))
+                {
+                    println!("   {} {}", CROSS, theme::warning(note));
+                }
+                print_preview(repo.as_ref(), &suggested.command, false);
+            }
 
-                        // Process each command section
-                        for section in sections[1..].iter() {
-                            let parts: Vec<&str> = section.split("\nEXPLANATION:").collect();
-                            if parts.len() == 2 {
-                                // Command with special formatting
-                                println!("\n{} {}", PENCIL, style(parts[0].trim()).green().bold());
-
-                                // Split explanation and note if present
-                                let explanation_parts: Vec<&str> =
-                                    parts[1].split("\nNOTE:").collect();
-                                println!("   {}", style(explanation_parts[0].trim()).white());
-
-                                // Print note if present, but only if it's important
-                                if explanation_parts.len() > 1 {
-                                    let note = explanation_parts[1].trim();
-                                    if note.contains("CAREFUL")
-                                        || note.contains("WARNING")
-                                        || note.contains("IMPORTANT")
-                                        || note.contains("DO NOT")
-                                    {
-                                        println!("   {} {}", CROSS, style(note).yellow());
-                                    }
-                                }
-                            }
-                        }
+            // Print additional tip if present and important
+            if let Some(tip_start) = suggestion.find("\nADDITIONAL TIP:") {
+                let tip = suggestion[tip_start..]
+                    .trim()
+                    .replace("ADDITIONAL TIP:", "")
+                    .trim()
+                    .to_string();
+                if tip.contains("CAREFUL")
+                    || tip.contains("WARNING")
+                    || tip.contains("IMPORTANT")
+                    || tip.contains("caution")
+                {
+                    println!("\n{} {}", SPARKLE, theme::warning(tip).italic());
+                }
+            }
+            print_glossary(&suggestion);
 
-                        // Print additional tip if present and important
-                        if let Some(tip_start) = suggestion.find("\nADDITIONAL TIP:") {
-                            let tip = suggestion[tip_start..]
-                                .trim()
-                                .replace("ADDITIONAL TIP:", "")
-                                .trim()
-                                .to_string();
-                            if tip.contains("CAREFUL")
-                                || tip.contains("WARNING")
-                                || tip.contains("IMPORTANT")
-                                || tip.contains("caution")
-                            {
-                                println!("\n{} {}", SPARKLE, style(tip).yellow().italic());
-                            }
-                        }
+            if run {
+                run_suggested(&commands, &cli.repo)?;
+            }
+        }
+        Commands::Completions { shell } => {
+            completions::write_registration(shell, &mut io::stdout())?;
+        }
+        Commands::Init => {
+            let config = if config::Config::exists() {
+                notice!(
+                    "{} {}",
+                    PENCIL,
+                    style(format!(
+                        "Updating {}. Your current settings are the defaults.",
+                        config::Config::get_config_path()?.display()
+                    ))
+                    .dim()
+                );
+                config::Config::load()?
+            } else {
+                config::Config::default()
+            };
+            if run_setup(config).await?.is_none() {
+                return Err(exit::aborted("Setup cancelled, nothing was saved"));
+            }
+        }
+        Commands::Stats { clear } => {
+            if clear {
+                if telemetry::Stats::clear()? {
+                    println!("{} {}", CHECKMARK, theme::added("Usage statistics deleted"));
+                } else {
+                    println!("{}", style("No usage statistics recorded").dim());
+                }
+                return Ok(());
+            }
+
+            let enabled = config::Config::load()?.telemetry.enabled;
+            let Some(stats) = telemetry::Stats::load()?.filter(|s| !s.commands.is_empty()) else {
+                println!(
+                    "{} {}",
+                    CROSS,
+                    theme::warning(if enabled {
+                        "No usage statistics recorded yet."
+                    } else {
+                        "Usage statistics are off. Turn them on with 'gyst config set telemetry.enabled true'."
+                    })
+                );
+                return Ok(());
+            };
+
+            println!(
+                "{} {}",
+                SPARKLE,
+                theme::prompt(format!("Usage since {}", dates::date(stats.since))).bold()
+            );
+            let width = stats
+                .commands
+                .keys()
+                .map(String::len)
+                .chain(["command".len()])
+                .max()
+                .unwrap_or(0);
+            println!(
+                "  {}",
+                style(format!(
+                    "{:<width$}  {:>6}  {:>6}  {:>8}",
+                    "command",
+                    "runs",
+                    "failed",
+                    "average",
+                    width = width
+                ))
+                .dim()
+            );
+            let mut commands: Vec<_> = stats.commands.iter().collect();
+            commands.sort_by(|a, b| b.1.runs.cmp(&a.1.runs).then(a.0.cmp(b.0)));
+            for (name, command) in commands {
+                let failed = format!("{:>6}", command.failures);
+                println!(
+                    "  {:<width$}  {:>6}  {}  {:>7.1}s",
+                    name,
+                    command.runs,
+                    if command.failures > 0 {
+                        theme::removed(failed)
                     } else {
-                        // Simple output for single-line suggestions
-                        println!("\n{} {}", PENCIL, style(suggestion).green());
+                        style(failed)
+                    },
+                    command.average().as_secs_f64(),
+                    width = width
+                );
+            }
+            if !enabled {
+                println!(
+                    "\n{}",
+                    style("Recording is off; these are the statistics from before.").dim()
+                );
+            }
+        }
+        Commands::Feedback { command } => match command {
+            FeedbackCommands::Show { limit } => {
+                let config = config::Config::load()?;
+                let corrections = feedback::corrections()?;
+                if corrections.is_empty() {
+                    println!(
+                        "{} {}",
+                        CROSS,
+                        theme::warning(if config.feedback.record {
+                            "No feedback recorded yet. Edit or reject a message in 'gyst commit' to record some."
+                        } else {
+                            "Recording feedback is off. Turn it on with 'gyst config set feedback.record true'."
+                        })
+                    );
+                    return Ok(());
+                }
+
+                println!(
+                    "{} {}",
+                    SPARKLE,
+                    theme::prompt(format!(
+                        "Recorded feedback ({} of {})",
+                        limit.min(corrections.len()),
+                        corrections.len()
+                    ))
+                    .bold()
+                );
+                for correction in corrections.iter().rev().take(limit) {
+                    let outcome = match correction.outcome {
+                        feedback::Outcome::Edited => theme::prompt("edited"),
+                        feedback::Outcome::Rejected => theme::removed("rejected"),
+                    };
+                    println!(
+                        "\n{}  {}",
+                        style(dates::date(correction.time)).dim(),
+                        outcome
+                    );
+                    for line in correction.original.lines() {
+                        println!("  {}", theme::removed(format!("- {}", line)));
+                    }
+                    for line in correction.edited.iter().flat_map(|edited| edited.lines()) {
+                        println!("  {}", theme::added(format!("+ {}", line)));
                     }
                 }
-                Err(e) => {
-                    println!("{} {}", CROSS, style(format!("Error: {}", e)).red());
+                if config.feedback.examples == 0 {
+                    println!(
+                        "\n{}",
+                        style("The AI does not see these. Show it your recent edits as examples with 'gyst config set feedback.examples 3'.").dim()
+                    );
+                }
+            }
+            FeedbackCommands::Clear => {
+                if feedback::clear()? {
+                    println!("{} {}", CHECKMARK, theme::added("Feedback deleted"));
+                } else {
+                    println!("{}", style("No feedback recorded").dim());
+                }
+            }
+        },
+        Commands::Usage { month } => {
+            let config = config::Config::load()?;
+            if !config.use_server() {
+                return Err(anyhow::anyhow!(
+                    "Usage is counted by the gyst server. In direct API mode, see the usage page of your Anthropic console"
+                ));
+            }
+            let month = month.unwrap_or_else(|| chrono::Local::now().format("%Y-%m").to_string());
+            let month =
+                chrono::NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+                    .map_err(|_| {
+                        anyhow::anyhow!("'{}' is not a month. Use the form 2026-09", month)
+                    })?;
+
+            let usage = server::ServerClient::new(config)
+                .usage(&month.format("%Y-%m").to_string())
+                .await?;
+            println!(
+                "{} {}",
+                SPARKLE,
+                theme::prompt(format!("gyst server usage for {}", month.format("%B %Y"))).bold()
+            );
+            println!("  {:<15} {}", "Requests", usage.requests);
+            println!("  {:<15} {}", "Input tokens", usage.input_tokens);
+            println!("  {:<15} {}", "Output tokens", usage.output_tokens);
+            if let Some(cost) = usage.estimated_cost {
+                println!("  {:<15} ${:.2}", "Estimated cost", cost);
+            }
+        }
+        Commands::Doctor => {
+            println!(
+                "{} {}\n",
+                SPARKLE,
+                theme::prompt("Checking gyst's setup").bold()
+            );
+            let checks = doctor::run(&cli.repo, print_check).await;
+
+            let failed = checks
+                .iter()
+                .filter(|check| check.status == doctor::Status::Failed)
+                .count();
+            let warnings = checks
+                .iter()
+                .filter(|check| check.status == doctor::Status::Warning)
+                .count();
+            println!();
+            if failed > 0 {
+                return Err(anyhow::anyhow!(
+                    "{} of {} checks failed. Follow the suggestions above and run 'gyst doctor' again",
+                    failed,
+                    checks.len()
+                ));
+            }
+            if warnings > 0 {
+                println!(
+                    "{} {}",
+                    CHECKMARK,
+                    theme::warning(format!(
+                        "gyst is ready to use, with {} warning{}",
+                        warnings,
+                        if warnings == 1 { "" } else { "s" }
+                    ))
+                );
+            } else {
+                println!(
+                    "{} {} {}",
+                    CHECKMARK,
+                    theme::added("Everything looks good").bold(),
+                    SPARKLE
+                );
+            }
+        }
+        Commands::Config {
+            command: Some(ConfigCommands::Get { key }),
+            ..
+        } => {
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            match config.get_setting(&key)? {
+                Some(value) => println!("{}", value),
+                None => eprintln!("{}", style(format!("{} is not set", key)).dim()),
+            }
+        }
+        Commands::Config {
+            command: Some(ConfigCommands::Set { key, value }),
+            ..
+        } => {
+            let config = config::Config::load()?
+                .set_setting(&key, &value)
+                .map_err(exit::invalid)?;
+            config.save()?;
+            println!(
+                "{} {}",
+                CHECKMARK,
+                theme::added(format!(
+                    "{} = {}",
+                    key,
+                    config.get_setting(&key)?.unwrap_or_default()
+                ))
+            );
+            print_repo_override(&cli.repo, &config, &key)?;
+            if key.starts_with("config.") && config.refresh_team_settings().await? {
+                println!("{} {}", CHECKMARK, theme::added("Team settings fetched"));
+            }
+        }
+        Commands::Config {
+            command: Some(ConfigCommands::Unset { key }),
+            ..
+        } => {
+            let config = config::Config::load()?
+                .unset_setting(&key)
+                .map_err(exit::invalid)?;
+            config.save()?;
+            let message = match config.get_setting(&key)? {
+                Some(value) => format!("{} reset to {}", key, value),
+                None => format!("{} unset", key),
+            };
+            println!("{} {}", CHECKMARK, theme::added(message));
+            print_repo_override(&cli.repo, &config, &key)?;
+        }
+        Commands::Config {
+            command: Some(ConfigCommands::List),
+            ..
+        } => {
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            for (key, value) in config.list_settings()? {
+                println!("{} = {}", theme::prompt(key), value);
+            }
+        }
+        Commands::Config {
+            command: Some(ConfigCommands::Export { file }),
+            ..
+        } => {
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            std::fs::write(&file, config.export_bundle()?)
+                .with_context(|| format!("Failed to write {}", file))?;
+            println!(
+                "{} {}",
+                CHECKMARK,
+                theme::added(format!("Settings exported to {}", file))
+            );
+            println!(
+                "{}",
+                style("API keys, tokens, URLs and server mode are not included.").dim()
+            );
+        }
+        Commands::Config {
+            command: Some(ConfigCommands::Import { file }),
+            ..
+        } => {
+            let yes = cli.yes || ci::active();
+            let contents = std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file))?;
+            let bundle = config::Config::load()?
+                .import_bundle(&contents)
+                .map_err(exit::invalid)?;
+
+            if !bundle.ignored.is_empty() {
+                notice!(
+                    "{} {}",
+                    PENCIL,
+                    style(format!(
+                        "Ignoring settings a bundle cannot change: {}",
+                        bundle.ignored.join(", ")
+                    ))
+                    .dim()
+                );
+            }
+            if bundle.changed.is_empty() {
+                println!(
+                    "{} {}",
+                    CHECKMARK,
+                    theme::added("Your configuration already matches the bundle")
+                );
+                return Ok(());
+            }
+
+            println!(
+                "\n{} {}",
+                SPARKLE,
+                theme::prompt("The bundle changes these settings:").bold()
+            );
+            for setting in &bundle.changed {
+                println!("   {}", setting);
+            }
+            if !yes {
+                print!("\n{} Apply them to ~/.gyst/config.toml? [Y/n] ", PENCIL);
+                let input = read_answer()?;
+                if matches!(input.as_str(), "n" | "no") {
+                    return Err(exit::aborted("Import aborted"));
                 }
             }
+
+            bundle.config.save()?;
+            println!(
+                "{} {}",
+                CHECKMARK,
+                theme::added(format!(
+                    "Imported {} settings from {}",
+                    bundle.changed.len(),
+                    file
+                ))
+            );
         }
         Commands::Config {
+            command: None,
             api_key,
             show,
             use_server,
@@ -481,12 +1381,12 @@ async fn main() -> anyhow::Result<()> {
             let mut config = config::Config::load()?;
 
             if let Some(ref key) = api_key {
-                println!("{} {}", PENCIL, style("Setting API key...").cyan());
+                println!("{} {}", PENCIL, theme::prompt("Setting API key..."));
                 config.set_api_key(key.clone())?;
                 println!(
                     "{} {}",
                     CHECKMARK,
-                    style("API key saved successfully!").green()
+                    theme::added("API key saved successfully!")
                 );
             }
 
@@ -494,21 +1394,19 @@ async fn main() -> anyhow::Result<()> {
                 println!(
                     "{} {}",
                     PENCIL,
-                    style(format!(
+                    theme::prompt(format!(
                         "{} server mode...",
                         if use_srv { "Enabling" } else { "Disabling" }
                     ))
-                    .cyan()
                 );
                 config.set_use_server(use_srv)?;
                 println!(
                     "{} {}",
                     CHECKMARK,
-                    style(format!(
+                    theme::added(format!(
                         "Server mode {} successfully!",
                         if use_srv { "enabled" } else { "disabled" }
                     ))
-                    .green()
                 );
             }
 
@@ -516,18 +1414,16 @@ async fn main() -> anyhow::Result<()> {
                 println!("{}", config.display());
             }
         }
-        Commands::Diff => {
-            println!("{} {}", PENCIL, style("Analyzing diff...").cyan().bold());
-            let repo = git::GitRepo::open(".")?;
+        Commands::Diff { blame } => {
+            println!("{} {}", PENCIL, theme::prompt("Analyzing diff...").bold());
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            let repo = git::GitRepo::open(&cli.repo)?
+                .with_similarity(config.git.rename_threshold, config.git.detect_copies);
 
             if !repo.has_staged_changes()? {
-                println!(
-                    "\n{} {}",
-                    CROSS,
-                    style("No staged changes found. Stage some changes first with 'git add'")
-                        .yellow()
-                );
-                return Ok(());
+                return Err(exit::nothing_staged(
+                    "No staged changes found. Stage some changes first with 'git add'",
+                ));
             }
 
             let changes = repo.get_staged_changes()?;
@@ -536,23 +1432,23 @@ async fn main() -> anyhow::Result<()> {
             println!(
                 "\n{} {}",
                 SPARKLE,
-                style("Summary").cyan().bold().underlined()
+                theme::prompt("Summary").bold().underlined()
             );
             println!(
                 "{} {}, {} {}, {} {}",
-                changes.stats.files_changed.to_string().bold(),
+                style(changes.stats.files_changed).bold(),
                 if changes.stats.files_changed == 1 {
                     "file"
                 } else {
                     "files"
                 },
-                changes.stats.insertions.to_string().green().bold(),
+                theme::added(changes.stats.insertions).bold(),
                 if changes.stats.insertions == 1 {
                     "insertion(+)"
                 } else {
                     "insertions(+)"
                 },
-                changes.stats.deletions.to_string().red().bold(),
+                theme::removed(changes.stats.deletions).bold(),
                 if changes.stats.deletions == 1 {
                     "deletion(-)"
                 } else {
@@ -562,35 +1458,48 @@ async fn main() -> anyhow::Result<()> {
 
             // Print file changes summary
             if !changes.added.is_empty() {
-                println!("\n{} {}", SPARKLE, style("Added files:").cyan().bold());
+                println!("\n{} {}", SPARKLE, theme::prompt("Added files:").bold());
                 for file in changes.added {
-                    println!("  {} {}", "+".green().bold(), style(file).green());
+                    println!("  {} {}", theme::added("+").bold(), theme::added(file));
                 }
             }
 
             if !changes.modified.is_empty() {
-                println!("\n{} {}", SPARKLE, style("Modified files:").cyan().bold());
+                println!("\n{} {}", SPARKLE, theme::prompt("Modified files:").bold());
                 for file in changes.modified {
-                    println!("  {} {}", "*".yellow().bold(), style(file).yellow());
+                    println!("  {} {}", theme::warning("*").bold(), theme::warning(file));
                 }
             }
 
             if !changes.deleted.is_empty() {
-                println!("\n{} {}", SPARKLE, style("Deleted files:").cyan().bold());
+                println!("\n{} {}", SPARKLE, theme::prompt("Deleted files:").bold());
                 for file in changes.deleted {
-                    println!("  {} {}", "-".red().bold(), style(file).red());
+                    println!("  {} {}", theme::removed("-").bold(), theme::removed(file));
                 }
             }
 
             if !changes.renamed.is_empty() {
-                println!("\n{} {}", SPARKLE, style("Renamed files:").cyan().bold());
+                println!("\n{} {}", SPARKLE, theme::prompt("Renamed files:").bold());
                 for (old, new) in changes.renamed {
                     println!(
                         "  {} {} {} {}",
-                        "→".blue().bold(),
+                        theme::prompt("→").bold(),
                         style(old).strikethrough(),
-                        "→".blue().bold(),
-                        style(new).blue()
+                        theme::prompt("→").bold(),
+                        theme::prompt(new)
+                    );
+                }
+            }
+
+            if !changes.copied.is_empty() {
+                println!("\n{} {}", SPARKLE, theme::prompt("Copied files:").bold());
+                for (source, new) in changes.copied {
+                    println!(
+                        "  {} {} {} {}",
+                        theme::prompt("⇉").bold(),
+                        style(source),
+                        theme::prompt("→").bold(),
+                        theme::prompt(new)
                     );
                 }
             }
@@ -599,44 +1508,3811 @@ async fn main() -> anyhow::Result<()> {
             println!(
                 "\n{} {}",
                 SPARKLE,
-                style("Detailed changes:").cyan().bold().underlined()
+                theme::prompt("Detailed changes:").bold().underlined()
             );
             let hunks = repo.get_structured_diff()?;
+            let origins = if blame {
+                removed_line_origins(&repo, &hunks)
+            } else {
+                HashMap::new()
+            };
+            let recent = chrono::Utc::now().timestamp() - RECENT_DAYS * 24 * 60 * 60;
             for hunk in hunks {
-                println!("\n{}", style(hunk.header).cyan());
+                println!("\n{}", theme::prompt(hunk.header));
                 for line in hunk.lines {
-                    match line.origin {
-                        '+' => print!("{}", style(line.content).green()),
-                        '-' => print!("{}", style(line.content).red()),
+                    let origin = line
+                        .old_lineno
+                        .and_then(|lineno| origins.get(&(line.path.clone(), lineno)));
+                    match (line.origin, origin) {
+                        ('+', _) => print!("{}", theme::added(line.content)),
+                        ('-', Some(commit)) => {
+                            let date = dates::when(commit.time);
+                            let note = format!("{} {} {}", commit.id, commit.author, date);
+                            // Lines changed lately may clash with someone else's work
+                            let note = if commit.time >= recent {
+                                theme::warning(note)
+                            } else {
+                                style(note).dim()
+                            };
+                            println!(
+                                "{}  {}",
+                                theme::removed(line.content.trim_end_matches('\n')),
+                                note
+                            );
+                        }
+                        ('-', None) => print!("{}", theme::removed(line.content)),
                         _ => print!("{}", style(line.content).dim()),
                     }
                 }
             }
         }
-        Commands::Branch { command } => match command {
-            cli::BranchCommands::Health {
-                all,
-                remote,
-                local: _,
-                days,
-                author,
-                format,
-            } => {
-                let analyzer = BranchAnalyzer::new(".")?;
-                let filter = if all {
-                    BranchFilter::All
-                } else if remote {
-                    BranchFilter::Remote
-                } else {
-                    BranchFilter::Local
-                };
+        Commands::Today {
+            since,
+            author,
+            all,
+            no_ai,
+        } => {
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            let repo = git::GitRepo::open(&cli.repo)?
+                .with_similarity(config.git.rename_threshold, config.git.detect_copies);
 
-                let results = analyzer.analyze_branches(filter, days, author)?;
-                let output = format_output(&results, format.as_str().into())?;
-                println!("{}", output);
+            let activity = standup::Activity::collect(&repo, &since, author.as_deref(), all)?;
+            if activity.is_empty() {
+                println!(
+                    "\n{} {}",
+                    CROSS,
+                    theme::warning(format!("No activity found since {}.", since))
+                );
+                return Ok(());
             }
-        },
-    }
 
-    Ok(())
+            let update = if no_ai {
+                activity.to_plain_update()
+            } else if !config.ai_enabled() {
+                print_ai_disabled_notice("listing activity instead of an AI summary");
+                activity.to_plain_update()
+            } else if config.use_server() {
+                notice!(
+                    "{} {}\n",
+                    PENCIL,
+                    style("AI standup summaries need direct API mode ('gyst config --use-server false'). Listing activity instead.")
+                        .dim()
+                );
+                activity.to_plain_update()
+            } else {
+                let mut sp = Progress::new(Spinners::Dots12, "Writing standup update...".into());
+                let generator = ai::CommitMessageGenerator::new(config).for_command("today");
+                match generator
+                    .complete(standup::SYSTEM_PROMPT, &activity.to_prompt(), 400, 0.3)
+                    .await
+                {
+                    Ok(update) => {
+                        sp.stop_with_message(format!(
+                            "{} {}\n",
+                            CHECKMARK,
+                            theme::added("Standup update ready!")
+                        ));
+                        update.trim().to_string()
+                    }
+                    Err(e) => {
+                        sp.stop_with_message(format!(
+                            "{} {}\n",
+                            CROSS,
+                            theme::removed("Failed to generate summary")
+                        ));
+                        println!("Error: {}. Listing activity instead.\n", e);
+                        activity.to_plain_update()
+                    }
+                }
+            };
+
+            println!("{}", update);
+        }
+        Commands::Status { no_ai } => {
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            let repo = git::GitRepo::open(&cli.repo)?;
+            let snapshot = status::Snapshot::collect(&repo)?;
+
+            let offline = || (snapshot.summary(), snapshot.next_steps());
+            let (summary, steps) = if no_ai {
+                offline()
+            } else if !config.ai_enabled() {
+                print_ai_disabled_notice("working out the summary from the repository state");
+                offline()
+            } else if config.use_server() {
+                notice!(
+                    "{} {}",
+                    PENCIL,
+                    style("AI status summaries need direct API mode ('gyst config --use-server false'). Working them out from the repository state instead.")
+                        .dim()
+                );
+                offline()
+            } else {
+                let mut sp = Progress::new(Spinners::Dots12, "Reading the repository...".into());
+                let generator = ai::CommitMessageGenerator::new(config).for_command("status");
+                let reply = generator
+                    .complete(status::SYSTEM_PROMPT, &snapshot.to_prompt(), 300, 0.2)
+                    .await;
+                match reply.as_deref().map(status::parse_reply) {
+                    Ok(Some((summary, steps))) => {
+                        sp.stop_with_message(format!(
+                            "{} {}",
+                            CHECKMARK,
+                            theme::added("Status summarized")
+                        ));
+                        let steps = if steps.is_empty() {
+                            snapshot.next_steps()
+                        } else {
+                            steps
+                        };
+                        (summary, steps)
+                    }
+                    result => {
+                        sp.stop_with_message(format!(
+                            "{} {}",
+                            CROSS,
+                            theme::removed("Failed to summarize the status")
+                        ));
+                        if let Err(e) = result {
+                            println!(
+                                "Error: {}. Working it out from the repository state instead.",
+                                e
+                            );
+                        }
+                        offline()
+                    }
+                }
+            };
+
+            print_status(&snapshot, &summary, &steps);
+        }
+        Commands::Help { topic } => match topic {
+            None => {
+                println!("{}", help::render_topic_list());
+                Cli::command().print_long_help()?;
+            }
+            Some(name) => {
+                if let Some(topic) = help::find_topic(&name) {
+                    println!("{}", help::render_topic(topic, &cli.repo)?);
+                } else if let Some(command) = Cli::command().find_subcommand_mut(&name) {
+                    command.print_long_help()?;
+                } else {
+                    println!(
+                        "{} {}",
+                        CROSS,
+                        theme::warning(format!("No help topic or command named '{}'", name))
+                    );
+                    println!("{}", help::render_topic_list());
+                }
+            }
+        },
+        Commands::History { command } => match command {
+            HistoryCommands::Search {
+                query,
+                limit,
+                diffs,
+                rebuild,
+                no_ai,
+            } => {
+                let config = config::Config::load_for_repo(&cli.repo)?;
+                let repo = git::GitRepo::open(&cli.repo)?;
+
+                let mut sp = Progress::new(Spinners::Dots9, "Indexing history...".into());
+                let (index, added) = history::Index::update(&repo, diffs, rebuild)?;
+                sp.stop_with_message(format!(
+                    "{} {}\n",
+                    CHECKMARK,
+                    theme::added(format!("{} commits indexed ({} new)", index.len(), added))
+                ));
+
+                let use_ai = !no_ai && config.ai_enabled() && !config.use_server();
+                let candidates = if use_ai {
+                    history::AI_CANDIDATES.max(limit)
+                } else {
+                    limit
+                };
+                let mut hits = index.search(&query, candidates);
+                if hits.is_empty() {
+                    println!(
+                        "\n{} {}",
+                        CROSS,
+                        theme::warning(format!("No commits match '{}'.", query))
+                    );
+                    return Ok(());
+                }
+
+                if no_ai {
+                    // Keyword matches only
+                } else if !config.ai_enabled() {
+                    print_ai_disabled_notice("showing keyword matches only");
+                } else if config.use_server() {
+                    notice!(
+                        "{} {}\n",
+                        PENCIL,
+                        style("AI answers need direct API mode ('gyst config --use-server false'). Showing keyword matches only.")
+                            .dim()
+                    );
+                } else {
+                    let mut sp = Progress::new(Spinners::Dots12, "Reading the matches...".into());
+                    let generator = ai::CommitMessageGenerator::new(config).for_command("history");
+                    match generator
+                        .complete(
+                            history::SYSTEM_PROMPT,
+                            &history::build_prompt(&query, &hits),
+                            500,
+                            0.2,
+                        )
+                        .await
+                    {
+                        Ok(reply) => {
+                            sp.stop_with_message(format!(
+                                "{} {}\n",
+                                CHECKMARK,
+                                theme::added("Answer ready!")
+                            ));
+                            let (answer, ranking) = history::parse_answer(&reply);
+                            hits = history::apply_ranking(hits, &ranking);
+                            println!("\n{}\n", answer);
+                        }
+                        Err(e) => {
+                            sp.stop_with_message(format!(
+                                "{} {}\n",
+                                CROSS,
+                                theme::removed("Failed to get an AI answer")
+                            ));
+                            println!("Error: {}. Showing keyword matches only.\n", e);
+                        }
+                    }
+                }
+
+                println!("{}", theme::prompt("Matching commits:").bold());
+                for hit in hits.iter().take(limit) {
+                    let date = dates::when(hit.entry.time);
+                    println!(
+                        "  {} {} {} {}",
+                        theme::warning(&hit.entry.short_id),
+                        style(date).dim(),
+                        hit.entry.summary,
+                        style(format!("({})", hit.entry.author)).dim()
+                    );
+                }
+            }
+        },
+        Commands::Release { command } => match command {
+            ReleaseCommands::Notes {
+                from,
+                to,
+                changelog,
+                no_ai,
+                copy,
+            } => {
+                let config = config::Config::load_for_repo(&cli.repo)?;
+                let copy = copy || config.ui.copy;
+                let repo = git::GitRepo::open(&cli.repo)?;
+
+                let notes = release::ReleaseNotes::collect(&repo, from.as_deref(), &to)?;
+                let range = match &notes.from {
+                    Some(from) => format!("{}..{}", from, to),
+                    None => to.clone(),
+                };
+                if notes.is_empty() {
+                    println!(
+                        "\n{} {}",
+                        CROSS,
+                        theme::warning(format!("No commits found in {}.", range))
+                    );
+                    return Ok(());
+                }
+
+                let draft = notes.to_markdown();
+                let markdown = if no_ai {
+                    draft
+                } else if !config.ai_enabled() {
+                    print_ai_disabled_notice("using the notes built from commit messages");
+                    draft
+                } else if config.use_server() {
+                    notice!(
+                        "{} {}\n",
+                        PENCIL,
+                        style("AI editing of release notes needs direct API mode ('gyst config --use-server false'). Using the notes built from commit messages.")
+                            .dim()
+                    );
+                    draft
+                } else {
+                    let mut sp = Progress::new(
+                        Spinners::Dots12,
+                        format!("Writing release notes for {}...", range),
+                    );
+                    let generator = ai::CommitMessageGenerator::new(config).for_command("release");
+                    match generator
+                        .complete(release::SYSTEM_PROMPT, &draft, 2048, 0.3)
+                        .await
+                    {
+                        Ok(polished) => {
+                            sp.stop_with_message(format!(
+                                "{} {}\n",
+                                CHECKMARK,
+                                theme::added("Release notes ready!")
+                            ));
+                            polished.trim().to_string()
+                        }
+                        Err(e) => {
+                            sp.stop_with_message(format!(
+                                "{} {}\n",
+                                CROSS,
+                                theme::removed("Failed to polish release notes")
+                            ));
+                            println!(
+                                "Error: {}. Using the notes built from commit messages.\n",
+                                e
+                            );
+                            draft
+                        }
+                    }
+                };
+
+                match changelog {
+                    Some(path) => {
+                        let path = repo.workdir()?.join(path);
+                        release::prepend_to_changelog(&path, &markdown, &notes.title)?;
+                        println!(
+                            "{} {}",
+                            CHECKMARK,
+                            theme::added(format!(
+                                "Added {} changes to {}",
+                                notes.changes.len(),
+                                path.display()
+                            ))
+                        );
+                    }
+                    None => println!("{}", markdown),
+                }
+                if copy {
+                    copy_to_clipboard(&markdown, "release notes");
+                }
+            }
+        },
+        Commands::Version { command } => match command {
+            VersionCommands::Suggest { diffs, tag, no_ai } => {
+                let config = config::Config::load_for_repo(&cli.repo)?;
+                let repo = git::GitRepo::open(&cli.repo)?;
+
+                let last_tag = repo.latest_tag("HEAD")?;
+                let current = match &last_tag {
+                    Some(last_tag) => version::Version::parse(last_tag)?,
+                    None => version::Version::initial(),
+                };
+                let notes = release::ReleaseNotes::collect(&repo, last_tag.as_deref(), "HEAD")?;
+                let since = last_tag.as_deref().unwrap_or("the first commit");
+                if notes.is_empty() {
+                    println!(
+                        "\n{} {}",
+                        CROSS,
+                        theme::warning(format!("No commits since {}.", since))
+                    );
+                    return Ok(());
+                }
+
+                println!(
+                    "{} {} {} since {}\n",
+                    SPARKLE,
+                    notes.changes.len(),
+                    if notes.changes.len() == 1 {
+                        "commit"
+                    } else {
+                        "commits"
+                    },
+                    theme::prompt(since).bold()
+                );
+
+                let (mut bump, reasons) = version::recommend(&notes.changes);
+                if reasons.is_empty() {
+                    println!("No features or breaking changes: {} release", bump);
+                } else {
+                    println!(
+                        "{}",
+                        theme::prompt(format!("These commits call for a {} release:", bump)).bold()
+                    );
+                    for change in reasons.iter().take(5) {
+                        println!(
+                            "  {} {}",
+                            theme::warning(&change.short_id),
+                            change.description
+                        );
+                    }
+                    if reasons.len() > 5 {
+                        println!(
+                            "  {}",
+                            style(format!("...and {} more", reasons.len() - 5)).dim()
+                        );
+                    }
+                }
+
+                if diffs {
+                    if !config.ai_enabled() {
+                        print_ai_disabled_notice("skipping the diff review");
+                    } else if config.use_server() {
+                        notice!(
+                            "\n{} {}",
+                            PENCIL,
+                            style("The diff review needs direct API mode ('gyst config --use-server false'). Skipping it.")
+                                .dim()
+                        );
+                    } else {
+                        let patch = repo.range_patch(
+                            last_tag.as_deref(),
+                            "HEAD",
+                            config.git.max_diff_size,
+                        )?;
+                        let mut sp =
+                            Progress::new(Spinners::Dots12, "Reviewing the diff...".into());
+                        let generator =
+                            ai::CommitMessageGenerator::new(config.clone()).for_command("version");
+                        let reply = generator
+                            .complete(
+                                version::SYSTEM_PROMPT,
+                                &version::build_prompt(&notes.changes, &patch),
+                                300,
+                                0.2,
+                            )
+                            .await;
+                        match reply.ok().as_deref().and_then(version::parse_verdict) {
+                            Some((ai_bump, reason)) => {
+                                sp.stop_with_message(format!(
+                                    "{} {}\n",
+                                    CHECKMARK,
+                                    theme::added(format!(
+                                        "The diff calls for a {} release",
+                                        ai_bump
+                                    ))
+                                ));
+                                println!("{}", reason);
+                                // The AI can find breaking changes, but never overrule the commit markers
+                                bump = bump.max(ai_bump);
+                            }
+                            None => {
+                                sp.stop_with_message(format!(
+                                    "{} {}\n",
+                                    CROSS,
+                                    theme::removed(
+                                        "The diff review failed, using the commits only"
+                                    )
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                if let Some(api) = semver::ApiChanges::between(&repo, last_tag.as_deref(), "HEAD")?
+                    && let Some(api_bump) = api.bump()
+                {
+                    println!(
+                        "\n{} The public API calls for a {} release ({} added, {} removed, {} changed; see 'gyst semver-check --since {}')",
+                        PENCIL,
+                        api_bump,
+                        api.added.len(),
+                        api.removed.len(),
+                        api.changed.len(),
+                        last_tag.as_deref().unwrap_or("<first commit>")
+                    );
+                    bump = bump.max(api_bump);
+                }
+
+                let next = current.bump(bump);
+                println!(
+                    "\n{} Recommended: {} release, {} {} {}",
+                    CHECKMARK,
+                    theme::added(bump).bold(),
+                    current,
+                    style("→").dim(),
+                    theme::added(&next).bold()
+                );
+
+                if tag {
+                    let name = next.to_string();
+                    if repo.is_tag(&name) {
+                        return Err(anyhow::anyhow!("Tag '{}' already exists", name));
+                    }
+                    let message = generate_tag_message(&config, &name, &notes, no_ai).await;
+                    let _lock = repo.lock(cli.wait)?;
+                    repo.create_tag(&name, "HEAD", &message)?;
+                    println!(
+                        "\n{} {}\n\n{}",
+                        CHECKMARK,
+                        theme::added(format!("Created tag {}", name)).bold(),
+                        message.trim()
+                    );
+                    println!("\nPush it with 'git push origin {}'", theme::prompt(&name));
+                }
+            }
+        },
+        Commands::Pr {
+            base,
+            title,
+            draft,
+            push,
+            template,
+            no_labels,
+            no_ai,
+            copy,
+        } => {
+            let options = PrOptions {
+                base,
+                title,
+                draft,
+                push,
+                template,
+                no_labels,
+                no_ai,
+                copy,
+                yes: cli.yes || ci::active(),
+            };
+            open_pull_request(&cli.repo, options).await?;
+        }
+        Commands::Tag {
+            command,
+            name,
+            target,
+            from,
+            no_ai,
+        } => match (command, name) {
+            (Some(TagCommands::List), _) | (None, None) => {
+                let repo = git::GitRepo::open(&cli.repo)?;
+                let tags = repo.list_tags()?;
+                if tags.is_empty() {
+                    println!(
+                        "{} {}",
+                        CROSS,
+                        theme::warning("No tags yet. Create one with 'gyst tag <name>'.")
+                    );
+                    return Ok(());
+                }
+
+                let main = repo.main_branch();
+                let width = tags.iter().map(|tag| tag.name.len()).max().unwrap_or(0);
+                let when: Vec<String> = tags.iter().map(|tag| dates::when(tag.time)).collect();
+                let date_width = when.iter().map(String::len).max().unwrap_or(0);
+                for (tag, date) in tags.iter().zip(&when) {
+                    let date = format!("{:<width$}", date, width = date_width);
+                    let reachable = match (tag.on_main, &main) {
+                        (Some(false), Some(main)) => {
+                            theme::warning(format!(" (not on {})", main)).to_string()
+                        }
+                        _ => String::new(),
+                    };
+                    println!(
+                        "{}  {}  {}  {}{}",
+                        theme::prompt(format!("{:<width$}", tag.name, width = width)).bold(),
+                        style(date).dim(),
+                        theme::warning(&tag.commit.id),
+                        tag.subject.as_deref().unwrap_or(&tag.commit.summary),
+                        reachable
+                    );
+                }
+            }
+            (None, Some(name)) => {
+                let config = config::Config::load_for_repo(&cli.repo)?;
+                let repo = git::GitRepo::open(&cli.repo)?;
+                if !git::GitRepo::is_valid_tag_name(&name) {
+                    return Err(anyhow::anyhow!("'{}' is not a valid tag name", name));
+                }
+                if repo.is_tag(&name) {
+                    return Err(anyhow::anyhow!("Tag '{}' already exists", name));
+                }
+
+                let mut notes = release::ReleaseNotes::collect(&repo, from.as_deref(), &target)?;
+                notes.title = name.clone();
+                println!(
+                    "{} {} {} since {}\n",
+                    SPARKLE,
+                    notes.changes.len(),
+                    if notes.changes.len() == 1 {
+                        "commit"
+                    } else {
+                        "commits"
+                    },
+                    theme::prompt(notes.from.as_deref().unwrap_or("the first commit")).bold()
+                );
+
+                let mut message = generate_tag_message(&config, &name, &notes, no_ai).await;
+
+                if !cli.yes && !ci::active() {
+                    println!(
+                        "\n{} {}",
+                        SPARKLE,
+                        theme::prompt("Proposed tag message:").bold()
+                    );
+                    println!("{}\n", theme::added(message.as_str()));
+                    print!("\n{} Create tag {}? [Y/n/e(edit)] ", PENCIL, name);
+                    let input = read_answer()?;
+                    match input.as_str() {
+                        "n" | "no" => {
+                            return Err(exit::aborted("Tag aborted"));
+                        }
+                        "e" | "edit" => {
+                            let Some(edited) = edit_in_editor(&cli.repo, &message)? else {
+                                return Err(exit::aborted("Tag aborted"));
+                            };
+                            message = edited;
+                        }
+                        _ => {}
+                    }
+                }
+
+                let _lock = repo.lock(cli.wait)?;
+                repo.create_tag(&name, &target, &message)?;
+                println!(
+                    "\n{} {}",
+                    CHECKMARK,
+                    theme::added(format!("Created tag {}", name)).bold()
+                );
+                println!("\nPush it with 'git push origin {}'", theme::prompt(&name));
+            }
+        },
+        Commands::SemverCheck { since } => {
+            let repo = git::GitRepo::open(&cli.repo)?;
+            let api = match &since {
+                Some(since) => semver::ApiChanges::between(&repo, Some(since), "HEAD")?,
+                None => semver::ApiChanges::staged(&repo)?,
+            };
+            let Some(api) = api else {
+                return Err(anyhow::anyhow!(
+                    "Not a Rust library crate: semver-check needs a Cargo.toml with a library target"
+                ));
+            };
+            let compared = match &since {
+                Some(since) => format!("between {} and HEAD", since),
+                None => "in the staged changes".to_string(),
+            };
+
+            let Some(bump) = api.bump() else {
+                println!(
+                    "{} No public API changes {}: patch release",
+                    CHECKMARK, compared
+                );
+                return Ok(());
+            };
+
+            println!("{} Public API changes {}\n", SPARKLE, compared);
+            if !api.removed.is_empty() {
+                println!("{}", theme::removed("Removed:").bold());
+                for item in &api.removed {
+                    println!("  - {} {}", item.signature, style(&item.file).dim());
+                }
+            }
+            if !api.changed.is_empty() {
+                println!("{}", theme::warning("Changed:").bold());
+                for (old, new) in &api.changed {
+                    println!("  ~ {} {}", new.name, style(&new.file).dim());
+                    println!("      {}", theme::removed(format!("- {}", old.signature)));
+                    println!("      {}", theme::added(format!("+ {}", new.signature)));
+                }
+            }
+            if !api.added.is_empty() {
+                println!("{}", theme::added("Added:").bold());
+                for item in &api.added {
+                    let required = if item.required {
+                        theme::warning(" (required, breaks implementations)").to_string()
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "  + {}{} {}",
+                        item.signature,
+                        required,
+                        style(&item.file).dim()
+                    );
+                }
+            }
+
+            let verdict = if bump == version::Bump::Major {
+                "breaking change: major release (mark the commit with '!')"
+            } else {
+                "backwards-compatible addition: minor release"
+            };
+            println!("\n{} {}", CHECKMARK, theme::prompt(verdict).bold());
+        }
+        Commands::Ui { count, force } => {
+            if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+                return Err(exit::invalid(anyhow::anyhow!(
+                    "gyst ui needs a terminal. Use 'gyst commit' or 'gyst suggest --pipe' in scripts"
+                )));
+            }
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            let repo = git::GitRepo::open(&cli.repo)?
+                .with_similarity(config.git.rename_threshold, config.git.detect_copies);
+            let template = template::Template::resolve(&config)
+                .map_err(exit::invalid)?
+                .with_commitlint(commitlint::Rules::load(repo.workdir()?)?)
+                .with_workspace(workspace::Workspace::load(repo.workdir()?));
+            let _lock = repo.lock(cli.wait)?;
+
+            let trailers = trailer::collect(&config.commit.trailers, &[])?;
+            let ticket = detect_ticket(&repo, &config)?;
+            let hints = prompt_hints(&repo, &config, &template, ticket.as_ref(), None).await?;
+            // The dashboard still opens when the main branch cannot be found
+            let branches = BranchAnalyzer::new(&cli.repo)?
+                .with_base(config.branch.base.as_deref())
+                .and_then(|analyzer| analyzer.analyze_branches(BranchFilter::Local, None, None))
+                .unwrap_or_default();
+            let mut dashboard = dashboard::Dashboard::new(
+                repo.get_current_branch()
+                    .unwrap_or_else(|_| "detached HEAD".to_string()),
+                branches,
+            );
+
+            let context = CommitContext {
+                repo: &repo,
+                config: &config,
+                template: &template,
+                hints: &hints,
+                ticket: ticket.as_ref(),
+                trailers: &trailers,
+            };
+            let mut terminal = ratatui::init();
+            let committed =
+                run_dashboard(&mut terminal, &mut dashboard, &context, count, force).await;
+            ratatui::restore();
+
+            let committed = committed?;
+            for (oid, message) in &committed {
+                notice!(
+                    "{} {} {}",
+                    CHECKMARK,
+                    style(&oid.to_string()[..7]).dim(),
+                    theme::added(message.lines().next().unwrap_or_default())
+                );
+            }
+        }
+        Commands::Watch { interval, branch } => {
+            let config = config::Config::load_for_repo(&cli.repo)?;
+            let repo = git::GitRepo::open(&cli.repo)?
+                .with_similarity(config.git.rename_threshold, config.git.detect_copies);
+            let template = template::Template::resolve(&config).map_err(exit::invalid)?;
+            let current = repo.get_current_branch().ok();
+            let branch = match branch {
+                Some(branch) => branch,
+                None => match &current {
+                    Some(current) => watch::default_branch(current),
+                    None => {
+                        return Err(exit::invalid(anyhow::anyhow!(
+                            "HEAD is detached. Name the branch for the snapshots with --branch"
+                        )));
+                    }
+                },
+            };
+            if !git::GitRepo::is_valid_branch_name(&branch) {
+                return Err(exit::invalid(anyhow::anyhow!(
+                    "'{}' is not a valid branch name",
+                    branch
+                )));
+            }
+            if current.as_deref() == Some(branch.as_str()) {
+                return Err(exit::invalid(anyhow::anyhow!(
+                    "'{}' is checked out. Snapshots go to a branch you are not working on",
+                    branch
+                )));
+            }
+            if !config.ai_enabled() {
+                print_ai_disabled_notice("so snapshots get template-based messages");
+            }
+
+            let mut watcher = watch::Watch::start(repo.workdir()?)?;
+            notice!(
+                "{} Watching {} for changes; snapshots go to {} after {}s without changes",
+                SPARKLE,
+                theme::prompt(repo.workdir()?.display()),
+                theme::prompt(&branch),
+                interval
+            );
+            if io::stdin().is_terminal() {
+                notice!(
+                    "{} {}",
+                    PENCIL,
+                    style("Press Enter to snapshot now, q or Ctrl-C to stop").dim()
+                );
+            }
+
+            // Start with the changes made before gyst watch was started
+            snapshot_wip(&repo, &config, &template, &branch).await?;
+            let debounce = Duration::from_secs(interval);
+            let mut pending = false;
+            loop {
+                match watcher.next(pending.then_some(debounce)).await {
+                    watch::Event::Changed(paths) => {
+                        pending |= paths.iter().any(|path| watch::relevant(&repo, path));
+                    }
+                    watch::Event::Settled | watch::Event::Now => {
+                        snapshot_wip(&repo, &config, &template, &branch).await?;
+                        pending = false;
+                    }
+                    watch::Event::Quit => {
+                        if pending {
+                            snapshot_wip(&repo, &config, &template, &branch).await?;
+                        }
+                        break;
+                    }
+                }
+            }
+            notice!(
+                "\n{} Stopped watching. Squash the snapshots with 'git merge --squash {}'",
+                CHECKMARK,
+                branch
+            );
+        }
+        Commands::Branch { command } => match command {
+            cli::BranchCommands::Health {
+                all,
+                remote,
+                local: _,
+                days,
+                author,
+                mine,
+                format,
+                sort,
+                limit,
+                group_by,
+                recommend,
+                anonymize,
+                exclude_with_open_pr,
+                include_ignored,
+                rebase,
+                behind,
+            } => {
+                let config = config::Config::load_for_repo(&cli.repo)?;
+                let mut analyzer = BranchAnalyzer::new(&cli.repo)?
+                    .with_base(config.branch.base.as_deref())?
+                    .with_progress(branch_progress);
+                if !include_ignored {
+                    let patterns = ignored_branches(&config, analyzer.base());
+                    analyzer = analyzer.ignoring(patterns);
+                }
+                if mine {
+                    analyzer = analyzer.only_mine()?;
+                }
+                let filter = if all {
+                    BranchFilter::All
+                } else if remote {
+                    BranchFilter::Remote
+                } else {
+                    BranchFilter::Local
+                };
+
+                let mut results = analyzer.analyze_branches(filter, days, author)?;
+                if exclude_with_open_pr {
+                    attach_pull_requests(&cli.repo, &mut results).await?;
+                }
+                if group_by.is_some() {
+                    let summaries = branch::group_by_author(&results);
+                    println!(
+                        "{}",
+                        branch::format_by_author(&summaries, format.as_str().into())?
+                    );
+                    if rebase {
+                        rebase_behind_branches(&cli.repo, cli.wait, &analyzer, &results, behind)?;
+                    }
+                    return Ok(());
+                }
+                if let Some(key) = &sort {
+                    branch::sort(&mut results, key.as_str().into());
+                }
+                // --rebase still looks at every branch, not just those reported
+                let shown = &results[..limit.unwrap_or(results.len()).min(results.len())];
+                let recommendations = if recommend {
+                    Some(recommend_branches(config, shown, anonymize).await)
+                } else {
+                    None
+                };
+                let output =
+                    format_output(shown, recommendations.as_ref(), format.as_str().into())?;
+                println!("{}", output);
+                if shown.len() < results.len() {
+                    enotice!(
+                        "{} {}",
+                        PENCIL,
+                        style(format!(
+                            "Showing {} of {} branches",
+                            shown.len(),
+                            results.len()
+                        ))
+                        .dim()
+                    );
+                }
+                if rebase {
+                    rebase_behind_branches(&cli.repo, cli.wait, &analyzer, &results, behind)?;
+                }
+            }
+            cli::BranchCommands::Stale {
+                all,
+                remote,
+                local: _,
+                author,
+                mine,
+                format,
+                exclude_with_open_pr,
+                include_ignored,
+            } => {
+                let config = config::Config::load_for_repo(&cli.repo)?;
+                let mut analyzer = BranchAnalyzer::new(&cli.repo)?
+                    .with_base(config.branch.base.as_deref())?
+                    .with_progress(branch_progress);
+                if !include_ignored {
+                    let patterns = ignored_branches(&config, analyzer.base());
+                    analyzer = analyzer.ignoring(patterns);
+                }
+                if mine {
+                    analyzer = analyzer.only_mine()?;
+                }
+                let filter = if all {
+                    BranchFilter::All
+                } else if remote {
+                    BranchFilter::Remote
+                } else {
+                    BranchFilter::Local
+                };
+
+                let mut results = analyzer.analyze_branches(filter, None, author)?;
+                results.retain(|health| health.status == branch::BranchStatus::Stale);
+                if exclude_with_open_pr {
+                    attach_pull_requests(&cli.repo, &mut results).await?;
+                    results.retain(|health| health.pull_request.is_none());
+                }
+                let output = format_output(&results, None, format.as_str().into())?;
+                println!("{}", output);
+            }
+            cli::BranchCommands::Delete { names, force } => {
+                let repo = git::GitRepo::open(&cli.repo)?;
+                let _lock = repo.lock(cli.wait)?;
+
+                let mut failed = Vec::new();
+                for name in &names {
+                    match repo.delete_branch(name, force) {
+                        Ok(oid) => {
+                            let oid = oid.to_string();
+                            audit::record(
+                                &repo,
+                                audit::Event::BranchDeleted {
+                                    branch: name.clone(),
+                                    oid: oid.clone(),
+                                },
+                            )?;
+                            println!(
+                                "{} {} {}",
+                                CHECKMARK,
+                                theme::added(format!("Deleted '{}'", name)),
+                                style(format!("(was {})", &oid[..7])).dim()
+                            );
+                        }
+                        Err(e) => {
+                            println!("{} {}", CROSS, theme::removed(format!("{:#}", e)));
+                            failed.push(name.as_str());
+                        }
+                    }
+                }
+                if failed.len() < names.len() {
+                    notice!(
+                        "\n{} {}",
+                        PENCIL,
+                        style("Changed your mind? Run 'gyst branch restore'").dim()
+                    );
+                }
+                if !failed.is_empty() {
+                    return Err(exit::git(anyhow::anyhow!(
+                        "Could not delete {} of {} branches: {}",
+                        failed.len(),
+                        names.len(),
+                        failed.join(", ")
+                    )));
+                }
+            }
+            cli::BranchCommands::Restore { names } => {
+                let repo = git::GitRepo::open(&cli.repo)?;
+                let _lock = repo.lock(cli.wait)?;
+                let deleted = audit::deleted_branches(&repo)?;
+
+                let chosen: Vec<&audit::DeletedBranch> = if names.is_empty() {
+                    if deleted.is_empty() {
+                        println!(
+                            "{} {}",
+                            CROSS,
+                            theme::warning(
+                                "No branches deleted with 'gyst branch delete' to restore."
+                            )
+                        );
+                        return Ok(());
+                    }
+                    let items: Vec<String> = deleted
+                        .iter()
+                        .map(|branch| {
+                            let summary = repo
+                                .resolve_commit(&branch.oid)
+                                .map(|commit| commit.summary)
+                                .unwrap_or_else(|_| "commit no longer exists".to_string());
+                            format!(
+                                "{} ({}, deleted {}): {}",
+                                branch.name,
+                                branch.oid.get(..7).unwrap_or(&branch.oid),
+                                dates::when(branch.time),
+                                summary
+                            )
+                        })
+                        .collect();
+                    if ci::active() || !io::stdin().is_terminal() {
+                        println!("{}", theme::prompt("Deleted branches:").bold());
+                        for item in &items {
+                            println!("   {}", item);
+                        }
+                        notice!(
+                            "\n{} {}",
+                            PENCIL,
+                            style("Restore one with 'gyst branch restore <name>'").dim()
+                        );
+                        return Ok(());
+                    }
+                    let Some(selection) = MultiSelect::with_theme(&theme::dialog())
+                        .with_prompt("Branches to restore (space selects, enter confirms)")
+                        .items(&items)
+                        .interact_opt()?
+                    else {
+                        return Err(exit::aborted("Nothing restored"));
+                    };
+                    selection.into_iter().map(|index| &deleted[index]).collect()
+                } else {
+                    names
+                        .iter()
+                        .map(|name| {
+                            deleted
+                                .iter()
+                                .find(|branch| &branch.name == name)
+                                .ok_or_else(|| {
+                                    anyhow::anyhow!(
+                                        "gyst has no record of deleting '{}'. Branches deleted with git itself may still be found with 'git reflog'",
+                                        name
+                                    )
+                                })
+                        })
+                        .collect::<anyhow::Result<_>>()?
+                };
+
+                for branch in chosen {
+                    repo.restore_branch(&branch.name, &branch.oid)?;
+                    audit::record(
+                        &repo,
+                        audit::Event::BranchRestored {
+                            branch: branch.name.clone(),
+                            oid: branch.oid.clone(),
+                        },
+                    )?;
+                    println!(
+                        "{} {} {}",
+                        CHECKMARK,
+                        theme::added(format!("Restored '{}'", branch.name)),
+                        style(format!("at {}", branch.oid.get(..7).unwrap_or(&branch.oid))).dim()
+                    );
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// Choose the description template for `gyst pr`: the one named with `--template`,
+/// the only one, the one named `default`, or the user's pick
+fn choose_description_template(
+    repo: &git::GitRepo,
+    name: Option<&str>,
+    yes: bool,
+) -> anyhow::Result<Option<pr::DescriptionTemplate>> {
+    let mut templates = pr::find_templates(repo.workdir()?);
+
+    if let Some(name) = name {
+        let Some(index) = templates
+            .iter()
+            .position(|t| t.name.eq_ignore_ascii_case(name))
+        else {
+            let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+            return Err(anyhow::anyhow!(
+                "No description template named '{}'. Available: {}",
+                name,
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            ));
+        };
+        return Ok(Some(templates.swap_remove(index)));
+    }
+
+    if templates.len() <= 1 {
+        return Ok(templates.pop());
+    }
+    if let Some(index) = templates
+        .iter()
+        .position(|t| t.name.eq_ignore_ascii_case("default"))
+    {
+        return Ok(Some(templates.swap_remove(index)));
+    }
+    if yes {
+        return Ok(None);
+    }
+
+    let mut options: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+    options.push("No template");
+    let choice = Select::with_theme(&theme::dialog())
+        .with_prompt("Description template")
+        .default(0)
+        .items(&options)
+        .interact_opt()?;
+    Ok(choice
+        .filter(|&index| index < templates.len())
+        .map(|index| templates.swap_remove(index)))
+}
+
+/// Point out when the repository's `.gyst.toml` overrides a setting that was
+/// just changed in the global configuration
+fn print_repo_override(repo_path: &str, global: &config::Config, key: &str) -> anyhow::Result<()> {
+    let effective = config::Config::load_for_repo(repo_path)?.get_setting(key)?;
+    if effective != global.get_setting(key)? {
+        println!(
+            "{} {}",
+            PENCIL,
+            theme::warning(format!(
+                "This repository's .gyst.toml overrides {} (it is {} here)",
+                key,
+                effective.as_deref().unwrap_or("not set")
+            ))
+        );
+    }
+    Ok(())
+}
+
+/// Look up the open pull request (and its review state) for each branch on GitHub or GitLab
+async fn attach_pull_requests(
+    repo_path: &str,
+    results: &mut [branch::BranchHealth],
+) -> anyhow::Result<()> {
+    let config = config::Config::load_for_repo(repo_path)?;
+    let repo = git::GitRepo::open(repo_path)?;
+    let host = forge::Forge::connect(&config, &repo)?;
+
+    let pulls = host.open_pull_requests().await?;
+
+    let mut found = 0;
+    for health in results.iter_mut() {
+        if let Some(pull) = pulls.get(health.branch_name()) {
+            let mut pull = pull.clone();
+            host.load_review_state(&mut pull).await?;
+            health.attach_pull_request(pull);
+            found += 1;
+        }
+    }
+    // Reported on stderr so JSON and markdown output stay clean
+    eprintln!(
+        "{} {}\n",
+        CHECKMARK,
+        theme::added(format!(
+            "{} branches have an open {}",
+            found,
+            host.request_name()
+        ))
+    );
+    Ok(())
+}
+
+/// Branches there must be before analyzing them shows a progress bar
+const BRANCH_PROGRESS_MIN: usize = 50;
+
+/// Show how far the branch analysis has got as a bar on stderr, cleared once
+/// every branch is done. Small repositories are analyzed too quickly to need one.
+fn branch_progress(done: usize, total: usize) {
+    if total < BRANCH_PROGRESS_MIN || !spinning() || !io::stderr().is_terminal() {
+        return;
+    }
+    if done == total {
+        eprint!("\r\x1b[2K");
+        return;
+    }
+    const WIDTH: usize = 30;
+    let filled = WIDTH * done / total;
+    eprint!(
+        "\r{} [{}{}] {}/{}",
+        style("Analyzing branches").dim(),
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled),
+        done,
+        total
+    );
+}
+
+/// The branches `gyst branch health` and `gyst branch stale` leave out:
+/// `branch.ignore_patterns`, and the base and protected branches, which are
+/// meant to live on and so are never cleanup candidates
+fn ignored_branches(config: &config::Config, base: Option<&branch::Base>) -> Vec<String> {
+    let mut patterns = config.branch.ignore_patterns.clone();
+    patterns.extend(config.git.protected_branches.iter().cloned());
+    patterns.extend(base.map(|base| base.branch.clone()));
+    patterns
+}
+
+/// What to do with each branch and a hygiene score for `gyst branch health
+/// --recommend`: from the AI in direct API mode, otherwise worked out from
+/// the numbers. Progress goes to stderr so JSON and CSV output stays clean.
+async fn recommend_branches(
+    config: config::Config,
+    results: &[branch::BranchHealth],
+    anonymize: bool,
+) -> branch::recommend::Recommendations {
+    let offline = || branch::recommend::Recommendations::offline(results);
+    if !config.ai_enabled() {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style("AI is disabled (ai.enabled = false); recommending from the branch numbers.")
+                .dim()
+        );
+        return offline();
+    }
+    if config.use_server() {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style("AI branch recommendations need direct API mode ('gyst config --use-server false'). Recommending from the branch numbers instead.")
+                .dim()
+        );
+        return offline();
+    }
+
+    let (prompt, names) = branch::recommend::build_prompt(results, anonymize);
+    // Not `Progress`, which prints its last message on stdout without a spinner
+    let mut sp =
+        spinning().then(|| Spinner::new(Spinners::Dots12, "Reviewing the branches...".into()));
+    let mut stop = |message: String| {
+        if let Some(spinner) = sp.as_mut() {
+            spinner.stop();
+            let _ = Term::stderr().clear_line();
+        }
+        enotice!("{}", message);
+    };
+    let generator = ai::CommitMessageGenerator::new(config).for_command("branch");
+    let reply = generator
+        .complete(branch::recommend::SYSTEM_PROMPT, &prompt, 1500, 0.2)
+        .await;
+    match reply
+        .as_deref()
+        .map(|reply| branch::recommend::parse_reply(reply, &names, results))
+    {
+        Ok(Some(recommendations)) => {
+            stop(format!(
+                "{} {}",
+                CHECKMARK,
+                theme::added("Branches reviewed")
+            ));
+            recommendations
+        }
+        result => {
+            stop(format!(
+                "{} {}",
+                CROSS,
+                theme::removed("Failed to review the branches")
+            ));
+            if let Err(e) = result {
+                eprintln!(
+                    "Error: {}. Recommending from the branch numbers instead.",
+                    e
+                );
+            }
+            offline()
+        }
+    }
+}
+
+/// Offer to rebase the local branches at least `min_behind` commits behind
+/// the main branch onto it, one at a time. A conflict stops the rebase in
+/// progress for the user to finish; the other branches are left alone.
+fn rebase_behind_branches(
+    repo_path: &str,
+    wait: bool,
+    analyzer: &BranchAnalyzer,
+    results: &[branch::BranchHealth],
+    min_behind: u32,
+) -> anyhow::Result<()> {
+    let repo = git::GitRepo::open(repo_path)?;
+    let config = config::Config::load_for_repo(repo_path)?;
+    let main = analyzer
+        .base()
+        .map(|base| base.name.clone())
+        .ok_or_else(|| anyhow::anyhow!("Could not find the main branch to rebase onto"))?;
+    let candidates: Vec<&branch::BranchHealth> = results
+        .iter()
+        .filter(|health| {
+            !health.is_remote()
+                && health.behind_count >= min_behind
+                && !health.is_base()
+                && !config.is_protected_branch(&health.name)
+        })
+        .collect();
+    if candidates.is_empty() {
+        println!(
+            "{} {}",
+            CHECKMARK,
+            theme::added(format!(
+                "No local branch is {} or more commits behind {}",
+                min_behind, main
+            ))
+        );
+        return Ok(());
+    }
+    if ci::active() || !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!(
+            "--rebase asks which branches to rebase, so it needs a terminal"
+        ));
+    }
+
+    let items: Vec<String> = candidates
+        .iter()
+        .map(|health| {
+            let published = if repo.remote_branch_at_head("origin", &health.name).is_some() {
+                ", on origin: needs a force push afterwards"
+            } else {
+                ""
+            };
+            format!(
+                "{} ({} behind, {} ahead{})",
+                health.name, health.behind_count, health.ahead_count, published
+            )
+        })
+        .collect();
+    let Some(selection) = MultiSelect::with_theme(&theme::dialog())
+        .with_prompt(format!(
+            "Branches to rebase onto {} (space selects, enter confirms)",
+            main
+        ))
+        .items(&items)
+        .interact_opt()?
+    else {
+        return Err(exit::aborted("Nothing rebased"));
+    };
+    if selection.is_empty() {
+        return Ok(());
+    }
+
+    let _lock = repo.lock(wait)?;
+    let original = repo.get_current_branch().ok();
+    let chosen: Vec<&str> = selection
+        .iter()
+        .map(|&index| candidates[index].name.as_str())
+        .collect();
+    for (done, name) in chosen.iter().enumerate() {
+        let mut sp = Progress::new(
+            Spinners::Dots12,
+            format!("Rebasing {} onto {}...", name, main),
+        );
+        match repo.rebase_branch(name, &main) {
+            Ok(git::RebaseOutcome::Done) => sp.stop_with_message(format!(
+                "{} {}",
+                CHECKMARK,
+                theme::added(format!("Rebased '{}' onto {}", name, main))
+            )),
+            Ok(git::RebaseOutcome::Conflicts(files)) => {
+                sp.stop_with_message(format!(
+                    "{} {}",
+                    CROSS,
+                    theme::removed(format!("Rebasing '{}' stopped on conflicts in:", name))
+                ));
+                for file in &files {
+                    println!("   {}", file);
+                }
+                println!(
+                    "\nFix the conflicts, 'git add' the files and run 'git rebase --continue', or 'git rebase --abort' to leave '{}' as it was.",
+                    name
+                );
+                notice!(
+                    "{} {}",
+                    PENCIL,
+                    style("Stuck? Ask 'gyst explain \"finish a rebase with conflicts\"'").dim()
+                );
+                let rest = &chosen[done + 1..];
+                if !rest.is_empty() {
+                    notice!(
+                        "{} {}",
+                        PENCIL,
+                        style(format!(
+                            "Not rebased yet: {}. Run 'gyst branch health --rebase' again once this one is done.",
+                            rest.join(", ")
+                        ))
+                        .dim()
+                    );
+                }
+                return Err(anyhow::anyhow!("The rebase of '{}' needs your help", name));
+            }
+            Err(e) => {
+                sp.stop_with_message(format!(
+                    "{} {}",
+                    CROSS,
+                    theme::removed(format!("Could not rebase '{}'", name))
+                ));
+                if let Some(original) = &original {
+                    repo.switch_branch(original)?;
+                }
+                return Err(e);
+            }
+        }
+    }
+    if let Some(original) = &original {
+        repo.switch_branch(original)?;
+    }
+    Ok(())
+}
+
+/// Ask which staged files go into the commit with `--interactive-files`, and
+/// set the others aside. `None` if every file was kept.
+fn choose_files(repo: &git::GitRepo) -> anyhow::Result<Option<git::SetAside<'_>>> {
+    if ci::active() || !io::stdin().is_terminal() {
+        return Err(exit::invalid(anyhow::anyhow!(
+            "--interactive-files asks which files to commit, so it needs a terminal"
+        )));
+    }
+    let changes = repo.get_staged_changes()?;
+    let files = changes.paths();
+    let Some(selection) = MultiSelect::with_theme(&theme::dialog())
+        .with_prompt("Files to commit (space toggles, enter confirms)")
+        .items(&files)
+        .defaults(&vec![true; files.len()])
+        .interact_opt()?
+    else {
+        return Err(exit::aborted("Commit aborted"));
+    };
+    if selection.is_empty() {
+        return Err(exit::aborted("No files selected. Commit aborted"));
+    }
+
+    let left_out: Vec<String> = files
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !selection.contains(index))
+        .map(|(_, file)| file.clone())
+        .collect();
+    if left_out.is_empty() {
+        return Ok(None);
+    }
+    let set_aside = repo.set_aside(&changes.with_old_names(&left_out))?;
+    notice!(
+        "{} {}",
+        PENCIL,
+        style(format!(
+            "Leaving out {}; {} staged again after the commit.",
+            left_out.join(", "),
+            if left_out.len() == 1 {
+                "it is"
+            } else {
+                "they are"
+            }
+        ))
+        .dim()
+    );
+    Ok(Some(set_aside))
+}
+
+/// Stage the files `choose_files` set aside again, now that the commit is made
+fn restage(set_aside: Option<git::SetAside<'_>>) -> anyhow::Result<()> {
+    match set_aside {
+        Some(set_aside) => set_aside.restore(),
+        None => Ok(()),
+    }
+}
+
+/// Ask before committing directly to a protected branch, offering to move the
+/// commit to a new feature branch instead. Fails if the commit should not proceed,
+/// and without asking when not `interactive` or with `--yes`, since no option
+/// is safe to pick for the user.
+fn confirm_protected_branch(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    message: &str,
+    force: bool,
+    interactive: bool,
+) -> anyhow::Result<()> {
+    let Ok(current) = repo.get_current_branch() else {
+        return Ok(());
+    };
+    if force || !config.is_protected_branch(&current) {
+        return Ok(());
+    }
+
+    let suggested = branch::suggest_branch_name(message);
+    if !interactive || output::assume_yes() {
+        return Err(exit::invalid(anyhow::anyhow!(
+            "'{}' is a protected branch. Create a feature branch first (e.g. 'git switch -c {}') or re-run with --force to commit anyway.",
+            current,
+            suggested
+        )));
+    }
+    println!(
+        "\n{} {}",
+        CROSS,
+        theme::warning(format!("'{}' is a protected branch.", current)).bold()
+    );
+
+    let options = [
+        format!("Create a new branch (suggested: {})", suggested),
+        format!("Commit to '{}' anyway", current),
+        "Abort".to_string(),
+    ];
+    let choice = Select::with_theme(&theme::dialog())
+        .with_prompt("How would you like to continue?")
+        .default(0)
+        .items(&options)
+        .interact_opt()?;
+
+    match choice {
+        Some(0) => {
+            let name: String = Input::with_theme(&theme::dialog())
+                .with_prompt("Branch name")
+                .default(suggested)
+                .interact_text()?;
+            repo.create_and_checkout_branch(&name)?;
+            notice!(
+                "{} {}",
+                CHECKMARK,
+                theme::added(format!("Switched to new branch '{}'", name))
+            );
+            Ok(())
+        }
+        Some(1) => Ok(()),
+        _ => Err(exit::aborted("Commit aborted")),
+    }
+}
+
+/// The answer to a `[Y/n]` question, trimmed and lowercased. With `--yes` the
+/// answer is "y" and nothing is read.
+fn read_answer() -> anyhow::Result<String> {
+    if output::assume_yes() {
+        println!("y");
+        return Ok("y".to_string());
+    }
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_lowercase())
+}
+
+/// Whether spinners are shown: not with `--quiet` or `--no-color`
+fn spinning() -> bool {
+    !output::quiet() && !output::plain()
+}
+
+/// A spinner, or nothing with `--quiet`. With `--no-color` only the final
+/// message is printed.
+struct Progress(Option<Spinner>);
+
+impl Progress {
+    fn new(spinner: Spinners, message: String) -> Self {
+        Self(spinning().then(|| Spinner::new(spinner, message)))
+    }
+
+    fn stop_with_message(&mut self, message: String) {
+        match &mut self.0 {
+            Some(spinner) => spinner.stop_with_message(message),
+            None if output::quiet() => {}
+            None => println!("{}", message),
+        }
+    }
+}
+
+/// A spinner that gives way to an answer streamed from the server: the text
+/// is shown dimmed as it arrives and cleared again when the spinner would stop
+struct StreamingSpinner {
+    spinner: Option<Spinner>,
+    /// Terminal rows the streamed text has moved down so far
+    rows: usize,
+    column: usize,
+}
+
+impl StreamingSpinner {
+    fn new(spinner: Spinners, message: String) -> Self {
+        Self {
+            spinner: spinning().then(|| Spinner::new(spinner, message)),
+            rows: 0,
+            column: 0,
+        }
+    }
+
+    /// Show the next piece of the answer. Only terminals get to see it.
+    fn show(&mut self, text: &str) {
+        if !io::stdout().is_terminal() || ci::active() || !spinning() {
+            return;
+        }
+        if let Some(mut spinner) = self.spinner.take() {
+            spinner.stop();
+            let _ = Term::stderr().clear_line();
+        }
+        let width = usize::from(Term::stdout().size().1).max(1);
+        for c in text.chars() {
+            if c == '\n' {
+                self.rows += 1;
+                self.column = 0;
+            } else if self.column == width {
+                self.rows += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        print!("{}", style(text).dim());
+        let _ = io::stdout().flush();
+    }
+
+    fn stop_with_message(&mut self, message: String) {
+        match self.spinner.take() {
+            Some(mut spinner) => spinner.stop_with_message(message),
+            None if output::quiet() => {}
+            None if output::plain() => println!("{}", message),
+            None => {
+                let term = Term::stdout();
+                let _ = term.clear_line();
+                let _ = term.clear_last_lines(self.rows);
+                eprintln!("{}", message);
+            }
+        }
+    }
+}
+
+/// Print the `gyst status` dashboard: the branch line, one line per kind of
+/// change with the first few paths, the summary and the next steps
+fn print_status(snapshot: &status::Snapshot, summary: &str, steps: &[status::Step]) {
+    const SHOWN_PATHS: usize = 4;
+
+    let branch = match (&snapshot.branch, &snapshot.head) {
+        (Some(branch), _) => theme::prompt(branch.clone()).bold().to_string(),
+        (None, head) => theme::warning(format!(
+            "HEAD detached at {}",
+            head.as_deref().unwrap_or("?")
+        ))
+        .bold()
+        .to_string(),
+    };
+    let tracking = match &snapshot.upstream {
+        Some(upstream) if upstream.ahead == 0 && upstream.behind == 0 => {
+            style(format!("up to date with {}", upstream.name))
+                .dim()
+                .to_string()
+        }
+        Some(upstream) => format!(
+            "{} {}",
+            theme::warning(format!(
+                "{} ahead, {} behind",
+                upstream.ahead, upstream.behind
+            )),
+            style(upstream.name.as_str()).dim()
+        ),
+        None if snapshot.branch.is_some() => style("no upstream").dim().to_string(),
+        None => String::new(),
+    };
+    if tracking.is_empty() {
+        println!("\n{}", branch);
+    } else {
+        println!("\n{} {}", branch, tracking);
+    }
+    if let Some(operation) = snapshot.operation {
+        println!(
+            "{} {}",
+            CAUTION,
+            theme::warning(format!("{} in progress", operation)).bold()
+        );
+    }
+
+    let tree = &snapshot.tree;
+    let labeled = |files: &[(String, &str)]| -> Vec<String> {
+        files
+            .iter()
+            .map(|(path, label)| match *label {
+                "modified" => path.clone(),
+                label => format!("{} ({})", path, label),
+            })
+            .collect()
+    };
+    let rows = [
+        (
+            "Conflicts",
+            tree.conflicted.clone(),
+            theme::removed as fn(String) -> _,
+        ),
+        ("Staged", labeled(&tree.staged), theme::added),
+        ("Unstaged", labeled(&tree.unstaged), theme::warning),
+        ("Untracked", tree.untracked.clone(), theme::removed),
+    ];
+    println!();
+    for (heading, paths, color) in rows {
+        if paths.is_empty() {
+            continue;
+        }
+        let mut shown = paths
+            .iter()
+            .take(SHOWN_PATHS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if paths.len() > SHOWN_PATHS {
+            shown.push_str(&format!(", +{} more", paths.len() - SHOWN_PATHS));
+        }
+        println!(
+            "  {:<10} {:>3}  {}",
+            heading,
+            color(paths.len().to_string()),
+            shown
+        );
+    }
+    if tree.is_clean() {
+        println!(
+            "  {}",
+            theme::added("Nothing to commit, working tree clean")
+        );
+    }
+    if snapshot.stashes > 0 {
+        println!(
+            "  {:<10} {:>3}  {}",
+            "Stashes",
+            snapshot.stashes,
+            style("see 'git stash list'").dim()
+        );
+    }
+
+    println!("\n{} {}", SPARKLE, summary);
+    if !steps.is_empty() {
+        println!("\n{}", theme::prompt("Next:").bold());
+        let width = steps
+            .iter()
+            .map(|step| step.command.len())
+            .max()
+            .unwrap_or(0);
+        for step in steps {
+            println!(
+                "   {}  {}",
+                theme::added(format!("{:<width$}", step.command, width = width)).bold(),
+                style(step.reason.as_str()).dim()
+            );
+        }
+    }
+}
+
+/// Define the git jargon used in an explanation, for readers new to git
+fn print_glossary(explanation: &str) {
+    let terms = glossary::terms_in(explanation);
+    if terms.is_empty() {
+        return;
+    }
+    println!("\n{}", style("Terms used above:").dim().bold());
+    for term in terms {
+        println!(
+            "   {} {}",
+            theme::prompt(term.name).bold(),
+            style(format!("— {}", term.definition)).dim()
+        );
+    }
+}
+
+/// Show what a destructive git command would do in this repository, worked out
+/// from the repository itself. `show_command` prints the command above it.
+fn print_preview(repo: Option<&git::GitRepo>, command: &str, show_command: bool) {
+    const SHOWN: usize = 10;
+
+    let Some(repo) = repo else {
+        return;
+    };
+    let preview = match preview::preview(repo, command.trim()) {
+        Ok(Some(preview)) => preview,
+        Ok(None) => return,
+        Err(e) => {
+            println!(
+                "   {} {}",
+                CROSS,
+                style(format!("Could not preview this command: {}", e)).dim()
+            );
+            return;
+        }
+    };
+
+    if show_command {
+        println!("{} {}", PENCIL, theme::added(command.trim()).bold());
+    }
+    println!(
+        "   {} {}",
+        CROSS,
+        theme::warning(format!("In this repository: {}", preview.summary)).bold()
+    );
+    for file in preview.files.iter().take(SHOWN) {
+        println!("       {}", theme::removed(file));
+    }
+    if preview.files.len() > SHOWN {
+        println!(
+            "       {}",
+            style(format!("...and {} more", preview.files.len() - SHOWN)).dim()
+        );
+    }
+    for commit in preview.commits.iter().take(SHOWN) {
+        println!("       {} {}", theme::warning(&commit.id), commit.summary);
+    }
+    if preview.commits.len() > SHOWN {
+        println!(
+            "       {}",
+            style(format!("...and {} more", preview.commits.len() - SHOWN)).dim()
+        );
+    }
+    if let Some(note) = preview.note {
+        println!("       {}", style(note).dim());
+    }
+}
+
+/// Marks the end of the message in the editor, like git's scissors line.
+/// Everything below it is dropped.
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Let the user edit a commit message in `$EDITOR`, with the template problems
+/// and other phrasings shown as comments below it. Returns `None` if the editor failed.
+async fn edit_commit_message(
+    repo_path: &str,
+    config: &config::Config,
+    message: &str,
+    template: &template::Template,
+    changes: &git::StagedChanges,
+    diff: &str,
+    hints: &[String],
+) -> anyhow::Result<Option<String>> {
+    const ALTERNATIVES: u8 = 3;
+
+    let mut sp = Progress::new(Spinners::Dots12, "Finding other phrasings...".into());
+    let alternatives =
+        generate_suggestions(config, template, changes, diff, hints, ALTERNATIVES).await;
+    sp.stop_with_message(String::new());
+
+    edit_message_with(repo_path, config, message, template, alternatives)
+}
+
+/// Let the user edit `message` in `$EDITOR` with the template problems and
+/// `alternatives` shown as comments below it. Returns `None` if the editor failed.
+fn edit_message_with(
+    repo_path: &str,
+    config: &config::Config,
+    message: &str,
+    template: &template::Template,
+    alternatives: anyhow::Result<Vec<String>>,
+) -> anyhow::Result<Option<String>> {
+    let mut comments = vec![
+        SCISSORS.to_string(),
+        "# Everything below this line is ignored.".to_string(),
+    ];
+    let problems = template.validate(message, config.commit.max_subject_length);
+    if !problems.is_empty() {
+        comments.push("#".to_string());
+        comments.push("# Problems with the message above:".to_string());
+        comments.extend(problems.iter().map(|problem| format!("#   - {}", problem)));
+    }
+    match alternatives {
+        Ok(alternatives) => {
+            let alternatives: Vec<&String> = alternatives
+                .iter()
+                .filter(|alternative| alternative.trim().lines().next() != message.lines().next())
+                .collect();
+            if !alternatives.is_empty() {
+                comments.push("#".to_string());
+                comments.push("# Other phrasings:".to_string());
+            }
+            for alternative in alternatives {
+                comments.push("#".to_string());
+                comments.extend(
+                    alternative
+                        .trim()
+                        .lines()
+                        .map(|line| format!("#   {}", line).trim_end().to_string()),
+                );
+            }
+        }
+        Err(e) => {
+            comments.push("#".to_string());
+            comments.push(format!("# No other phrasings: {}", e));
+        }
+    }
+
+    let text = format!("{}\n\n{}", message, comments.join("\n"));
+    let Some(edited) = edit_in_editor(repo_path, &text)? else {
+        return Ok(None);
+    };
+    let edited = match edited.split_once(SCISSORS) {
+        Some((message, _)) => message,
+        None => edited.as_str(),
+    };
+    Ok(Some(edited.trim().to_string()))
+}
+
+/// Print one `gyst doctor` result with its suggested fix
+fn print_check(check: &doctor::Check) {
+    let (icon, name) = match check.status {
+        doctor::Status::Ok => (CHECKMARK.to_string(), theme::added(check.name)),
+        doctor::Status::Warning => ("!".to_string(), theme::warning(check.name)),
+        doctor::Status::Failed => (CROSS.to_string(), theme::removed(check.name)),
+        doctor::Status::Skipped => ("-".to_string(), style(check.name).dim()),
+    };
+    println!("{} {:<16} {}", icon, name.bold(), check.detail);
+    if let Some(fix) = &check.fix {
+        println!("  {}", style(format!("→ {}", fix)).dim());
+    }
+}
+
+/// Let the user edit `text` in the editor git would use for the repository at
+/// `repo_path`. Returns `None` if the editor failed.
+fn edit_in_editor(repo_path: &str, text: &str) -> anyhow::Result<Option<String>> {
+    println!("\n{} {}", PENCIL, theme::prompt("Opening in editor..."));
+    // Create a temporary file with the message
+    let mut temp = tempfile::NamedTempFile::new()?;
+    writeln!(temp, "{}", text)?;
+
+    // Get the path before the file is closed
+    let temp_path = temp.path().to_path_buf();
+
+    let editor = editor::Editor::resolve(repo_path);
+    let status = editor
+        .command(&temp_path)
+        .status()
+        .with_context(|| format!("Failed to start the editor '{}'", editor.command))?;
+
+    if !status.success() {
+        println!("{} {}", CROSS, theme::removed("Editor returned with error"));
+        return Ok(None);
+    }
+
+    // Read back the edited message
+    let edited = std::fs::read_to_string(&temp_path)?;
+    // Editors on Windows save CRLF line endings and Notepad may add a byte order mark
+    let edited = edited.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    Ok(Some(edited.trim().to_string()))
+}
+
+/// The subcommand's name for usage statistics, e.g. `branch health`. Arguments are left out.
+fn command_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
+/// Whether `command` asks the AI for something
+fn uses_ai(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Commit { .. }
+            | Commands::Suggest { .. }
+            | Commands::Explain { .. }
+            | Commands::Ui { .. }
+            | Commands::Watch { .. }
+    )
+}
+
+/// In CI, what keeps `command` from running, since there is nobody to ask:
+/// direct API mode without an API key
+fn missing_ci_setting(repo_path: &str, command: &Commands) -> Option<String> {
+    if !ci::active() || !uses_ai(command) {
+        return None;
+    }
+    let config = config::Config::load_for_repo(repo_path).ok()?;
+    (config.ai_enabled() && !config.use_server() && config.get_api_key().is_none()).then(|| {
+        format!(
+            "No Anthropic API key is configured ({} detected). Add a step that runs 'gyst config set ai.api_key <key>' from a secret, use the gyst server with 'gyst config set server.use_server true', or turn the AI off with 'gyst config set ai.enabled false'",
+            ci::provider().unwrap_or("CI")
+        )
+    })
+}
+
+/// Whether to run the setup wizard before `command`: it uses the AI, runs in a
+/// terminal outside CI, and there is no configuration yet or direct API mode has no key
+fn needs_setup(repo_path: &str, command: &Commands) -> bool {
+    if !uses_ai(command)
+        || matches!(command, Commands::Suggest { pipe: true, .. })
+        || ci::active()
+        || !io::stdin().is_terminal()
+        || !io::stdout().is_terminal()
+    {
+        return false;
+    }
+    let Ok(config) = config::Config::load_for_repo(repo_path) else {
+        return false;
+    };
+    config.ai_enabled()
+        && (!config::Config::exists() || (!config.use_server() && config.get_api_key().is_none()))
+}
+
+/// Ask for the essential settings, starting from `config`, and save them.
+/// Returns `None` if the user backed out, leaving the configuration untouched.
+async fn run_setup(mut config: config::Config) -> anyhow::Result<Option<config::Config>> {
+    println!("\n{} {}\n", SPARKLE, theme::prompt("Set up gyst").bold());
+
+    let modes = [
+        "The gyst server (no API key needed)",
+        "The Anthropic API with my own key",
+        "No AI: build messages from templates",
+    ];
+    let current = if !config.ai.enabled {
+        2
+    } else if config.use_server() {
+        0
+    } else {
+        1
+    };
+    let Some(mode) = Select::with_theme(&theme::dialog())
+        .with_prompt("How should gyst write commit messages?")
+        .items(&modes)
+        .default(current)
+        .interact_opt()?
+    else {
+        return Ok(None);
+    };
+    config.ai.enabled = mode != 2;
+    if mode != 2 {
+        config.server.use_server = mode == 0;
+    }
+
+    while config.ai.enabled && !config.use_server() {
+        let has_key = config.get_api_key().is_some();
+        let key: String = Password::with_theme(&theme::dialog())
+            .with_prompt(if has_key {
+                "Anthropic API key (empty keeps the current one)"
+            } else {
+                "Anthropic API key"
+            })
+            .allow_empty_password(has_key)
+            .interact()?;
+        if !key.trim().is_empty() {
+            config.ai.provider = "anthropic".to_string();
+            config.ai.api_key = key.trim().to_string();
+        }
+
+        let mut sp = Progress::new(Spinners::Dots12, "Checking the API key...".into());
+        let result = ai::CommitMessageGenerator::new(config.clone())
+            .validate_key()
+            .await;
+        let Err(e) = result else {
+            sp.stop_with_message(format!(
+                "{} {}\n",
+                CHECKMARK,
+                theme::added("The API key works")
+            ));
+            break;
+        };
+        sp.stop_with_message(format!(
+            "{} {}\n",
+            CROSS,
+            theme::removed("The API key could not be verified")
+        ));
+        println!("{}\n", style(format!("{:#}", e)).dim());
+
+        let choice = Select::with_theme(&theme::dialog())
+            .with_prompt("What would you like to do?")
+            .default(0)
+            .items(&[
+                "Enter a different key",
+                "Keep this key anyway",
+                "Use the gyst server instead",
+            ])
+            .interact_opt()?;
+        match choice {
+            Some(0) => {}
+            Some(1) => break,
+            Some(2) => config.server.use_server = true,
+            _ => return Ok(None),
+        }
+    }
+
+    let mut styles: Vec<&str> = template::PRESETS.to_vec();
+    styles.extend(config.commit.templates.keys().map(String::as_str));
+    let current = styles
+        .iter()
+        .position(|name| *name == config.commit.template)
+        .unwrap_or(0);
+    let Some(choice) = Select::with_theme(&theme::dialog())
+        .with_prompt("Commit message style")
+        .items(&styles)
+        .default(current)
+        .interact_opt()?
+    else {
+        return Ok(None);
+    };
+    config.commit.template = styles[choice].to_string();
+
+    let language: String = Input::with_theme(&theme::dialog())
+        .with_prompt("Language for commit messages")
+        .default(
+            config
+                .commit
+                .language
+                .clone()
+                .unwrap_or_else(|| "English".to_string()),
+        )
+        .interact_text()?;
+    config.commit.language = Some(language.trim().to_string());
+    if config.message_language().is_none() {
+        config.commit.language = None;
+    }
+
+    config.save()?;
+    println!(
+        "\n{} {}",
+        CHECKMARK,
+        theme::added(format!(
+            "Saved to {}",
+            config::Config::get_config_path()?.display()
+        ))
+    );
+    println!(
+        "{}\n",
+        style("Change any setting later with 'gyst config set <key> <value>'.").dim()
+    );
+    Ok(Some(config))
+}
+
+/// Apply a `--model` override. Only direct API mode can honor it; the gyst
+/// server picks its own model.
+fn override_model(config: &mut config::Config, model: Option<String>) -> anyhow::Result<()> {
+    let Some(model) = model else {
+        return Ok(());
+    };
+    ai::check_model(&config.ai.provider, &model)?;
+    if config.ai_enabled() && config.use_server() {
+        // On stderr so piped suggestions stay clean
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(
+                "The gyst server chooses its own model; --model only applies in direct API mode."
+            )
+            .dim()
+        );
+    }
+    config.ai.model = model;
+    Ok(())
+}
+
+/// The colored risk level shown next to a suggested command
+fn risk_badge(risk: command_suggest::Risk) -> String {
+    let label = risk.label();
+    match risk {
+        command_suggest::Risk::Safe => {
+            theme::added(format!("[{} {}]", CHECKMARK, label)).to_string()
+        }
+        command_suggest::Risk::RewritesHistory => {
+            theme::warning(format!("[{} {}]", CAUTION, label)).to_string()
+        }
+        command_suggest::Risk::Destructive => theme::removed(format!("[{} {}]", STOP, label))
+            .bold()
+            .to_string(),
+    }
+}
+
+/// Run the suggested commands one by one for `gyst explain --run`, each after
+/// confirmation (or right away with `--yes` if it is safe). A destructive
+/// command runs only once its name is typed, even with `--yes`.
+fn run_suggested(commands: &[command_suggest::SuggestedCommand], dir: &str) -> anyhow::Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(exit::invalid(anyhow::anyhow!(
+            "--run needs a terminal to confirm each command"
+        )));
+    }
+
+    for suggested in commands {
+        let command = suggested.command.as_str();
+        println!(
+            "\n{} {} {}",
+            theme::prompt("Next:").bold(),
+            theme::added(command).bold(),
+            risk_badge(suggested.risk)
+        );
+        if command.contains('<') && command.contains('>') {
+            notice!(
+                "{} {}",
+                PENCIL,
+                style("Skipped: fill in the <placeholders> and run it yourself").dim()
+            );
+            continue;
+        }
+
+        let confirmed = if suggested.risk == command_suggest::Risk::Destructive {
+            let word = command_suggest::confirmation_word(command);
+            let typed: String = Input::with_theme(&theme::dialog())
+                .with_prompt(format!(
+                    "This can lose work for good. Type '{}' to run it, or press Enter to skip",
+                    word
+                ))
+                .allow_empty(true)
+                .interact_text()?;
+            typed.trim() == word
+        } else if output::assume_yes() && suggested.risk == command_suggest::Risk::Safe {
+            true
+        } else {
+            Confirm::with_theme(&theme::dialog())
+                .with_prompt("Run it?")
+                .default(suggested.risk == command_suggest::Risk::Safe)
+                .interact()?
+        };
+        if !confirmed {
+            notice!("{} {}", PENCIL, style("Skipped").dim());
+            continue;
+        }
+
+        let mut shell = if cfg!(windows) {
+            std::process::Command::new("cmd")
+        } else {
+            std::process::Command::new("sh")
+        };
+        let status = shell
+            .arg(if cfg!(windows) { "/C" } else { "-c" })
+            .arg(command)
+            .current_dir(dir)
+            .status()
+            .with_context(|| format!("Failed to run '{}'", command))?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("'{}' failed ({})", command, status));
+        }
+    }
+    Ok(())
+}
+
+/// Copy `text` for `--copy` or `ui.copy`. Failing to is reported, not an error:
+/// the text was printed or committed anyway.
+fn copy_to_clipboard(text: &str, what: &str) {
+    match clipboard::copy(text) {
+        Ok(()) => notice!(
+            "{} {}",
+            CHECKMARK,
+            theme::added(format!("Copied the {} to the clipboard", what))
+        ),
+        Err(e) => enotice!(
+            "{} {}",
+            CROSS,
+            theme::warning(format!("Could not copy the {}: {:#}", what, e))
+        ),
+    }
+}
+
+/// Tell the user that a feature is falling back because AI is turned off for this repository
+fn print_ai_disabled_notice(fallback: &str) {
+    notice!(
+        "{} {}",
+        PENCIL,
+        style(format!(
+            "AI is disabled for this repository (ai.enabled = false), {}.",
+            fallback
+        ))
+        .dim()
+    );
+}
+
+/// After `gyst commit --push`: push the new commit and, with `create_pr`,
+/// open a pull request for the branch. With `confirm`, the push is asked
+/// about first; the pull request is shown before it is opened.
+async fn publish(
+    repo_path: &str,
+    repo: &git::GitRepo,
+    config: &config::Config,
+    remotes: &[String],
+    create_pr: bool,
+    confirm: bool,
+) -> anyhow::Result<()> {
+    if create_pr && confirm {
+        let targets = if remotes.is_empty() {
+            &config.git.push_remotes
+        } else {
+            remotes
+        };
+        let tracking = if repo.upstream().is_none() {
+            " and track it"
+        } else {
+            ""
+        };
+        print!(
+            "\n{} Push '{}' to {}{}? [Y/n] ",
+            PENCIL,
+            repo.get_current_branch()?,
+            targets.join(", "),
+            tracking
+        );
+        if matches!(read_answer()?.as_str(), "n" | "no") {
+            return Err(exit::aborted(
+                "Not pushed. The commit is kept; open a pull request later with 'gyst pr --push'",
+            ));
+        }
+    }
+    push_with_spinner(repo, config, remotes)?;
+
+    if create_pr {
+        let options = PrOptions {
+            push: true,
+            yes: !confirm,
+            ..PrOptions::default()
+        };
+        open_pull_request(repo_path, options).await?;
+    }
+    Ok(())
+}
+
+/// What `gyst pr` was asked for. `gyst commit --create-pr` opens a pull
+/// request with the defaults.
+#[derive(Default)]
+struct PrOptions {
+    base: Option<String>,
+    title: Option<String>,
+    draft: bool,
+    push: bool,
+    template: Option<String>,
+    no_labels: bool,
+    no_ai: bool,
+    copy: bool,
+    /// Open it without showing the description and asking first
+    yes: bool,
+}
+
+/// Open a pull request (a merge request on GitLab) for the current branch
+async fn open_pull_request(repo_path: &str, options: PrOptions) -> anyhow::Result<()> {
+    let PrOptions {
+        base,
+        title,
+        draft,
+        push,
+        template,
+        no_labels,
+        no_ai,
+        copy,
+        yes,
+    } = options;
+    let config = config::Config::load_for_repo(repo_path)?;
+    let repo = git::GitRepo::open(repo_path)?;
+    let branch = repo.get_current_branch()?;
+    let host = forge::Forge::connect(&config, &repo)?;
+    let request_name = host.request_name();
+
+    let base = match base {
+        Some(base) => base,
+        None => host.default_branch().await?,
+    };
+    if branch == base {
+        return Err(anyhow::anyhow!(
+            "You are on '{}', the base branch. Switch to the branch you want to open a {} for",
+            base,
+            request_name
+        ));
+    }
+    if let Some(pull) = host.open_pull_requests().await?.get(&branch) {
+        println!(
+            "{} '{}' already has an open {}: {}",
+            CHECKMARK,
+            branch,
+            request_name,
+            theme::prompt(&pull.url)
+        );
+        return Ok(());
+    }
+    let description_template = choose_description_template(&repo, template.as_deref(), yes)?;
+
+    match repo.remote_branch_at_head("origin", &branch) {
+        Some(true) => {}
+        _ if push => push_with_spinner(&repo, &config, &["origin".to_string()])?,
+        Some(false) => println!(
+            "{} {}",
+            PENCIL,
+            theme::warning(format!(
+                "origin/{} is not up to date, so the {} will not show your latest commits. Use --push to push them first.",
+                branch, request_name
+            ))
+        ),
+        None => {
+            return Err(exit::git(anyhow::anyhow!(
+                "'{}' is not on origin yet. Push it with 'git push -u origin {}' or re-run with --push",
+                branch,
+                branch
+            )));
+        }
+    }
+
+    // Compare with the remote base when it is known, as the forge does
+    let remote_base = format!("origin/{}", base);
+    let from = if repo.resolve_commit(&remote_base).is_ok() {
+        remote_base
+    } else {
+        base.clone()
+    };
+    let notes = release::ReleaseNotes::collect(&repo, Some(&from), "HEAD")?;
+    if notes.is_empty() {
+        return Err(anyhow::anyhow!(
+            "'{}' has no commits that are not on '{}'",
+            branch,
+            base
+        ));
+    }
+
+    let issue = linked_issue(&repo, &config).await;
+    let ticket = detect_ticket(&repo, &config)?;
+    let jira_issue = jira_issue(&config, ticket.as_ref()).await;
+    let issue_hints: Vec<String> = jira_issue
+        .iter()
+        .map(|issue| issue.prompt_hint())
+        .chain(issue.iter().map(|issue| issue.prompt_hint()))
+        .collect();
+    let mut description = if no_ai || !config.ai_enabled() {
+        if !no_ai {
+            print_ai_disabled_notice("listing the commits instead");
+        }
+        pr::plain_description(&branch, &notes.changes, description_template.as_ref())
+    } else if config.use_server() {
+        notice!(
+                "{} {}",
+                PENCIL,
+                style("Generated descriptions need direct API mode ('gyst config --use-server false'). Listing the commits instead.")
+                    .dim()
+            );
+        pr::plain_description(&branch, &notes.changes, description_template.as_ref())
+    } else {
+        let patch = repo.range_patch(Some(&from), "HEAD", config.git.max_diff_size)?;
+        let mut sp = Progress::new(Spinners::Dots12, "Writing the description...".into());
+        let generator = ai::CommitMessageGenerator::new(config.clone()).for_command("pr");
+        let reply = generator
+            .complete(
+                pr::SYSTEM_PROMPT,
+                &pr::build_prompt(
+                    &notes.changes,
+                    &patch,
+                    &issue_hints,
+                    description_template.as_ref(),
+                ),
+                1000,
+                0.3,
+            )
+            .await;
+        match reply.ok().as_deref().and_then(pr::parse_reply) {
+            Some(description) => {
+                sp.stop_with_message(format!(
+                    "{} {}",
+                    CHECKMARK,
+                    theme::added("Description ready!")
+                ));
+                description
+            }
+            None => {
+                sp.stop_with_message(format!(
+                    "{} {}",
+                    CROSS,
+                    theme::removed("Failed to write the description, listing the commits instead")
+                ));
+                pr::plain_description(&branch, &notes.changes, description_template.as_ref())
+            }
+        }
+    };
+    if let Some(title) = title {
+        description.title = title;
+    }
+    if config.forge.suggest_reviewers
+        && let Some(owners) = repo.workdir().ok().and_then(codeowners::CodeOwners::load)
+    {
+        let paths = repo.range_paths(Some(&from), "HEAD")?;
+        let reviewers: Vec<String> = owners
+            .others(&paths, &codeowners::mine(&config, &repo))
+            .into_iter()
+            .map(|area| area.owner)
+            .collect();
+        description.suggest_reviewers(&reviewers);
+    }
+    if let Some(issue) = &issue {
+        description.link_issue(issue);
+    }
+    if let Some(issue) = &jira_issue {
+        description.link_jira_issue(issue);
+    }
+    let labels = if no_labels {
+        Vec::new()
+    } else {
+        pr::labels(&config, &notes.changes)
+    };
+
+    if !yes {
+        println!(
+            "\n{} {} {} {}\n",
+            SPARKLE,
+            theme::prompt(format!("{}:", template::capitalize(request_name))).bold(),
+            style(&branch).bold(),
+            style(format!("→ {}", base)).dim()
+        );
+        println!("{}\n", theme::added(description.title.as_str()).bold());
+        println!("{}", description.body);
+        if !labels.is_empty() {
+            println!("\n{} {}", style("Labels:").dim(), labels.join(", "));
+        }
+        print!("\n{} Open this {}? [Y/n/e(edit)] ", PENCIL, request_name);
+        let input = read_answer()?;
+        match input.as_str() {
+            "n" | "no" => {
+                return Err(exit::aborted(&format!(
+                    "{} aborted",
+                    template::capitalize(request_name)
+                )));
+            }
+            "e" | "edit" => {
+                let text = format!("{}\n\n{}", description.title, description.body);
+                let Some(edited) = edit_in_editor(repo_path, &text)? else {
+                    return Err(exit::aborted(&format!(
+                        "{} aborted",
+                        template::capitalize(request_name)
+                    )));
+                };
+                let (title, body) = edited.split_once('\n').unwrap_or((&edited, ""));
+                description.title = title.trim().to_string();
+                description.body = body.trim().to_string();
+            }
+            _ => {}
+        }
+    }
+    if copy || config.ui.copy {
+        copy_to_clipboard(&description.body, "description");
+    }
+
+    let mut sp = Progress::new(Spinners::Dots12, format!("Opening the {}...", request_name));
+    let pull = match host
+        .create_pull_request(&branch, &base, &description.title, &description.body, draft)
+        .await
+    {
+        Ok(pull) => pull,
+        Err(e) => {
+            sp.stop_with_message(format!(
+                "{} {}",
+                CROSS,
+                theme::removed(format!("Failed to open the {}", request_name))
+            ));
+            return Err(e);
+        }
+    };
+    sp.stop_with_message(format!(
+        "{} {}",
+        CHECKMARK,
+        theme::added(format!(
+            "Opened {} {}",
+            request_name,
+            host.reference(pull.number)
+        ))
+        .bold()
+    ));
+
+    if !labels.is_empty() {
+        match host.add_labels(pull.number, &labels).await {
+            Ok(()) => println!("{} Labeled {}", CHECKMARK, labels.join(", ")),
+            Err(e) => println!(
+                "{} {}",
+                CROSS,
+                theme::warning(format!("Failed to add labels: {}", e))
+            ),
+        }
+    }
+    println!("\n{}", theme::prompt(&pull.url));
+    Ok(())
+}
+
+/// Push the current branch to each selected remote, showing progress per remote.
+/// `requested` comes from `--remote`; when empty, `git.push_remotes` is used.
+fn push_with_spinner(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    requested: &[String],
+) -> anyhow::Result<()> {
+    let requested = if requested.is_empty() {
+        &config.git.push_remotes
+    } else {
+        requested
+    };
+
+    let available = repo.remote_names()?;
+    let remotes: Vec<String> = if requested.iter().any(|name| name == "all") {
+        available.clone()
+    } else {
+        requested.to_vec()
+    };
+
+    if remotes.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No remotes configured. Add one with 'git remote add origin <url>'"
+        ));
+    }
+    if let Some(unknown) = remotes.iter().find(|name| !available.contains(name)) {
+        return Err(anyhow::anyhow!(
+            "Unknown remote '{}'. Available remotes: {}",
+            unknown,
+            available.join(", ")
+        ));
+    }
+
+    let mut failed = Vec::new();
+    for remote in &remotes {
+        let mut sp = Progress::new(Spinners::Dots9, format!("Pushing to {}...", remote));
+        match repo.push_changes(remote) {
+            Ok(()) => sp.stop_with_message(format!(
+                "{} {} {}\n",
+                CHECKMARK,
+                theme::added(format!("Pushed to {}", remote)).bold(),
+                SPARKLE
+            )),
+            Err(e) => {
+                sp.stop_with_message(format!(
+                    "{} {}\n",
+                    CROSS,
+                    theme::removed(format!("Push to {} failed", remote))
+                ));
+                println!("Error: {}", e);
+                failed.push(remote.as_str());
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(exit::git(anyhow::anyhow!(
+            "Push failed for {} of {} remotes: {}",
+            failed.len(),
+            remotes.len(),
+            failed.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Resolve the commit a fixup/squash commit should target. With `auto`, recent
+/// commits touching the staged files are ranked (by the AI in direct API mode,
+/// otherwise by file overlap) and, when interactive, offered for selection.
+async fn select_fixup_target(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    changes: &git::StagedChanges,
+    diff: &str,
+    target: &str,
+    interactive: bool,
+) -> anyhow::Result<git::CommitInfo> {
+    if target != fixup::AUTO_TARGET {
+        return repo.resolve_commit(target);
+    }
+
+    let history = repo.recent_commits_with_files(fixup::HISTORY_DEPTH)?;
+    let candidates = fixup::rank_candidates(changes, history);
+    if candidates.is_empty() {
+        return Err(anyhow::anyhow!(
+            "None of the last {} commits touched the staged files. Pass the target explicitly, e.g. --fixup HEAD~3",
+            fixup::HISTORY_DEPTH
+        ));
+    }
+
+    let mut suggested = 0;
+    if candidates.len() > 1 && config.ai_enabled() && !config.use_server() {
+        let mut sp = Progress::new(
+            Spinners::Dots12,
+            "Finding the commit these changes belong to...".into(),
+        );
+        let generator = ai::CommitMessageGenerator::new(config.clone()).for_command("fixup");
+        match generator
+            .complete(
+                fixup::SYSTEM_PROMPT,
+                &fixup::build_prompt(&candidates, diff),
+                10,
+                0.0,
+            )
+            .await
+        {
+            Ok(reply) => {
+                suggested = fixup::parse_choice(&reply, candidates.len()).unwrap_or(0);
+                sp.stop_with_message(format!(
+                    "{} {}\n",
+                    CHECKMARK,
+                    theme::added("Found a likely target")
+                ));
+            }
+            Err(e) => {
+                sp.stop_with_message(format!(
+                    "{} {}\n",
+                    CROSS,
+                    theme::warning("AI ranking failed, using file overlap instead")
+                ));
+                println!("Error: {}", e);
+            }
+        }
+    }
+
+    if !interactive {
+        return Ok(candidates[suggested].commit.clone());
+    }
+
+    let items: Vec<String> = candidates
+        .iter()
+        .map(|candidate| {
+            format!(
+                "{} {} ({} shared {})",
+                candidate.commit.id,
+                candidate.commit.summary,
+                candidate.overlap.len(),
+                if candidate.overlap.len() == 1 {
+                    "file"
+                } else {
+                    "files"
+                }
+            )
+        })
+        .collect();
+
+    let selection = Select::with_theme(&theme::dialog())
+        .with_prompt("Select the commit to fix up")
+        .default(suggested)
+        .items(&items)
+        .interact_opt()?;
+
+    match selection {
+        Some(index) => Ok(candidates[index].commit.clone()),
+        None => Err(exit::aborted("Commit aborted")),
+    }
+}
+
+/// Find the ticket ID for the current branch, if `commit.ticket_pattern` is set
+fn detect_ticket(
+    repo: &git::GitRepo,
+    config: &config::Config,
+) -> anyhow::Result<Option<ticket::Ticket>> {
+    // Detached HEAD has no branch name to take a ticket from
+    match repo.get_current_branch() {
+        Ok(branch) => ticket::Ticket::detect(config, &branch),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Point out the staged files that CODEOWNERS gives to someone else
+fn print_code_owners(repo: &git::GitRepo, config: &config::Config, changes: &git::StagedChanges) {
+    let Some(owners) = repo.workdir().ok().and_then(codeowners::CodeOwners::load) else {
+        return;
+    };
+    let mut paths = changes.paths();
+    paths.extend(changes.renamed.iter().map(|(old, _)| old.clone()));
+    let areas = owners.others(&paths, &codeowners::mine(config, repo));
+    if areas.is_empty() {
+        return;
+    }
+    println!(
+        "{} {}",
+        PENCIL,
+        theme::warning("This commit touches code owned by others:")
+    );
+    let width = areas.iter().map(|area| area.owner.len()).max().unwrap_or(0);
+    for area in &areas {
+        println!(
+            "   {}  {}",
+            style(format!("{:<width$}", area.owner, width = width)).bold(),
+            style(area.file_list()).dim()
+        );
+    }
+}
+
+/// Point out the terms and misspellings that were corrected in a generated message
+fn print_corrections(corrections: &[wording::Correction]) {
+    if corrections.is_empty() {
+        return;
+    }
+    let corrections: Vec<String> = corrections
+        .iter()
+        .map(|correction| format!("{} -> {}", correction.from, correction.to))
+        .collect();
+    notice!(
+        "{} {}",
+        PENCIL,
+        style(format!("Corrected the wording: {}", corrections.join(", "))).dim()
+    );
+}
+
+/// Warn about ways `message` does not follow the commit template
+fn print_template_problems(template: &template::Template, message: &str, config: &config::Config) {
+    let problems = template.validate(message, config.commit.max_subject_length);
+    if problems.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n{} {}",
+        CROSS,
+        theme::warning("The message does not follow the commit rules:")
+    );
+    for problem in problems {
+        println!("  - {}", problem);
+    }
+}
+
+/// The commit that last changed each removed line, keyed by file and old line number
+fn removed_line_origins(
+    repo: &git::GitRepo,
+    hunks: &[git::DiffHunk],
+) -> HashMap<(String, u32), git::CommitInfo> {
+    let mut lines: BTreeMap<&str, Vec<u32>> = BTreeMap::new();
+    for line in hunks.iter().flat_map(|hunk| &hunk.lines) {
+        if line.origin == '-'
+            && let Some(lineno) = line.old_lineno
+        {
+            lines.entry(&line.path).or_default().push(lineno);
+        }
+    }
+
+    let mut origins = HashMap::new();
+    for (path, lines) in lines {
+        match repo.blame_lines(path, &lines) {
+            Ok(commits) => origins.extend(
+                commits
+                    .into_iter()
+                    .map(|(lineno, commit)| ((path.to_string(), lineno), commit)),
+            ),
+            Err(e) => println!(
+                "{}",
+                style(format!("Could not blame {}: {}", path, e)).dim()
+            ),
+        }
+    }
+    origins
+}
+
+/// Say when the prompt describes the staged changes by directory. On stderr so
+/// piped suggestions stay clean.
+fn print_large_change_notice(changes: &git::StagedChanges) {
+    if changes.is_large() {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!(
+                "{} files staged, so the prompt describes them by directory",
+                changes.stats.files_changed
+            ))
+            .dim()
+        );
+    }
+}
+
+/// The staged diff sent to the AI, cut down to `MAX_PROMPT_DIFF`. When it is
+/// longer, large files are described by their cached summaries instead, which
+/// the AI writes first in direct API mode. Notices go to stderr so piped
+/// suggestions stay clean.
+async fn diff_text(repo: &git::GitRepo, config: &config::Config) -> anyhow::Result<String> {
+    if !config.ai.compress_prompts {
+        return repo.staged_patch(MAX_PROMPT_DIFF);
+    }
+    // Read past the limit: summaries can make room for more files
+    let (patches, unread) = repo.staged_file_patches(MAX_PROMPT_DIFF * 2)?;
+    let total: usize = patches.iter().map(|patch| patch.text.len()).sum();
+    if total <= MAX_PROMPT_DIFF {
+        return Ok(git::join_patches(&patches, unread, MAX_PROMPT_DIFF, |_| {
+            None
+        }));
+    }
+
+    let mut cache = summaries::Cache::load(repo);
+    let missing = cache.missing(&patches);
+    if !missing.is_empty()
+        && config.ai_enabled()
+        && !config.use_server()
+        && !logging::showing_prompt()
+    {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!(
+                "The diff is too long for the prompt; summarizing {} large file{} first",
+                missing.len(),
+                if missing.len() == 1 { "" } else { "s" }
+            ))
+            .dim()
+        );
+        let generator = ai::CommitMessageGenerator::new(config.clone()).for_command("summaries");
+        match generator
+            .complete(
+                summaries::SYSTEM_PROMPT,
+                &summaries::build_prompt(&missing, MAX_PROMPT_DIFF),
+                1000,
+                0.2,
+            )
+            .await
+        {
+            Ok(reply) => {
+                for (key, summary) in summaries::parse_reply(&reply, &missing) {
+                    cache.insert(key, summary);
+                }
+            }
+            Err(e) => logging::log(|| format!("Could not summarize large files: {:#}", e)),
+        }
+    }
+
+    let found = cache.take(&patches);
+    if let Err(e) = cache.save(repo) {
+        logging::log(|| format!("Could not save file summaries: {:#}", e));
+    }
+    if !found.is_empty() {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!(
+                "{} large file{} described by {} summar{} to keep the prompt short",
+                found.len(),
+                if found.len() == 1 { " is" } else { "s are" },
+                if found.len() == 1 { "a" } else { "their" },
+                if found.len() == 1 { "y" } else { "ies" }
+            ))
+            .dim()
+        );
+    }
+    Ok(git::join_patches(
+        &patches,
+        unread,
+        MAX_PROMPT_DIFF,
+        |patch| found.get(&patch.key).cloned(),
+    ))
+}
+
+/// Write the annotated tag message for the tag `name`: by the AI in direct API
+/// mode, otherwise a list of the changes
+async fn generate_tag_message(
+    config: &config::Config,
+    name: &str,
+    notes: &release::ReleaseNotes,
+    no_ai: bool,
+) -> String {
+    let plain = version::plain_tag_message(name, &notes.changes);
+    if no_ai || !config.ai_enabled() || config.use_server() {
+        return plain;
+    }
+
+    let mut sp = Progress::new(Spinners::Dots12, "Writing tag message...".into());
+    let generator = ai::CommitMessageGenerator::new(config.clone()).for_command("tag");
+    let prompt = format!("Version: {}\n\n{}", name, notes.to_markdown());
+    match generator
+        .complete(version::TAG_PROMPT, &prompt, 500, 0.3)
+        .await
+    {
+        Ok(message) => {
+            sp.stop_with_message(format!(
+                "{} {}",
+                CHECKMARK,
+                theme::added("Tag message ready!")
+            ));
+            message.trim().to_string()
+        }
+        Err(e) => {
+            sp.stop_with_message(format!(
+                "{} {}",
+                CROSS,
+                theme::removed("Failed to write tag message")
+            ));
+            println!("Error: {}. Listing the changes instead.", e);
+            plain
+        }
+    }
+}
+
+/// What `gyst ui` needs to turn staged changes into commits
+struct CommitContext<'a> {
+    repo: &'a git::GitRepo,
+    config: &'a config::Config,
+    template: &'a template::Template,
+    hints: &'a [String],
+    ticket: Option<&'a ticket::Ticket>,
+    trailers: &'a [String],
+}
+
+/// Run the `gyst ui` dashboard until it is closed, generating suggestions in
+/// the background so the screen stays responsive. Returns the commits made.
+async fn run_dashboard(
+    terminal: &mut ratatui::DefaultTerminal,
+    dashboard: &mut dashboard::Dashboard,
+    context: &CommitContext<'_>,
+    count: u8,
+    force: bool,
+) -> anyhow::Result<Vec<(git2::Oid, String)>> {
+    use ratatui::crossterm::event::{self, Event};
+
+    let repo = context.repo;
+    let load = |dashboard: &mut dashboard::Dashboard| -> anyhow::Result<()> {
+        let (patches, _) = repo.staged_file_patches(MAX_PROMPT_DIFF)?;
+        dashboard.set_changes(&repo.get_staged_changes()?, patches);
+        Ok(())
+    };
+    load(dashboard)?;
+
+    let mut committed = Vec::new();
+    // The staged snapshot the pending or shown suggestions describe
+    let mut generated_for = None;
+    let mut pending: Option<tokio::task::JoinHandle<anyhow::Result<Vec<String>>>> = None;
+    loop {
+        terminal.draw(|frame| dashboard.draw(frame))?;
+
+        if let Some(task) = pending.take_if(|task| task.is_finished()) {
+            match task.await? {
+                Ok(messages) => dashboard.set_suggestions(
+                    messages
+                        .iter()
+                        .map(|message| {
+                            let message = wording::fix(&context.config.commit, message).0;
+                            let message = finish_message(
+                                &context.config.commit,
+                                &message,
+                                context.ticket,
+                                None,
+                                context.trailers,
+                            );
+                            dashboard::Suggestion {
+                                problems: context
+                                    .template
+                                    .validate(&message, context.config.commit.max_subject_length),
+                                message,
+                            }
+                        })
+                        .collect(),
+                ),
+                Err(e) => dashboard.set_error(format!("Failed to generate suggestions: {:#}", e)),
+            }
+            continue;
+        }
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        match dashboard.handle_key(key) {
+            None => {}
+            Some(dashboard::Action::Quit) => break,
+            Some(dashboard::Action::Reload) => {
+                load(dashboard)?;
+                dashboard.set_status("Reloaded the staged changes");
+            }
+            Some(dashboard::Action::Generate) => {
+                generated_for = Some(repo.staged_snapshot()?);
+                let changes = repo.get_staged_changes()?;
+                let diff = repo.staged_patch(MAX_PROMPT_DIFF)?;
+                let config = context.config.clone();
+                let template = context.template.clone();
+                let hints = context.hints.to_vec();
+                pending = Some(tokio::spawn(async move {
+                    generate_suggestions(&config, &template, &changes, &diff, &hints, count).await
+                }));
+                dashboard.set_busy();
+            }
+            Some(dashboard::Action::Commit(message)) => {
+                if generated_for != Some(repo.staged_snapshot()?) {
+                    load(dashboard)?;
+                    dashboard.set_error(
+                        "The staged changes changed since the suggestions were made. Press g to generate new ones.",
+                    );
+                    continue;
+                }
+                if let Err(e) =
+                    confirm_protected_branch(repo, context.config, &message, force, false)
+                {
+                    dashboard.set_error(e.to_string());
+                    continue;
+                }
+                let oid = repo.create_commit(&message)?;
+                load(dashboard)?;
+                dashboard.set_success(format!(
+                    "Committed {} {}",
+                    &oid.to_string()[..7],
+                    message.lines().next().unwrap_or_default()
+                ));
+                committed.push((oid, message));
+            }
+        }
+    }
+    Ok(committed)
+}
+
+/// Commit the working tree to the shadow branch `branch` if it changed since
+/// the last snapshot
+async fn snapshot_wip(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    template: &template::Template,
+    branch: &str,
+) -> anyhow::Result<()> {
+    let tree = repo.worktree_snapshot()?;
+    let (changes, diff) = repo.tree_changes(repo.wip_base(branch)?, tree, MAX_PROMPT_DIFF)?;
+    if changes.stats.files_changed == 0 {
+        return Ok(());
+    }
+
+    let message = wip_message(config, template, &changes, &diff).await;
+    let oid = repo.commit_wip(branch, tree, &message)?;
+    println!(
+        "{} {} {} {}",
+        style(chrono::Local::now().format("%H:%M:%S")).dim(),
+        style(&oid.to_string()[..7]).dim(),
+        theme::added(message.lines().next().unwrap_or_default()),
+        style(format!(
+            "({} file{}, +{} -{})",
+            changes.stats.files_changed,
+            if changes.stats.files_changed == 1 {
+                ""
+            } else {
+                "s"
+            },
+            changes.stats.insertions,
+            changes.stats.deletions
+        ))
+        .dim()
+    );
+    Ok(())
+}
+
+/// The message for a snapshot. A failed request is not worth stopping
+/// gyst watch for, so the template-based message stands in for it.
+async fn wip_message(
+    config: &config::Config,
+    template: &template::Template,
+    changes: &git::StagedChanges,
+    diff: &str,
+) -> String {
+    let offline = || template.convert_conventional(&offline::generate_message(changes));
+    if !config.ai_enabled() {
+        return offline();
+    }
+    let generated = if config.use_server() {
+        server::ServerClient::new(config.clone())
+            .generate_message(changes, diff, &[], &mut |_| {})
+            .await
+    } else {
+        ai::CommitMessageGenerator::new(config.clone())
+            .for_command("watch")
+            .generate_message(changes, diff, template, &[])
+            .await
+    };
+    let message = generated.unwrap_or_else(|e| {
+        enotice!(
+            "{} {}",
+            CROSS,
+            theme::warning(format!("{}; using a template-based message", e))
+        );
+        offline()
+    });
+    layout::apply(&message, config.commit.body_width)
+}
+
+/// Extra instructions for the AI: the ticket reference, the work item from
+/// `--link` or else the Jira or forge issue named in the branch, what a documentation-only change documents or how the
+/// change affects a library's public API and, for the
+/// server (which has its own system prompt), the template rules
+async fn prompt_hints(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    template: &template::Template,
+    ticket: Option<&ticket::Ticket>,
+    link: Option<&link::WorkItem>,
+) -> anyhow::Result<Vec<String>> {
+    let mut hints: Vec<String> = ticket.iter().map(|t| t.prompt_hint()).collect();
+    hints.extend(wording::prompt_hint(&config.commit));
+    hints.extend(feedback::prompt_hint(config));
+    if let Some(item) = link {
+        hints.push(item.prompt_hint());
+    } else if let Some(issue) = jira_issue(config, ticket).await {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!("Using Jira issue {}: {}", issue.key, issue.summary)).dim()
+        );
+        hints.push(issue.prompt_hint());
+    } else if let Some(issue) = linear_issue(config, ticket).await {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!(
+                "Using Linear issue {}: {}",
+                issue.identifier, issue.title
+            ))
+            .dim()
+        );
+        hints.push(issue.prompt_hint());
+    }
+    if link.is_none()
+        && let Some(issue) = linked_issue(repo, config).await
+    {
+        // On stderr so piped suggestions stay clean
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!(
+                "Using {} {} {}: {}",
+                issue.forge,
+                issue.kind,
+                issue.reference(),
+                issue.title
+            ))
+            .dim()
+        );
+        hints.push(issue.prompt_hint());
+    }
+    if let Ok(branch) = repo.get_current_branch()
+        && let Some(description) = repo.branch_description(&branch)
+    {
+        enotice!(
+            "{} {}",
+            PENCIL,
+            style(format!("Using the description of branch {}", branch)).dim()
+        );
+        hints.push(format!(
+            "The author describes the purpose of this branch as:\n{}",
+            description
+        ));
+    }
+    if let Some(docs) = docs::DocsChange::staged(repo)? {
+        hints.push(docs.prompt_hint());
+    } else if let Some(tests) = testing::TestChange::staged(repo)? {
+        hints.push(tests.prompt_hint());
+    } else if let Some(api) = semver::ApiChanges::staged(repo)? {
+        hints.extend(api.prompt_hint());
+    }
+    if template.uses_scopes()
+        && let Some(workspace) = template.workspace()
+    {
+        let changes = repo.get_staged_changes()?;
+        let touched = workspace.touched(
+            changes
+                .added
+                .iter()
+                .chain(&changes.modified)
+                .chain(&changes.deleted)
+                .chain(changes.renamed.iter().map(|(_, new)| new))
+                .chain(changes.copied.iter().map(|(_, new)| new))
+                .map(String::as_str),
+        );
+        hints.push(workspace.prompt_hint(&touched));
+    }
+    if let Some(language) = config.message_language() {
+        hints.push(format!(
+            "Write the commit message in {}. Keep type prefixes and ticket IDs unchanged.",
+            language
+        ));
+    }
+    if config.use_server() {
+        hints.extend(template.prompt_hint());
+    }
+    Ok(hints)
+}
+
+/// The GitHub or GitLab issue named in the current branch or, for branches
+/// without an issue number, the branch's open pull request, if `forge.link_issues`
+/// is on and it can be fetched. Lookup failures are not worth interrupting a commit for.
+async fn linked_issue(repo: &git::GitRepo, config: &config::Config) -> Option<forge::Issue> {
+    if !config.forge.link_issues {
+        return None;
+    }
+    let branch = repo.get_current_branch().ok()?;
+    let host = forge::Forge::from_repo(config, repo).ok()??;
+    match forge::issue_number_from_branch(&branch) {
+        Some(number) => host.issue(number).await.ok(),
+        None => host.pull_request_for_branch(&branch).await.ok()?,
+    }
+}
+
+/// The Jira issue for the branch's ticket, if Jira is configured and the issue
+/// can be fetched. Lookup failures are not worth interrupting a commit for.
+async fn jira_issue(
+    config: &config::Config,
+    ticket: Option<&ticket::Ticket>,
+) -> Option<jira::JiraIssue> {
+    let jira = jira::Jira::from_config(config).ok()??;
+    jira.issue(&ticket?.id).await.ok()
+}
+
+/// The Linear issue for the branch's ticket, if `linear.link_issues` is on,
+/// Linear is configured and the issue can be fetched
+async fn linear_issue(
+    config: &config::Config,
+    ticket: Option<&ticket::Ticket>,
+) -> Option<linear::LinearIssue> {
+    if !config.linear.link_issues {
+        return None;
+    }
+    let linear = linear::Linear::from_config(config).ok()??;
+    linear.issue(&ticket?.id).await.ok()
+}
+
+/// The commits to split the staged changes into: proposed by the AI in direct
+/// API mode, otherwise grouped by directory. AI failures fall back to directories.
+async fn propose_split(
+    config: &config::Config,
+    changes: &git::StagedChanges,
+    diff: &str,
+    hints: &[String],
+) -> Vec<split::Group> {
+    if !config.ai_enabled() {
+        return split::by_topic(changes);
+    }
+    if config.use_server() {
+        notice!(
+            "{} {}",
+            PENCIL,
+            style("AI commit splitting needs direct API mode ('gyst config --use-server false'). Grouping files by directory instead.")
+                .dim()
+        );
+        return split::by_topic(changes);
+    }
+    if changes.is_large() {
+        notice!(
+            "{} {}",
+            PENCIL,
+            style("Too many files for the AI to sort. Grouping files by directory instead.").dim()
+        );
+        return split::by_topic(changes);
+    }
+
+    let mut sp = Progress::new(
+        Spinners::Dots12,
+        "Grouping the changes into commits...".into(),
+    );
+    let generator = ai::CommitMessageGenerator::new(config.clone()).for_command("split");
+    match generator
+        .complete(
+            split::SYSTEM_PROMPT,
+            &split::build_prompt(changes, diff, hints),
+            800,
+            0.2,
+        )
+        .await
+    {
+        Ok(reply) => {
+            let groups = split::parse_groups(&reply, changes);
+            sp.stop_with_message(format!(
+                "{} {}\n",
+                CHECKMARK,
+                theme::added(format!(
+                    "Proposed {} commit{}",
+                    groups.len(),
+                    if groups.len() == 1 { "" } else { "s" }
+                ))
+            ));
+            groups
+        }
+        Err(e) => {
+            sp.stop_with_message(format!(
+                "{} {}\n",
+                CROSS,
+                theme::removed("Failed to group the changes")
+            ));
+            println!("Error: {}. Grouping files by directory instead.\n", e);
+            split::by_topic(changes)
+        }
+    }
+}
+
+/// Commit the files of each group with its message, one commit after the
+/// other, and list them. Returns the messages as committed.
+fn commit_groups(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    groups: &[split::Group],
+    changes: &git::StagedChanges,
+    snapshot: git2::Oid,
+    ticket: Option<&ticket::Ticket>,
+    trailers: &[String],
+) -> anyhow::Result<Vec<String>> {
+    println!();
+    let mut messages = Vec::new();
+    for group in groups {
+        let message = finish_message(&config.commit, &group.message, ticket, None, trailers);
+        let oid = repo.commit_staged_paths(snapshot, &group.paths(changes), &message)?;
+        notice!(
+            "{} {} {}",
+            CHECKMARK,
+            style(&oid.to_string()[..7]).dim(),
+            theme::added(message.lines().next().unwrap_or_default())
+        );
+        messages.push(message);
+    }
+    notice!(
+        "\n{} {} {}\n",
+        CHECKMARK,
+        theme::added(format!(
+            "{} commit{} created successfully!",
+            groups.len(),
+            if groups.len() == 1 { "" } else { "s" }
+        ))
+        .bold(),
+        SPARKLE
+    );
+    Ok(messages)
+}
+
+/// `gyst suggest --by-package`: show how the staged changes fall into the
+/// monorepo's packages, with a message for each package, and offer to commit
+/// them one package at a time. Returns the messages committed, or `None` to
+/// go on with one commit for all of them.
+async fn commit_by_package(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    template: &template::Template,
+    hints: &[String],
+    ticket: Option<&ticket::Ticket>,
+    trailers: &[String],
+    force: bool,
+) -> anyhow::Result<Option<Vec<String>>> {
+    let Some(workspace) = template.workspace() else {
+        notice!(
+            "{} {}",
+            PENCIL,
+            style("No monorepo packages found; suggesting messages for one commit.").dim()
+        );
+        return Ok(None);
+    };
+    let snapshot = repo.staged_snapshot()?;
+    let changes = &repo.get_staged_changes()?;
+    let mut groups = split::by_package(changes, workspace);
+    if groups.len() < 2 {
+        notice!(
+            "{} {}",
+            PENCIL,
+            style("The staged changes are all in one package; suggesting messages for one commit.")
+                .dim()
+        );
+        return Ok(None);
+    }
+
+    // Each package's message is generated from its own part of the diff,
+    // with its own name as the scope
+    let (patches, _) = repo.staged_file_patches(MAX_PROMPT_DIFF)?;
+    let mut group_patches: Vec<Vec<git::FilePatch>> = groups.iter().map(|_| Vec::new()).collect();
+    for patch in patches {
+        if let Some(i) = groups
+            .iter()
+            .position(|group| group.files.contains(&patch.path))
+        {
+            group_patches[i].push(patch);
+        }
+    }
+    let touched = workspace.touched(changes.paths().iter().map(String::as_str));
+    let combined_hint = workspace.prompt_hint(&touched);
+
+    let mut sp = Progress::new(
+        Spinners::Dots12,
+        format!("Generating messages for {} commits...", groups.len()),
+    );
+    for (group, patches) in groups.iter_mut().zip(&group_patches) {
+        group.message = template.convert_conventional(&group.message);
+        let diff = git::join_patches(patches, 0, MAX_PROMPT_DIFF, |_| None);
+        let mut group_hints: Vec<String> = hints
+            .iter()
+            .filter(|hint| **hint != combined_hint)
+            .cloned()
+            .collect();
+        if template.uses_scopes() {
+            group_hints.push(
+                workspace.prompt_hint(&workspace.touched(group.files.iter().map(String::as_str))),
+            );
+        }
+        let generated = generate_suggestions(
+            config,
+            template,
+            &changes.only(&group.files),
+            &diff,
+            &group_hints,
+            1,
+        )
+        .await;
+        match generated {
+            Ok(messages) => {
+                if let Some(message) = messages.first() {
+                    group.message = wording::fix(&config.commit, message).0;
+                }
+            }
+            Err(e) => {
+                sp.stop_with_message(format!(
+                    "{} {}\n",
+                    CROSS,
+                    theme::removed("Failed to generate messages")
+                ));
+                return Err(e);
+            }
+        }
+    }
+    sp.stop_with_message(format!(
+        "{} {} {}",
+        CHECKMARK,
+        theme::added("Messages generated!"),
+        SPARKLE
+    ));
+
+    loop {
+        print_groups("The staged changes span several packages:", &groups);
+        let actions = [
+            format!("Create {} commits, one per package", groups.len()),
+            "Create one commit for all of them".to_string(),
+            "Rearrange the commits".to_string(),
+            "Cancel".to_string(),
+        ];
+        let Some(action) = Select::with_theme(&theme::dialog())
+            .with_prompt("How should they be committed?")
+            .items(&actions)
+            .default(0)
+            .interact_opt()?
+        else {
+            return Err(exit::aborted("Commit aborted"));
+        };
+        match action {
+            0 => break,
+            1 => return Ok(None),
+            2 => match edit_split(groups.clone(), changes, template)? {
+                Some(edited) => {
+                    groups = edited;
+                    break;
+                }
+                None => continue,
+            },
+            _ => return Err(exit::aborted("Commit aborted")),
+        }
+    }
+    if repo.staged_snapshot()? != snapshot {
+        return Err(anyhow::anyhow!(
+            "The staged changes changed while you were choosing. Run 'gyst suggest --by-package' again."
+        ));
+    }
+    for group in &groups {
+        print_template_problems(template, &group.message, config);
+    }
+    confirm_protected_branch(repo, config, &groups[0].message, force, true)?;
+
+    let messages = commit_groups(repo, config, &groups, changes, snapshot, ticket, trailers)?;
+    Ok(Some(messages))
+}
+
+/// List the commits `groups` would make, with their files, under `heading`
+fn print_groups(heading: &str, groups: &[split::Group]) {
+    println!("\n{} {}", SPARKLE, theme::prompt(heading).bold());
+    for (i, group) in groups.iter().enumerate() {
+        println!(
+            "  {}. {}",
+            i + 1,
+            theme::added(group.message.lines().next().unwrap_or_default())
+        );
+        for file in &group.files {
+            println!("       {}", style(file).dim());
+        }
+    }
+    println!();
+}
+
+/// Show the proposed commits and let the user move files between them or edit
+/// their messages. Returns `None` if they cancel.
+fn edit_split(
+    mut groups: Vec<split::Group>,
+    changes: &git::StagedChanges,
+    template: &template::Template,
+) -> anyhow::Result<Option<Vec<split::Group>>> {
+    loop {
+        print_groups("Proposed commits:", &groups);
+
+        let subjects: Vec<String> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, group)| {
+                format!(
+                    "{}. {}",
+                    i + 1,
+                    group.message.lines().next().unwrap_or_default()
+                )
+            })
+            .collect();
+        let actions = [
+            format!(
+                "Create {} commit{}",
+                groups.len(),
+                if groups.len() == 1 { "" } else { "s" }
+            ),
+            "Move files to another commit".to_string(),
+            "Edit a commit message".to_string(),
+            "Cancel".to_string(),
+        ];
+        let Some(action) = Select::with_theme(&theme::dialog())
+            .with_prompt("What next?")
+            .items(&actions)
+            .default(0)
+            .interact_opt()?
+        else {
+            return Ok(None);
+        };
+
+        match action {
+            0 => return Ok(Some(groups)),
+            1 => {
+                let files: Vec<(usize, &String)> = groups
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, group)| group.files.iter().map(move |file| (i, file)))
+                    .collect();
+                let labels: Vec<String> = files
+                    .iter()
+                    .map(|(i, file)| format!("{} (commit {})", file, i + 1))
+                    .collect();
+                let Some(selection) = MultiSelect::with_theme(&theme::dialog())
+                    .with_prompt("Files to move (space to select, enter to confirm)")
+                    .items(&labels)
+                    .interact_opt()?
+                else {
+                    continue;
+                };
+                if selection.is_empty() {
+                    continue;
+                }
+                let moving: Vec<String> = selection
+                    .iter()
+                    .map(|&index| files[index].1.clone())
+                    .collect();
+
+                let mut targets = subjects.clone();
+                targets.push("A new commit".to_string());
+                let Some(target) = Select::with_theme(&theme::dialog())
+                    .with_prompt("Move them to")
+                    .items(&targets)
+                    .default(0)
+                    .interact_opt()?
+                else {
+                    continue;
+                };
+                let mut message = String::new();
+                if target == groups.len() {
+                    let proposed = template.convert_conventional(
+                        &split::Group::offline(changes, moving.clone()).message,
+                    );
+                    message = Input::with_theme(&theme::dialog())
+                        .with_prompt("Message for the new commit")
+                        .with_initial_text(proposed)
+                        .interact_text()?;
+                }
+                split::move_files(&mut groups, &moving, target, message.trim());
+            }
+            2 => {
+                let Some(index) = Select::with_theme(&theme::dialog())
+                    .with_prompt("Which commit?")
+                    .items(&subjects)
+                    .default(0)
+                    .interact_opt()?
+                else {
+                    continue;
+                };
+                let group = &mut groups[index];
+                let (subject, body) = match group.message.split_once('\n') {
+                    Some((subject, body)) => (subject.to_string(), Some(body.to_string())),
+                    None => (group.message.clone(), None),
+                };
+                let subject: String = Input::with_theme(&theme::dialog())
+                    .with_prompt("Subject")
+                    .with_initial_text(subject)
+                    .interact_text()?;
+                group.message = match body {
+                    Some(body) => format!("{}\n{}", subject.trim(), body),
+                    None => subject.trim().to_string(),
+                };
+            }
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Look up the work item for `--link`, failing the commit if it cannot be found
+async fn fetch_work_item(
+    repo: &git::GitRepo,
+    config: &config::Config,
+    reference: &str,
+) -> anyhow::Result<link::WorkItem> {
+    let mut sp = Progress::new(Spinners::Dots9, format!("Looking up {}...", reference));
+    match link::WorkItem::fetch(config, repo, reference).await {
+        Ok(item) => {
+            sp.stop_with_message(format!(
+                "{} {}",
+                CHECKMARK,
+                theme::added(format!(
+                    "Linking to {} issue {}: {}",
+                    item.tracker.name(),
+                    item.reference,
+                    item.title
+                ))
+            ));
+            Ok(item)
+        }
+        Err(e) => {
+            sp.stop_with_message(format!(
+                "{} {}",
+                CROSS,
+                theme::removed(format!("Could not link {}", reference))
+            ));
+            Err(e)
+        }
+    }
+}
+
+/// Add the ticket reference, the `--link` footer and trailers to a generated
+/// message, and lay it out following `commit.body_width`
+fn finish_message(
+    config: &config::CommitConfig,
+    message: &str,
+    ticket: Option<&ticket::Ticket>,
+    link: Option<&link::WorkItem>,
+    trailers: &[String],
+) -> String {
+    let message = match ticket {
+        Some(ticket) => ticket.apply(message),
+        None => message.to_string(),
+    };
+    let message = match link {
+        Some(item) => item.apply(&message),
+        None => message,
+    };
+    layout::apply(&trailer::append(&message, trailers), config.body_width)
+}
+
+/// Generate `count` commit message suggestions offline, through the server, or
+/// directly with the AI provider, depending on the configuration
+async fn generate_suggestions(
+    config: &config::Config,
+    template: &template::Template,
+    changes: &git::StagedChanges,
+    diff: &str,
+    hints: &[String],
+    count: u8,
+) -> anyhow::Result<Vec<String>> {
+    if !config.ai_enabled() {
+        return Ok(offline::generate_suggestions(changes, count)
+            .iter()
+            .map(|message| template.convert_conventional(message))
+            .collect());
+    }
+
+    if config.use_server() {
+        let server_client = server::ServerClient::new(config.clone());
+        if let Err(e) = server_client.health_check().await {
+            return Err(exit::provider(anyhow::anyhow!(
+                "Failed to connect to server: {}. Check server URL or use direct API mode with 'gyst config --use-server false'",
+                e
+            )));
+        }
+        return server_client
+            .generate_suggestions(changes, diff, hints, count)
+            .await;
+    }
+
+    let generator = ai::CommitMessageGenerator::new(config.clone());
+    generator
+        .generate_suggestions(changes, diff, template, hints, count)
+        .await
+}
+
+/// What to do when the staged changes moved after the message was generated
+enum StaleMessage {
+    Regenerate,
+    Keep,
+    Abort,
+}
+
+fn ask_about_stale_message() -> anyhow::Result<StaleMessage> {
+    println!(
+        "\n{} {}",
+        CROSS,
+        theme::warning("The staged changes were modified after the message was generated.")
+    );
+
+    let choice = Select::with_theme(&theme::dialog())
+        .with_prompt("What would you like to do?")
+        .default(0)
+        .items(&[
+            "Regenerate the message for the current changes",
+            "Commit the current changes with this message anyway",
+            "Abort",
+        ])
+        .interact_opt()?;
+
+    Ok(match choice {
+        Some(0) => StaleMessage::Regenerate,
+        Some(1) => StaleMessage::Keep,
+        _ => StaleMessage::Abort,
+    })
 }
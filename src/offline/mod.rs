@@ -0,0 +1,522 @@
+use crate::git::StagedChanges;
+use crate::testing;
+use regex::Regex;
+use std::path::Path;
+
+/// Manifests whose version line a release bumps, and the lock files that follow them
+const VERSION_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "package.json",
+    "package-lock.json",
+    "pyproject.toml",
+];
+
+/// A canned answer for `gyst explain` when AI is unavailable, written in the
+/// same COMMAND/EXPLANATION/NOTE format the AI is asked to use
+struct Recipe {
+    keywords: &'static [&'static str],
+    answer: &'static str,
+}
+
+const RECIPES: &[Recipe] = &[
+    Recipe {
+        keywords: &["undo", "last", "commit"],
+        answer: "To undo the last commit but keep its changes staged:
+COMMAND: git reset --soft HEAD~1
+EXPLANATION: Moves the branch back one commit and leaves the changes in the index
+NOTE: CAREFUL: if the commit was already pushed, use git revert instead",
+    },
+    Recipe {
+        keywords: &["revert", "pushed", "public"],
+        answer: "To undo a commit that others may already have:
+COMMAND: git revert <commit>
+EXPLANATION: Creates a new commit that reverses the changes of <commit> without rewriting history",
+    },
+    Recipe {
+        keywords: &["discard", "changes", "file", "restore"],
+        answer: "To throw away uncommitted changes to a file:
+COMMAND: git restore <file>
+EXPLANATION: Resets the file in the working tree to its staged (or committed) version
+NOTE: WARNING: the discarded changes cannot be recovered",
+    },
+    Recipe {
+        keywords: &["unstage", "remove", "staged", "index"],
+        answer: "To unstage a file while keeping your edits:
+COMMAND: git restore --staged <file>
+EXPLANATION: Removes the file from the index; the working tree is not touched",
+    },
+    Recipe {
+        keywords: &["create", "new", "branch", "switch"],
+        answer: "To create a branch and switch to it:
+COMMAND: git switch -c <branch>
+EXPLANATION: Creates <branch> at the current commit and checks it out",
+    },
+    Recipe {
+        keywords: &["delete", "remove", "branch"],
+        answer: "To delete a branch that has been merged:
+COMMAND: git branch -d <branch>
+EXPLANATION: Deletes the local branch; refuses if it has unmerged work
+COMMAND: git push origin --delete <branch>
+EXPLANATION: Deletes the branch on the remote
+NOTE: CAREFUL: git branch -D deletes the branch even if it is not merged",
+    },
+    Recipe {
+        keywords: &["rename", "branch"],
+        answer: "To rename the current branch:
+COMMAND: git branch -m <new-name>
+EXPLANATION: Renames the branch you are on; push it again and delete the old name on the remote if needed",
+    },
+    Recipe {
+        keywords: &["amend", "change", "fix", "last", "commit", "message"],
+        answer: "To change the most recent commit:
+COMMAND: git commit --amend
+EXPLANATION: Replaces the last commit with one containing the currently staged changes and an edited message
+NOTE: CAREFUL: do not amend commits that were already pushed to a shared branch",
+    },
+    Recipe {
+        keywords: &["stash", "save", "later", "temporarily", "shelve"],
+        answer: "To set work aside temporarily:
+COMMAND: git stash push -m \"<description>\"
+EXPLANATION: Saves your uncommitted changes and cleans the working tree
+COMMAND: git stash pop
+EXPLANATION: Re-applies the most recent stash and removes it from the stash list",
+    },
+    Recipe {
+        keywords: &["merge", "conflict", "conflicts", "resolve"],
+        answer: "To resolve merge conflicts:
+COMMAND: git status
+EXPLANATION: Lists the files with conflicts
+COMMAND: git add <file>
+EXPLANATION: Marks a file as resolved after you have edited the conflict markers out
+COMMAND: git merge --continue
+EXPLANATION: Completes the merge once all conflicts are resolved (use git merge --abort to give up)",
+    },
+    Recipe {
+        keywords: &["rebase", "update", "branch", "main", "onto"],
+        answer: "To bring your branch up to date with main:
+COMMAND: git fetch origin
+EXPLANATION: Downloads the latest commits from the remote
+COMMAND: git rebase origin/main
+EXPLANATION: Replays your commits on top of the latest main
+NOTE: CAREFUL: rebasing rewrites history; only force-push branches nobody else uses",
+    },
+    Recipe {
+        keywords: &["squash", "combine", "commits"],
+        answer: "To combine several commits into one:
+COMMAND: git rebase -i HEAD~<n>
+EXPLANATION: Opens an editor where you can mark commits as squash or fixup
+NOTE: CAREFUL: this rewrites history",
+    },
+    Recipe {
+        keywords: &["history", "log", "see", "show", "commits"],
+        answer: "To browse the history:
+COMMAND: git log --oneline --graph --decorate
+EXPLANATION: Shows one line per commit with branch structure and labels",
+    },
+    Recipe {
+        keywords: &["who", "blame", "changed", "line", "wrote"],
+        answer: "To find who last changed each line of a file:
+COMMAND: git blame <file>
+EXPLANATION: Annotates every line with the commit and author that last modified it",
+    },
+    Recipe {
+        keywords: &["cherry", "pick", "apply", "commit", "another"],
+        answer: "To copy a single commit onto the current branch:
+COMMAND: git cherry-pick <commit>
+EXPLANATION: Applies the changes from <commit> as a new commit",
+    },
+    Recipe {
+        keywords: &["tag", "release", "version"],
+        answer: "To tag a release:
+COMMAND: git tag -a v<version> -m \"Release v<version>\"
+EXPLANATION: Creates an annotated tag at the current commit
+COMMAND: git push origin v<version>
+EXPLANATION: Publishes the tag to the remote",
+    },
+    Recipe {
+        keywords: &["recover", "lost", "deleted", "reflog"],
+        answer: "To find commits that seem lost:
+COMMAND: git reflog
+EXPLANATION: Lists every position HEAD has been at, including commits from deleted branches
+COMMAND: git branch <name> <commit>
+EXPLANATION: Recreates a branch pointing at a commit found in the reflog",
+    },
+    Recipe {
+        keywords: &["remote", "url", "origin", "change"],
+        answer: "To point origin at a different URL:
+COMMAND: git remote set-url origin <url>
+EXPLANATION: Changes where fetch and push go for the origin remote",
+    },
+];
+
+/// Answer a `gyst explain` request from the built-in knowledge base
+pub fn explain(description: &str) -> String {
+    let description = description.to_lowercase();
+    let words: Vec<&str> = description
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let best = RECIPES
+        .iter()
+        .map(|recipe| {
+            let score = recipe
+                .keywords
+                .iter()
+                .filter(|keyword| words.contains(keyword))
+                .count();
+            (score, recipe)
+        })
+        .filter(|(score, _)| *score >= 2)
+        .max_by_key(|(score, _)| *score);
+
+    match best {
+        Some((_, recipe)) => recipe.answer.to_string(),
+        None => "No offline answer matches that description. Try rephrasing it (e.g. \"undo last commit\") or see 'git help <command>'.".to_string(),
+    }
+}
+
+/// Generate a conventional commit message from the staged file list alone
+pub fn generate_message(changes: &StagedChanges) -> String {
+    let kind = infer_type(changes);
+    match infer_scope(changes) {
+        Some(scope) => format!("{}({}): {}", kind, scope, describe(changes, Some(&scope))),
+        None => format!("{}: {}", kind, describe(changes, None)),
+    }
+}
+
+/// A message for a change too small to need the AI, or `None`. Recognized are
+/// version bumps in package manifests, a single misspelled word fixed in one
+/// file, and new `.gitignore` entries.
+pub fn trivial_message(changes: &StagedChanges, diff: &str) -> Option<String> {
+    let (removed, added) = changed_lines(diff);
+    let files = all_paths(changes);
+    let only_modified = changes.modified.len() == files.len();
+
+    if only_modified
+        && files
+            .iter()
+            .all(|path| VERSION_FILES.contains(&file_name(path).as_str()))
+    {
+        return version_bump(&files, &removed, &added)
+            .map(|version| format!("chore: bump version to {}", version));
+    }
+
+    if files.len() == 1 && files[0].ends_with(".gitignore") && removed.is_empty() {
+        let patterns: Vec<&str> = added
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        return match patterns.as_slice() {
+            [] => None,
+            [pattern] => Some(format!("chore: ignore {}", pattern)),
+            _ => Some(format!(
+                "chore: add {} entries to .gitignore",
+                patterns.len()
+            )),
+        };
+    }
+
+    if only_modified
+        && files.len() == 1
+        && removed.len() == 1
+        && added.len() == 1
+        && is_typo_fix(&removed[0], &added[0])
+    {
+        let name = file_name(&files[0]);
+        return Some(if is_doc(&files[0]) {
+            format!("docs: fix typo in {}", name)
+        } else {
+            format!("fix: correct typo in {}", name)
+        });
+    }
+    None
+}
+
+/// The removed and added lines of a patch, without the `-`/`+` markers
+fn changed_lines(diff: &str) -> (Vec<String>, Vec<String>) {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            continue;
+        }
+        if let Some(line) = line.strip_prefix('-') {
+            removed.push(line.to_string());
+        } else if let Some(line) = line.strip_prefix('+') {
+            added.push(line.to_string());
+        }
+    }
+    (removed, added)
+}
+
+/// The new version, if every changed line is a `version` line and at least
+/// one manifest (not only a lock file) changed
+fn version_bump(files: &[String], removed: &[String], added: &[String]) -> Option<String> {
+    let has_manifest = files
+        .iter()
+        .any(|path| !file_name(path).ends_with(".lock") && !path.ends_with("-lock.json"));
+    let pattern = Regex::new(r#"^\s*"?version"?\s*[=:]\s*"([^"]+)",?\s*$"#).ok()?;
+    if !has_manifest || added.is_empty() || removed.len() != added.len() {
+        return None;
+    }
+    if !removed.iter().all(|line| pattern.is_match(line)) {
+        return None;
+    }
+    let mut versions = added
+        .iter()
+        .map(|line| pattern.captures(line).map(|caps| caps[1].to_string()));
+    let version = versions.next()??;
+    // Lock files also list dependencies; they must all move to the same version
+    versions
+        .all(|other| other.as_deref() == Some(version.as_str()))
+        .then_some(version)
+}
+
+/// Whether `after` is `before` with one misspelled word corrected
+fn is_typo_fix(before: &str, after: &str) -> bool {
+    let before: Vec<&str> = before.split_whitespace().collect();
+    let after: Vec<&str> = after.split_whitespace().collect();
+    if before.len() != after.len() {
+        return false;
+    }
+    let differing: Vec<(&str, &str)> = before
+        .iter()
+        .zip(&after)
+        .filter(|(a, b)| a != b)
+        .map(|(a, b)| (*a, *b))
+        .collect();
+    let [(old, new)] = differing.as_slice() else {
+        return false;
+    };
+    let word = |text: &str| {
+        text.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_string()
+    };
+    let (old, new) = (word(old), word(new));
+    // Short words get one edit, otherwise too many real word changes would pass
+    let allowed = if old.chars().count() <= 4 { 1 } else { 2 };
+    old.chars().count() >= 3
+        && old.chars().all(char::is_alphabetic)
+        && new.chars().all(char::is_alphabetic)
+        && (1..=allowed).contains(&edit_distance(&old, &new))
+}
+
+/// Edit distance between two words, counting a swap of neighbouring letters
+/// ("teh" for "the") as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in distances[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = best;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Generate up to `count` deterministic variants of the commit message
+pub fn generate_suggestions(changes: &StagedChanges, count: u8) -> Vec<String> {
+    let kind = infer_type(changes);
+    let scope = infer_scope(changes);
+
+    let mut suggestions = vec![generate_message(changes)];
+    if scope.is_some() {
+        suggestions.push(format!("{}: {}", kind, describe(changes, None)));
+    }
+
+    let files = all_paths(changes);
+    if files.len() > 1 {
+        let mut message = generate_message(changes);
+        message.push_str("\n\n");
+        for file in files.iter().take(10) {
+            message.push_str(&format!("- {}\n", file));
+        }
+        suggestions.push(message.trim_end().to_string());
+    }
+
+    suggestions.truncate(count.max(1) as usize);
+    suggestions
+}
+
+fn all_paths(changes: &StagedChanges) -> Vec<String> {
+    changes
+        .added
+        .iter()
+        .chain(changes.modified.iter())
+        .chain(changes.deleted.iter())
+        .cloned()
+        .chain(changes.renamed.iter().map(|(_, new)| new.clone()))
+        .chain(changes.copied.iter().map(|(_, new)| new.clone()))
+        .collect()
+}
+
+fn is_doc(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.ends_with(".md")
+        || lower.ends_with(".rst")
+        || lower.ends_with(".adoc")
+        || lower.starts_with("docs/")
+        || lower.contains("/docs/")
+        || Path::new(&lower)
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("license"))
+}
+
+fn is_test(path: &str) -> bool {
+    testing::is_test_file(path)
+}
+
+fn is_ci(path: &str) -> bool {
+    path.starts_with(".github/workflows/")
+        || path == ".gitlab-ci.yml"
+        || path.starts_with(".circleci/")
+}
+
+fn is_build(path: &str) -> bool {
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    matches!(
+        name.as_str(),
+        "Cargo.toml"
+            | "Cargo.lock"
+            | "package.json"
+            | "package-lock.json"
+            | "yarn.lock"
+            | "pnpm-lock.yaml"
+            | "go.mod"
+            | "go.sum"
+            | "Makefile"
+            | "Dockerfile"
+    )
+}
+
+fn infer_type(changes: &StagedChanges) -> &'static str {
+    let paths = all_paths(changes);
+    if paths.is_empty() {
+        return "chore";
+    }
+
+    if paths.iter().all(|p| is_doc(p)) {
+        "docs"
+    } else if paths.iter().all(|p| is_test(p)) {
+        "test"
+    } else if paths.iter().all(|p| is_ci(p)) {
+        "ci"
+    } else if paths.iter().all(|p| is_build(p)) {
+        "build"
+    } else if changes.modified.is_empty() && changes.added.is_empty() && changes.deleted.is_empty()
+    {
+        "refactor"
+    } else if changes
+        .added
+        .iter()
+        .any(|p| !is_doc(p) && !is_test(p) && !is_build(p))
+    {
+        "feat"
+    } else {
+        "chore"
+    }
+}
+
+/// Use the shared module directory (e.g. `git` for `src/git/mod.rs`) as the scope
+fn infer_scope(changes: &StagedChanges) -> Option<String> {
+    let scopes: Vec<String> = all_paths(changes)
+        .iter()
+        .filter_map(|path| module_dir(path))
+        .collect();
+
+    let first = scopes.first()?;
+    if scopes.len() == all_paths(changes).len() && scopes.iter().all(|s| s == first) {
+        Some(first.clone())
+    } else {
+        None
+    }
+}
+
+/// The module directory of `path`: `git` for `src/git/mod.rs`, `docs` for `docs/intro.md`
+fn module_dir(path: &str) -> Option<String> {
+    let components: Vec<&str> = path.split('/').collect();
+    match components.as_slice() {
+        ["src" | "lib" | "packages" | "apps" | "crates", dir, _, ..] => Some(dir.to_string()),
+        [dir, _, ..] if !dir.starts_with('.') => Some(dir.to_string()),
+        _ => None,
+    }
+}
+
+/// What `path` is grouped under when changes are split into several commits
+/// without the AI: `docs`, `ci`, `build`, its module directory, or `.` for
+/// other top-level files
+pub fn topic(path: &str) -> String {
+    if is_doc(path) {
+        "docs".to_string()
+    } else if is_ci(path) {
+        "ci".to_string()
+    } else if is_build(path) {
+        "build".to_string()
+    } else {
+        module_dir(path).unwrap_or_else(|| ".".to_string())
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn describe(changes: &StagedChanges, scope: Option<&str>) -> String {
+    let total = all_paths(changes).len();
+
+    if total == 1 {
+        if let Some(file) = changes.added.first() {
+            return format!("add {}", file_name(file));
+        }
+        if let Some(file) = changes.deleted.first() {
+            return format!("remove {}", file_name(file));
+        }
+        if let Some((old, new)) = changes.renamed.first() {
+            return format!("rename {} to {}", file_name(old), file_name(new));
+        }
+        if let Some((source, new)) = changes.copied.first() {
+            return format!("copy {} to {}", file_name(source), file_name(new));
+        }
+        if let Some(file) = changes.modified.first() {
+            return format!("update {}", file_name(file));
+        }
+    }
+
+    let verb = if changes.added.len() == total {
+        "add"
+    } else if changes.deleted.len() == total {
+        "remove"
+    } else if changes.renamed.len() == total {
+        "rename"
+    } else {
+        "update"
+    };
+
+    match scope {
+        Some(scope) => format!("{} {} files in {}", verb, total, scope),
+        None => format!("{} {} files", verb, total),
+    }
+}
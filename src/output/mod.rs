@@ -0,0 +1,57 @@
+//! How gyst prints, as chosen by the global flags.
+//!
+//! `--quiet`: only results, prompts and errors are printed. Status lines,
+//! notices and spinners are left out, so wrappers and editor plugins can rely
+//! on the exit status (see `exit`) instead of the text.
+//!
+//! `--no-color` or `NO_COLOR`: no colors, emoji or spinners, for logs and
+//! terminals that cannot show them.
+//!
+//! `--yes`: confirmations are answered with yes instead of being asked.
+
+use console::Emoji;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static YES: AtomicBool = AtomicBool::new(false);
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+pub fn init(quiet: bool, yes: bool, no_color: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    YES.store(yes, Ordering::Relaxed);
+    // https://no-color.org: any value but an empty one counts
+    let plain = no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    PLAIN.store(plain, Ordering::Relaxed);
+    if plain {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn assume_yes() -> bool {
+    YES.load(Ordering::Relaxed)
+}
+
+/// Whether colors, emoji and spinners are off
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// An emoji that is printed as its fallback with `--no-color` and on
+/// terminals that cannot show emoji
+pub struct Symbol(pub &'static str, pub &'static str);
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if plain() {
+            f.write_str(self.1)
+        } else {
+            Emoji(self.0, self.1).fmt(f)
+        }
+    }
+}
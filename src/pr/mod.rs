@@ -0,0 +1,236 @@
+use crate::config::Config;
+use crate::forge::Issue;
+use crate::jira::JiraIssue;
+use crate::release::Change;
+use std::fs;
+use std::path::Path;
+
+/// Files holding a single description template, relative to the repository root
+const TEMPLATE_FILES: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+];
+
+/// Directories holding one template per `.md` file, named after the file
+const TEMPLATE_DIRS: &[&str] = &[
+    ".gitlab/merge_request_templates",
+    ".github/PULL_REQUEST_TEMPLATE",
+    ".github/pull_request_template",
+];
+
+pub const SYSTEM_PROMPT: &str = r###"You are an assistant that writes pull request descriptions.
+You are given the commits on the branch, the diff, and sometimes the issue or Jira ticket the branch addresses and a description template.
+Follow these rules:
+1. Start with a first line of the form "TITLE: <title>": a concise title under 72 characters in the imperative mood, in the conventional commit format if the commits use it
+2. Then write the description in markdown: a "## Summary" section with 2-4 sentences on what changed and why, and a "## Changes" bullet list
+3. If anything breaks existing users, add a "## Breaking Changes" section
+4. Describe only what the commits and the diff show; never invent testing steps or results
+5. Do not reference the issue number or ticket key; they are added automatically
+6. If a template is given, fill in its sections instead of writing "## Summary" and "## Changes": keep its headings and order, leave checkboxes unchecked, and drop HTML comments
+
+Return ONLY the title line and the description."###;
+
+/// A pull request title and description
+pub struct Description {
+    pub title: String,
+    pub body: String,
+}
+
+impl Description {
+    /// Close the linked issue when the pull request is merged
+    pub fn link_issue(&mut self, issue: &Issue) {
+        if !self.body.contains(&format!("#{}", issue.number)) {
+            self.body = format!("{}\n\nCloses #{}", self.body.trim_end(), issue.number);
+        }
+    }
+
+    /// Name the code owners of the changed files as reviewers
+    pub fn suggest_reviewers(&mut self, owners: &[String]) {
+        if !owners.is_empty() {
+            self.body = format!(
+                "{}\n\nSuggested reviewers: {}",
+                self.body.trim_end(),
+                owners.join(", ")
+            );
+        }
+    }
+
+    /// Link the Jira issue the branch implements
+    pub fn link_jira_issue(&mut self, issue: &JiraIssue) {
+        if !self.body.contains(&issue.key) {
+            self.body = format!(
+                "{}\n\nJira: [{}]({})",
+                self.body.trim_end(),
+                issue.key,
+                issue.url
+            );
+        }
+    }
+}
+
+/// A pull request or merge request description template from the repository
+pub struct DescriptionTemplate {
+    /// The file name without `.md`, e.g. `Bug` for `.gitlab/merge_request_templates/Bug.md`
+    pub name: String,
+    pub text: String,
+}
+
+/// The description templates in the repository at `workdir`, in the order
+/// GitLab and GitHub look for them. Empty and unreadable files are skipped.
+pub fn find_templates(workdir: &Path) -> Vec<DescriptionTemplate> {
+    let mut paths = Vec::new();
+    for dir in TEMPLATE_DIRS {
+        let Ok(entries) = fs::read_dir(workdir.join(dir)) else {
+            continue;
+        };
+        let mut files: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        files.sort();
+        paths.extend(files);
+    }
+    paths.extend(
+        TEMPLATE_FILES
+            .iter()
+            .map(|file| workdir.join(file))
+            .filter(|path| path.is_file()),
+    );
+
+    let mut templates: Vec<DescriptionTemplate> = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        // On case-insensitive file systems both spellings find the same file
+        if text.trim().is_empty() || templates.iter().any(|t| t.text == text) {
+            continue;
+        }
+        templates.push(DescriptionTemplate {
+            name: name.to_string(),
+            text,
+        });
+    }
+    templates
+}
+
+/// Build the prompt from the branch's commits, its diff, what is known about the
+/// linked issues and the description template
+pub fn build_prompt(
+    changes: &[Change],
+    patch: &str,
+    issues: &[String],
+    template: Option<&DescriptionTemplate>,
+) -> String {
+    let mut prompt = String::new();
+    for issue in issues {
+        prompt.push_str(&format!("{}\n\n", issue));
+    }
+    if let Some(template) = template {
+        prompt.push_str(&format!(
+            "Description template:\n{}\n\n",
+            template.text.trim()
+        ));
+    }
+    prompt.push_str("Commits on the branch:\n");
+    for change in changes {
+        prompt.push_str(&format!("- {}\n", subject(change)));
+    }
+    prompt.push_str(&format!("\nDiff:\n{}", patch));
+    prompt
+}
+
+/// Split the AI's reply into the `TITLE:` line and the description
+pub fn parse_reply(reply: &str) -> Option<Description> {
+    let reply = reply.trim();
+    let (first, rest) = reply.split_once('\n').unwrap_or((reply, ""));
+    let title = first.trim().strip_prefix("TITLE:")?.trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(Description {
+        title: title.to_string(),
+        body: rest.trim().to_string(),
+    })
+}
+
+/// A description listing the commits, for when the AI is not used. A single
+/// commit gives the title; otherwise the branch name does. The commit list
+/// goes below the template, which is left for the author to fill in.
+pub fn plain_description(
+    branch: &str,
+    changes: &[Change],
+    template: Option<&DescriptionTemplate>,
+) -> Description {
+    let title = match changes {
+        [change] => subject(change),
+        _ => {
+            let name = branch
+                .rsplit('/')
+                .next()
+                .unwrap_or(branch)
+                .replace(['-', '_'], " ");
+            let mut chars = name.trim().chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_else(|| branch.to_string())
+        }
+    };
+
+    let mut body = template
+        .map(|template| format!("{}\n\n", template.text.trim_end()))
+        .unwrap_or_default();
+    body.push_str(if template.is_some() {
+        "## Commits\n\n"
+    } else {
+        "## Changes\n\n"
+    });
+    for change in changes {
+        body.push_str(&format!("- {} ({})\n", subject(change), change.short_id));
+    }
+    Description {
+        title,
+        body: body.trim_end().to_string(),
+    }
+}
+
+/// Labels for the commit types on the branch, from `forge.labels`. The
+/// `breaking` key labels branches with breaking changes.
+pub fn labels(config: &Config, changes: &[Change]) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    let kinds = changes
+        .iter()
+        .filter(|change| change.breaking)
+        .map(|_| "breaking")
+        .chain(changes.iter().filter_map(|change| change.kind.as_deref()));
+    for kind in kinds {
+        if let Some(label) = config.forge.labels.get(kind)
+            && !labels.contains(label)
+        {
+            labels.push(label.clone());
+        }
+    }
+    labels
+}
+
+/// The commit subject, with the type and scope put back
+fn subject(change: &Change) -> String {
+    let Some(kind) = &change.kind else {
+        return change.description.clone();
+    };
+    let scope = change
+        .scope
+        .as_ref()
+        .map(|scope| format!("({})", scope))
+        .unwrap_or_default();
+    let bang = if change.breaking { "!" } else { "" };
+    format!("{}{}{}: {}", kind, scope, bang, change.description)
+}
@@ -0,0 +1,408 @@
+use crate::git::{CommitInfo, GitRepo};
+use crate::shell::split_words;
+use anyhow::Result;
+
+/// What a destructive command would do in this repository, worked out from the
+/// repository itself rather than from the AI's explanation
+pub struct Preview {
+    pub summary: String,
+    /// Files that would lose changes or be deleted, with their status
+    pub files: Vec<String>,
+    /// Commits that would be lost or overwritten
+    pub commits: Vec<CommitInfo>,
+    pub note: Option<String>,
+}
+
+/// Preview `command` if it is a git command that can destroy work. Returns
+/// `None` for other commands and for revisions that do not resolve.
+pub fn preview(repo: &GitRepo, command: &str) -> Result<Option<Preview>> {
+    let words = split_words(command);
+    let Some(start) = words.iter().position(|word| word == "git") else {
+        return Ok(None);
+    };
+    let Some((subcommand, args)) = words[start + 1..].split_first() else {
+        return Ok(None);
+    };
+    let args = Args::parse(args);
+
+    match subcommand.as_str() {
+        "reset" => reset(repo, &args),
+        "checkout" => checkout(repo, &args),
+        "restore" => restore(repo, &args),
+        "clean" => clean(repo, &args),
+        "branch" => delete_branch(repo, &args),
+        "push" => force_push(repo, &args),
+        "stash" => stash(repo, &args),
+        _ => Ok(None),
+    }
+}
+
+/// Options and positional arguments of a git command; everything after `--` is a path
+struct Args {
+    flags: Vec<String>,
+    positional: Vec<String>,
+    paths: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Self {
+        let mut parsed = Self {
+            flags: Vec::new(),
+            positional: Vec::new(),
+            paths: Vec::new(),
+        };
+        let mut after_dashes = false;
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if after_dashes {
+                parsed.paths.push(arg.clone());
+            } else if arg == "--" {
+                after_dashes = true;
+            } else if arg.starts_with('-') {
+                parsed.flags.push(arg.clone());
+                // Options that take a separate value
+                if matches!(arg.as_str(), "-s" | "--source" | "-m" | "--message")
+                    && let Some(value) = iter.next()
+                {
+                    parsed.flags.push(value.clone());
+                }
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        parsed
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    /// Whether a short option is given, alone or combined (`-f`, `-fd`)
+    fn has_short(&self, option: char) -> bool {
+        self.flags
+            .iter()
+            .any(|f| !f.starts_with("--") && f.chars().skip(1).any(|c| c == option))
+    }
+}
+
+/// A placeholder such as `<file>` left in a suggested command
+fn is_placeholder(word: &str) -> bool {
+    word.contains('<') || word.contains('>')
+}
+
+fn reset(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    let target = args
+        .positional
+        .first()
+        .map(String::as_str)
+        .unwrap_or("HEAD");
+    if is_placeholder(target) || repo.resolve_commit(target).is_err() {
+        return Ok(None);
+    }
+    let head_ref = repo.head_ref_name();
+    let branch = head_ref.strip_prefix("refs/heads/").unwrap_or("HEAD");
+
+    if args.has("--hard") {
+        let commits = repo.commits_lost("HEAD", Some(target), Some(&head_ref))?;
+        let files = repo.uncommitted_files(&[], false)?;
+        let summary = match (files.len(), commits.len()) {
+            (0, 0) => "Nothing would be lost: there are no uncommitted changes or unique commits"
+                .to_string(),
+            (files, 0) => format!("Discards uncommitted changes in {}", count(files, "file")),
+            (0, commits) => format!("Loses {}", count(commits, "commit")),
+            (files, commits) => format!(
+                "Discards uncommitted changes in {} and loses {}",
+                count(files, "file"),
+                count(commits, "commit")
+            ),
+        };
+        return Ok(Some(Preview {
+            summary,
+            files: files
+                .into_iter()
+                .map(|(path, status)| format!("{}: {}", status, path))
+                .collect(),
+            note: reflog_note(&commits),
+            commits,
+        }));
+    }
+
+    // --soft and --mixed keep the files but take commits off the branch
+    let commits: Vec<CommitInfo> = repo
+        .commits_in_range(Some(target), "HEAD")?
+        .into_iter()
+        .map(|commit| commit.info)
+        .collect();
+    if commits.is_empty() {
+        return Ok(None);
+    }
+    let kept = if args.has("--soft") {
+        "staged"
+    } else {
+        "in the working tree"
+    };
+    Ok(Some(Preview {
+        summary: format!(
+            "Takes {} off {}; {} changes stay {}",
+            count(commits.len(), "commit"),
+            branch,
+            if commits.len() == 1 { "its" } else { "their" },
+            kept
+        ),
+        files: Vec::new(),
+        commits,
+        note: None,
+    }))
+}
+
+fn checkout(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    let forced = args.has("-f") || args.has("--force");
+    // `git checkout <branch>` refuses to overwrite changes; paths and --force do not
+    let mut paths = args.paths.clone();
+    if args.paths.is_empty() && args.positional.iter().any(|p| p == ".") {
+        paths.push(".".to_string());
+    }
+    if paths.is_empty() && !forced {
+        return Ok(None);
+    }
+    discard(repo, &paths, forced)
+}
+
+fn restore(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    let staged = args.has("--staged") || args.has("-S");
+    let worktree = args.has("--worktree") || args.has("-W");
+    if staged && !worktree {
+        // Only unstages; the working tree keeps the changes
+        return Ok(None);
+    }
+    let mut paths = args.positional.clone();
+    paths.extend(args.paths.iter().cloned());
+    discard(repo, &paths, staged)
+}
+
+/// Preview discarding the changes under `paths` (placeholders mean any file)
+fn discard(repo: &GitRepo, paths: &[String], with_staged: bool) -> Result<Option<Preview>> {
+    let placeholder = paths.iter().any(|path| is_placeholder(path));
+    // `.` means everything, which libgit2 spells as no pathspec at all
+    let pathspecs: Vec<String> = if placeholder {
+        Vec::new()
+    } else {
+        paths
+            .iter()
+            .map(|path| path.trim_start_matches("./").trim_end_matches('/'))
+            .filter(|path| !path.is_empty() && *path != ".")
+            .map(str::to_string)
+            .collect()
+    };
+    let files = repo.uncommitted_files(&pathspecs, !with_staged)?;
+
+    let summary = match (files.is_empty(), placeholder) {
+        (true, _) => {
+            "Nothing would be lost: no matching files have uncommitted changes".to_string()
+        }
+        (false, true) => format!(
+            "{} with uncommitted changes it could discard",
+            count(files.len(), "file")
+        ),
+        (false, false) => format!(
+            "Discards uncommitted changes in {}",
+            count(files.len(), "file")
+        ),
+    };
+    let note = (!placeholder && !with_staged && !files.is_empty())
+        .then(|| "Staged changes are kept; only unstaged edits are replaced".to_string());
+    Ok(Some(Preview {
+        summary,
+        files: files
+            .into_iter()
+            .map(|(path, status)| format!("{}: {}", status, path))
+            .collect(),
+        commits: Vec::new(),
+        note,
+    }))
+}
+
+fn clean(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    if !(args.has_short('f') || args.has("--force")) || args.has_short('n') || args.has("--dry-run")
+    {
+        // Without --force git refuses, and --dry-run only lists
+        return Ok(None);
+    }
+    let files = repo.clean_candidates(
+        args.has_short('d'),
+        args.has_short('x'),
+        args.has_short('X'),
+    )?;
+    let summary = if files.is_empty() {
+        "Nothing would be deleted: there are no untracked files".to_string()
+    } else {
+        format!(
+            "Deletes {} that git has never stored",
+            count(files.len(), "untracked path")
+        )
+    };
+    Ok(Some(Preview {
+        summary,
+        files,
+        commits: Vec::new(),
+        note: Some("Deleted untracked files cannot be recovered".to_string()),
+    }))
+}
+
+fn delete_branch(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    let forced = args.has("-D")
+        || ((args.has("-d") || args.has("--delete")) && (args.has("-f") || args.has("--force")));
+    if !forced {
+        // `git branch -d` refuses to delete unmerged work
+        return Ok(None);
+    }
+
+    let mut commits = Vec::new();
+    let mut branches = Vec::new();
+    for name in &args.positional {
+        if is_placeholder(name) || repo.resolve_commit(name).is_err() {
+            continue;
+        }
+        let lost = repo.commits_lost(name, None, Some(&format!("refs/heads/{}", name)))?;
+        branches.push(name.as_str());
+        commits.extend(lost);
+    }
+    if branches.is_empty() {
+        return Ok(None);
+    }
+
+    let summary = if commits.is_empty() {
+        format!(
+            "Nothing would be lost: every commit on {} is on another branch or tag",
+            branches.join(", ")
+        )
+    } else {
+        format!(
+            "Deletes {} and loses {} found on no other branch or tag",
+            branches.join(", "),
+            count(commits.len(), "commit")
+        )
+    };
+    Ok(Some(Preview {
+        summary,
+        files: Vec::new(),
+        note: reflog_note(&commits),
+        commits,
+    }))
+}
+
+fn force_push(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    let forced = args.has_short('f')
+        || args
+            .flags
+            .iter()
+            .any(|f| f == "--force" || f.starts_with("--force-with-lease"))
+        || args
+            .positional
+            .iter()
+            .skip(1)
+            .any(|refspec| refspec.starts_with('+'));
+    if !forced {
+        return Ok(None);
+    }
+
+    let remote = args
+        .positional
+        .first()
+        .map(String::as_str)
+        .unwrap_or("origin");
+    let (local, remote_branch) = match args.positional.get(1) {
+        Some(refspec) => {
+            let refspec = refspec.trim_start_matches('+');
+            match refspec.split_once(':') {
+                Some((src, dst)) => (
+                    src.to_string(),
+                    dst.trim_start_matches("refs/heads/").to_string(),
+                ),
+                None => (refspec.to_string(), refspec.to_string()),
+            }
+        }
+        None => {
+            let branch = repo.get_current_branch()?;
+            (branch.clone(), branch)
+        }
+    };
+    if is_placeholder(remote) || is_placeholder(&local) {
+        return Ok(None);
+    }
+
+    let tracking = format!("{}/{}", remote, remote_branch);
+    if repo.resolve_commit(&tracking).is_err() || repo.resolve_commit(&local).is_err() {
+        return Ok(None);
+    }
+    let overwritten = repo.commits_in_range(Some(&local), &tracking)?;
+    let summary = if overwritten.is_empty() {
+        format!(
+            "Nothing would be overwritten: {} has no commits missing from {}",
+            tracking, local
+        )
+    } else {
+        format!(
+            "Replaces {} and drops {} that {} does not have",
+            tracking,
+            count(overwritten.len(), "commit"),
+            local
+        )
+    };
+    Ok(Some(Preview {
+        summary,
+        files: Vec::new(),
+        commits: overwritten.into_iter().map(|commit| commit.info).collect(),
+        note: Some(format!(
+            "Based on {} as of your last fetch; run 'git fetch {}' first to be sure",
+            tracking, remote
+        )),
+    }))
+}
+
+fn stash(repo: &GitRepo, args: &Args) -> Result<Option<Preview>> {
+    let entries = repo.stash_entries();
+    let dropped: Vec<String> = match args.positional.first().map(String::as_str) {
+        Some("clear") => entries,
+        Some("drop") => {
+            let index = args
+                .positional
+                .get(1)
+                .and_then(|name| name.strip_prefix("stash@{"))
+                .and_then(|rest| rest.trim_end_matches('}').parse::<usize>().ok())
+                .unwrap_or(0);
+            entries.into_iter().skip(index).take(1).collect()
+        }
+        _ => return Ok(None),
+    };
+
+    let summary = if dropped.is_empty() {
+        "Nothing would be lost: there is no matching stash".to_string()
+    } else {
+        format!("Deletes {}", count(dropped.len(), "stash entry"))
+    };
+    Ok(Some(Preview {
+        summary,
+        files: dropped,
+        commits: Vec::new(),
+        note: None,
+    }))
+}
+
+fn reflog_note(commits: &[CommitInfo]) -> Option<String> {
+    (!commits.is_empty()).then(|| {
+        "Lost commits stay in 'git reflog' for a while, so they can be recovered".to_string()
+    })
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}", n, plural(n, noun))
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    match (n, noun.strip_suffix('y')) {
+        (1, _) => noun.to_string(),
+        (_, Some(stem)) => format!("{}ies", stem),
+        _ => format!("{}s", noun),
+    }
+}
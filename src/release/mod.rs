@@ -0,0 +1,301 @@
+use crate::git::{GitRepo, RangeCommit};
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const SYSTEM_PROMPT: &str = r#"You are an editor who polishes release notes for a software project.
+You are given draft release notes in markdown, generated from commit messages.
+Follow these rules:
+1. Keep the release heading, the section headings, and their order
+2. Keep every pull request reference (#123 or !123) and commit hash
+3. Rewrite each entry as a short sentence for users of the project, starting with a capital letter
+4. Merge entries that describe the same change into one, keeping all of their references
+5. Keep the bold **scope:** prefixes
+6. Never invent changes that are not in the draft
+
+Return ONLY the markdown, without any introduction."#;
+
+/// Heading used when the notes do not end at a tag
+pub const UNRELEASED: &str = "Unreleased";
+
+/// Release note sections and the commit types that go into each, in display order
+const SECTIONS: &[(&str, &[&str])] = &[
+    ("Features", &["feat"]),
+    ("Bug Fixes", &["fix"]),
+    ("Performance", &["perf"]),
+    ("Reverts", &["revert"]),
+    ("Documentation", &["docs"]),
+    ("Refactoring", &["refactor"]),
+    ("Tests", &["test"]),
+    ("Build and CI", &["build", "ci"]),
+    ("Chores", &["chore", "style"]),
+];
+
+/// One entry in the release notes
+#[derive(Debug)]
+pub struct Change {
+    /// Conventional commit type, if the subject has one
+    pub kind: Option<String>,
+    pub scope: Option<String>,
+    pub description: String,
+    pub breaking: bool,
+    /// Pull or merge request reference such as `#123` (GitHub) or `!123` (GitLab)
+    pub pull_request: Option<String>,
+    pub short_id: String,
+}
+
+/// The changes between two revisions
+pub struct ReleaseNotes {
+    pub title: String,
+    pub date: String,
+    pub from: Option<String>,
+    pub changes: Vec<Change>,
+}
+
+impl ReleaseNotes {
+    /// Collect the commits after `from` up to `to`. Without `from`, the notes
+    /// start after the most recent tag before `to`.
+    pub fn collect(repo: &GitRepo, from: Option<&str>, to: &str) -> Result<Self> {
+        let from = match from {
+            Some(from) => Some(from.to_string()),
+            // `to^` fails for a root commit, which simply means there is no earlier tag
+            None => repo.latest_tag(&format!("{}^", to)).ok().flatten(),
+        };
+
+        let commits = repo.commits_in_range(from.as_deref(), to)?;
+        let pull_requests = pull_requests_by_commit(repo, &commits)?;
+
+        let changes = commits
+            .iter()
+            .filter(|commit| commit.parent_ids.len() <= 1)
+            .map(|commit| parse_change(commit, pull_requests.get(&commit.full_id)))
+            .collect();
+
+        let title = if repo.is_tag(to) {
+            to.to_string()
+        } else {
+            UNRELEASED.to_string()
+        };
+        let end = repo.resolve_commit(to)?;
+        let date = chrono::DateTime::from_timestamp(end.time, 0)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+
+        Ok(Self {
+            title,
+            date,
+            from,
+            changes,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Render the notes as a markdown section, with changes grouped by type and scope
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("## {} ({})\n", self.title, self.date);
+
+        let breaking: Vec<&Change> = self.changes.iter().filter(|c| c.breaking).collect();
+        push_section(&mut output, "⚠ Breaking Changes", breaking);
+
+        for (heading, kinds) in SECTIONS {
+            let changes = self
+                .changes
+                .iter()
+                .filter(|c| !c.breaking && c.kind.as_deref().is_some_and(|k| kinds.contains(&k)))
+                .collect();
+            push_section(&mut output, heading, changes);
+        }
+
+        let other = self
+            .changes
+            .iter()
+            .filter(|c| {
+                !c.breaking
+                    && !c
+                        .kind
+                        .as_deref()
+                        .is_some_and(|k| SECTIONS.iter().any(|(_, kinds)| kinds.contains(&k)))
+            })
+            .collect();
+        push_section(&mut output, "Other Changes", other);
+
+        output
+    }
+}
+
+/// Add `notes` to the top of the changelog at `path`, below its title. Notes
+/// for an unreleased version replace the previous unreleased section.
+pub fn prepend_to_changelog(path: &Path, notes: &str, title: &str) -> Result<()> {
+    let existing = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let notes = notes.trim();
+
+    let heading = format!("## {} ", title);
+    let sections = section_starts(&existing);
+    if let Some(pos) = sections
+        .iter()
+        .position(|&start| existing[start..].starts_with(&heading))
+    {
+        if title != UNRELEASED {
+            return Err(anyhow!(
+                "{} already has notes for {}",
+                path.display(),
+                title
+            ));
+        }
+        let start = sections[pos];
+        let end = sections.get(pos + 1).copied().unwrap_or(existing.len());
+        let updated = format!("{}{}\n\n{}", &existing[..start], notes, &existing[end..]);
+        return write_changelog(path, updated.trim_end());
+    }
+
+    let updated = if existing.trim().is_empty() {
+        format!("# Changelog\n\n{}", notes)
+    } else if let Some(&first) = sections.first() {
+        // Released versions go below the unreleased section, which stays on top
+        let unreleased = format!("## {} ", UNRELEASED);
+        let start = if existing[first..].starts_with(&unreleased) {
+            sections.get(1).copied().unwrap_or(existing.len())
+        } else {
+            first
+        };
+        let before = existing[..start].trim_end();
+        format!("{}\n\n{}\n\n{}", before, notes, &existing[start..])
+    } else if existing.starts_with("# ") {
+        format!("{}\n\n{}", existing.trim_end(), notes)
+    } else {
+        format!("{}\n\n{}", notes, existing)
+    };
+    write_changelog(path, updated.trim_end())
+}
+
+fn write_changelog(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, format!("{}\n", contents))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Byte offsets of the `## ` headings in a changelog
+fn section_starts(text: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.starts_with("## ") {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    starts
+}
+
+fn push_section(output: &mut String, heading: &str, mut changes: Vec<&Change>) {
+    if changes.is_empty() {
+        return;
+    }
+
+    // Stable sort keeps the commit order within each scope
+    changes.sort_by_key(|change| change.scope.clone());
+
+    output.push_str(&format!("\n### {}\n\n", heading));
+    for change in changes {
+        output.push_str("- ");
+        if let Some(scope) = &change.scope {
+            output.push_str(&format!("**{}:** ", scope));
+        }
+        output.push_str(&change.description);
+        if let Some(pull_request) = &change.pull_request {
+            output.push_str(&format!(" ({})", pull_request));
+        }
+        output.push_str(&format!(" ({})\n", change.short_id));
+    }
+}
+
+/// Map each commit that arrived through a merged pull request to its reference
+fn pull_requests_by_commit(
+    repo: &GitRepo,
+    commits: &[RangeCommit],
+) -> Result<HashMap<String, String>> {
+    let github = Regex::new(r"^Merge pull request #(\d+)")?;
+    let gitlab = Regex::new(r"See merge request \S*!(\d+)")?;
+
+    let mut pull_requests = HashMap::new();
+    for commit in commits {
+        let [first, second] = commit.parent_ids.as_slice() else {
+            continue;
+        };
+        let reference = if let Some(caps) = github.captures(&commit.info.summary) {
+            format!("#{}", &caps[1])
+        } else if let Some(caps) = gitlab.captures(&commit.message) {
+            format!("!{}", &caps[1])
+        } else {
+            continue;
+        };
+
+        for merged in repo.commits_in_range(Some(first), second)? {
+            pull_requests
+                .entry(merged.full_id)
+                .or_insert_with(|| reference.clone());
+        }
+    }
+    Ok(pull_requests)
+}
+
+/// Parse a `type(scope)!: description (#123)` subject into a change
+fn parse_change(commit: &RangeCommit, pull_request: Option<&String>) -> Change {
+    let mut subject = commit.info.summary.trim().to_string();
+
+    // Squash merges end the subject with the pull request number
+    let mut pull_request = pull_request.cloned();
+    if let Some(caps) = Regex::new(r"\s*\(#(\d+)\)$")
+        .ok()
+        .and_then(|re| re.captures(&subject))
+    {
+        pull_request = Some(format!("#{}", &caps[1]));
+        let end = subject.len() - caps[0].len();
+        subject.truncate(end);
+    }
+
+    let body_breaking = commit
+        .message
+        .lines()
+        .any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+
+    let conventional = Regex::new(r"^(\w+)(?:\(([^)]+)\))?(!)?: (.+)$")
+        .ok()
+        .and_then(|re| {
+            re.captures(&subject).map(|caps| {
+                (
+                    caps[1].to_lowercase(),
+                    caps.get(2).map(|m| m.as_str().to_string()),
+                    caps.get(3).is_some(),
+                    caps[4].to_string(),
+                )
+            })
+        });
+
+    match conventional {
+        Some((kind, scope, bang, description)) => Change {
+            kind: Some(kind),
+            scope,
+            description,
+            breaking: bang || body_breaking,
+            pull_request,
+            short_id: commit.info.id.clone(),
+        },
+        None => Change {
+            kind: None,
+            scope: None,
+            description: subject,
+            breaking: body_breaking,
+            pull_request,
+            short_id: commit.info.id.clone(),
+        },
+    }
+}
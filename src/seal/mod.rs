@@ -0,0 +1,48 @@
+//! End-to-end encryption of requests to the gyst server.
+//!
+//! With `server.public_key` set, each request body is sealed to the relay
+//! server's X25519 key (a libsodium sealed box) and sent as
+//! `{"sealed": "<base64>"}`, so load balancers, proxies and their logs in
+//! between only see ciphertext. Only the server process holding the secret
+//! key can open it. Answers come back as plain JSON.
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use crypto_box::PublicKey;
+use crypto_box::aead::OsRng;
+use serde::Serialize;
+
+/// Tells the server how the body is encrypted
+pub const HEADER: &str = "X-Gyst-Encryption";
+pub const SCHEME: &str = "sealed-box";
+
+#[derive(Serialize)]
+struct Sealed {
+    sealed: String,
+}
+
+/// Read a base64-encoded 32-byte X25519 public key
+pub fn parse_key(text: &str) -> Result<PublicKey> {
+    let bytes = STANDARD
+        .decode(text.trim())
+        .map_err(|_| anyhow!("The server's public key is not valid base64"))?;
+    let bytes: [u8; crypto_box::KEY_SIZE] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow!(
+            "The server's public key must be {} bytes, not {}",
+            crypto_box::KEY_SIZE,
+            bytes.len()
+        )
+    })?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// The JSON body carrying `body` sealed to `key`
+pub fn seal(key: &PublicKey, body: &[u8]) -> Result<Vec<u8>> {
+    let sealed = key
+        .seal(&mut OsRng, body)
+        .map_err(|_| anyhow!("Failed to encrypt the request"))?;
+    Ok(serde_json::to_vec(&Sealed {
+        sealed: STANDARD.encode(sealed),
+    })?)
+}
@@ -0,0 +1,1027 @@
+use crate::git::GitRepo;
+use crate::version::Bump;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// A public item of a library crate's API
+#[derive(Debug, Clone)]
+pub struct ApiItem {
+    /// Path from the crate root, such as `config::Config::load` or `Style::Plain`
+    pub name: String,
+    pub file: String,
+    /// The declaration without its body, e.g. `pub fn load(path: &Path) -> Result<Self>`
+    pub signature: String,
+    /// A trait item without a default, which every implementation must provide
+    pub required: bool,
+}
+
+/// How the public API differs between two versions of the sources
+#[derive(Debug, Default)]
+pub struct ApiChanges {
+    pub added: Vec<ApiItem>,
+    pub removed: Vec<ApiItem>,
+    /// Items whose declaration changed, old and new
+    pub changed: Vec<(ApiItem, ApiItem)>,
+}
+
+impl ApiChanges {
+    /// Compare the public API in the staged sources with HEAD. Returns `None`
+    /// if the repository is not a Rust library crate.
+    pub fn staged(repo: &GitRepo) -> Result<Option<Self>> {
+        if !is_library(repo.workdir()?) {
+            return Ok(None);
+        }
+        let from = repo.has_head().then_some("HEAD");
+        if repo
+            .changed_file_versions(from, None, is_library_source)?
+            .is_empty()
+        {
+            return Ok(Some(Self::default()));
+        }
+        let old = match from {
+            Some(from) => repo.file_contents(Some(from), is_library_source)?,
+            None => BTreeMap::new(),
+        };
+        let new = repo.file_contents(None, is_library_source)?;
+        Ok(Some(Self::compare(&public_api(&old), &public_api(&new))))
+    }
+
+    /// Compare the public API at `from` (or an empty crate) with `to`
+    pub fn between(repo: &GitRepo, from: Option<&str>, to: &str) -> Result<Option<Self>> {
+        if !is_library(repo.workdir()?) {
+            return Ok(None);
+        }
+        if repo
+            .changed_file_versions(from, Some(to), is_library_source)?
+            .is_empty()
+        {
+            return Ok(Some(Self::default()));
+        }
+        let old = match from {
+            Some(from) => repo.file_contents(Some(from), is_library_source)?,
+            None => BTreeMap::new(),
+        };
+        let new = repo.file_contents(Some(to), is_library_source)?;
+        Ok(Some(Self::compare(&public_api(&old), &public_api(&new))))
+    }
+
+    fn compare(old: &BTreeMap<String, ApiItem>, new: &BTreeMap<String, ApiItem>) -> Self {
+        let mut changes = Self::default();
+        for (name, item) in old {
+            match new.get(name) {
+                None => changes.removed.push(item.clone()),
+                Some(current) if current.signature != item.signature => {
+                    changes.changed.push((item.clone(), current.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, item) in new {
+            if !old.contains_key(name) {
+                changes.added.push(item.clone());
+            }
+        }
+        changes
+    }
+
+    /// Removed or changed items break users (major), and so do new required
+    /// trait items; other new items are a feature (minor)
+    pub fn bump(&self) -> Option<Bump> {
+        if !self.removed.is_empty()
+            || !self.changed.is_empty()
+            || self.added.iter().any(|item| item.required)
+        {
+            Some(Bump::Major)
+        } else if !self.added.is_empty() {
+            Some(Bump::Minor)
+        } else {
+            None
+        }
+    }
+
+    /// Tell the AI how the change affects the public API, so the commit type
+    /// and breaking-change marker match
+    pub fn prompt_hint(&self) -> Option<String> {
+        let names = |items: &mut dyn Iterator<Item = &ApiItem>| {
+            items
+                .take(5)
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self.bump()? {
+            Bump::Major => {
+                let mut broken = self
+                    .removed
+                    .iter()
+                    .chain(self.changed.iter().map(|(old, _)| old))
+                    .chain(self.added.iter().filter(|item| item.required));
+                Some(format!(
+                    "These changes remove or change public API items of the library ({}). This is a breaking change: add '!' after the type and a 'BREAKING CHANGE:' footer explaining the migration",
+                    names(&mut broken)
+                ))
+            }
+            Bump::Minor => Some(format!(
+                "These changes add public API items to the library ({}), so this is a 'feat' commit",
+                names(&mut self.added.iter())
+            )),
+            Bump::Patch => None,
+        }
+    }
+}
+
+/// Whether `workdir` holds a Rust library crate (or a workspace of them)
+fn is_library(workdir: &Path) -> bool {
+    let Ok(manifest) = fs::read_to_string(workdir.join("Cargo.toml")) else {
+        return false;
+    };
+    workdir.join("src/lib.rs").is_file()
+        || manifest.contains("[lib]")
+        || manifest.contains("[workspace]")
+}
+
+/// Library sources: `.rs` files under a `src` directory, excluding binaries
+fn is_library_source(path: &str) -> bool {
+    let parts: Vec<&str> = path.split('/').collect();
+    path.ends_with(".rs")
+        && parts.contains(&"src")
+        && !parts.contains(&"bin")
+        && !path.ends_with("src/main.rs")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Module,
+    Impl,
+    Trait,
+    Struct,
+    Enum,
+    /// Function bodies, expressions and anything else that holds no API items
+    Body,
+}
+
+struct Frame {
+    scope: Scope,
+    name: String,
+    public: bool,
+}
+
+/// A `pub` item as declared in one file, before the path of the file's module
+/// is known
+struct Declared {
+    /// Inline modules around the item, e.g. `["imp"]` for `mod imp { pub fn f() {} }`
+    module: Vec<String>,
+    /// Whether those modules are all `pub`
+    module_public: bool,
+    /// The type of the `impl` block around the item, e.g. `Config`
+    impl_target: Option<String>,
+    /// Path from the module, e.g. `load`, `Config::path` or `Style::Plain`
+    name: String,
+    signature: String,
+    required: bool,
+}
+
+/// A `mod name;` declaration, whose items are in another file
+struct ModuleFile {
+    /// Inline modules around the declaration
+    parents: Vec<String>,
+    name: String,
+    /// Whether the declaration and the inline modules around it are all `pub`
+    public: bool,
+}
+
+/// A `pub use` declaration
+struct Reexport {
+    /// Inline modules around the declaration
+    module: Vec<String>,
+    /// Whether those modules are all `pub`
+    public: bool,
+    /// The paths it names and the name each is exported as, see `use_paths`
+    paths: Vec<(String, String)>,
+}
+
+/// What one source file declares
+#[derive(Default)]
+struct FileApi {
+    items: Vec<Declared>,
+    /// Structs, enums, unions, traits and type aliases of any visibility, with
+    /// the inline modules around them, so `impl` blocks can be matched to them
+    types: Vec<(Vec<String>, String)>,
+    modules: Vec<ModuleFile>,
+    reexports: Vec<Reexport>,
+}
+
+/// The public API of the library crates in `files`, keyed by path from the
+/// crate root, e.g. `config::Config::load`. Crates in a workspace are prefixed
+/// with their directory name.
+///
+/// Modules are followed from each `src/lib.rs` through their `mod`
+/// declarations. Items in a private module only count when a public module
+/// re-exports them with `pub use` (re-exports of re-exports are not followed),
+/// and methods only count when the type they are implemented for is public.
+fn public_api(files: &BTreeMap<String, String>) -> BTreeMap<String, ApiItem> {
+    // Every `pub` item of the crates, and whether it can be reached from outside
+    let mut items: BTreeMap<String, (ApiItem, bool)> = BTreeMap::new();
+    // The paths of the types declared with each name
+    let mut types: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut impl_items = Vec::new();
+    let mut reexports = Vec::new();
+
+    let roots = files
+        .keys()
+        .filter(|path| *path == "src/lib.rs" || path.ends_with("/src/lib.rs"));
+    for root in roots {
+        let krate: Vec<String> = root
+            .strip_suffix("/src/lib.rs")
+            .map(|dir| dir.rsplit('/').next().unwrap_or(dir).to_string())
+            .into_iter()
+            .collect();
+        let mut queue = vec![(root.clone(), krate.clone(), true)];
+        while let Some((file, module, public)) = queue.pop() {
+            let Some(source) = files.get(&file) else {
+                continue;
+            };
+            let declared = parse_file(source);
+            let path_of = |inline: &[String]| -> Vec<String> {
+                module.iter().chain(inline).cloned().collect()
+            };
+
+            let dir = module_dir(&file);
+            for child in declared.modules {
+                let mut path = path_of(&child.parents);
+                path.push(child.name);
+                let base = std::iter::once(dir.as_str())
+                    .chain(path[module.len()..].iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let found = [format!("{}.rs", base), format!("{}/mod.rs", base)]
+                    .into_iter()
+                    .find(|candidate| files.contains_key(candidate));
+                if let Some(found) = found {
+                    queue.push((found, path, public && child.public));
+                }
+            }
+            for (inline, name) in declared.types {
+                let path = join_path(&path_of(&inline), &name);
+                types.entry(name).or_default().push(path);
+            }
+            for item in declared.items {
+                let path = path_of(&item.module);
+                let reachable = public && item.module_public;
+                if item.impl_target.is_some() {
+                    impl_items.push((path, file.clone(), item));
+                    continue;
+                }
+                let name = join_path(&path, &item.name);
+                items.insert(name.clone(), (api_item(name, &file, item), reachable));
+            }
+            for reexport in declared.reexports {
+                if public && reexport.public {
+                    reexports.push((krate.clone(), path_of(&reexport.module), reexport.paths));
+                }
+            }
+        }
+    }
+
+    // Methods belong to the type their `impl` is for: the one of that name in
+    // the same module, or else the only one of that name in the crate
+    for (module, file, item) in impl_items {
+        let Some(target) = item.impl_target.as_deref() else {
+            continue;
+        };
+        let candidates = types.get(target).map(Vec::as_slice).unwrap_or_default();
+        let local = join_path(&module, target);
+        let owner = match candidates {
+            _ if candidates.contains(&local) => local,
+            [only] => only.clone(),
+            _ => continue,
+        };
+        // Types that are not `pub` are not in `items`
+        let Some(&(_, reachable)) = items.get(&owner) else {
+            continue;
+        };
+        let name = join_path(&[owner], &item.name);
+        items.insert(name.clone(), (api_item(name, &file, item), reachable));
+    }
+
+    let mut api: BTreeMap<String, ApiItem> = items
+        .iter()
+        .filter(|(_, (_, reachable))| *reachable)
+        .map(|(name, (item, _))| (name.clone(), item.clone()))
+        .collect();
+    for (krate, module, paths) in reexports {
+        for (path, exported) in paths {
+            let source = resolve_use(&krate, &module, &path);
+            for (name, (item, _)) in &items {
+                let rest = name.strip_prefix(&source);
+                let exported_name = match rest {
+                    Some("") if exported != "*" => exported.clone(),
+                    Some(rest) => match rest.strip_prefix("::") {
+                        Some(rest) if exported == "*" => rest.to_string(),
+                        Some(rest) => format!("{}::{}", exported, rest),
+                        None => continue,
+                    },
+                    None => continue,
+                };
+                let name = join_path(&module, &exported_name);
+                api.entry(name.clone()).or_insert_with(|| ApiItem {
+                    name,
+                    ..item.clone()
+                });
+            }
+        }
+    }
+    api
+}
+
+fn api_item(name: String, file: &str, item: Declared) -> ApiItem {
+    ApiItem {
+        name,
+        file: file.to_string(),
+        signature: item.signature,
+        required: item.required,
+    }
+}
+
+/// `module::name`, or just `name` at the crate root
+fn join_path(module: &[String], name: &str) -> String {
+    module
+        .iter()
+        .map(String::as_str)
+        .chain([name])
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// The directory holding the files of the modules `file` declares: `src` for
+/// `src/lib.rs` and `src/a/mod.rs` or `src/a.rs` for `src/a`
+fn module_dir(file: &str) -> String {
+    let (dir, name) = file.rsplit_once('/').unwrap_or(("", file));
+    match name {
+        "lib.rs" | "mod.rs" => dir.to_string(),
+        _ => format!("{}/{}", dir, name.trim_end_matches(".rs")),
+    }
+}
+
+/// The crate path that `path` in a `use` declaration in `module` refers to.
+/// Other crates are not told apart from local modules; they match no item.
+fn resolve_use(krate: &[String], module: &[String], path: &str) -> String {
+    let mut segments = path
+        .split("::")
+        .filter(|segment| !segment.is_empty())
+        .peekable();
+    let mut resolved = if segments.peek() == Some(&"crate") {
+        segments.next();
+        krate.to_vec()
+    } else {
+        module.to_vec()
+    };
+    for segment in segments {
+        match segment {
+            "self" => {}
+            "super" => {
+                if resolved.len() > krate.len() {
+                    resolved.pop();
+                }
+            }
+            _ => resolved.push(segment.to_string()),
+        }
+    }
+    resolved.join("::")
+}
+
+/// The paths a `use` tree names, each with the name it is imported as:
+/// `a::{B, c::D as E, f::*}` gives `a::B` as `B`, `a::c::D` as `E` and `a::f`
+/// as `*`, i.e. everything in it
+fn use_paths(prefix: &str, tree: &str, paths: &mut Vec<(String, String)>) {
+    let tree = tree.trim();
+    let join = |path: &str| match (prefix, path) {
+        ("", path) => path.to_string(),
+        (prefix, "" | "self") => prefix.to_string(),
+        (prefix, path) => format!("{}::{}", prefix, path),
+    };
+
+    if let Some(open) = tree.find('{') {
+        let prefix = join(tree[..open].trim().trim_end_matches("::"));
+        let inner = tree[open + 1..].trim_end();
+        let inner = inner.strip_suffix('}').unwrap_or(inner);
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in inner.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    use_paths(&prefix, &inner[start..i], paths);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        use_paths(&prefix, &inner[start..], paths);
+    } else if let Some(path) = tree.strip_suffix('*') {
+        paths.push((join(path.trim_end_matches("::")), "*".to_string()));
+    } else if !tree.is_empty() {
+        let (path, alias) = match tree.split_once(" as ") {
+            Some((path, alias)) => (join(path.trim()), alias.trim().to_string()),
+            None => {
+                let path = join(tree);
+                let name = path.rsplit("::").next().unwrap_or(&path).to_string();
+                (path, name)
+            }
+        };
+        if alias != "_" {
+            paths.push((path, alias));
+        }
+    }
+}
+
+/// Collect what one source file declares: its `pub` items, types, `mod`
+/// declarations and re-exports.
+///
+/// This is a line-based scan rather than a full parse: it understands nesting of
+/// modules, impls, traits, structs and enums, and skips comments and strings,
+/// which is enough to compare two versions of hand-formatted code.
+fn parse_file(source: &str) -> FileApi {
+    let mut api = FileApi::default();
+    let mut scanner = Scanner::default();
+    let mut stack: Vec<Frame> = Vec::new();
+    // The block the next `{` opens, set by the declaration that precedes it
+    let mut opening: Option<Frame> = None;
+    // A declaration spanning several lines: the item, whether it is in a trait,
+    // and the text so far
+    let mut declaration: Option<(Declared, bool, String)> = None;
+    // A `pub use` spanning several lines: its modules, whether they are public,
+    // and the text so far
+    let mut reexport: Option<(Vec<String>, bool, String)> = None;
+    // Open `(` and `[`, so the `;` in `[u8; 4]` does not end a declaration
+    let mut brackets = 0i32;
+
+    for line in source.lines() {
+        let code = scanner.code(line);
+        let code = code.trim();
+        if code.is_empty() || code.starts_with("#[") || code.starts_with("#![") {
+            continue;
+        }
+
+        if declaration.is_none() && reexport.is_none() {
+            // Modules only nest in modules, so they are at the bottom of the stack
+            let depth = stack
+                .iter()
+                .take_while(|frame| frame.scope == Scope::Module)
+                .count();
+            let (modules, frames) = stack.split_at(depth);
+            let module: Vec<String> = modules.iter().map(|frame| frame.name.clone()).collect();
+            let module_public = modules.iter().all(|frame| frame.public);
+
+            let declared = declared_block(code).map(|(name, block, is_type)| {
+                if frames.is_empty() && is_type {
+                    api.types.push((module.clone(), name.clone()));
+                }
+                if frames.is_empty() && block.scope == Scope::Module && code.ends_with(';') {
+                    api.modules.push(ModuleFile {
+                        parents: module.clone(),
+                        name: name.clone(),
+                        public: block.public && module_public,
+                    });
+                }
+                opening = Some(block);
+                name
+            });
+
+            let visible = frames
+                .iter()
+                .all(|frame| frame.public && frame.scope != Scope::Body);
+            let scope = stack.last().map_or(Scope::Module, |frame| frame.scope);
+            if frames.is_empty() && code.starts_with("pub use ") {
+                reexport = Some((module, module_public, String::new()));
+            } else if visible && let Some(name) = item_name(scope, code, declared) {
+                let impl_target = frames
+                    .first()
+                    .filter(|frame| frame.scope == Scope::Impl)
+                    .map(|frame| frame.name.clone());
+                let mut path: Vec<&str> = frames
+                    .iter()
+                    .filter(|frame| frame.scope != Scope::Impl)
+                    .map(|frame| frame.name.as_str())
+                    .collect();
+                path.push(&name);
+                let item = Declared {
+                    module,
+                    module_public,
+                    impl_target,
+                    name: path.join("::"),
+                    signature: String::new(),
+                    required: false,
+                };
+                declaration = Some((item, scope == Scope::Trait, String::new()));
+            }
+        }
+
+        if let Some((module, public, mut text)) = reexport.take() {
+            text.push(' ');
+            text.push_str(code);
+            match text.split_once(';') {
+                Some((tree, _)) => {
+                    let mut paths = Vec::new();
+                    use_paths("", tree.trim().trim_start_matches("pub use "), &mut paths);
+                    api.reexports.push(Reexport {
+                        module,
+                        public,
+                        paths,
+                    });
+                }
+                None => reexport = Some((module, public, text)),
+            }
+        }
+
+        if let Some((mut item, in_trait, mut text)) = declaration.take() {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(code);
+            match declaration_end(&text) {
+                Some(end) => {
+                    item.signature = signature(&text[..end]);
+                    item.required = in_trait && text[end..].starts_with(';');
+                    api.items.push(item);
+                }
+                None => declaration = Some((item, in_trait, text)),
+            }
+        }
+
+        for c in code.chars() {
+            match c {
+                '(' | '[' => brackets += 1,
+                ')' | ']' => brackets -= 1,
+                '{' => stack.push(opening.take().unwrap_or(Frame {
+                    scope: Scope::Body,
+                    name: String::new(),
+                    public: false,
+                })),
+                '}' => {
+                    stack.pop();
+                }
+                // `struct Unit;` and trait method declarations never open their block
+                ';' if brackets <= 0 => opening = None,
+                _ => {}
+            }
+        }
+    }
+
+    api
+}
+
+/// Normalize whitespace and drop the values of constants, which can change
+/// without breaking anyone
+fn signature(declaration: &str) -> String {
+    let signature = declaration
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .trim_end_matches(',')
+        .to_string();
+    match signature.split_once(" = ") {
+        Some((head, _))
+            if !head.contains(" fn ") && (head.contains("const ") || head.contains("static ")) =>
+        {
+            head.to_string()
+        }
+        _ => signature,
+    }
+}
+
+/// If `code` starts with an item keyword, return the declared name, the block
+/// the item would open and whether it declares a type
+fn declared_block(code: &str) -> Option<(String, Frame, bool)> {
+    let (public, rest) = match code.strip_prefix("pub ") {
+        Some(rest) => (true, rest),
+        None if code.starts_with("pub(") => (false, code.split_once(')')?.1.trim_start()),
+        None => (false, code),
+    };
+
+    let mut words = rest.split_whitespace().peekable();
+    while let Some(&word) = words.peek() {
+        if matches!(word, "async" | "unsafe" | "extern" | "default" | "\"\"")
+            || (word == "const" && rest.contains(" fn "))
+        {
+            words.next();
+        } else {
+            break;
+        }
+    }
+
+    let keyword = words.next()?;
+    let ident = |word: &str| {
+        word.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .next()
+            .unwrap_or(word)
+            .to_string()
+    };
+    let mut name = words.next().map(ident).unwrap_or_default();
+
+    let frame = |scope, name: &str, public| Frame {
+        scope,
+        name: name.to_string(),
+        public,
+    };
+    let block = match keyword {
+        "mod" => frame(Scope::Module, &name, public),
+        "struct" | "union" => frame(Scope::Struct, &name, public),
+        "enum" => frame(Scope::Enum, &name, public),
+        "trait" => frame(Scope::Trait, &name, public),
+        "impl" => frame(Scope::Impl, &impl_target(code), true),
+        _ if keyword.starts_with("impl<") => frame(Scope::Impl, &impl_target(code), true),
+        "fn" | "type" | "const" | "static" | "macro_rules!" => frame(Scope::Body, &name, false),
+        "use" => {
+            name = rest
+                .trim_start_matches("use")
+                .trim()
+                .trim_end_matches(';')
+                .to_string();
+            frame(Scope::Body, &name, false)
+        }
+        _ => return None,
+    };
+
+    if keyword == "static" && name == "mut" {
+        name = words.next().map(ident).unwrap_or_default();
+    }
+    let is_type = matches!(keyword, "struct" | "union" | "enum" | "trait" | "type");
+    Some((name, block, is_type))
+}
+
+/// The name of the item `code` declares, if it is public API in `scope`
+fn item_name(scope: Scope, code: &str, declared: Option<String>) -> Option<String> {
+    let first = code.split_whitespace().next()?;
+    match scope {
+        Scope::Module | Scope::Impl => declared.filter(|_| code.starts_with("pub ")),
+        // Every item of a public trait is public
+        Scope::Trait => {
+            declared.filter(|_| matches!(first, "fn" | "const" | "type" | "async" | "unsafe"))
+        }
+        // `pub name: Type,`
+        Scope::Struct => code
+            .strip_prefix("pub ")
+            .and_then(|rest| rest.split_once(':'))
+            .map(|(field, _)| field.trim().to_string())
+            .filter(|field| field.chars().all(|c| c.is_alphanumeric() || c == '_')),
+        // Every variant of a public enum is public
+        Scope::Enum => first.chars().next().filter(|c| c.is_uppercase()).map(|_| {
+            first
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or(first)
+                .to_string()
+        }),
+        Scope::Body => None,
+    }
+}
+
+/// The type an `impl` block is for: `Foo` in `impl<T> Display for Foo<T> {`
+fn impl_target(code: &str) -> String {
+    let header = code.split('{').next().unwrap_or(code);
+    let header = header.split(" where ").next().unwrap_or(header);
+    let target = match header.rsplit_once(" for ") {
+        Some((_, target)) => target,
+        None => {
+            // Skip the generics of `impl<T: Bound>`
+            let rest = header.trim_start_matches("impl");
+            let mut depth = 0;
+            let start = rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0 && c.is_alphabetic()
+                })
+                .map_or(0, |(i, _)| i);
+            &rest[start..]
+        }
+    };
+    target
+        .trim()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .next()
+        .unwrap_or("")
+        .rsplit("::")
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Where a declaration ends: its first `{` or `;` outside brackets
+fn declaration_end(text: &str) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '{' | ';' if depth <= 0 => return Some(i),
+            // A tuple struct or enum variant ends at the line's `,`
+            ',' if depth <= 0 && text[i + 1..].trim().is_empty() => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Strips comments and the contents of string and char literals, keeping state
+/// across lines for block comments and multi-line strings
+#[derive(Default)]
+struct Scanner {
+    block_comment: usize,
+    string: bool,
+    /// Number of `#` in the raw string being read
+    raw_string: Option<usize>,
+}
+
+impl Scanner {
+    fn code(&mut self, line: &str) -> String {
+        let chars: Vec<char> = line.chars().collect();
+        let mut code = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+
+            if self.block_comment > 0 {
+                if c == '*' && next == Some('/') {
+                    self.block_comment -= 1;
+                    i += 1;
+                } else if c == '/' && next == Some('*') {
+                    self.block_comment += 1;
+                    i += 1;
+                }
+            } else if let Some(hashes) = self.raw_string {
+                if c == '"' && chars[i + 1..].iter().take_while(|&&h| h == '#').count() >= hashes {
+                    self.raw_string = None;
+                    code.push('"');
+                    i += hashes;
+                }
+            } else if self.string {
+                if c == '\\' {
+                    i += 1;
+                } else if c == '"' {
+                    self.string = false;
+                    code.push('"');
+                }
+            } else if c == '/' && next == Some('/') {
+                break;
+            } else if c == '/' && next == Some('*') {
+                self.block_comment += 1;
+                i += 1;
+            } else if c == '"' {
+                self.string = true;
+                code.push('"');
+            } else if c == 'r'
+                && matches!(next, Some('"' | '#'))
+                && !chars[..i]
+                    .last()
+                    .is_some_and(|p| p.is_alphanumeric() || *p == '_')
+            {
+                let hashes = chars[i + 1..].iter().take_while(|&&h| h == '#').count();
+                if chars.get(i + 1 + hashes) == Some(&'"') {
+                    self.raw_string = Some(hashes);
+                    code.push('"');
+                    i += 1 + hashes;
+                } else {
+                    code.push(c);
+                }
+            } else if c == '\'' && chars.get(i + 2) == Some(&'\'') {
+                // A char literal such as '{'
+                code.push_str("' '");
+                i += 2;
+            } else if c == '\'' && next == Some('\\') {
+                // An escaped char literal such as '\n' or '\u{7f}'
+                let end = chars[i + 2..]
+                    .iter()
+                    .position(|&q| q == '\'')
+                    .map_or(chars.len(), |p| i + 2 + p);
+                code.push_str("' '");
+                i = end;
+            } else {
+                code.push(c);
+            }
+            i += 1;
+        }
+
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(files: &[(&str, &str)]) -> BTreeMap<String, ApiItem> {
+        let files = files
+            .iter()
+            .map(|(path, source)| (path.to_string(), source.to_string()))
+            .collect();
+        public_api(&files)
+    }
+
+    fn names(files: &[(&str, &str)]) -> Vec<String> {
+        api(files).into_keys().collect()
+    }
+
+    #[test]
+    fn items_are_named_by_module_path() {
+        let names = names(&[
+            ("src/lib.rs", "pub mod a;\npub mod b;\n"),
+            (
+                "src/a.rs",
+                "pub struct A;\n\nimpl A {\n    pub fn new() -> Self {\n        A\n    }\n}\n\npub fn new() {}\n",
+            ),
+            (
+                "src/b/mod.rs",
+                "pub struct B;\n\nimpl B {\n    pub fn new() -> Self {\n        B\n    }\n}\n\npub fn new() {}\n",
+            ),
+        ]);
+
+        assert_eq!(
+            names,
+            [
+                "a",
+                "a::A",
+                "a::A::new",
+                "a::new",
+                "b",
+                "b::B",
+                "b::B::new",
+                "b::new"
+            ]
+        );
+    }
+
+    #[test]
+    fn private_modules_are_not_public() {
+        let names = names(&[
+            (
+                "src/lib.rs",
+                "mod internal;\npub mod api;\n\nmod inline {\n    pub fn hidden() {}\n}\n",
+            ),
+            ("src/internal.rs", "pub fn helper() {}\n\npub mod nested;\n"),
+            ("src/internal/nested.rs", "pub fn deep() {}\n"),
+            (
+                "src/api.rs",
+                "pub fn call() {}\n\npub(crate) fn internal() {}\n",
+            ),
+        ]);
+
+        assert_eq!(names, ["api", "api::call"]);
+    }
+
+    #[test]
+    fn methods_of_private_types_are_not_public() {
+        let names = names(&[(
+            "src/lib.rs",
+            "struct Private;\n\nimpl Private {\n    pub fn new() -> Self {\n        Private\n    }\n}\n\npub struct Public;\n\nimpl Public {\n    pub fn new() -> Self {\n        Public\n    }\n\n    fn helper(&self) {}\n}\n",
+        )]);
+
+        assert_eq!(names, ["Public", "Public::new"]);
+    }
+
+    #[test]
+    fn methods_follow_their_type_into_other_modules() {
+        let names = names(&[
+            ("src/lib.rs", "pub mod types;\nmod imp;\n"),
+            (
+                "src/types.rs",
+                "pub struct Config {\n    pub path: String,\n}\n",
+            ),
+            (
+                "src/imp.rs",
+                "use crate::types::Config;\n\nimpl Config {\n    pub fn load() -> Self {\n        todo!()\n    }\n}\n",
+            ),
+        ]);
+
+        assert_eq!(
+            names,
+            [
+                "types",
+                "types::Config",
+                "types::Config::load",
+                "types::Config::path"
+            ]
+        );
+    }
+
+    #[test]
+    fn reexports_make_private_items_public() {
+        let names = names(&[
+            (
+                "src/lib.rs",
+                "mod internal;\nmod style;\n\npub use internal::{Thing, helper as run};\npub use style::*;\n",
+            ),
+            (
+                "src/internal.rs",
+                "pub struct Thing;\n\nimpl Thing {\n    pub fn new() -> Self {\n        Thing\n    }\n}\n\npub fn helper() {}\n\npub fn unexported() {}\n",
+            ),
+            ("src/style.rs", "pub enum Style {\n    Plain,\n}\n"),
+        ]);
+
+        assert_eq!(
+            names,
+            ["Style", "Style::Plain", "Thing", "Thing::new", "run"]
+        );
+    }
+
+    #[test]
+    fn workspace_crates_are_prefixed() {
+        let names = names(&[
+            ("crates/core/src/lib.rs", "pub fn run() {}\n"),
+            ("crates/cli/src/lib.rs", "pub fn run() {}\n"),
+            ("crates/cli/src/main.rs", "pub fn main() {}\n"),
+        ]);
+
+        assert_eq!(names, ["cli::run", "core::run"]);
+    }
+
+    #[test]
+    fn use_trees_are_expanded() {
+        let mut paths = Vec::new();
+        use_paths("", "a::{B, c::{D as E, self}, f::*, G as _}", &mut paths);
+
+        let expected = [
+            ("a::B", "B"),
+            ("a::c::D", "E"),
+            ("a::c", "c"),
+            ("a::f", "*"),
+        ];
+        assert_eq!(
+            paths,
+            expected.map(|(path, name)| (path.to_string(), name.to_string()))
+        );
+    }
+
+    #[test]
+    fn changed_signature_in_a_module_is_breaking() {
+        let old = api(&[
+            ("src/lib.rs", "pub mod config;\n"),
+            ("src/config.rs", "pub fn load(path: &str) {}\n"),
+        ]);
+        let new = api(&[
+            ("src/lib.rs", "pub mod config;\n"),
+            (
+                "src/config.rs",
+                "pub fn load(path: &str, strict: bool) {}\n",
+            ),
+        ]);
+
+        let changes = ApiChanges::compare(&old, &new);
+
+        assert_eq!(changes.changed.len(), 1);
+        assert_eq!(changes.changed[0].1.name, "config::load");
+        assert_eq!(changes.bump(), Some(Bump::Major));
+    }
+
+    #[test]
+    fn new_required_trait_method_is_breaking() {
+        let old = api(&[(
+            "src/lib.rs",
+            "pub trait Render {\n    fn render(&self);\n}\n",
+        )]);
+        let new = api(&[(
+            "src/lib.rs",
+            "pub trait Render {\n    fn render(&self);\n    fn width(&self) -> usize;\n    fn name(&self) -> &str {\n        \"\"\n    }\n}\n",
+        )]);
+
+        let changes = ApiChanges::compare(&old, &new);
+
+        let added: Vec<(&str, bool)> = changes
+            .added
+            .iter()
+            .map(|item| (item.name.as_str(), item.required))
+            .collect();
+        assert_eq!(added, [("Render::name", false), ("Render::width", true)]);
+        assert_eq!(changes.bump(), Some(Bump::Major));
+    }
+
+    #[test]
+    fn private_items_do_not_change_the_api() {
+        let old = api(&[
+            ("src/lib.rs", "mod internal;\npub fn run() {}\n"),
+            ("src/internal.rs", "pub fn step() {}\n"),
+        ]);
+        let new = api(&[
+            ("src/lib.rs", "mod internal;\npub fn run() {}\n"),
+            (
+                "src/internal.rs",
+                "pub fn step(fast: bool) {}\n\npub fn other() {}\n",
+            ),
+        ]);
+
+        assert_eq!(ApiChanges::compare(&old, &new).bump(), None);
+    }
+}
@@ -1,7 +1,60 @@
+use crate::chaos;
+use crate::exit;
 use crate::git::StagedChanges;
+use crate::http;
+use crate::logging;
+use crate::seal;
 use anyhow::{Context, Result, anyhow};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Header carrying the client-generated request ID, echoed back by the server
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Header identifying one logical request across retries, so the server can
+/// return the original response instead of generating again
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Content type of the streaming endpoints' Server-Sent Events
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// Header carrying a hash of the request's content, as a hint for the server's
+/// cache. It is only a hint: the server hashes the body it received itself and
+/// answers from its cache only when its own hash matches, so a client sending
+/// a wrong key cannot poison the cache for others.
+const CACHE_KEY_HEADER: &str = "X-Gyst-Cache-Key";
+
+/// Version of the request and response formats this client speaks. Requests go
+/// to `/api/v<N>/...` and name the version in `API_VERSION_HEADER`, so a newer
+/// server can keep answering older clients in the format they understand.
+const API_VERSION: u32 = 1;
+
+/// Header telling the server which API version the client speaks, and telling
+/// the client which version the server answered with
+const API_VERSION_HEADER: &str = "X-Gyst-Api-Version";
+
+/// Environment variable checked for a server token when `server.token` is not set
+const TOKEN_VAR: &str = "GYST_SERVER_TOKEN";
+
+/// Set once the server turns out to predate versioned paths, so the remaining
+/// requests of this run go straight to the unversioned ones
+static LEGACY_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// How often a request is sent before giving up on timeouts, rate limits and
+/// gateway errors
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Longest `Retry-After` wait that is honored; longer waits give up instead
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// How long to wait for the server before retrying
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Usage statistics are sent once, briefly, so they never hold up a command
+const STATS_TIMEOUT: Duration = Duration::from_secs(3);
 
 // Response structures
 #[derive(Debug, Deserialize)]
@@ -11,7 +64,24 @@ struct CommitResponse {
 
 #[derive(Debug, Deserialize)]
 struct SuggestionsResponse {
-    suggestions: Vec<String>,
+    suggestions: Vec<Suggestion>,
+}
+
+/// A suggestion as plain text, or as an object with the text in `message` and
+/// other fields this client does not use yet
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Suggestion {
+    Text(String),
+    Structured { message: String },
+}
+
+impl Suggestion {
+    fn into_message(self) -> String {
+        match self {
+            Self::Text(message) | Self::Structured { message } => message,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,6 +96,32 @@ struct CommitRequest {
     diff: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     count: Option<u8>,
+    /// Extra instructions for the model, e.g. to keep a ticket reference
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hints: Vec<String>,
+}
+
+impl CommitRequest {
+    /// Large changes name only a few files and describe the rest by directory
+    fn new(changes: &StagedChanges, diff: &str, hints: &[String], count: Option<u8>) -> Self {
+        let mut hints = hints.to_vec();
+        let changes = if changes.is_large() {
+            hints.push(format!(
+                "{} files changed; only some are listed. Changes by directory:\n{}",
+                changes.stats.files_changed,
+                changes.directory_summary()
+            ));
+            changes.condensed()
+        } else {
+            changes.clone()
+        };
+        Self {
+            changes,
+            diff: diff.to_string(),
+            count,
+            hints,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -35,48 +131,368 @@ struct CommandRequest {
 
 pub struct ServerClient {
     client: Client,
+    /// Whether requests carry a token, to tell a missing token from a wrong one
+    has_token: bool,
+    /// The server's key from `server.public_key`, to seal request bodies to
+    public_key: Option<String>,
 }
 
 impl ServerClient {
-    pub fn new(_config: crate::config::Config) -> Self {
+    pub fn new(config: crate::config::Config) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(API_VERSION_HEADER, API_VERSION.into());
+        let token = config
+            .server
+            .token
+            .clone()
+            .filter(|token| !token.is_empty())
+            .or_else(|| {
+                std::env::var(TOKEN_VAR)
+                    .ok()
+                    .filter(|token| !token.is_empty())
+            });
+        let authorization = token.as_deref().and_then(|token| {
+            logging::hide(token);
+            let mut value =
+                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).ok()?;
+            value.set_sensitive(true);
+            Some(value)
+        });
+        let has_token = authorization.is_some();
+        if let Some(value) = authorization {
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
         Self {
-            client: Client::new(),
+            has_token,
+            public_key: config
+                .server
+                .public_key
+                .clone()
+                .filter(|key| !key.is_empty()),
+            client: http::builder_or_warn(&config)
+                .timeout(REQUEST_TIMEOUT)
+                .default_headers(headers)
+                .build()
+                .unwrap_or_default(),
         }
     }
 
-    fn get_server_url(&self) -> String {
+    pub fn get_server_url(&self) -> String {
         // Use the deployed server URL
         "https://gyst-cli.vercel.app".to_string()
     }
 
-    pub async fn generate_message(&self, changes: &StagedChanges, diff: &str) -> Result<String> {
-        let server_url = self.get_server_url();
-        let url = format!("{}/api/commit", server_url);
+    /// The URL of `endpoint` (e.g. `commit/suggestions`): the versioned path,
+    /// or the unversioned one for servers that predate versioning
+    fn url(&self, endpoint: &str) -> String {
+        if LEGACY_PATHS.load(Ordering::Relaxed) {
+            format!("{}/api/{}", self.get_server_url(), endpoint)
+        } else {
+            format!(
+                "{}/api/v{}/{}",
+                self.get_server_url(),
+                API_VERSION,
+                endpoint
+            )
+        }
+    }
 
-        let request = CommitRequest {
-            changes: changes.clone(),
-            diff: diff.to_string(),
-            count: None,
+    /// Whether `response` means the server has no versioned paths. The first
+    /// such answer switches this run over to the unversioned paths.
+    fn fall_back_to_legacy(response: &reqwest::Response) -> bool {
+        response.status() == reqwest::StatusCode::NOT_FOUND
+            && !response.headers().contains_key(API_VERSION_HEADER)
+            && !LEGACY_PATHS.swap(true, Ordering::Relaxed)
+    }
+
+    /// POST a JSON request to the server, tagging it with a request ID that is
+    /// included in any error so it can be matched against server logs.
+    /// Timeouts, rate limits and gateway errors are retried with the same
+    /// idempotency key.
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        request: &Req,
+    ) -> Result<Resp> {
+        let url = self.url(endpoint);
+        log_request(&url, request);
+        let idempotency_key = uuid::Uuid::new_v4().to_string();
+        let cache_key = cache_key(endpoint, request)?;
+        logging::log(|| format!("Cache key {}", cache_key));
+
+        let (response, sent_id) = match self
+            .send(&url, request, &idempotency_key, &cache_key, false)
+            .await?
+        {
+            (response, _) if Self::fall_back_to_legacy(&response) => {
+                let url = self.url(endpoint);
+                logging::log(|| format!("The server has no versioned API; retrying at {}", url));
+                self.send(&url, request, &idempotency_key, &cache_key, false)
+                    .await?
+            }
+            sent => sent,
         };
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
+        let (response, request_id) = self
+            .check(response, sent_id)
+            .await
+            .map_err(exit::provider)?;
+        response
+            .json()
             .await
-            .context("Failed to send request to server")?;
+            .with_context(|| {
+                format!(
+                    "Failed to parse server response (request id: {})",
+                    request_id
+                )
+            })
+            .map_err(exit::provider)
+    }
+
+    /// POST a JSON request to the streaming variant of `endpoint` and hand
+    /// each piece of text to `on_text` as it arrives. Returns the complete
+    /// text, or `None` if the server has no streaming endpoint.
+    async fn stream<Req: Serialize>(
+        &self,
+        endpoint: &str,
+        request: &Req,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<Option<String>> {
+        let endpoint = format!("{}/stream", endpoint);
+        let url = self.url(&endpoint);
+        log_request(&url, request);
+        let idempotency_key = uuid::Uuid::new_v4().to_string();
+        let cache_key = cache_key(&endpoint, request)?;
+
+        let (response, sent_id) = self
+            .send(&url, request, &idempotency_key, &cache_key, true)
+            .await?;
+        if matches!(
+            response.status(),
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::METHOD_NOT_ALLOWED
+        ) {
+            logging::log(|| "The server does not stream; sending the request again".to_string());
+            return Ok(None);
+        }
+        let (mut response, request_id) = self
+            .check(response, sent_id)
+            .await
+            .map_err(exit::provider)?;
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with(EVENT_STREAM));
+        if !is_stream {
+            logging::log(|| {
+                "The server did not answer with a stream; sending the request again".to_string()
+            });
+            return Ok(None);
+        }
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut text = String::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .with_context(|| {
+                format!(
+                    "The connection to the server broke off (request id: {})",
+                    request_id
+                )
+            })
+            .map_err(exit::provider)?
+        {
+            buffer.extend(chunk.iter().filter(|&&byte| byte != b'\r'));
+            while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                match StreamEvent::parse(&String::from_utf8_lossy(&event)) {
+                    Some(StreamEvent::Text(delta)) => {
+                        on_text(&delta);
+                        text.push_str(&delta);
+                    }
+                    Some(StreamEvent::Done(message)) => return Ok(Some(message.unwrap_or(text))),
+                    Some(StreamEvent::Error(error)) => {
+                        return Err(exit::provider(anyhow!(
+                            "Server error: {} (request id: {})",
+                            error,
+                            request_id
+                        )));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Err(exit::provider(anyhow!(
+            "The server's answer ended before it was complete (request id: {})",
+            request_id
+        )))
+    }
+
+    /// Turn error statuses into errors. Returns the response and the request
+    /// ID to report, preferring the one the server actually logged.
+    async fn check(
+        &self,
+        response: reqwest::Response,
+        sent_id: String,
+    ) -> Result<(reqwest::Response, String)> {
+        let request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or(sent_id);
 
+        if response.status() == reqwest::StatusCode::UPGRADE_REQUIRED
+            || response.status() == reqwest::StatusCode::GONE
+        {
+            return Err(anyhow!(
+                "The gyst server no longer supports this version of gyst ({}, API v{}). Update gyst (see 'Updating' in the README) or use direct API mode with 'gyst config --use-server false' (request id: {})",
+                env!("CARGO_PKG_VERSION"),
+                API_VERSION,
+                request_id
+            ));
+        }
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(anyhow!(
+                "{} Set it with 'gyst config set server.token <token>' or the {} environment variable (request id: {})",
+                if self.has_token {
+                    "The gyst server rejected your token."
+                } else {
+                    "The gyst server requires a token."
+                },
+                TOKEN_VAR,
+                request_id
+            ));
+        }
+        if response.status() == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
+            let reason = error_message(&response.text().await.unwrap_or_default());
+            return Err(anyhow!(
+                "The staged changes are too large for the gyst server{}. Commit them in smaller parts, or use direct API mode with 'gyst config --use-server false' (request id: {})",
+                if reason.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", reason)
+                },
+                request_id
+            ));
+        }
+        if response.status() == reqwest::StatusCode::BAD_REQUEST
+            || response.status() == reqwest::StatusCode::UNPROCESSABLE_ENTITY
+        {
+            let reason = error_message(&response.text().await.unwrap_or_default());
+            return Err(anyhow!(
+                "The gyst server rejected the request: {} (request id: {})",
+                if reason.is_empty() {
+                    "no reason given"
+                } else {
+                    reason.as_str()
+                },
+                request_id
+            ));
+        }
         if !response.status().is_success() {
+            let status = response.status();
             let error_text = response.text().await?;
-            return Err(anyhow!("Server error: {}", error_text));
+            return Err(anyhow!(
+                "Server error ({}): {} (request id: {})",
+                status,
+                error_text,
+                request_id
+            ));
         }
+        Ok((response, request_id))
+    }
 
-        let commit_response: CommitResponse = response
-            .json()
-            .await
-            .context("Failed to parse server response")?;
+    /// The JSON body for `request`, sealed to the server's key when one is set
+    fn body<Req: Serialize>(&self, request: &Req) -> Result<Vec<u8>> {
+        let body = serde_json::to_vec(request).context("Failed to serialize request")?;
+        let Some(key) = &self.public_key else {
+            return Ok(body);
+        };
+        let key = seal::parse_key(key).context(
+            "Invalid server.public_key; requests are not sent unencrypted while it is set",
+        )?;
+        logging::log(|| "Sealing the request to the server's public key".to_string());
+        seal::seal(&key, &body)
+    }
+
+    /// Send `request` to `url`, retrying as long as `retry_delay` allows.
+    /// Returns the last response and the request ID it was sent with.
+    async fn send<Req: Serialize>(
+        &self,
+        url: &str,
+        request: &Req,
+        idempotency_key: &str,
+        cache_key: &str,
+        stream: bool,
+    ) -> Result<(reqwest::Response, String)> {
+        let accept = if stream {
+            EVENT_STREAM
+        } else {
+            "application/json"
+        };
+        let body = self.body(request)?;
+        let mut attempt = 1;
+        let sent = loop {
+            let request_id = uuid::Uuid::new_v4().to_string();
+            let started = Instant::now();
+            let mut builder = self
+                .client
+                .post(url)
+                .header(REQUEST_ID_HEADER, &request_id)
+                .header(IDEMPOTENCY_KEY_HEADER, idempotency_key)
+                .header(reqwest::header::ACCEPT, accept)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            // The cache key is derived from the diff, so it would let whoever
+            // sees the headers link identical diffs or confirm a guessed one
+            builder = if self.public_key.is_some() {
+                builder.header(seal::HEADER, seal::SCHEME)
+            } else {
+                builder.header(CACHE_KEY_HEADER, cache_key)
+            };
+            let result = chaos::send(&self.client, builder).await;
+            logging::attempt(
+                &format!("Server (request id {})", request_id),
+                attempt,
+                &result,
+                started,
+            );
 
+            let delay = retry_delay(&result, attempt);
+            if delay.is_none() || attempt == MAX_ATTEMPTS {
+                let response = result
+                    .with_context(|| {
+                        format!(
+                            "Failed to send request to server (request id: {})",
+                            request_id
+                        )
+                    })
+                    .map_err(exit::provider)?;
+                break (response, request_id);
+            }
+
+            tokio::time::sleep(delay.unwrap_or_default()).await;
+            attempt += 1;
+        };
+        Ok(sent)
+    }
+
+    /// Generate a commit message, handing it to `on_text` piece by piece
+    /// while it is written. Servers without streaming answer all at once.
+    pub async fn generate_message(
+        &self,
+        changes: &StagedChanges,
+        diff: &str,
+        hints: &[String],
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<String> {
+        let request = CommitRequest::new(changes, diff, hints, None);
+        if let Some(message) = self.stream("commit", &request, on_text).await? {
+            return Ok(message);
+        }
+        let commit_response: CommitResponse = self.post("commit", &request).await?;
         Ok(commit_response.message)
     }
 
@@ -84,78 +500,312 @@ impl ServerClient {
         &self,
         changes: &StagedChanges,
         diff: &str,
+        hints: &[String],
         count: u8,
     ) -> Result<Vec<String>> {
-        let server_url = self.get_server_url();
-        let url = format!("{}/api/commit/suggestions", server_url);
+        let request = CommitRequest::new(changes, diff, hints, Some(count));
 
-        let request = CommitRequest {
-            changes: changes.clone(),
-            diff: diff.to_string(),
-            count: Some(count),
-        };
+        let suggestions_response: SuggestionsResponse =
+            self.post("commit/suggestions", &request).await?;
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to server")?;
+        Ok(suggestions_response
+            .suggestions
+            .into_iter()
+            .map(Suggestion::into_message)
+            .collect())
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Server error: {}", error_text));
+    /// Suggest git commands for `description`, handing the answer to
+    /// `on_text` piece by piece while it is written
+    pub async fn suggest_command(
+        &self,
+        description: &str,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<String> {
+        let request = CommandRequest {
+            description: description.to_string(),
+        };
+        if let Some(suggestion) = self.stream("command", &request, on_text).await? {
+            return Ok(suggestion);
         }
+        let command_response: CommandResponse = self.post("command", &request).await?;
+        Ok(command_response.suggestion)
+    }
 
-        let suggestions_response: SuggestionsResponse = response
-            .json()
+    /// Send usage totals. Tried once; failures are left to the caller to ignore.
+    pub async fn upload_stats<T: Serialize>(&self, stats: &T) -> Result<()> {
+        let started = Instant::now();
+        let result = chaos::send(
+            &self.client,
+            self.client
+                .post(self.url("stats"))
+                .timeout(STATS_TIMEOUT)
+                .json(stats),
+        )
+        .await;
+        logging::attempt("Usage statistics upload", 1, &result, started);
+        let mut response = result.context("Failed to send usage statistics")?;
+        if Self::fall_back_to_legacy(&response) {
+            response = chaos::send(
+                &self.client,
+                self.client
+                    .post(self.url("stats"))
+                    .timeout(STATS_TIMEOUT)
+                    .json(stats),
+            )
             .await
-            .context("Failed to parse server response")?;
-
-        Ok(suggestions_response.suggestions)
+            .context("Failed to send usage statistics")?;
+        }
+        response
+            .error_for_status()
+            .context("The server rejected the usage statistics")?;
+        Ok(())
     }
 
-    pub async fn suggest_command(&self, description: &str) -> Result<String> {
-        let server_url = self.get_server_url();
-        let url = format!("{}/api/command", server_url);
+    /// The usage the server counted for this client's token in `month` (`YYYY-MM`)
+    pub async fn usage(&self, month: &str) -> Result<Usage> {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let mut attempt = 1;
+        loop {
+            let started = Instant::now();
+            let result = chaos::send(
+                &self.client,
+                self.client
+                    .get(self.url("usage"))
+                    .query(&[("month", month)])
+                    .header(REQUEST_ID_HEADER, &request_id),
+            )
+            .await;
+            logging::attempt("Server usage", attempt, &result, started);
+            let response = result
+                .with_context(|| {
+                    format!("Failed to connect to server (request id: {})", request_id)
+                })
+                .map_err(exit::provider)?;
 
-        let request = CommandRequest {
-            description: description.to_string(),
-        };
+            if attempt == 1 && Self::fall_back_to_legacy(&response) {
+                attempt += 1;
+                continue;
+            }
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(anyhow!(
+                    "This gyst server does not count usage (request id: {})",
+                    request_id
+                ));
+            }
+            let (response, request_id) = self
+                .check(response, request_id)
+                .await
+                .map_err(exit::provider)?;
+            return response.json().await.with_context(|| {
+                format!(
+                    "Failed to parse server response (request id: {})",
+                    request_id
+                )
+            });
+        }
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to server")?;
+    pub async fn health_check(&self) -> Result<Health> {
+        // Nothing is sent with --show-prompt, so the server need not be up
+        if logging::showing_prompt() {
+            return Ok(Health {
+                ok: true,
+                ..Health::default()
+            });
+        }
+        let request_id = uuid::Uuid::new_v4().to_string();
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Server error: {}", error_text));
+        let mut attempt = 1;
+        loop {
+            let started = Instant::now();
+            let result = chaos::send(
+                &self.client,
+                self.client
+                    .get(self.url("health"))
+                    .header(REQUEST_ID_HEADER, &request_id),
+            )
+            .await;
+            logging::attempt("Server health check", attempt, &result, started);
+            let response = result
+                .with_context(|| {
+                    format!("Failed to connect to server (request id: {})", request_id)
+                })
+                .map_err(exit::provider)?;
+
+            // Servers without versioned paths get one more try at the old path
+            if attempt == 1 && Self::fall_back_to_legacy(&response) {
+                attempt += 1;
+                continue;
+            }
+            if let Some(version) = response.headers().get(API_VERSION_HEADER) {
+                logging::log(|| format!("Server speaks API version {:?}", version));
+            }
+            let status = response.status();
+            // Older servers answer with a bare status and no details
+            let mut health: Health = response.json().await.unwrap_or_default();
+            health.ok = status.is_success();
+            health.draining = status == reqwest::StatusCode::SERVICE_UNAVAILABLE;
+            return Ok(health);
         }
+    }
+}
 
-        let command_response: CommandResponse = response
-            .json()
-            .await
-            .context("Failed to parse server response")?;
+/// What the server counted for the token in one month
+#[derive(Debug, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub requests: u64,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// In US dollars
+    #[serde(default)]
+    pub estimated_cost: Option<f64>,
+}
 
-        Ok(command_response.suggestion)
+/// What the server's health endpoint reports. Every detail is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct Health {
+    /// Whether the endpoint answered with a success status
+    #[serde(skip)]
+    pub ok: bool,
+    /// The server answered 503: it is starting up or finishing its requests before shutting down
+    #[serde(skip)]
+    pub draining: bool,
+    #[serde(default, alias = "uptime")]
+    pub uptime_seconds: Option<i64>,
+    /// Whether the server can reach the Anthropic API
+    #[serde(default, alias = "anthropic")]
+    pub anthropic_reachable: Option<bool>,
+    /// Requests waiting for the Anthropic API
+    #[serde(default, alias = "queue")]
+    pub queue_depth: Option<u64>,
+}
+
+impl Health {
+    /// The reported details in a few words, e.g. "up 3 hours, Anthropic reachable, 2 queued"
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(seconds) = self.uptime_seconds {
+            parts.push(format!("up {}", crate::dates::duration(seconds)));
+        }
+        match self.anthropic_reachable {
+            Some(true) => parts.push("Anthropic reachable".to_string()),
+            Some(false) => parts.push("Anthropic unreachable".to_string()),
+            None => {}
+        }
+        if let Some(queued) = self.queue_depth {
+            parts.push(format!("{} queued", queued));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
     }
+}
 
-    pub async fn health_check(&self) -> Result<bool> {
-        let server_url = self.get_server_url();
-        let url = format!("{}/api/health", server_url);
+/// Log the request body, or with `--show-prompt`, print it and exit
+fn log_request<Req: Serialize>(url: &str, request: &Req) {
+    if logging::enabled() || logging::showing_prompt() {
+        let body = serde_json::to_string_pretty(request).unwrap_or_default();
+        logging::show_prompt(&format!("Request to {}", url), &body);
+        logging::log(|| format!("Server request to {}:\n{}", url, body));
+    }
+}
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .context("Failed to connect to server")?;
+/// The reason in an error body: its `error`, `message` or `detail` field, or
+/// the body itself when it is not JSON
+fn error_message(body: &str) -> String {
+    let parsed = serde_json::from_str::<serde_json::Value>(body).ok();
+    let field = parsed.as_ref().and_then(|value| {
+        ["error", "message", "detail"]
+            .iter()
+            .find_map(|key| value.get(key)?.as_str())
+    });
+    field.unwrap_or(body).trim().to_string()
+}
+
+/// One Server-Sent Event from a streaming endpoint
+#[derive(Debug, PartialEq)]
+enum StreamEvent {
+    /// `event: delta` with `{"text": "..."}`: the next piece of the answer
+    Text(String),
+    /// `event: done`, optionally with the complete answer in `{"message": "..."}`
+    Done(Option<String>),
+    /// `event: error` with `{"error": "..."}`
+    Error(String),
+}
+
+impl StreamEvent {
+    /// Parse one event block. Comments, keep-alives and unknown events give `None`.
+    fn parse(block: &str) -> Option<Self> {
+        let mut name = "message";
+        let mut data = Vec::new();
+        for line in block.lines() {
+            if let Some(value) = line.strip_prefix("event:") {
+                name = value.trim();
+            } else if let Some(value) = line.strip_prefix("data:") {
+                data.push(value.strip_prefix(' ').unwrap_or(value));
+            }
+        }
+        let data = data.join("\n");
+        let field = |key: &str| {
+            serde_json::from_str::<serde_json::Value>(&data)
+                .ok()
+                .and_then(|value| value.get(key)?.as_str().map(str::to_string))
+        };
+        match name {
+            "delta" => field("text").map(Self::Text),
+            "done" => Some(Self::Done(field("message"))),
+            "error" => Some(Self::Error(field("error").unwrap_or(data))),
+            _ if data == "[DONE]" => Some(Self::Done(None)),
+            _ => None,
+        }
+    }
+}
+
+/// A hash of what `request` asks `endpoint` for. Line endings and trailing
+/// whitespace are left out, so the same change staged on different machines
+/// gets the same key. The server computes the same hash from the body to
+/// check it.
+fn cache_key<Req: Serialize>(endpoint: &str, request: &Req) -> Result<String> {
+    let body = serde_json::to_string(request).context("Failed to serialize request")?;
+    let normalized: String = body
+        .replace("\\r\\n", "\\n")
+        .split("\\n")
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\\n");
+    let key = git2::Oid::hash_object(
+        git2::ObjectType::Blob,
+        format!("v{} {}\n{}", API_VERSION, endpoint, normalized).as_bytes(),
+    )?;
+    Ok(key.to_string())
+}
 
-        Ok(response.status().is_success())
+/// How long to wait before sending a request again after `result`, or `None`
+/// if it should not be retried. Rate limits wait as long as `Retry-After`
+/// asks (within reason); timeouts and gateway errors back off exponentially.
+pub fn retry_delay(result: &reqwest::Result<reqwest::Response>, attempt: u32) -> Option<Duration> {
+    let backoff = Duration::from_millis(500 * 2u64.pow(attempt - 1));
+    match result {
+        Ok(response) => match response.status().as_u16() {
+            429 => {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse().ok())
+                    .map(Duration::from_secs);
+                match retry_after {
+                    Some(wait) if wait > MAX_RETRY_AFTER => None,
+                    Some(wait) => Some(wait),
+                    None => Some(backoff),
+                }
+            }
+            502..=504 | 529 => Some(backoff),
+            _ => None,
+        },
+        Err(e) if e.is_timeout() || e.is_connect() => Some(backoff),
+        Err(_) => None,
     }
 }
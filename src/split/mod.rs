@@ -0,0 +1,169 @@
+//! Splitting the staged changes into a sequence of commits with `gyst commit --split`.
+
+use crate::git::StagedChanges;
+use crate::offline;
+use crate::workspace::Workspace;
+use std::collections::BTreeMap;
+
+pub const SYSTEM_PROMPT: &str = r#"You are an assistant that splits staged git changes into a sequence of focused commits.
+You are given the changed files and their diff.
+Follow these rules:
+1. Put changes that belong together in the same commit, e.g. a feature with its tests and docs, or a rename with the code that uses the new name
+2. Use as few commits as make sense; a single commit is fine if everything is one piece of work
+3. Put every file in exactly one commit, using the paths exactly as listed
+4. Order the commits so each one makes sense on top of the ones before it
+5. Give each commit a conventional commit subject: type(scope): description, in the imperative mood
+
+Reply with one block per commit and nothing else:
+COMMIT: <subject>
+FILES:
+<path>
+<path>"#;
+
+/// One of the commits the staged changes are split into
+#[derive(Debug, Clone)]
+pub struct Group {
+    /// A `type(scope): description` subject, converted to the template when committed
+    pub message: String,
+    /// The changed paths, with renamed files under their new name
+    pub files: Vec<String>,
+}
+
+impl Group {
+    /// A group for `files` with a message built from the file list alone
+    pub fn offline(changes: &StagedChanges, files: Vec<String>) -> Self {
+        Self {
+            message: offline::generate_message(&changes.only(&files)),
+            files,
+        }
+    }
+
+    /// The paths to commit: the files, plus the old names of renamed ones
+    pub fn paths(&self, changes: &StagedChanges) -> Vec<String> {
+        changes.with_old_names(&self.files)
+    }
+}
+
+/// Build the prompt asking the AI to group `changes`
+pub fn build_prompt(changes: &StagedChanges, diff: &str, hints: &[String]) -> String {
+    let mut prompt = String::from("Changed files:\n");
+    for (kind, files) in [
+        ("added", &changes.added),
+        ("modified", &changes.modified),
+        ("deleted", &changes.deleted),
+    ] {
+        for file in files {
+            prompt.push_str(&format!("  {} ({})\n", file, kind));
+        }
+    }
+    for (old, new) in &changes.renamed {
+        prompt.push_str(&format!("  {} (renamed from {})\n", new, old));
+    }
+    for (source, new) in &changes.copied {
+        prompt.push_str(&format!("  {} (copied from {})\n", new, source));
+    }
+
+    prompt.push_str("\nDiff:\n");
+    prompt.push_str(diff);
+
+    if !hints.is_empty() {
+        prompt.push_str("\nAdditional instructions for the subjects:\n");
+        for hint in hints {
+            prompt.push_str(&format!("- {}\n", hint));
+        }
+    }
+    prompt
+}
+
+/// Read the AI's commits from `reply`. Paths that are not staged are dropped,
+/// and staged files the AI left out get a commit of their own at the end.
+pub fn parse_groups(reply: &str, changes: &StagedChanges) -> Vec<Group> {
+    let staged = changes.paths();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut assigned: Vec<String> = Vec::new();
+
+    for line in reply.lines() {
+        let line = line.trim();
+        if let Some(subject) = line.strip_prefix("COMMIT:") {
+            groups.push(Group {
+                message: subject.trim().to_string(),
+                files: Vec::new(),
+            });
+            continue;
+        }
+        let path = line.trim_start_matches("- ").trim_matches('`');
+        if let Some(group) = groups.last_mut()
+            && staged.iter().any(|file| file == path)
+            && !assigned.iter().any(|file| file == path)
+        {
+            group.files.push(path.to_string());
+            assigned.push(path.to_string());
+        }
+    }
+    groups.retain(|group| !group.files.is_empty() && !group.message.is_empty());
+
+    let rest: Vec<String> = staged
+        .into_iter()
+        .filter(|file| !assigned.contains(file))
+        .collect();
+    if !rest.is_empty() {
+        groups.push(Group::offline(changes, rest));
+    }
+    groups
+}
+
+/// Group the changes by topic (docs, CI, build files, then module directory),
+/// for when the AI is not available
+pub fn by_topic(changes: &StagedChanges) -> Vec<Group> {
+    let mut topics: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for path in changes.paths() {
+        topics.entry(offline::topic(&path)).or_default().push(path);
+    }
+    topics
+        .into_values()
+        .map(|files| Group::offline(changes, files))
+        .collect()
+}
+
+/// Group the changes by the monorepo package they are in, for `gyst suggest
+/// --by-package`. Files outside every package come last, in a commit of
+/// their own.
+pub fn by_package(changes: &StagedChanges, workspace: &Workspace) -> Vec<Group> {
+    let mut packages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut rest: Vec<String> = Vec::new();
+    for path in changes.paths() {
+        match workspace.package_for(&path) {
+            Some(package) => packages.entry(package.name.clone()).or_default().push(path),
+            None => rest.push(path),
+        }
+    }
+    packages
+        .into_values()
+        .chain((!rest.is_empty()).then_some(rest))
+        .map(|files| Group::offline(changes, files))
+        .collect()
+}
+
+/// Move `files` out of their commits into `groups[target]`, or into a new
+/// commit with `message` when `target` is past the end. Commits left without
+/// files are dropped.
+pub fn move_files(groups: &mut Vec<Group>, files: &[String], target: usize, message: &str) {
+    if target >= groups.len() {
+        groups.push(Group {
+            message: message.to_string(),
+            files: Vec::new(),
+        });
+    }
+    for (i, group) in groups.iter_mut().enumerate() {
+        if i == target {
+            for file in files {
+                if !group.files.contains(file) {
+                    group.files.push(file.clone());
+                }
+            }
+        } else {
+            group.files.retain(|file| !files.contains(file));
+        }
+    }
+    groups.retain(|group| !group.files.is_empty());
+}
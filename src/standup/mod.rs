@@ -0,0 +1,184 @@
+use crate::git::{CommitInfo, GitRepo, StagedChanges};
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+
+pub const SYSTEM_PROMPT: &str = r#"You are an assistant that writes short daily standup updates for software developers.
+Follow these rules:
+1. Use Slack-friendly markdown: *bold* section titles and "•" bullets
+2. Use the sections *Done*, *In progress*, and *Next* (omit a section if there is nothing for it)
+3. Group related commits into a single bullet and describe outcomes, not file names
+4. Keep it under 10 bullets in total
+5. Write in the first person, past tense for done work
+
+Return ONLY the update, without any introduction."#;
+
+/// Everything a developer did since a point in time
+pub struct Activity {
+    pub since_label: String,
+    pub commits: Vec<CommitInfo>,
+    pub staged: StagedChanges,
+    pub unstaged: Vec<String>,
+}
+
+impl Activity {
+    /// Collect commits and uncommitted work from `repo`
+    pub fn collect(
+        repo: &GitRepo,
+        since: &str,
+        author: Option<&str>,
+        all_authors: bool,
+    ) -> Result<Self> {
+        let since_time = parse_since(since)?;
+
+        let author = match author {
+            Some(author) => Some(author.to_string()),
+            None if all_authors => None,
+            None => repo.user_email(),
+        };
+
+        Ok(Self {
+            since_label: since.to_string(),
+            commits: repo.commits_since(since_time, author.as_deref())?,
+            staged: repo.get_staged_changes()?,
+            unstaged: repo.get_unstaged_files()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty() && self.staged.stats.files_changed == 0 && self.unstaged.is_empty()
+    }
+
+    /// Build the prompt describing this activity for the AI
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = format!("Here is my git activity since {}:\n\n", self.since_label);
+
+        if !self.commits.is_empty() {
+            prompt.push_str("Commits:\n");
+            for commit in &self.commits {
+                prompt.push_str(&format!("  - {} ({})\n", commit.summary, commit.id));
+            }
+        }
+
+        let staged = self.staged_files();
+        if !staged.is_empty() {
+            prompt.push_str("\nStaged but not committed:\n");
+            for file in staged {
+                prompt.push_str(&format!("  - {}\n", file));
+            }
+        }
+
+        if !self.unstaged.is_empty() {
+            prompt.push_str("\nModified but not staged:\n");
+            for file in &self.unstaged {
+                prompt.push_str(&format!("  - {}\n", file));
+            }
+        }
+
+        prompt.push_str("\nPlease write my standup update.");
+        prompt
+    }
+
+    /// Format the activity as a standup update without using the AI
+    pub fn to_plain_update(&self) -> String {
+        let mut output = String::new();
+
+        if !self.commits.is_empty() {
+            output.push_str("*Done*\n");
+            for commit in &self.commits {
+                output.push_str(&format!("• {} (`{}`)\n", commit.summary, commit.id));
+            }
+        }
+
+        let staged = self.staged_files();
+        if !staged.is_empty() || !self.unstaged.is_empty() {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str("*In progress*\n");
+            for file in staged.into_iter().chain(self.unstaged.iter()) {
+                output.push_str(&format!("• `{}`\n", file));
+            }
+        }
+
+        output
+    }
+
+    fn staged_files(&self) -> Vec<&String> {
+        self.staged
+            .added
+            .iter()
+            .chain(self.staged.modified.iter())
+            .chain(self.staged.deleted.iter())
+            .chain(self.staged.renamed.iter().map(|(_, new)| new))
+            .chain(self.staged.copied.iter().map(|(_, new)| new))
+            .collect()
+    }
+}
+
+/// Parse a point in time such as `24h`, `3d`, `1w`, `yesterday`, `today`,
+/// a weekday (`monday`), or a date (`2024-05-01`) into seconds since the Unix epoch
+pub fn parse_since(since: &str) -> Result<i64> {
+    let now = Local::now();
+    let input = since.trim().to_lowercase();
+
+    let start_of = |date: NaiveDate| -> Result<i64> {
+        let midnight = date
+            .and_hms_opt(0, 0, 0)
+            .ok_or_else(|| anyhow!("Invalid date '{}'", since))?;
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|dt| dt.timestamp())
+            .ok_or_else(|| anyhow!("Invalid date '{}'", since))
+    };
+
+    match input.as_str() {
+        "today" => return start_of(now.date_naive()),
+        "yesterday" => return start_of(now.date_naive() - Duration::days(1)),
+        _ => {}
+    }
+
+    let weekdays = [
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+    if let Some(index) = weekdays.iter().position(|day| *day == input) {
+        let today = now.weekday().num_days_from_monday() as i64;
+        let mut days_back = (today - index as i64).rem_euclid(7);
+        if days_back == 0 {
+            days_back = 7;
+        }
+        return start_of(now.date_naive() - Duration::days(days_back));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return start_of(date);
+    }
+
+    let unit_start = input.char_indices().last().map(|(i, _)| i).unwrap_or(0);
+    let (amount, unit) = input.split_at(unit_start);
+    let amount: i64 = amount.parse().map_err(|_| {
+        anyhow!(
+            "Could not understand '{}'. Use e.g. 24h, 3d, 1w, yesterday, monday, or 2024-05-01",
+            since
+        )
+    })?;
+    let duration = match unit {
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        _ => {
+            return Err(anyhow!(
+                "Could not understand '{}'. Use e.g. 24h, 3d, 1w, yesterday, monday, or 2024-05-01",
+                since
+            ));
+        }
+    };
+
+    Ok((now - duration).timestamp())
+}
@@ -0,0 +1,353 @@
+//! `gyst status`: where the repository stands (branch, upstream, an operation
+//! in progress, changed files) and what to do next, summarized by the AI or
+//! worked out from the state itself
+
+use crate::git::{GitRepo, Upstream, WorkingTreeStatus};
+use anyhow::Result;
+
+pub const SYSTEM_PROMPT: &str = r#"You look at the state of a git repository and tell the developer where they stand and what to do next.
+Reply in exactly this format, with nothing before or after it:
+SUMMARY: <one sentence of at most 25 words about the state of the work>
+NEXT: <a single command> | <why, in a few words>
+
+Give one to three NEXT lines, the most important first. Suggest gyst commands where one fits:
+- gyst commit: commit the staged changes with a generated message
+- gyst commit --split: split many unrelated staged changes into several commits
+- gyst pr --push: push the branch and open a pull request
+- gyst diff: review the staged changes
+Otherwise suggest plain git commands. Never suggest commands that can lose work, such as reset --hard, clean -f or push --force."#;
+
+/// Files of each kind the prompt lists before it gives a count instead
+const MAX_PROMPT_FILES: usize = 20;
+
+/// How many next steps are shown
+const MAX_STEPS: usize = 3;
+
+/// A suggested command with the reason for it
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub command: String,
+    pub reason: String,
+}
+
+impl Step {
+    fn new(command: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// The state of the repository at one moment
+pub struct Snapshot {
+    /// The current branch, or `None` when HEAD is detached
+    pub branch: Option<String>,
+    /// The abbreviated commit HEAD points at, if there is one
+    pub head: Option<String>,
+    pub upstream: Option<Upstream>,
+    /// The operation git is in the middle of, e.g. `rebase`
+    pub operation: Option<&'static str>,
+    pub tree: WorkingTreeStatus,
+    pub stashes: usize,
+    /// The repository's main branch, if it has one of the usual names
+    pub main_branch: Option<String>,
+    /// Commits on HEAD that are not on the main branch
+    pub new_commits: usize,
+    pub has_origin: bool,
+}
+
+impl Snapshot {
+    pub fn collect(repo: &GitRepo) -> Result<Self> {
+        let main_branch = repo.main_branch();
+        let new_commits = main_branch
+            .as_deref()
+            .and_then(|main| repo.commits_in_range(Some(main), "HEAD").ok())
+            .map_or(0, |commits| commits.len());
+        Ok(Self {
+            branch: (!repo.is_head_detached())
+                .then(|| repo.get_current_branch().ok())
+                .flatten(),
+            head: repo.resolve_commit("HEAD").ok().map(|commit| commit.id),
+            upstream: repo.upstream(),
+            operation: repo.operation_in_progress(),
+            tree: repo.working_tree_status()?,
+            stashes: repo.stash_entries().len(),
+            main_branch,
+            new_commits,
+            has_origin: repo
+                .remote_names()
+                .is_ok_and(|remotes| remotes.iter().any(|remote| remote == "origin")),
+        })
+    }
+
+    /// A one-line description of the state, for when the AI is not asked
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(operation) = self.operation {
+            parts.push(format!("A {} is in progress", operation));
+        }
+        if !self.tree.conflicted.is_empty() {
+            parts.push(count(self.tree.conflicted.len(), "file has", "files have") + " conflicts");
+        }
+        if !self.tree.staged.is_empty() {
+            parts.push(count(self.tree.staged.len(), "file", "files") + " staged");
+        }
+        if !self.tree.unstaged.is_empty() {
+            parts
+                .push(count(self.tree.unstaged.len(), "file", "files") + " changed but not staged");
+        }
+        if !self.tree.untracked.is_empty() {
+            parts.push(count(
+                self.tree.untracked.len(),
+                "untracked path",
+                "untracked paths",
+            ));
+        }
+        if parts.is_empty() {
+            parts.push("The working tree is clean".to_string());
+        }
+        match &self.upstream {
+            Some(upstream) if upstream.ahead > 0 && upstream.behind > 0 => parts.push(format!(
+                "the branch has diverged from {} ({} ahead, {} behind)",
+                upstream.name, upstream.ahead, upstream.behind
+            )),
+            Some(upstream) if upstream.ahead > 0 => parts.push(format!(
+                "{} ahead of {}",
+                count(upstream.ahead, "commit", "commits"),
+                upstream.name
+            )),
+            Some(upstream) if upstream.behind > 0 => parts.push(format!(
+                "{} behind {}",
+                count(upstream.behind, "commit", "commits"),
+                upstream.name
+            )),
+            Some(upstream) => parts.push(format!("up to date with {}", upstream.name)),
+            None if self.branch.is_none() && self.operation.is_none() => {
+                parts.push("HEAD is detached".to_string())
+            }
+            None => {}
+        }
+        let mut summary = parts.join(", ");
+        summary.push('.');
+        summary
+    }
+
+    /// What to do next, worked out from the state: an operation or conflicts
+    /// first, then committing, then syncing with the upstream
+    pub fn next_steps(&self) -> Vec<Step> {
+        let mut steps = Vec::new();
+        let tree = &self.tree;
+
+        if let Some(operation) = self.operation {
+            if !tree.conflicted.is_empty() {
+                steps.push(Step::new(
+                    "git add <file>",
+                    "Mark each conflicted file as resolved once it is fixed",
+                ));
+            }
+            if operation == "bisect" {
+                steps.push(Step::new(
+                    "git bisect reset",
+                    "End the bisect and return to where you started",
+                ));
+            } else {
+                let finish = if operation == "merge" {
+                    "git commit".to_string()
+                } else {
+                    format!("git {} --continue", operation)
+                };
+                let reason = if tree.conflicted.is_empty() {
+                    format!("Carry on with the {}", operation)
+                } else {
+                    format!("Then carry on with the {}", operation)
+                };
+                steps.push(Step::new(finish, reason));
+                steps.push(Step::new(
+                    format!("git {} --abort", operation),
+                    format!("Give up the {} and go back to where you were", operation),
+                ));
+            }
+            steps.truncate(MAX_STEPS);
+            return steps;
+        }
+
+        if self.branch.is_none() && self.head.is_some() {
+            steps.push(Step::new(
+                "git switch -c <name>",
+                "Keep the commits you make here on a branch",
+            ));
+        }
+
+        if !tree.staged.is_empty() {
+            steps.push(Step::new(
+                "gyst commit",
+                format!(
+                    "Commit {} with a generated message",
+                    the(tree.staged.len(), "staged file", "staged files")
+                ),
+            ));
+            if tree.staged.len() >= 10 {
+                steps.push(Step::new(
+                    "gyst commit --split",
+                    "Split the staged changes into focused commits",
+                ));
+            }
+        } else if !tree.unstaged.is_empty() || !tree.untracked.is_empty() {
+            let changed = tree.unstaged.len() + tree.untracked.len();
+            let add = match (tree.unstaged.as_slice(), tree.untracked.as_slice()) {
+                ([(path, _)], []) => format!("git add {}", quote(path)),
+                ([], [path]) => format!("git add {}", quote(path)),
+                _ => "git add -A".to_string(),
+            };
+            steps.push(Step::new(
+                add,
+                format!("Stage {}", the(changed, "changed path", "changed paths")),
+            ));
+            steps.push(Step::new(
+                "gyst commit",
+                "Then commit it with a generated message",
+            ));
+        }
+
+        match &self.upstream {
+            Some(upstream) if upstream.behind > 0 => steps.push(Step::new(
+                "git pull --rebase",
+                format!(
+                    "Bring in {} from {}",
+                    count(upstream.behind, "new commit", "new commits"),
+                    upstream.name
+                ),
+            )),
+            Some(upstream) if upstream.ahead > 0 => steps.push(Step::new(
+                "git push",
+                format!(
+                    "Share {} with {}",
+                    count(upstream.ahead, "commit", "commits"),
+                    upstream.name
+                ),
+            )),
+            None if self.has_origin && self.branch.is_some() && self.new_commits > 0 => {
+                steps.push(Step::new(
+                    "gyst pr --push",
+                    "Publish the branch and open a pull request",
+                ))
+            }
+            _ => {}
+        }
+
+        steps.truncate(MAX_STEPS);
+        steps
+    }
+
+    /// Describe the state for the AI
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = String::from("Here is the state of my repository:\n\n");
+        match (&self.branch, &self.head) {
+            (Some(branch), Some(_)) => prompt.push_str(&format!("Branch: {}\n", branch)),
+            (Some(branch), None) => {
+                prompt.push_str(&format!("Branch: {} (no commits yet)\n", branch))
+            }
+            (None, head) => prompt.push_str(&format!(
+                "HEAD is detached at {}\n",
+                head.as_deref().unwrap_or("an unknown commit")
+            )),
+        }
+        if let Some(main) = &self.main_branch {
+            prompt.push_str(&format!("Main branch: {}\n", main));
+        }
+        match &self.upstream {
+            Some(upstream) => prompt.push_str(&format!(
+                "Upstream: {} ({} ahead, {} behind)\n",
+                upstream.name, upstream.ahead, upstream.behind
+            )),
+            None => prompt.push_str(if self.has_origin {
+                "Upstream: none (the branch has not been pushed to origin)\n"
+            } else {
+                "Upstream: none (no origin remote)\n"
+            }),
+        }
+        if let Some(operation) = self.operation {
+            prompt.push_str(&format!("In progress: {}\n", operation));
+        }
+        if self.stashes > 0 {
+            prompt.push_str(&format!("Stash entries: {}\n", self.stashes));
+        }
+
+        let labeled = |files: &[(String, &str)]| -> Vec<String> {
+            files
+                .iter()
+                .map(|(path, label)| format!("{} ({})", path, label))
+                .collect()
+        };
+        push_files(&mut prompt, "Staged", &labeled(&self.tree.staged));
+        push_files(&mut prompt, "Not staged", &labeled(&self.tree.unstaged));
+        push_files(&mut prompt, "Untracked", &self.tree.untracked);
+        push_files(&mut prompt, "Conflicted", &self.tree.conflicted);
+        if self.tree.is_clean() {
+            prompt.push_str("\nThe working tree is clean.\n");
+        }
+        prompt
+    }
+}
+
+/// The summary and next steps of a reply in the SUMMARY/NEXT format, or
+/// `None` if it has no summary
+pub fn parse_reply(reply: &str) -> Option<(String, Vec<Step>)> {
+    let mut summary = None;
+    let mut steps = Vec::new();
+    for line in reply.lines().map(str::trim) {
+        if let Some(text) = line.strip_prefix("SUMMARY:") {
+            summary = Some(text.trim().to_string());
+        } else if let Some(text) = line.strip_prefix("NEXT:") {
+            let (command, reason) = text.split_once('|').unwrap_or((text, ""));
+            let command = command.trim().trim_matches('`');
+            if !command.is_empty() {
+                steps.push(Step::new(command, reason.trim()));
+            }
+        }
+    }
+    steps.truncate(MAX_STEPS);
+    summary
+        .filter(|summary| !summary.is_empty())
+        .map(|summary| (summary, steps))
+}
+
+fn push_files(prompt: &mut String, heading: &str, files: &[String]) {
+    if files.is_empty() {
+        return;
+    }
+    prompt.push_str(&format!("\n{} ({}):\n", heading, files.len()));
+    for file in files.iter().take(MAX_PROMPT_FILES) {
+        prompt.push_str(&format!("  - {}\n", file));
+    }
+    if files.len() > MAX_PROMPT_FILES {
+        prompt.push_str(&format!(
+            "  ... and {} more\n",
+            files.len() - MAX_PROMPT_FILES
+        ));
+    }
+}
+
+/// `n` with the singular or plural noun, e.g. `1 file` or `3 files`
+fn count(n: usize, one: &str, many: &str) -> String {
+    format!("{} {}", n, if n == 1 { one } else { many })
+}
+
+/// `the file` for one, `the 3 files` for more
+fn the(n: usize, one: &str, many: &str) -> String {
+    if n == 1 {
+        format!("the {}", one)
+    } else {
+        format!("the {} {}", n, many)
+    }
+}
+
+/// `path` quoted for a shell if it needs it
+fn quote(path: &str) -> String {
+    if path.contains(|c: char| c.is_whitespace() || "'\"$`\\*?[]()&;|<>#~".contains(c)) {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    } else {
+        path.to_string()
+    }
+}
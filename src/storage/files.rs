@@ -0,0 +1,90 @@
+use super::Store;
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Documents as `<name>.json` and logs as `<name>.jsonl` in one directory
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    pub fn document_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    pub fn log_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", name))
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))
+    }
+}
+
+impl Store for FileStore {
+    fn read(&self, name: &str) -> Result<Option<String>> {
+        let path = self.document_path(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Some(contents))
+    }
+
+    fn write(&self, name: &str, contents: &str) -> Result<()> {
+        self.create_dir()?;
+        let path = self.document_path(name);
+        // Write a temporary file and move it into place, so readers never see half a document
+        let mut temp = tempfile::NamedTempFile::new_in(&self.dir)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        temp.write_all(contents.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        temp.persist(&path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> Result<bool> {
+        let path = self.document_path(name);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?;
+        Ok(true)
+    }
+
+    fn append(&self, name: &str, record: &str) -> Result<()> {
+        self.create_dir()?;
+        let path = self.log_path(name);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", record).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn records(&self, name: &str) -> Result<Vec<String>> {
+        let path = self.log_path(name);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
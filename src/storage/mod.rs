@@ -0,0 +1,49 @@
+//! Where gyst keeps the data it collects: usage statistics, the history index,
+//! file summaries and the audit log.
+//!
+//! With `storage.backend = "jsonl"` (the default) each document is a JSON file
+//! and each log a JSONL file. With `"sqlite"` they live in one `gyst.db` per
+//! directory instead, where any SQLite client can query them. Files written
+//! before switching to SQLite are copied into the database when first read.
+
+mod files;
+mod sqlite;
+
+use crate::config::Config;
+use anyhow::Result;
+use files::FileStore;
+use sqlite::SqliteStore;
+use std::path::Path;
+
+/// The values `storage.backend` accepts
+pub const BACKENDS: &[&str] = &["jsonl", "sqlite"];
+
+/// Documents that are replaced as a whole, and logs that only grow
+pub trait Store {
+    /// The document saved as `name`, or `None` if there is none
+    fn read(&self, name: &str) -> Result<Option<String>>;
+
+    /// Save `contents` as the document `name`, replacing what was there
+    fn write(&self, name: &str, contents: &str) -> Result<()>;
+
+    /// Delete the document `name`. Returns whether there was one.
+    fn remove(&self, name: &str) -> Result<bool>;
+
+    /// Add `record` (one line of JSON) to the end of the log `name`
+    fn append(&self, name: &str, record: &str) -> Result<()>;
+
+    /// The records of the log `name`, oldest first
+    fn records(&self, name: &str) -> Result<Vec<String>>;
+}
+
+/// The store for `dir` (`~/.gyst` or a repository's `.git/gyst`), using the
+/// backend from your own `~/.gyst/config.toml`
+pub fn open(dir: &Path) -> Result<Box<dyn Store>> {
+    let backend = Config::load()
+        .map(|config| config.storage.backend)
+        .unwrap_or_default();
+    if backend == "sqlite" {
+        return Ok(Box::new(SqliteStore::open(dir)?));
+    }
+    Ok(Box::new(FileStore::new(dir)))
+}
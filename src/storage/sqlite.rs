@@ -0,0 +1,150 @@
+use super::Store;
+use super::files::FileStore;
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DATABASE_FILE: &str = "gyst.db";
+
+/// How long to wait for another gyst process that is writing to the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS documents (
+    name TEXT PRIMARY KEY,
+    contents TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log TEXT NOT NULL,
+    contents TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS records_by_log ON records (log, id);
+CREATE TABLE IF NOT EXISTS imported (
+    name TEXT PRIMARY KEY
+);
+";
+
+/// Documents and logs in `gyst.db`. Each record is a row of the `records`
+/// table, so SQLite's JSON functions can query them.
+pub struct SqliteStore {
+    connection: Connection,
+    path: PathBuf,
+    /// Where the JSON backend keeps the same data, copied in once
+    files: FileStore,
+}
+
+impl SqliteStore {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(DATABASE_FILE);
+        let connection = Connection::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to set up {}", path.display()))?;
+        Ok(Self {
+            connection,
+            path,
+            files: FileStore::new(dir),
+        })
+    }
+
+    /// Copy the JSON backend's file for `name` into the database, the first
+    /// time `name` is used
+    fn import(&self, name: &str) -> Result<()> {
+        let imported: Option<String> = self
+            .connection
+            .query_row("SELECT name FROM imported WHERE name = ?1", [name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        if imported.is_some() {
+            return Ok(());
+        }
+
+        let document = self.files.read(name)?;
+        let records = self.files.records(name)?;
+        let transaction = self.connection.unchecked_transaction()?;
+        if let Some(contents) = document {
+            transaction.execute(
+                "INSERT OR IGNORE INTO documents (name, contents, updated_at) VALUES (?1, ?2, ?3)",
+                params![name, contents, chrono::Utc::now().timestamp()],
+            )?;
+        }
+        for record in records {
+            transaction.execute(
+                "INSERT INTO records (log, contents) VALUES (?1, ?2)",
+                params![name, record],
+            )?;
+        }
+        transaction.execute("INSERT INTO imported (name) VALUES (?1)", [name])?;
+        transaction
+            .commit()
+            .with_context(|| format!("Failed to import {} into {}", name, self.path.display()))
+    }
+}
+
+impl Store for SqliteStore {
+    fn read(&self, name: &str) -> Result<Option<String>> {
+        self.import(name)?;
+        self.connection
+            .query_row(
+                "SELECT contents FROM documents WHERE name = ?1",
+                [name],
+                |row| row.get(0),
+            )
+            .optional()
+            .with_context(|| format!("Failed to read {} from {}", name, self.path.display()))
+    }
+
+    fn write(&self, name: &str, contents: &str) -> Result<()> {
+        self.import(name)?;
+        self.connection
+            .execute(
+                "INSERT INTO documents (name, contents, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (name) DO UPDATE SET contents = excluded.contents, updated_at = excluded.updated_at",
+                params![name, contents, chrono::Utc::now().timestamp()],
+            )
+            .with_context(|| format!("Failed to write {} to {}", name, self.path.display()))?;
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> Result<bool> {
+        self.import(name)?;
+        let removed = self
+            .connection
+            .execute("DELETE FROM documents WHERE name = ?1", [name])
+            .with_context(|| format!("Failed to delete {} from {}", name, self.path.display()))?;
+        // The copy is gone too, so switching back does not bring the data back
+        self.files.remove(name)?;
+        Ok(removed > 0)
+    }
+
+    fn append(&self, name: &str, record: &str) -> Result<()> {
+        self.import(name)?;
+        self.connection
+            .execute(
+                "INSERT INTO records (log, contents) VALUES (?1, ?2)",
+                params![name, record],
+            )
+            .with_context(|| format!("Failed to write {} to {}", name, self.path.display()))?;
+        Ok(())
+    }
+
+    fn records(&self, name: &str) -> Result<Vec<String>> {
+        self.import(name)?;
+        let mut statement = self
+            .connection
+            .prepare("SELECT contents FROM records WHERE log = ?1 ORDER BY id")?;
+        let records = statement
+            .query_map([name], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .with_context(|| format!("Failed to read {} from {}", name, self.path.display()))?;
+        Ok(records)
+    }
+}
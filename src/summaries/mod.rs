@@ -0,0 +1,156 @@
+//! Short summaries of large files' diffs, cached by the change's blob IDs.
+//!
+//! When the staged diff is too long for the prompt, files with a large patch
+//! are described by a summary instead. The AI writes each summary once; it is
+//! kept in the repository's `.git/gyst`, so asking again for the same changes
+//! (regenerating, `suggest` after `commit`) sends the summary, not the patch.
+
+use crate::git::{FilePatch, GitRepo};
+use crate::storage;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const SUMMARIES_DOCUMENT: &str = "file-summaries";
+
+/// Patches shorter than this are always sent as they are
+const MIN_SUMMARIZED_BYTES: usize = 4_000;
+
+/// Most of one file's patch sent to be summarized
+const MAX_FILE_BYTES: usize = 12_000;
+
+/// Summaries kept; the ones used least recently are dropped first
+const MAX_ENTRIES: usize = 1_000;
+
+pub const SYSTEM_PROMPT: &str = r#"You summarize the diffs of single files for another assistant that writes commit messages.
+Follow these rules:
+1. Write one or two sentences per file on what changed: behavior, functions, types or settings added, removed or changed
+2. Be specific, and leave out what the file name already says
+3. Use the paths exactly as given
+
+Reply with one block per file and nothing else:
+FILE: <path>
+SUMMARY: <summary>"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    summary: String,
+    /// Unix timestamp of the last time the summary was used
+    used: i64,
+}
+
+/// The summaries saved for a repository, by `FilePatch::key`
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap<String, Entry>,
+    changed: bool,
+}
+
+impl Cache {
+    /// The saved summaries; none if they cannot be read
+    pub fn load(repo: &GitRepo) -> Self {
+        let entries = storage::open(&repo.data_dir())
+            .ok()
+            .and_then(|store| store.read(SUMMARIES_DOCUMENT).ok().flatten())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self {
+            entries,
+            changed: false,
+        }
+    }
+
+    /// The patches that would be summarized but have no summary yet
+    pub fn missing<'a>(&self, patches: &'a [FilePatch]) -> Vec<&'a FilePatch> {
+        patches
+            .iter()
+            .filter(|patch| worth_summarizing(patch) && !self.entries.contains_key(&patch.key))
+            .collect()
+    }
+
+    /// The summaries for `patches`, by key, marking them as used
+    pub fn take(&mut self, patches: &[FilePatch]) -> HashMap<String, String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut found = HashMap::new();
+        for patch in patches.iter().filter(|patch| worth_summarizing(patch)) {
+            if let Some(entry) = self.entries.get_mut(&patch.key) {
+                entry.used = now;
+                self.changed = true;
+                found.insert(patch.key.clone(), entry.summary.clone());
+            }
+        }
+        found
+    }
+
+    pub fn insert(&mut self, key: String, summary: String) {
+        let used = chrono::Utc::now().timestamp();
+        self.entries.insert(key, Entry { summary, used });
+        self.changed = true;
+    }
+
+    /// Write the summaries back if anything changed
+    pub fn save(&mut self, repo: &GitRepo) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            let mut used: Vec<i64> = self.entries.values().map(|entry| entry.used).collect();
+            used.sort_unstable_by(|a, b| b.cmp(a));
+            let oldest_kept = used[MAX_ENTRIES - 1];
+            self.entries.retain(|_, entry| entry.used >= oldest_kept);
+        }
+        let contents =
+            serde_json::to_string(&self.entries).context("Failed to serialize file summaries")?;
+        storage::open(&repo.data_dir())?
+            .write(SUMMARIES_DOCUMENT, &contents)
+            .context("Failed to write file summaries")?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+fn worth_summarizing(patch: &FilePatch) -> bool {
+    patch.text.len() >= MIN_SUMMARIZED_BYTES
+}
+
+/// Build the prompt asking for summaries of `patches`, each cut to
+/// `MAX_FILE_BYTES`, stopping before `max_bytes`
+pub fn build_prompt(patches: &[&FilePatch], max_bytes: usize) -> String {
+    let mut prompt = String::new();
+    for patch in patches {
+        let mut end = patch.text.len().min(MAX_FILE_BYTES);
+        while !patch.text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let text = &patch.text[..end];
+        if !prompt.is_empty() && prompt.len() + text.len() > max_bytes {
+            break;
+        }
+        prompt.push_str(&format!("FILE: {}\n{}\n", patch.path, text));
+        if end < patch.text.len() {
+            prompt.push_str("[rest of this file's diff left out]\n");
+        }
+        prompt.push('\n');
+    }
+    prompt
+}
+
+/// Read the summaries in `reply`, by the key of the patch they describe.
+/// Paths that were not asked about are ignored.
+pub fn parse_reply(reply: &str, patches: &[&FilePatch]) -> Vec<(String, String)> {
+    let mut summaries = Vec::new();
+    let mut current: Option<&FilePatch> = None;
+    for line in reply.lines() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("FILE:") {
+            let path = path.trim().trim_matches('`');
+            current = patches.iter().copied().find(|patch| patch.path == path);
+        } else if let Some(summary) = line.strip_prefix("SUMMARY:")
+            && let Some(patch) = current.take()
+            && !summary.trim().is_empty()
+        {
+            summaries.push((patch.key.clone(), summary.trim().to_string()));
+        }
+    }
+    summaries
+}
@@ -0,0 +1,163 @@
+//! Opt-in usage statistics.
+//!
+//! Nothing is recorded unless `telemetry.enabled` is set in your own
+//! `~/.gyst/config.toml`. Only command names (e.g. `branch health`, never
+//! arguments), how long they took and whether they succeeded are kept, as
+//! running totals in `~/.gyst/stats.json` (or `gyst.db`, see `storage`). With `telemetry.upload`, the totals
+//! gathered since the last upload are sent to the gyst server once a day.
+
+use crate::config::Config;
+use crate::logging;
+use crate::server::ServerClient;
+use crate::storage::{self, Store};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const STATS_DOCUMENT: &str = "stats";
+
+/// Upload at most this often
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Running totals for one command
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommandStats {
+    pub runs: u64,
+    pub failures: u64,
+    /// Time spent in the command, in milliseconds
+    pub total_ms: u64,
+}
+
+impl CommandStats {
+    fn add(&mut self, duration: Duration, success: bool) {
+        self.runs += 1;
+        if !success {
+            self.failures += 1;
+        }
+        self.total_ms += duration.as_millis() as u64;
+    }
+
+    pub fn average(&self) -> Duration {
+        Duration::from_millis(self.total_ms / self.runs.max(1))
+    }
+}
+
+/// What is kept in `~/.gyst/stats.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    /// When recording started, in seconds since the Unix epoch
+    pub since: i64,
+    pub commands: BTreeMap<String, CommandStats>,
+    /// Totals not sent to the server yet
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pending: BTreeMap<String, CommandStats>,
+    /// When sending totals was last tried, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_upload: Option<i64>,
+}
+
+/// The body of an upload: the gyst version and per-command totals, nothing else
+#[derive(Serialize)]
+struct Upload<'a> {
+    version: &'static str,
+    commands: &'a BTreeMap<String, CommandStats>,
+}
+
+impl Stats {
+    /// The recorded statistics, or `None` if nothing was ever recorded
+    pub fn load() -> Result<Option<Self>> {
+        let Some(contents) = stats_store()?.read(STATS_DOCUMENT)? else {
+            return Ok(None);
+        };
+        let stats = serde_json::from_str(&contents).context("Failed to parse statistics")?;
+        Ok(Some(stats))
+    }
+
+    /// Delete all recorded statistics. Returns whether there were any.
+    pub fn clear() -> Result<bool> {
+        stats_store()?.remove(STATS_DOCUMENT)
+    }
+
+    fn save(&self) -> Result<()> {
+        let contents =
+            serde_json::to_string_pretty(self).context("Failed to serialize statistics")?;
+        stats_store()?
+            .write(STATS_DOCUMENT, &contents)
+            .context("Failed to save statistics")
+    }
+
+    fn upload_due(&self, now: i64) -> bool {
+        !self.pending.is_empty()
+            && self
+                .last_upload
+                .is_none_or(|last| now - last >= UPLOAD_INTERVAL.as_secs() as i64)
+    }
+}
+
+/// Add one run of `command` to the statistics if they are turned on. Never fails:
+/// problems are only logged with `--verbose`.
+pub async fn record(command: &str, duration: Duration, success: bool) {
+    // Looking at or clearing the statistics is not worth counting
+    if command == "stats" {
+        return;
+    }
+    // Only the global settings count, so a repository cannot opt you in
+    let Ok(config) = Config::load() else {
+        return;
+    };
+    if !config.telemetry.enabled {
+        return;
+    }
+    if let Err(e) = update(&config, command, duration, success).await {
+        logging::log(|| format!("Could not record usage statistics: {:#}", e));
+    }
+}
+
+async fn update(config: &Config, command: &str, duration: Duration, success: bool) -> Result<()> {
+    let now = chrono::Local::now().timestamp();
+    let mut stats = Stats::load()?.unwrap_or_else(|| Stats {
+        since: now,
+        ..Stats::default()
+    });
+    stats
+        .commands
+        .entry(command.to_string())
+        .or_default()
+        .add(duration, success);
+
+    if config.telemetry.upload {
+        stats
+            .pending
+            .entry(command.to_string())
+            .or_default()
+            .add(duration, success);
+        if stats.upload_due(now) {
+            let upload = Upload {
+                version: env!("CARGO_PKG_VERSION"),
+                commands: &stats.pending,
+            };
+            match ServerClient::new(config.clone())
+                .upload_stats(&upload)
+                .await
+            {
+                Ok(()) => stats.pending.clear(),
+                // Keep the totals and try again tomorrow
+                Err(e) => logging::log(|| format!("{:#}", e)),
+            }
+            stats.last_upload = Some(now);
+        }
+    } else {
+        stats.pending.clear();
+    }
+    stats.save()
+}
+
+/// The store in `~/.gyst`, next to the configuration
+fn stats_store() -> Result<Box<dyn Store>> {
+    let path = Config::get_config_path()?;
+    let dir = path
+        .parent()
+        .context("Config path has no parent directory")?;
+    storage::open(dir)
+}
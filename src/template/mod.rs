@@ -0,0 +1,429 @@
+use crate::commitlint;
+use crate::config::Config;
+use crate::workspace;
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+
+/// Built-in presets selectable with `commit.template` or `--template`
+pub const PRESETS: &[&str] = &[
+    "conventional",
+    "plain",
+    "gitmoji",
+    "angular",
+    "jira-prefixed",
+];
+
+const CONVENTIONAL_TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "chore", "ci", "build",
+];
+
+const ANGULAR_TYPES: &[&str] = &[
+    "build", "ci", "docs", "feat", "fix", "perf", "refactor", "test",
+];
+
+const GITMOJIS: &[(&str, &str)] = &[
+    ("feat", "✨"),
+    ("fix", "🐛"),
+    ("docs", "📝"),
+    ("style", "🎨"),
+    ("refactor", "♻️"),
+    ("perf", "⚡️"),
+    ("test", "✅"),
+    ("chore", "🔧"),
+    ("ci", "👷"),
+    ("build", "📦️"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Style {
+    Conventional,
+    Angular,
+    Plain,
+    Gitmoji,
+    JiraPrefixed,
+    Custom,
+}
+
+/// How commit messages are written: the rules given to the AI and the checks
+/// applied to the result
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    style: Style,
+    rules: String,
+    required_sections: Vec<String>,
+    subject_pattern: Option<Regex>,
+    commitlint: Option<commitlint::Rules>,
+    /// The packages of a monorepo, whose names are the only scopes allowed
+    workspace: Option<workspace::Workspace>,
+}
+
+impl Template {
+    /// Look up `commit.template` among the user-defined templates in
+    /// `commit.templates` and the built-in presets
+    pub fn resolve(config: &Config) -> Result<Self> {
+        let name = config.commit.template.as_str();
+
+        if let Some(custom) = config.commit.templates.get(name) {
+            let subject_pattern = custom
+                .subject_pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .with_context(|| format!("Invalid subject_pattern in commit.templates.{}", name))?;
+            return Ok(Self {
+                name: name.to_string(),
+                style: Style::Custom,
+                rules: custom.instructions.trim().to_string(),
+                required_sections: custom.required_sections.clone(),
+                subject_pattern,
+                commitlint: None,
+                workspace: None,
+            });
+        }
+
+        let (style, rules) = match name {
+            "conventional" => (
+                Style::Conventional,
+                format!(
+                    "Use the conventional commit format: <type>(<scope>): <description>\nTypes: {}",
+                    CONVENTIONAL_TYPES.join(", ")
+                ),
+            ),
+            "angular" => (
+                Style::Angular,
+                format!(
+                    "Use the Angular commit format: <type>(<scope>): <short summary>\nThe scope is required and names the affected package or module\nTypes: {}\nExplain the motivation for the change in the body",
+                    ANGULAR_TYPES.join(", ")
+                ),
+            ),
+            "plain" => (
+                Style::Plain,
+                "Write a plain subject line without a type prefix, starting with a capital letter (e.g. \"Add login form validation\")".to_string(),
+            ),
+            "gitmoji" => (
+                Style::Gitmoji,
+                format!(
+                    "Start the subject line with the gitmoji that matches the change, followed by a space and the description (e.g. \"✨ Add login form\")\nGitmojis: {}",
+                    GITMOJIS
+                        .iter()
+                        .map(|(kind, emoji)| format!("{} {}", emoji, kind))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+            "jira-prefixed" => (
+                Style::JiraPrefixed,
+                "Start the subject line with the ticket ID in square brackets, followed by a plain description (e.g. \"[JIRA-123] Add login form\")".to_string(),
+            ),
+            _ => {
+                let mut available: Vec<&str> = PRESETS.to_vec();
+                available.extend(config.commit.templates.keys().map(String::as_str));
+                return Err(anyhow!(
+                    "Unknown commit template '{}'. Available templates: {}",
+                    name,
+                    available.join(", ")
+                ));
+            }
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            style,
+            rules,
+            required_sections: Vec::new(),
+            subject_pattern: None,
+            commitlint: None,
+            workspace: None,
+        })
+    }
+
+    /// Also follow the project's commitlint rules, so generated messages pass its commit-msg hook
+    pub fn with_commitlint(mut self, rules: Option<commitlint::Rules>) -> Self {
+        self.commitlint = rules;
+        self
+    }
+
+    /// Also require the scope to name a package of the monorepo, if the
+    /// repository is one (see `workspace`)
+    pub fn with_workspace(mut self, workspace: Option<workspace::Workspace>) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    pub fn workspace(&self) -> Option<&workspace::Workspace> {
+        self.workspace.as_ref()
+    }
+
+    /// Whether the subject line has a `type(scope):` prefix
+    pub fn uses_scopes(&self) -> bool {
+        matches!(self.style, Style::Conventional | Style::Angular)
+    }
+
+    /// Whether this is the default conventional commits preset
+    pub fn is_conventional(&self) -> bool {
+        self.style == Style::Conventional
+    }
+
+    /// Whether messages must start with a ticket ID taken from the branch name
+    pub fn requires_ticket(&self) -> bool {
+        self.style == Style::JiraPrefixed
+    }
+
+    /// The system prompt for generating messages in this format
+    pub fn system_prompt(&self, max_subject_length: usize) -> String {
+        let mut prompt = String::from(
+            "You are an AI assistant that helps developers write clear and meaningful git commit messages.\nFollow these rules:\n",
+        );
+
+        let mut rules: Vec<String> = self.rules.lines().map(str::to_string).collect();
+        rules.push(format!(
+            "Keep the subject line under {} characters",
+            max_subject_length
+        ));
+        rules.push("Use the imperative mood (\"add\" not \"added\")".to_string());
+        rules.push("Don't end the subject line with a period".to_string());
+        rules.push("Focus on WHY and WHAT, not HOW".to_string());
+        rules.push("If there are breaking changes, add BREAKING CHANGE: in the body".to_string());
+        if let Some(commitlint) = &self.commitlint {
+            rules.extend(commitlint.prompt_rules());
+        }
+        for section in &self.required_sections {
+            rules.push(format!("Include a \"{}:\" section in the body", section));
+        }
+
+        for (i, rule) in rules.iter().enumerate() {
+            prompt.push_str(&format!("{}. {}\n", i + 1, rule));
+        }
+
+        prompt.push_str("\nReturn ONLY the commit message, without any prefixes or explanations.");
+        prompt
+    }
+
+    /// The template and commitlint rules as a prompt instruction, for the server
+    /// which uses conventional commits unless told otherwise
+    pub fn prompt_hint(&self) -> Option<String> {
+        let mut rules = Vec::new();
+        if !self.is_conventional() {
+            rules.push(format!(
+                "Ignore the conventional commit format and follow these rules instead: {}",
+                self.rules.replace('\n', "; ")
+            ));
+        }
+        for section in &self.required_sections {
+            rules.push(format!("include a \"{}:\" section in the body", section));
+        }
+        if let Some(commitlint) = &self.commitlint {
+            rules.extend(commitlint.prompt_rules());
+        }
+
+        if rules.is_empty() {
+            None
+        } else {
+            Some(rules.join("; "))
+        }
+    }
+
+    /// Strip any explanation the model put before the message
+    pub fn clean(&self, message: &str) -> String {
+        let message = message
+            .trim()
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim();
+
+        match self.style {
+            Style::Conventional | Style::Angular => CONVENTIONAL_TYPES
+                .iter()
+                .find_map(|kind| message.find(kind))
+                .map(|idx| message[idx..].trim().to_string())
+                .unwrap_or_else(|| message.to_string()),
+            _ => message.to_string(),
+        }
+    }
+
+    /// Convert a `type(scope): description` message (as produced by the offline
+    /// generator) into this template's format
+    pub fn convert_conventional(&self, message: &str) -> String {
+        let (subject, body) = match message.split_once('\n') {
+            Some((subject, body)) => (subject, Some(body)),
+            None => (message, None),
+        };
+
+        let (kind, description) = match subject.split_once(": ") {
+            Some((prefix, description)) => {
+                (prefix.split('(').next().unwrap_or(prefix), description)
+            }
+            None => ("chore", subject),
+        };
+
+        let subject = match self.style {
+            Style::Conventional | Style::Angular => match &self.commitlint {
+                // Swap in a type the project's commitlint config accepts
+                Some(rules)
+                    if subject.starts_with(kind)
+                        && !rules.types.is_empty()
+                        && !rules.types.iter().any(|t| t == kind) =>
+                {
+                    let allowed = rules
+                        .types
+                        .iter()
+                        .find(|t| *t == "chore")
+                        .unwrap_or(&rules.types[0]);
+                    format!("{}{}", allowed, &subject[kind.len()..])
+                }
+                _ => subject.to_string(),
+            },
+            Style::Custom => subject.to_string(),
+            Style::Plain | Style::JiraPrefixed => capitalize(description),
+            Style::Gitmoji => {
+                let emoji = GITMOJIS
+                    .iter()
+                    .find(|(k, _)| *k == kind)
+                    .map(|(_, emoji)| *emoji)
+                    .unwrap_or("🔧");
+                format!("{} {}", emoji, capitalize(description))
+            }
+        };
+
+        let subject = match (&self.workspace, conventional_scope(&subject)) {
+            (Some(workspace), Some(scope)) if self.uses_scopes() => {
+                // The generator scopes by directory: name the package instead,
+                // or leave out a directory that is not one
+                let package = workspace
+                    .packages
+                    .iter()
+                    .find(|p| p.name == scope || p.dir.rsplit('/').next() == Some(scope));
+                let scoped = format!("({})", scope);
+                let replacement = package.map_or(String::new(), |p| format!("({})", p.name));
+                subject.replacen(&scoped, &replacement, 1)
+            }
+            _ => subject,
+        };
+
+        match body {
+            Some(body) => format!("{}\n{}", subject, body),
+            None => subject,
+        }
+    }
+
+    /// Check `message` against the template and return a description of each problem
+    pub fn validate(&self, message: &str, max_subject_length: usize) -> Vec<String> {
+        let mut problems = Vec::new();
+        let subject = message.lines().next().unwrap_or("").trim();
+
+        if subject.is_empty() {
+            problems.push("The subject line is empty".to_string());
+            return problems;
+        }
+
+        let length = subject.chars().count();
+        if length > max_subject_length {
+            problems.push(format!(
+                "The subject line is {} characters long (limit: {})",
+                length, max_subject_length
+            ));
+        }
+
+        let format_ok = match self.style {
+            Style::Conventional => conventional_subject(subject, CONVENTIONAL_TYPES, false),
+            Style::Angular => conventional_subject(subject, ANGULAR_TYPES, true),
+            Style::Plain => {
+                subject.chars().next().is_some_and(|c| !c.is_lowercase()) && !subject.ends_with('.')
+            }
+            Style::Gitmoji => {
+                GITMOJIS
+                    .iter()
+                    .any(|(_, emoji)| subject.starts_with(emoji.trim_end_matches('\u{fe0f}')))
+                    || (subject.starts_with(':') && subject[1..].contains(": "))
+            }
+            Style::JiraPrefixed => Regex::new(r"^\[[A-Z][A-Z0-9]*-\d+\] \S")
+                .map(|re| re.is_match(subject))
+                .unwrap_or(true),
+            Style::Custom => self
+                .subject_pattern
+                .as_ref()
+                .is_none_or(|re| re.is_match(subject)),
+        };
+        if !format_ok {
+            problems.push(format!(
+                "The subject line does not follow the '{}' template",
+                self.name
+            ));
+        }
+
+        // A section is a body line like "Why: ..." or a "## Why" heading
+        for section in &self.required_sections {
+            let name = section.to_lowercase();
+            let found = message.lines().skip(1).any(|line| {
+                let line = line.trim().trim_start_matches('#').trim().to_lowercase();
+                line.strip_prefix(&name)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+            });
+            if !found {
+                problems.push(format!("Missing required section '{}'", section));
+            }
+        }
+
+        // A commitlint scope-enum already says which scopes are allowed
+        if self.uses_scopes()
+            && let Some(workspace) = &self.workspace
+            && self
+                .commitlint
+                .as_ref()
+                .is_none_or(|rules| rules.scopes.is_empty())
+            && let Some(scope) = conventional_scope(subject)
+            && !scope
+                .split([',', '/'])
+                .all(|s| workspace.packages.iter().any(|p| p.name == s.trim()))
+        {
+            problems.push(format!(
+                "Scope '{}' is not a package of this workspace (packages: {})",
+                scope,
+                workspace.names().join(", ")
+            ));
+        }
+
+        if let Some(commitlint) = &self.commitlint {
+            problems.extend(commitlint.validate(message));
+        }
+
+        problems
+    }
+}
+
+/// The scope of a `type(scope)!: description` subject line, if it has one
+fn conventional_scope(subject: &str) -> Option<&str> {
+    let (prefix, _) = subject.split_once(": ")?;
+    let (_, rest) = prefix.trim_end_matches('!').split_once('(')?;
+    rest.strip_suffix(')').filter(|scope| !scope.is_empty())
+}
+
+/// Check a `type(scope)!: description` subject line
+fn conventional_subject(subject: &str, types: &[&str], scope_required: bool) -> bool {
+    let Some((prefix, description)) = subject.split_once(": ") else {
+        return false;
+    };
+    if description.trim().is_empty() {
+        return false;
+    }
+
+    let prefix = prefix.trim_end_matches('!');
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, rest)) => match rest.strip_suffix(')') {
+            Some(scope) if !scope.is_empty() => (kind, Some(scope)),
+            _ => return false,
+        },
+        None => (prefix, None),
+    };
+
+    types.contains(&kind) && (scope.is_some() || !scope_required)
+}
+
+pub fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,308 @@
+use crate::git::{FileVersions, GitRepo};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+/// Show at most this many tests of each kind in the prompt
+const MAX_TESTS: usize = 8;
+
+/// Show at most this many assertions per test
+const MAX_ASSERTS: usize = 3;
+
+/// Assertions are cut off after this many characters
+const MAX_ASSERT_LEN: usize = 100;
+
+/// A change still counts as test-only with up to this many changed lines outside tests
+const MAX_SOURCE_LINES: usize = 20;
+
+/// A test function and the assertions that show what it checks
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    pub file: String,
+    pub asserts: Vec<String>,
+}
+
+/// What a change made up of tests (plus small source tweaks) tests
+#[derive(Debug, Default)]
+pub struct TestChange {
+    pub added: Vec<TestCase>,
+    /// Existing tests whose bodies changed
+    pub updated: Vec<TestCase>,
+    pub removed: Vec<String>,
+    /// Files with changes outside tests
+    pub source_files: Vec<String>,
+    /// Lines added or removed outside tests
+    pub source_lines: usize,
+}
+
+impl TestChange {
+    /// Look at the staged changes. Returns `None` unless they add, change or
+    /// remove tests and change at most a few lines of other code.
+    pub fn staged(repo: &GitRepo) -> Result<Option<Self>> {
+        let from = repo.has_head().then_some("HEAD");
+        let files = repo.changed_file_versions(from, None, |_| true)?;
+        Ok(Self::compare(&files))
+    }
+
+    fn compare(files: &[FileVersions]) -> Option<Self> {
+        let mut change = Self::default();
+        let mut test_lines = 0;
+        for file in files {
+            let old = file.old.as_deref().unwrap_or("");
+            let new = file.new.as_deref().unwrap_or("");
+            let (old_tests, old_rest) = split_tests(&file.path, old);
+            let (new_tests, new_rest) = split_tests(&file.path, new);
+
+            for (name, body) in &new_tests {
+                let case = || TestCase {
+                    name: name.clone(),
+                    file: file.path.clone(),
+                    asserts: assertions(body),
+                };
+                match old_tests.get(name) {
+                    None => change.added.push(case()),
+                    Some(old_body) if old_body != body => change.updated.push(case()),
+                    Some(_) => {}
+                }
+            }
+            change.removed.extend(
+                old_tests
+                    .keys()
+                    .filter(|name| !new_tests.contains_key(*name))
+                    .cloned(),
+            );
+            test_lines += changed_lines(
+                &old_tests
+                    .values()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                &new_tests
+                    .values()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+
+            let source_lines = changed_lines(&old_rest, &new_rest);
+            if source_lines > 0 {
+                change.source_files.push(file.path.clone());
+                change.source_lines += source_lines;
+            }
+        }
+
+        let has_tests =
+            !change.added.is_empty() || !change.updated.is_empty() || !change.removed.is_empty();
+        (has_tests && change.source_lines <= MAX_SOURCE_LINES && change.source_lines < test_lines)
+            .then_some(change)
+    }
+
+    /// Instructions for the AI to describe the behavior the tests cover
+    pub fn prompt_hint(&self) -> String {
+        let mut hint = String::from(
+            "These changes are mostly tests. Write a 'test' commit whose subject says which behavior or edge cases the tests cover (e.g. 'test(parser): cover empty-input edge cases'), not just that tests were added or changed. Use the module under test as the scope.",
+        );
+        for (label, cases) in [("New tests", &self.added), ("Changed tests", &self.updated)] {
+            if cases.is_empty() {
+                continue;
+            }
+            hint.push_str(&format!("\n{}:", label));
+            for case in cases.iter().take(MAX_TESTS) {
+                hint.push_str(&format!("\n  {} ({})", case.name, case.file));
+                for assert in &case.asserts {
+                    hint.push_str(&format!("\n    {}", assert));
+                }
+            }
+            if cases.len() > MAX_TESTS {
+                hint.push_str(&format!("\n  and {} more", cases.len() - MAX_TESTS));
+            }
+        }
+        if !self.removed.is_empty() {
+            hint.push_str(&format!("\nRemoved tests: {}", self.removed.join(", ")));
+        }
+        if !self.source_files.is_empty() {
+            hint.push_str(&format!(
+                "\nThe source also changes slightly ({} lines in {}); mention it in the body if it matters.",
+                self.source_lines,
+                self.source_files.join(", ")
+            ));
+        }
+        hint
+    }
+}
+
+/// Files that hold only tests, going by the usual directory and file naming conventions
+pub fn is_test_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
+    let stem = name.split('.').next().unwrap_or(name);
+    lower.starts_with("tests/")
+        || lower.starts_with("test/")
+        || lower.starts_with("spec/")
+        || lower.contains("/tests/")
+        || lower.contains("/test/")
+        || lower.contains("/spec/")
+        || lower.contains("/__tests__/")
+        || name.contains("_test.")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+        || (name.ends_with(".py") && stem.starts_with("test_"))
+        || ((name.ends_with(".java") || name.ends_with(".kt") || name.ends_with(".cs"))
+            && (stem.ends_with("test") || stem.ends_with("tests")))
+}
+
+/// The file's test functions, keyed by name, and the rest of the file. In a
+/// Rust source file the tests are everything from `#[cfg(test)]` on.
+fn split_tests(path: &str, source: &str) -> (BTreeMap<String, String>, String) {
+    if source.is_empty() {
+        return (BTreeMap::new(), String::new());
+    }
+    let test_file = is_test_file(path);
+    if !test_file && path.ends_with(".rs") {
+        return match source.find("#[cfg(test)]") {
+            Some(start) => (
+                test_functions(path, &source[start..]),
+                source[..start].to_string(),
+            ),
+            None => (BTreeMap::new(), source.to_string()),
+        };
+    }
+    if test_file {
+        let tests = test_functions(path, source);
+        // Helpers and fixtures in test files are part of the tests
+        (tests, String::new())
+    } else {
+        (BTreeMap::new(), source.to_string())
+    }
+}
+
+/// Test functions and their bodies: `#[test]` functions in Rust, `test_*`
+/// functions in Python, `Test*` functions in Go, `@Test` methods, and
+/// `it(...)`/`test(...)` blocks in JavaScript
+fn test_functions(path: &str, source: &str) -> BTreeMap<String, String> {
+    let lines: Vec<&str> = source.lines().collect();
+    let python = path.ends_with(".py");
+    let Ok(named) = Regex::new(
+        r#"^\s*(?:pub\s+)?(?:async\s+)?(?:fn|def|func)\s+((?:test|Test)\w*)|^\s*(?:it|test)(?:\.\w+)?\(\s*["'`](.+?)["'`]"#,
+    ) else {
+        return BTreeMap::new();
+    };
+    let Ok(item) = Regex::new(
+        r"^\s*(?:pub\s+)?(?:async\s+)?(?:fn|def|func)\s+(\w+)|^\s*(?:public\s+|private\s+|protected\s+|internal\s+)?(?:suspend\s+)?(?:fun|void|async\s+Task|[\w<>\[\]]+)\s+(\w+)\s*\(",
+    ) else {
+        return BTreeMap::new();
+    };
+
+    let mut tests = BTreeMap::new();
+    let mut marked = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        // `#[test]`, `#[tokio::test]`, `@Test`, `[Fact]` and similar mark the next function
+        if trimmed.starts_with("#[") && trimmed.contains("test")
+            || trimmed.starts_with("@Test")
+            || trimmed.starts_with("@ParameterizedTest")
+            || matches!(trimmed, "[Fact]" | "[Test]" | "[TestMethod]")
+        {
+            marked = true;
+            continue;
+        }
+
+        let name = match named.captures(line) {
+            Some(caps) => caps.get(1).or(caps.get(2)).map(|m| m.as_str().to_string()),
+            None if marked => item
+                .captures(line)
+                .and_then(|caps| caps.get(1).or(caps.get(2)))
+                .map(|m| m.as_str().to_string()),
+            None => None,
+        };
+        if trimmed.starts_with("#[") || trimmed.starts_with('@') || trimmed.is_empty() {
+            continue;
+        }
+        marked = false;
+        if let Some(name) = name {
+            let body = if python {
+                indented_block(&lines[i..])
+            } else {
+                braced_block(&lines[i..])
+            };
+            tests.insert(name, body);
+        }
+    }
+    tests
+}
+
+/// The lines from the first one until its braces are balanced again
+fn braced_block(lines: &[&str]) -> String {
+    let mut depth = 0i32;
+    let mut opened = false;
+    let mut body = Vec::new();
+    for line in lines {
+        body.push(*line);
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if opened && depth <= 0 {
+            break;
+        }
+    }
+    body.join("\n")
+}
+
+/// The first line and the lines indented deeper than it
+fn indented_block(lines: &[&str]) -> String {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let Some((first, rest)) = lines.split_first() else {
+        return String::new();
+    };
+    let mut body = vec![*first];
+    body.extend(
+        rest.iter()
+            .take_while(|line| line.trim().is_empty() || indent(line) > indent(first)),
+    );
+    body.join("\n")
+}
+
+/// Assertion lines in a test body, which say best what the test checks
+fn assertions(body: &str) -> Vec<String> {
+    let Ok(assert) = Regex::new(
+        r"\b(?:assert\w*!?|expect|should|require\.\w+|t\.(?:Error|Fatal)\w*|check\w*!?|verify)\b",
+    ) else {
+        return Vec::new();
+    };
+    body.lines()
+        .map(str::trim)
+        .filter(|line| assert.is_match(line))
+        .take(MAX_ASSERTS)
+        .map(|line| {
+            if line.chars().count() > MAX_ASSERT_LEN {
+                let cut: String = line.chars().take(MAX_ASSERT_LEN).collect();
+                format!("{}...", cut)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// How many non-blank lines were added or removed, ignoring order
+fn changed_lines(old: &str, new: &str) -> usize {
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    for line in old.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        *counts.entry(line).or_default() -= 1;
+    }
+    for line in new.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        *counts.entry(line).or_default() += 1;
+    }
+    counts
+        .values()
+        .map(|count| count.unsigned_abs() as usize)
+        .sum()
+}
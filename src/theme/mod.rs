@@ -0,0 +1,244 @@
+use crate::config::ThemeConfig;
+use crate::output::{self, Symbol};
+use anyhow::{Result, anyhow};
+use console::{Style, StyledObject};
+use dialoguer::theme::ColorfulTheme;
+use std::sync::OnceLock;
+
+/// Built-in presets selectable with `ui.theme.preset`
+pub const PRESETS: &[&str] = &["default", "solarized", "high-contrast", "monochrome"];
+
+/// Style words accepted in `ui.theme` values besides color names and 256-color numbers
+const MODIFIERS: &[&str] = &[
+    "bright",
+    "bold",
+    "dim",
+    "italic",
+    "underlined",
+    "reverse",
+    "strikethrough",
+];
+
+const COLORS: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// The styles for each semantic role in gyst's output
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Additions and successful outcomes
+    added: Style,
+    /// Deletions, errors and failures
+    removed: Style,
+    /// Warnings and aborted actions
+    warning: Style,
+    /// Prompts, headings and progress messages
+    prompt: Style,
+    /// The same roles for the `gyst ui` dashboard
+    screen: ScreenStyles,
+}
+
+/// The roles of `Theme` as ratatui styles
+#[derive(Debug, Clone)]
+pub struct ScreenStyles {
+    pub added: ratatui::style::Style,
+    pub removed: ratatui::style::Style,
+    pub warning: ratatui::style::Style,
+    pub prompt: ratatui::style::Style,
+}
+
+impl Theme {
+    /// Build the theme from `ui.theme`: the preset, with any per-role overrides applied
+    pub fn from_config(config: &ThemeConfig) -> Result<Self> {
+        let [added, removed, warning, prompt] = match config.preset.as_str() {
+            "default" => ["green", "red", "yellow", "cyan"],
+            "solarized" => ["64", "160", "136", "37"],
+            "high-contrast" => [
+                "green.bright.bold",
+                "red.bright.bold",
+                "yellow.bright.bold",
+                "cyan.bright.bold",
+            ],
+            "monochrome" => ["none", "none", "bold", "none"],
+            other => {
+                return Err(anyhow!(
+                    "Unknown theme preset '{}'. Available presets: {}",
+                    other,
+                    PRESETS.join(", ")
+                ));
+            }
+        };
+
+        let role = |name: &str, value: &Option<String>, preset: &str| {
+            parse_style(value.as_deref().unwrap_or(preset))
+                .map_err(|e| anyhow!("Invalid ui.theme.{}: {}", name, e))
+        };
+        let screen =
+            |value: &Option<String>, preset: &str| screen_style(value.as_deref().unwrap_or(preset));
+
+        Ok(Self {
+            added: role("added", &config.added, added)?,
+            removed: role("removed", &config.removed, removed)?,
+            warning: role("warning", &config.warning, warning)?,
+            prompt: role("prompt", &config.prompt, prompt)?,
+            screen: ScreenStyles {
+                added: screen(&config.added, added),
+                removed: screen(&config.removed, removed),
+                warning: screen(&config.warning, warning),
+                prompt: screen(&config.prompt, prompt),
+            },
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            added: Style::new().green(),
+            removed: Style::new().red(),
+            warning: Style::new().yellow(),
+            prompt: Style::new().cyan(),
+            screen: ScreenStyles {
+                added: screen_style("green"),
+                removed: screen_style("red"),
+                warning: screen_style("yellow"),
+                prompt: screen_style("cyan"),
+            },
+        }
+    }
+}
+
+/// Set the theme used for the rest of the process. An invalid theme is
+/// reported and the default colors are used instead. With `--no-color` the
+/// `monochrome` preset is used whatever the configuration says.
+pub fn init(config: &ThemeConfig) {
+    if output::plain() {
+        let _ = THEME.set(monochrome());
+        return;
+    }
+    let theme = Theme::from_config(config).unwrap_or_else(|e| {
+        eprintln!("{}", Style::new().yellow().apply_to(e));
+        Theme::default()
+    });
+    let _ = THEME.set(theme);
+}
+
+fn current() -> &'static Theme {
+    THEME.get_or_init(|| {
+        if output::plain() {
+            monochrome()
+        } else {
+            Theme::default()
+        }
+    })
+}
+
+/// The `monochrome` preset without overrides, whose dashboard styles have no colors
+fn monochrome() -> Theme {
+    Theme::from_config(&ThemeConfig {
+        preset: "monochrome".to_string(),
+        ..ThemeConfig::default()
+    })
+    .unwrap_or_default()
+}
+
+pub fn added<D>(text: D) -> StyledObject<D> {
+    current().added.apply_to(text)
+}
+
+pub fn removed<D>(text: D) -> StyledObject<D> {
+    current().removed.apply_to(text)
+}
+
+pub fn warning<D>(text: D) -> StyledObject<D> {
+    current().warning.apply_to(text)
+}
+
+pub fn prompt<D>(text: D) -> StyledObject<D> {
+    current().prompt.apply_to(text)
+}
+
+/// The theme's styles for the `gyst ui` dashboard
+pub fn screen() -> &'static ScreenStyles {
+    &current().screen
+}
+
+/// The theme for interactive selections and inputs. Its symbols fall back to
+/// ASCII with `--no-color` and on terminals that cannot show them, such as
+/// the old Windows console.
+pub fn dialog() -> ColorfulTheme {
+    let theme = current();
+    let symbol = |emoji, fallback| Symbol(emoji, fallback).to_string();
+    let faint = Style::new().for_stderr().black().bright();
+    ColorfulTheme {
+        prompt_prefix: theme.warning.apply_to("?".to_string()),
+        prompt_suffix: faint.apply_to(symbol("›", ">")),
+        success_prefix: theme.added.apply_to(symbol("✔", "v")),
+        success_suffix: faint.apply_to(symbol("·", "-")),
+        error_prefix: theme.removed.apply_to(symbol("✘", "x")),
+        error_style: theme.removed.clone(),
+        values_style: theme.added.clone(),
+        active_item_style: theme.prompt.clone(),
+        active_item_prefix: theme.added.apply_to(symbol("❯", ">")),
+        checked_item_prefix: theme.added.apply_to(symbol("✔", "[x]")),
+        unchecked_item_prefix: theme.prompt.apply_to(symbol("⬚", "[ ]")),
+        picked_item_prefix: theme.added.apply_to(symbol("❯", ">")),
+        ..ColorfulTheme::default()
+    }
+}
+
+/// Parse a dotted style such as `green`, `red.bold`, `bright.cyan` or `208`
+/// (a 256-color code). `none` means no styling.
+fn parse_style(value: &str) -> Result<Style> {
+    let value = value.trim();
+    if value.is_empty() || value == "none" {
+        return Ok(Style::new());
+    }
+
+    for part in value.split('.') {
+        if !COLORS.contains(&part) && !MODIFIERS.contains(&part) && part.parse::<u8>().is_err() {
+            return Err(anyhow!(
+                "unknown style '{}'. Use a color ({}), a 256-color number, or one of: {}",
+                part,
+                COLORS.join(", "),
+                MODIFIERS.join(", ")
+            ));
+        }
+    }
+
+    // `from_dotted_str` understands every accepted word except `italic`
+    let style = Style::from_dotted_str(value);
+    Ok(if value.split('.').any(|part| part == "italic") {
+        style.italic()
+    } else {
+        style
+    })
+}
+
+/// The ratatui style for a dotted style that `parse_style` accepts
+fn screen_style(value: &str) -> ratatui::style::Style {
+    use ratatui::style::{Color, Modifier};
+
+    let parts: Vec<&str> = value.trim().split('.').collect();
+    let bright = parts.contains(&"bright");
+    parts
+        .iter()
+        .fold(ratatui::style::Style::new(), |style, part| match *part {
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reverse" => style.add_modifier(Modifier::REVERSED),
+            "strikethrough" => style.add_modifier(Modifier::CROSSED_OUT),
+            // The first 16 colors are the basic ones, then their bright versions
+            part => match (COLORS.iter().position(|color| *color == part), part.parse()) {
+                (Some(color), _) => {
+                    style.fg(Color::Indexed(color as u8 + if bright { 8 } else { 0 }))
+                }
+                (None, Ok(code)) => style.fg(Color::Indexed(code)),
+                _ => style,
+            },
+        })
+}
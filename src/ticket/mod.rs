@@ -0,0 +1,76 @@
+use crate::config::{Config, TicketPlacement};
+use crate::template::Template;
+use crate::trailer;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Ticket pattern used by the jira-prefixed template when `commit.ticket_pattern` is not set
+const DEFAULT_PATTERN: &str = r"[A-Z][A-Z0-9]+-\d+";
+
+/// A ticket/issue ID taken from the branch name, e.g. `JIRA-123` from `feature/JIRA-123-login`
+pub struct Ticket {
+    pub id: String,
+    placement: TicketPlacement,
+}
+
+impl Ticket {
+    /// Extract a ticket ID from `branch` using `commit.ticket_pattern`, or the
+    /// `jira.project` key when no pattern is set. Returns `None` when neither is
+    /// configured or the branch does not match.
+    pub fn detect(config: &Config, branch: &str) -> Result<Option<Self>> {
+        // The jira-prefixed template needs a ticket even without a configured pattern
+        let requires_ticket = Template::resolve(config).is_ok_and(|t| t.requires_ticket());
+
+        let jira_pattern = config
+            .jira
+            .project
+            .as_deref()
+            .filter(|project| !project.is_empty())
+            .map(|project| format!(r"{}-\d+", regex::escape(project)));
+        let pattern = match (config.commit.ticket_pattern.as_deref(), &jira_pattern) {
+            (Some(pattern), _) => pattern,
+            (None, Some(pattern)) => pattern.as_str(),
+            (None, None) if requires_ticket => DEFAULT_PATTERN,
+            (None, None) => return Ok(None),
+        };
+
+        let regex = Regex::new(pattern)
+            .with_context(|| format!("Invalid commit.ticket_pattern '{}'", pattern))?;
+
+        Ok(regex.find(branch).map(|m| Self {
+            id: m.as_str().to_string(),
+            placement: if requires_ticket {
+                TicketPlacement::Prefix
+            } else {
+                config.commit.ticket_placement
+            },
+        }))
+    }
+
+    /// Instruction for the AI so it keeps the ticket reference intact
+    pub fn prompt_hint(&self) -> String {
+        match self.placement {
+            TicketPlacement::Trailer => format!(
+                "This work belongs to ticket {}. End the message with the trailer line \"Refs: {}\" after a blank line.",
+                self.id, self.id
+            ),
+            TicketPlacement::Prefix => format!(
+                "This work belongs to ticket {}. Start the subject line with \"[{}] \".",
+                self.id, self.id
+            ),
+        }
+    }
+
+    /// Add the ticket reference to `message` unless it already mentions the ticket
+    pub fn apply(&self, message: &str) -> String {
+        let message = message.trim();
+        if message.contains(&self.id) {
+            return message.to_string();
+        }
+
+        match self.placement {
+            TicketPlacement::Prefix => format!("[{}] {}", self.id, message),
+            TicketPlacement::Trailer => trailer::append(message, &[format!("Refs: {}", self.id)]),
+        }
+    }
+}
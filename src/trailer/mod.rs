@@ -0,0 +1,74 @@
+use anyhow::{Result, anyhow};
+
+/// Build the trailers for a commit: the configured `commit.trailers` followed by
+/// one `Co-authored-by` trailer per `--co-author`, validated up front
+pub fn collect(configured: &[String], co_authors: &[String]) -> Result<Vec<String>> {
+    let mut trailers = Vec::new();
+
+    for trailer in configured {
+        if !is_trailer_line(trailer) {
+            return Err(anyhow!(
+                "Invalid trailer '{}' in commit.trailers. Use the form 'Token: value', e.g. 'Reviewed-by: Jane Doe <jane@example.com>'",
+                trailer
+            ));
+        }
+        trailers.push(trailer.trim().to_string());
+    }
+
+    for co_author in co_authors {
+        trailers.push(format!("Co-authored-by: {}", parse_identity(co_author)?));
+    }
+
+    Ok(trailers)
+}
+
+/// Append `trailers` to `message`, joining an existing trailer block or starting
+/// a new one after a blank line. Trailers already present are not repeated.
+pub fn append(message: &str, trailers: &[String]) -> String {
+    let mut message = message.trim().to_string();
+
+    let existing: Vec<String> = message.lines().map(|l| l.trim().to_lowercase()).collect();
+    let missing: Vec<&str> = trailers
+        .iter()
+        .map(|t| t.trim())
+        .filter(|t| !existing.contains(&t.to_lowercase()))
+        .collect();
+    if missing.is_empty() {
+        return message;
+    }
+
+    let last_paragraph = message.rsplit("\n\n").next().unwrap_or_default();
+    let has_trailers = message.contains("\n\n") && last_paragraph.lines().all(is_trailer_line);
+    message.push_str(if has_trailers { "\n" } else { "\n\n" });
+    message.push_str(&missing.join("\n"));
+    message
+}
+
+/// Whether `line` looks like a git trailer (`Token: value`)
+pub fn is_trailer_line(line: &str) -> bool {
+    line.trim().split_once(": ").is_some_and(|(token, value)| {
+        !token.is_empty()
+            && token.chars().all(|c| c.is_alphanumeric() || c == '-')
+            && !value.trim().is_empty()
+    })
+}
+
+/// Check that `value` has the `Name <email>` form git expects for co-authors
+fn parse_identity(value: &str) -> Result<String> {
+    let value = value.trim();
+    let valid = value
+        .strip_suffix('>')
+        .and_then(|rest| rest.split_once('<'))
+        .is_some_and(|(name, email)| {
+            !name.trim().is_empty() && email.contains('@') && !email.contains(['<', '>', ' '])
+        });
+
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(anyhow!(
+            "Invalid co-author '{}'. Use the form \"Name <email>\", e.g. --co-author \"Jane Doe <jane@example.com>\"",
+            value
+        ))
+    }
+}
@@ -0,0 +1,207 @@
+use crate::release::Change;
+use anyhow::{Result, anyhow};
+use std::fmt;
+
+pub const SYSTEM_PROMPT: &str = r#"You are an assistant that decides the semantic version bump for a software release.
+You are given the commits since the last release and the diff of the changes.
+Follow these rules:
+1. major: anything that breaks existing users, such as removed or renamed public APIs, changed signatures, changed defaults, or removed options
+2. minor: new functionality that is backwards compatible
+3. patch: bug fixes, documentation, refactoring, and internal changes only
+4. Judge from the diff, not only from the commit messages, which may be incomplete
+
+Reply with a first line of the form:
+BUMP: major|minor|patch
+followed by one or two sentences explaining the decision."#;
+
+pub const TAG_PROMPT: &str = r#"You are an assistant that writes annotated git tag messages for releases.
+You are given the release version and its release notes.
+Follow these rules:
+1. Start with a one-line summary of the release (under 72 characters), without the version number
+2. Add a blank line, then a short "-" bullet list of the most notable changes
+3. Mention breaking changes first
+4. Use plain text, no markdown headings or bold text
+
+Return ONLY the tag message, without any introduction."#;
+
+/// How much a release changes the version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "major" => Some(Self::Major),
+            "minor" => Some(Self::Minor),
+            "patch" => Some(Self::Patch),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bump::Patch => write!(f, "patch"),
+            Bump::Minor => write!(f, "minor"),
+            Bump::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// A `v1.2.3`-style version taken from a tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// Text before the number, usually `v`
+    pub prefix: String,
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// Parse a tag such as `v1.2.3`, `1.2.3` or `release-1.2.3`. Pre-release
+    /// and build suffixes (`-rc.1`, `+build`) are ignored.
+    pub fn parse(tag: &str) -> Result<Self> {
+        let start = tag
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(|| anyhow!("Tag '{}' does not contain a version number", tag))?;
+        let (prefix, rest) = tag.split_at(start);
+        let core = rest.split(['-', '+']).next().unwrap_or(rest);
+
+        let numbers: Vec<u64> = core
+            .split('.')
+            .map(|part| part.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow!("Tag '{}' is not a semantic version (e.g. v1.2.3)", tag))?;
+        let [major, minor, patch] = numbers[..] else {
+            return Err(anyhow!(
+                "Tag '{}' is not a semantic version (e.g. v1.2.3)",
+                tag
+            ));
+        };
+
+        Ok(Self {
+            prefix: prefix.to_string(),
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// The version before the first release
+    pub fn initial() -> Self {
+        Self {
+            prefix: "v".to_string(),
+            major: 0,
+            minor: 0,
+            patch: 0,
+        }
+    }
+
+    /// Apply `bump`. Before 1.0.0 breaking changes only bump the minor
+    /// version, as the semver spec allows.
+    pub fn bump(&self, bump: Bump) -> Self {
+        let (major, minor, patch) = match bump {
+            Bump::Major if self.major == 0 => (0, self.minor + 1, 0),
+            Bump::Major => (self.major + 1, 0, 0),
+            Bump::Minor => (self.major, self.minor + 1, 0),
+            Bump::Patch => (self.major, self.minor, self.patch + 1),
+        };
+        Self {
+            prefix: self.prefix.clone(),
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}.{}.{}",
+            self.prefix, self.major, self.minor, self.patch
+        )
+    }
+}
+
+/// The bump the commit messages call for, with the commits that decided it
+pub fn recommend(changes: &[Change]) -> (Bump, Vec<&Change>) {
+    let breaking: Vec<&Change> = changes.iter().filter(|c| c.breaking).collect();
+    if !breaking.is_empty() {
+        return (Bump::Major, breaking);
+    }
+
+    let features: Vec<&Change> = changes
+        .iter()
+        .filter(|c| c.kind.as_deref() == Some("feat"))
+        .collect();
+    if !features.is_empty() {
+        return (Bump::Minor, features);
+    }
+
+    (Bump::Patch, Vec::new())
+}
+
+/// Build the prompt asking the AI to judge the bump from the commits and the diff
+pub fn build_prompt(changes: &[Change], patch: &str) -> String {
+    let mut prompt = String::from("Commits since the last release:\n");
+    for change in changes {
+        prompt.push_str(&format!("- {} ", change.short_id));
+        if let Some(kind) = &change.kind {
+            prompt.push_str(kind);
+            if let Some(scope) = &change.scope {
+                prompt.push_str(&format!("({})", scope));
+            }
+            prompt.push_str(": ");
+        }
+        prompt.push_str(&change.description);
+        if change.breaking {
+            prompt.push_str(" [marked as breaking]");
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str(&format!("\nDiff:\n{}", patch));
+    prompt
+}
+
+/// Read the `BUMP:` line and the explanation from the AI's reply
+pub fn parse_verdict(reply: &str) -> Option<(Bump, String)> {
+    let mut bump = None;
+    let mut reason = Vec::new();
+    for line in reply.lines() {
+        match line.trim().strip_prefix("BUMP:") {
+            Some(value) => bump = Bump::parse(value),
+            None if !line.trim().is_empty() => reason.push(line.trim()),
+            None => {}
+        }
+    }
+    bump.map(|bump| (bump, reason.join(" ")))
+}
+
+/// A tag message built from the commits, for when the AI is not used
+pub fn plain_tag_message(name: &str, changes: &[Change]) -> String {
+    let mut message = format!("Release {}\n", name);
+    if !changes.is_empty() {
+        message.push('\n');
+    }
+
+    let mut ordered: Vec<&Change> = changes.iter().collect();
+    ordered.sort_by_key(|change| !change.breaking);
+    for change in ordered {
+        let breaking = if change.breaking { "BREAKING: " } else { "" };
+        let scope = change
+            .scope
+            .as_ref()
+            .map(|scope| format!("{}: ", scope))
+            .unwrap_or_default();
+        message.push_str(&format!("- {}{}{}\n", breaking, scope, change.description));
+    }
+    message
+}
@@ -0,0 +1,101 @@
+//! `gyst watch`: snapshot the working tree to a shadow branch as it changes,
+//! giving a local history of work in progress that can be squashed later
+
+use crate::git::GitRepo;
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// The shadow branch snapshots of `branch` go to unless `--branch` names another
+pub fn default_branch(branch: &str) -> String {
+    format!("gyst/wip/{}", branch)
+}
+
+pub enum Event {
+    /// Files changed somewhere in the working tree
+    Changed(Vec<PathBuf>),
+    /// Nothing changed for the debounce interval
+    Settled,
+    /// Enter was pressed: snapshot right away
+    Now,
+    /// `q`, the end of input or Ctrl-C
+    Quit,
+}
+
+/// Watches the working tree and, in a terminal, the keyboard
+pub struct Watch {
+    _watcher: notify::RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<Event>,
+}
+
+impl Watch {
+    pub fn start(workdir: &Path) -> Result<Self> {
+        let (sender, events) = mpsc::unbounded_channel();
+
+        let changes = sender.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+                if let Ok(event) = result
+                    && !matches!(event.kind, EventKind::Access(_))
+                {
+                    let _ = changes.send(Event::Changed(event.paths));
+                }
+            })
+            .context("Failed to watch the working tree")?;
+        watcher
+            .watch(workdir, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", workdir.display()))?;
+
+        // Without a terminal, gyst watch runs until it is stopped
+        if io::stdin().is_terminal() {
+            std::thread::spawn(move || {
+                for line in io::stdin().lines() {
+                    let quit = matches!(line.as_deref().map(str::trim), Ok("q") | Err(_));
+                    let event = if quit { Event::Quit } else { Event::Now };
+                    if sender.send(event).is_err() || quit {
+                        return;
+                    }
+                }
+                let _ = sender.send(Event::Quit);
+            });
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Wait for the next event; `Settled` once `debounce` passes without one
+    pub async fn next(&mut self, debounce: Option<Duration>) -> Event {
+        let settled = async {
+            match debounce {
+                Some(debounce) => tokio::time::sleep(debounce).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            event = self.events.recv() => event.unwrap_or(Event::Quit),
+            _ = tokio::signal::ctrl_c() => Event::Quit,
+            () = settled => Event::Settled,
+        }
+    }
+}
+
+/// Whether a change to `path` can end up in a snapshot: it is in the working
+/// tree, outside `.git`, and not ignored
+pub fn relevant(repo: &GitRepo, path: &Path) -> bool {
+    if path.starts_with(repo.git_dir()) {
+        return false;
+    }
+    let Ok(workdir) = repo.workdir() else {
+        return false;
+    };
+    match path.strip_prefix(workdir) {
+        Ok(relative) => !relative.as_os_str().is_empty() && !repo.is_ignored(relative),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,283 @@
+//! Fixes the wording of generated messages: the project's terminology from
+//! `commit.terminology` and common misspellings
+
+use crate::config::CommitConfig;
+use regex::{Regex, RegexBuilder};
+use std::collections::BTreeMap;
+
+/// Misspellings that turn up in commit messages, with their correction
+const MISSPELLINGS: &[(&str, &str)] = &[
+    ("accomodate", "accommodate"),
+    ("acheive", "achieve"),
+    ("adress", "address"),
+    ("agressive", "aggressive"),
+    ("aleady", "already"),
+    ("algorithim", "algorithm"),
+    ("analagous", "analogous"),
+    ("apparant", "apparent"),
+    ("arguement", "argument"),
+    ("asynchonous", "asynchronous"),
+    ("authentification", "authentication"),
+    ("availible", "available"),
+    ("begining", "beginning"),
+    ("beleive", "believe"),
+    ("calender", "calendar"),
+    ("cancelation", "cancellation"),
+    ("comparision", "comparison"),
+    ("compatability", "compatibility"),
+    ("compatable", "compatible"),
+    ("completly", "completely"),
+    ("concurent", "concurrent"),
+    ("configuraiton", "configuration"),
+    ("consistant", "consistent"),
+    ("convertion", "conversion"),
+    ("definately", "definitely"),
+    ("dependancy", "dependency"),
+    ("dependancies", "dependencies"),
+    ("deprecaed", "deprecated"),
+    ("descripton", "description"),
+    ("enviroment", "environment"),
+    ("existant", "existent"),
+    ("explicitely", "explicitly"),
+    ("funtion", "function"),
+    ("funtionality", "functionality"),
+    ("guarentee", "guarantee"),
+    ("handeling", "handling"),
+    ("implmentation", "implementation"),
+    ("independant", "independent"),
+    ("initalize", "initialize"),
+    ("intialize", "initialize"),
+    ("lenght", "length"),
+    ("mantain", "maintain"),
+    ("neccessary", "necessary"),
+    ("necessery", "necessary"),
+    ("occured", "occurred"),
+    ("occurence", "occurrence"),
+    ("paramter", "parameter"),
+    ("paramters", "parameters"),
+    ("performace", "performance"),
+    ("persistant", "persistent"),
+    ("posible", "possible"),
+    ("preceeding", "preceding"),
+    ("prefered", "preferred"),
+    ("recieve", "receive"),
+    ("recieved", "received"),
+    ("recursivly", "recursively"),
+    ("refered", "referred"),
+    ("relevent", "relevant"),
+    ("reponse", "response"),
+    ("repositry", "repository"),
+    ("requirment", "requirement"),
+    ("responsability", "responsibility"),
+    ("retreive", "retrieve"),
+    ("seperate", "separate"),
+    ("seperately", "separately"),
+    ("sucess", "success"),
+    ("succesful", "successful"),
+    ("successfull", "successful"),
+    ("sucessfully", "successfully"),
+    ("supress", "suppress"),
+    ("teh", "the"),
+    ("threshhold", "threshold"),
+    ("transfered", "transferred"),
+    ("truely", "truly"),
+    ("unecessary", "unnecessary"),
+    ("untill", "until"),
+    ("usefull", "useful"),
+    ("validaton", "validation"),
+    ("writting", "writing"),
+];
+
+/// A word or phrase that was replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub from: String,
+    pub to: String,
+}
+
+struct Rule {
+    pattern: Regex,
+    to: String,
+    /// Carry the capitalization of the original over, as for a misspelling
+    /// at the start of a sentence. Terms are always written as configured.
+    keep_case: bool,
+}
+
+/// Rewrite `message` to use the configured terminology and, with
+/// `commit.spell_check`, to correct common misspellings. Text in backticks
+/// and words that are part of a path or identifier are left alone.
+pub fn fix(config: &CommitConfig, message: &str) -> (String, Vec<Correction>) {
+    let rules = rules(config);
+    if rules.is_empty() {
+        return (message.to_string(), Vec::new());
+    }
+    let code = code_spans(message);
+
+    // Earlier rules win where matches overlap
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for rule in &rules {
+        for found in rule.pattern.find_iter(message) {
+            let (start, end) = (found.start(), found.end());
+            if code.iter().any(|&(from, to)| start < to && end > from)
+                || !standalone(message, start, end)
+                || replacements
+                    .iter()
+                    .any(|&(from, to, _)| start < to && end > from)
+            {
+                continue;
+            }
+            let to = if rule.keep_case {
+                match_case(found.as_str(), &rule.to)
+            } else {
+                rule.to.clone()
+            };
+            if to != found.as_str() {
+                replacements.push((start, end, to));
+            }
+        }
+    }
+    replacements.sort_by_key(|&(start, _, _)| start);
+
+    let mut fixed = String::with_capacity(message.len());
+    let mut corrections: Vec<Correction> = Vec::new();
+    let mut last = 0;
+    for (start, end, to) in replacements {
+        fixed.push_str(&message[last..start]);
+        fixed.push_str(&to);
+        let correction = Correction {
+            from: message[start..end].to_string(),
+            to,
+        };
+        if !corrections.contains(&correction) {
+            corrections.push(correction);
+        }
+        last = end;
+    }
+    fixed.push_str(&message[last..]);
+    (fixed, corrections)
+}
+
+/// Tell the AI how the project spells its terms, so it gets them right the first time
+pub fn prompt_hint(config: &CommitConfig) -> Option<String> {
+    let mut variants: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (variant, term) in terms(config) {
+        variants.entry(term).or_default().push(variant);
+    }
+    if variants.is_empty() {
+        return None;
+    }
+    let terms: Vec<String> = variants
+        .iter()
+        .map(|(term, variants)| format!("\"{}\" (not \"{}\")", term, variants.join("\", \"")))
+        .collect();
+    Some(format!(
+        "Write these terms exactly as shown: {}.",
+        terms.join(", ")
+    ))
+}
+
+/// The configured `(variant, term)` pairs, without blank or unchanged ones
+fn terms(config: &CommitConfig) -> Vec<(&str, &str)> {
+    config
+        .terminology
+        .iter()
+        .map(|(variant, term)| (variant.trim(), term.trim()))
+        .filter(|(variant, term)| !variant.is_empty() && !term.is_empty() && variant != term)
+        .collect()
+}
+
+fn rules(config: &CommitConfig) -> Vec<Rule> {
+    let terms = terms(config);
+    // Longer phrases first, so "github actions" wins over "github"
+    let mut phrases: Vec<(&str, &str)> = terms.clone();
+    // A term in the wrong case, e.g. "postgresql" for "PostgreSQL"
+    phrases.extend(terms.iter().map(|&(_, term)| (term, term)));
+    phrases.sort_by_key(|&(phrase, _)| std::cmp::Reverse(phrase.len()));
+
+    let mut rules: Vec<Rule> = phrases
+        .into_iter()
+        .filter_map(|(phrase, to)| {
+            Some(Rule {
+                pattern: pattern(phrase)?,
+                to: to.to_string(),
+                keep_case: false,
+            })
+        })
+        .collect();
+    if config.spell_check {
+        rules.extend(MISSPELLINGS.iter().filter_map(|&(wrong, right)| {
+            Some(Rule {
+                pattern: pattern(wrong)?,
+                to: right.to_string(),
+                keep_case: true,
+            })
+        }));
+    }
+    rules
+}
+
+/// A case-insensitive pattern for `phrase` as whole words, with any spacing
+/// between them. Terms like `C++` that end in punctuation are bounded by
+/// `standalone` instead.
+fn pattern(phrase: &str) -> Option<Regex> {
+    let words: Vec<String> = phrase.split_whitespace().map(regex::escape).collect();
+    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let start = if word(phrase.chars().next()) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if word(phrase.chars().next_back()) {
+        r"\b"
+    } else {
+        ""
+    };
+    RegexBuilder::new(&format!("{}{}{}", start, words.join(r"\s+"), end))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+/// Whether the text at `start..end` is a word of its own rather than part of a
+/// path, file name, identifier or URL such as `src/postgres.rs`
+fn standalone(message: &str, start: usize, end: usize) -> bool {
+    let before = message[..start].chars().next_back();
+    if before.is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '/' | '-' | '@' | '$'))
+    {
+        return false;
+    }
+    let mut after = message[end..].chars();
+    match after.next() {
+        Some(c) if c.is_alphanumeric() || c == '_' => false,
+        Some('.' | '/' | '-' | ':') => !after.next().is_some_and(|c| c.is_alphanumeric()),
+        _ => true,
+    }
+}
+
+/// Byte ranges of text between backticks, including fenced code blocks
+fn code_spans(message: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut open: Option<usize> = None;
+    for (index, _) in message.match_indices('`') {
+        match open.take() {
+            Some(start) => spans.push((start, index)),
+            None => open = Some(index),
+        }
+    }
+    if let Some(start) = open {
+        spans.push((start, message.len()));
+    }
+    spans
+}
+
+/// `to` in the capitalization of `original`: all caps, a capital first letter or as is
+fn match_case(original: &str, to: &str) -> String {
+    let letters: Vec<char> = original.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        to.to_uppercase()
+    } else if original.chars().next().is_some_and(char::is_uppercase) {
+        crate::template::capitalize(to)
+    } else {
+        to.to_string()
+    }
+}
@@ -0,0 +1,240 @@
+//! Monorepo layouts: the packages of a Cargo workspace, of npm, yarn or pnpm
+//! workspaces, or of `packages/*` and `apps/*` directories. The packages the
+//! staged changes touch are offered to the AI as commit scopes, and a scope
+//! that names no package is reported.
+
+use std::fs;
+use std::path::Path;
+
+/// Where packages live when no workspace manifest lists them
+const CONVENTIONAL_DIRS: &[&str] = &["packages/*", "apps/*"];
+
+#[derive(Debug, Clone)]
+pub struct Package {
+    /// The name from the package's manifest, without an npm `@scope/`, or
+    /// its directory name
+    pub name: String,
+    /// The package's directory relative to the repository root, e.g. `packages/ui`
+    pub dir: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub packages: Vec<Package>,
+}
+
+impl Workspace {
+    /// The packages in the repository at `workdir`, or `None` if it has fewer
+    /// than two and so is not a monorepo. Manifests that cannot be read or
+    /// parsed are skipped.
+    pub fn load(workdir: &Path) -> Option<Self> {
+        let mut patterns = cargo_members(workdir);
+        patterns.extend(node_workspaces(workdir));
+        if patterns.is_empty() {
+            patterns = CONVENTIONAL_DIRS.iter().map(|p| p.to_string()).collect();
+        }
+
+        let mut packages: Vec<Package> = Vec::new();
+        for dir in patterns.iter().flat_map(|pattern| expand(workdir, pattern)) {
+            if packages.iter().any(|package| package.dir == dir) {
+                continue;
+            }
+            if let Some(name) = package_name(&workdir.join(&dir)) {
+                packages.push(Package { name, dir });
+            }
+        }
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        (packages.len() >= 2).then_some(Self { packages })
+    }
+
+    /// Every package name, sorted
+    pub fn names(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .map(|package| package.name.clone())
+            .collect()
+    }
+
+    /// The package `path` belongs to: the one with the deepest directory
+    /// containing it
+    pub fn package_for(&self, path: &str) -> Option<&Package> {
+        self.packages
+            .iter()
+            .filter(|package| {
+                path.strip_prefix(&package.dir)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|package| package.dir.len())
+    }
+
+    /// The names of the packages `paths` touch, in the order they first appear
+    pub fn touched<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for package in paths.into_iter().filter_map(|path| self.package_for(path)) {
+            if !names.contains(&package.name) {
+                names.push(package.name.clone());
+            }
+        }
+        names
+    }
+
+    /// Tell the AI which package names can be used as the scope, and which
+    /// of them the staged changes touch
+    pub fn prompt_hint(&self, touched: &[String]) -> String {
+        let mut hint = format!(
+            "This repository is a monorepo with these packages: {}. Use a package name as the scope.",
+            self.names().join(", ")
+        );
+        match touched {
+            [] => hint.push_str(" The staged changes are outside every package, so leave the scope out."),
+            [package] => hint.push_str(&format!(
+                " The staged changes are in '{}', so use '{}' as the scope.",
+                package, package
+            )),
+            _ => hint.push_str(&format!(
+                " The staged changes touch {}; use the package most of the change is in, or list them separated by commas.",
+                touched.join(", ")
+            )),
+        }
+        hint
+    }
+}
+
+/// The `members` of the `[workspace]` table in the root `Cargo.toml`,
+/// without those in `exclude`
+fn cargo_members(workdir: &Path) -> Vec<String> {
+    let Some(manifest) = read_toml(&workdir.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Some(workspace) = manifest.get("workspace") else {
+        return Vec::new();
+    };
+    let list = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|value| value.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str())
+                    .map(|value| value.trim_end_matches('/').to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excluded = list("exclude");
+    list("members")
+        .into_iter()
+        .filter(|member| !excluded.contains(member))
+        .collect()
+}
+
+/// The workspace patterns of `package.json` (an array, or yarn's
+/// `{ "packages": [...] }`) and of `pnpm-workspace.yaml`. Negated patterns
+/// are left out.
+fn node_workspaces(workdir: &Path) -> Vec<String> {
+    let mut patterns: Vec<String> = Vec::new();
+
+    if let Some(manifest) = read_json(&workdir.join("package.json"))
+        && let Some(workspaces) = manifest.get("workspaces")
+    {
+        let list = workspaces
+            .as_array()
+            .or_else(|| workspaces.get("packages").and_then(|p| p.as_array()));
+        patterns.extend(
+            list.into_iter()
+                .flatten()
+                .filter_map(|value| value.as_str())
+                .map(str::to_string),
+        );
+    }
+
+    // The `packages:` list of pnpm-workspace.yaml, read line by line
+    if let Ok(text) = fs::read_to_string(workdir.join("pnpm-workspace.yaml")) {
+        let mut in_packages = false;
+        for line in text.lines() {
+            if !line.starts_with([' ', '\t', '-']) {
+                in_packages = line.trim_end() == "packages:";
+                continue;
+            }
+            if in_packages && let Some(item) = line.trim().strip_prefix('-') {
+                let item = item.split(" #").next().unwrap_or("").trim();
+                patterns.push(item.trim_matches(['\'', '"']).to_string());
+            }
+        }
+    }
+
+    patterns.retain(|pattern| !pattern.is_empty() && !pattern.starts_with('!'));
+    patterns
+}
+
+/// The directories a workspace pattern names, relative to `workdir`. A
+/// trailing `*` or `**` stands for every directory below the prefix; other
+/// patterns are taken as a directory.
+fn expand(workdir: &Path, pattern: &str) -> Vec<String> {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    // The root package of a workspace is not a scope
+    if pattern.is_empty() || pattern == "." {
+        return Vec::new();
+    }
+    let parent = pattern
+        .strip_suffix("/**")
+        .or_else(|| pattern.strip_suffix("/*"));
+    let Some(parent) = parent else {
+        return if workdir.join(pattern).is_dir() {
+            vec![pattern.to_string()]
+        } else {
+            Vec::new()
+        };
+    };
+    if parent.contains('*') {
+        return Vec::new();
+    }
+
+    let Ok(entries) = fs::read_dir(workdir.join(parent)) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.') && name != "node_modules")
+        .map(|name| format!("{}/{}", parent, name))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// The name of the package in `dir`: from `package.json`, then `Cargo.toml`,
+/// then the directory name. Directories with neither manifest only count
+/// when they hold files, so empty placeholders are skipped.
+fn package_name(dir: &Path) -> Option<String> {
+    if let Some(manifest) = read_json(&dir.join("package.json")) {
+        if let Some(name) = manifest.get("name").and_then(|name| name.as_str()) {
+            // `@acme/ui` is scoped as `ui`
+            let name = name.rsplit('/').next().unwrap_or(name);
+            if !name.is_empty() {
+                return Some(name.to_string());
+            }
+        }
+    } else if let Some(manifest) = read_toml(&dir.join("Cargo.toml")) {
+        if let Some(name) = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+        {
+            return Some(name.to_string());
+        }
+    } else if fs::read_dir(dir).ok()?.next().is_none() {
+        return None;
+    }
+    dir.file_name()?.to_str().map(str::to_string)
+}
+
+fn read_toml(path: &Path) -> Option<toml::Value> {
+    toml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
@@ -0,0 +1,65 @@
+//! A settings bundle can share the project's conventions, but importing one
+//! cannot change where gyst sends requests, and with them your tokens
+
+mod common;
+
+use common::{TestRepo, stderr, stdout};
+use std::fs;
+
+fn setting(repo: &TestRepo, key: &str) -> String {
+    let output = repo.gyst("ok", &["config", "get", key]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).trim().to_string()
+}
+
+#[test]
+fn bundle_import_ignores_urls() {
+    let repo = TestRepo::direct();
+    let bundle = repo.path().join("team.toml");
+    fs::write(
+        &bundle,
+        "[commit]\ntemplate = \"plain\"\n\n[jira]\nurl = \"http://127.0.0.1:9\"\n\n[forge]\ngithub_url = \"https://github.example.com\"\n\n[ai]\nprovider = \"openai\"\n",
+    )
+    .expect("write bundle");
+
+    let output = repo.gyst(
+        "ok",
+        &[
+            "--yes",
+            "config",
+            "import",
+            bundle.to_str().expect("bundle path"),
+        ],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let ignored = format!("{}{}", stdout(&output), stderr(&output));
+    assert!(
+        ignored.contains("jira.url") && ignored.contains("forge.github_url"),
+        "{}",
+        ignored
+    );
+    assert_eq!(setting(&repo, "commit.template"), "plain");
+    assert_eq!(setting(&repo, "jira.url"), "");
+    assert_eq!(setting(&repo, "forge.github_url"), "");
+    assert_eq!(setting(&repo, "ai.provider"), "anthropic");
+}
+
+#[test]
+fn bundle_export_leaves_out_urls() {
+    let repo = TestRepo::new(
+        "[ai]\nprovider = \"anthropic\"\napi_key = \"test-key\"\n\n[server]\nuse_server = false\n\n[jira]\nurl = \"https://jira.example.com\"\nproject = \"PROJ\"\n",
+    );
+    let bundle = repo.path().join("team.toml");
+
+    let output = repo.gyst(
+        "ok",
+        &["config", "export", bundle.to_str().expect("bundle path")],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let contents = fs::read_to_string(&bundle).expect("read bundle");
+    assert!(contents.contains("PROJ"), "{}", contents);
+    assert!(!contents.contains("jira.example.com"), "{}", contents);
+    assert!(!contents.contains("test-key"), "{}", contents);
+}
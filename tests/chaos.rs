@@ -0,0 +1,129 @@
+//! Provider failures injected with `GYST_CHAOS`: transient ones are retried,
+//! the rest fail the command without creating a commit
+
+mod common;
+
+use common::{TestRepo, stderr, stdout};
+
+/// The message the `ok` fault replies with
+const CANNED_SUBJECT: &str = "chore: canned response from GYST_CHAOS";
+
+#[test]
+fn direct_api_retries_after_rate_limit() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("rate-limit,ok", &["commit", "-q"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(repo.last_subject().as_deref(), Some(CANNED_SUBJECT));
+}
+
+#[test]
+fn quiet_commit_prints_nothing() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("ok", &["commit", "-q", "--quiet"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(repo.last_subject().as_deref(), Some(CANNED_SUBJECT));
+}
+
+#[test]
+fn direct_api_retries_after_timeouts() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("timeout:2,ok", &["commit", "-q"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(repo.last_subject().as_deref(), Some(CANNED_SUBJECT));
+}
+
+#[test]
+fn direct_api_gives_up_when_rate_limited_on_every_attempt() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("rate-limit:3,ok", &["commit", "-q"]);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).contains("429"), "{}", stderr(&output));
+    assert_eq!(repo.last_subject(), None);
+}
+
+#[test]
+fn direct_api_reports_malformed_json_without_retrying() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("malformed,ok", &["commit", "-q"]);
+
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Failed to parse Anthropic response"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(repo.last_subject(), None);
+}
+
+#[test]
+fn partial_response_keeps_changes_staged() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("partial,ok", &["commit", "-q"]);
+
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Failed to read Anthropic response"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(repo.last_subject(), None);
+    assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "notes.txt");
+}
+
+#[test]
+fn server_retries_timeouts_with_the_same_request() {
+    let repo = TestRepo::server();
+    // The health check goes through, then the commit request times out twice
+    let output = repo.gyst("ok,timeout:2,ok", &["commit", "-q"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(repo.last_subject().as_deref(), Some(CANNED_SUBJECT));
+}
+
+#[test]
+fn server_that_never_answers_does_not_commit() {
+    let repo = TestRepo::server();
+    let output = repo.gyst("timeout:*", &["commit", "-q"]);
+
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("Failed to connect to server"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(repo.last_subject(), None);
+}
+
+#[test]
+fn server_errors_include_the_request_id() {
+    let repo = TestRepo::server();
+    let output = repo.gyst("ok,malformed", &["suggest", "--pipe"]);
+
+    assert!(!output.status.success());
+    let error = stderr(&output);
+    assert!(
+        error.contains("Failed to parse server response"),
+        "{}",
+        error
+    );
+    assert!(error.contains("request id:"), "{}", error);
+}
+
+#[test]
+fn unknown_fault_is_rejected() {
+    let repo = TestRepo::direct();
+    let output = repo.gyst("slow", &["commit", "-q"]);
+
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Unknown GYST_CHAOS fault 'slow'"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(repo.last_subject(), None);
+}
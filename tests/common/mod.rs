@@ -0,0 +1,90 @@
+//! Helpers for running the gyst binary against a throwaway repository and home
+//! directory
+
+// Each test file uses only some of the helpers
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// A git repository with one staged file, and a home directory holding the
+/// gyst configuration
+pub struct TestRepo {
+    repo: TempDir,
+    home: TempDir,
+}
+
+impl TestRepo {
+    /// A repository on a `work` branch whose global gyst configuration is `config`
+    pub fn new(config: &str) -> Self {
+        let test = Self {
+            repo: TempDir::new().expect("create repository directory"),
+            home: TempDir::new().expect("create home directory"),
+        };
+        let gyst_dir = test.home.path().join(".gyst");
+        fs::create_dir_all(&gyst_dir).expect("create config directory");
+        fs::write(gyst_dir.join("config.toml"), config).expect("write config");
+
+        test.git(&["init", "-q", "-b", "work"]);
+        test.git(&["config", "user.name", "Test"]);
+        test.git(&["config", "user.email", "test@example.com"]);
+        fs::write(test.path().join("notes.txt"), "hello\n").expect("write file");
+        test.git(&["add", "notes.txt"]);
+        test
+    }
+
+    /// Uses the Anthropic API directly
+    pub fn direct() -> Self {
+        Self::new(
+            "[ai]\nprovider = \"anthropic\"\napi_key = \"test-key\"\n\n[server]\nuse_server = false\n",
+        )
+    }
+
+    /// Uses the gyst server
+    pub fn server() -> Self {
+        Self::new("[ai]\nprovider = \"anthropic\"\napi_key = \"\"\n\n[server]\nuse_server = true\n")
+    }
+
+    pub fn path(&self) -> &Path {
+        self.repo.path()
+    }
+
+    /// Run gyst in the repository with `GYST_CHAOS` set to `chaos`
+    pub fn gyst(&self, chaos: &str, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_gyst"))
+            .args(args)
+            .current_dir(self.path())
+            .env("HOME", self.home.path())
+            .env("GYST_CHAOS", chaos)
+            .env("NO_COLOR", "1")
+            .env_remove("ANTHROPIC_API_KEY")
+            .output()
+            .expect("run gyst")
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(self.path())
+            .env("HOME", self.home.path())
+            .output()
+            .expect("run git");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// The subject of the latest commit, or `None` before the first commit
+    pub fn last_subject(&self) -> Option<String> {
+        let subject = self.git(&["log", "-1", "--format=%s"]);
+        (!subject.is_empty()).then_some(subject)
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
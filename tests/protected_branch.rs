@@ -0,0 +1,33 @@
+//! Commits to a protected branch ask what to do, except in automation
+
+mod common;
+
+use common::{TestRepo, stderr};
+
+/// Direct API mode, with the test repository's `work` branch protected
+const CONFIG_PROTECTING_WORK: &str = "[ai]\nprovider = \"anthropic\"\napi_key = \"test-key\"\n\n[server]\nuse_server = false\n\n[git]\nprotected_branches = [\"work\"]\n";
+
+#[test]
+fn yes_refuses_a_protected_branch_without_force() {
+    let repo = TestRepo::new(CONFIG_PROTECTING_WORK);
+
+    let output = repo.gyst("ok", &["--yes", "commit"]);
+
+    assert_eq!(output.status.code(), Some(6), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("protected branch"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(repo.last_subject(), None);
+}
+
+#[test]
+fn yes_and_force_commit_to_a_protected_branch() {
+    let repo = TestRepo::new(CONFIG_PROTECTING_WORK);
+
+    let output = repo.gyst("ok", &["--yes", "commit", "--force"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(repo.last_subject().is_some());
+}
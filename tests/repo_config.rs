@@ -0,0 +1,76 @@
+//! A repository's `.gyst.toml` can change the project's conventions, but not
+//! where gyst sends requests, and with them your tokens
+
+mod common;
+
+use common::{TestRepo, stderr, stdout};
+use std::fs;
+use std::net::TcpListener;
+
+/// Direct API mode, with a Jira token but no Jira site of your own
+const CONFIG_WITH_JIRA_TOKEN: &str = "[ai]\nprovider = \"anthropic\"\napi_key = \"test-key\"\n\n[server]\nuse_server = false\n\n[jira]\ntoken = \"secret-token\"\nproject = \"PROJ\"\n";
+
+fn write_repo_config(repo: &TestRepo, contents: &str) {
+    fs::write(repo.path().join(".gyst.toml"), contents).expect("write .gyst.toml");
+}
+
+fn setting(repo: &TestRepo, key: &str) -> String {
+    let output = repo.gyst("ok", &["config", "get", key]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).trim().to_string()
+}
+
+#[test]
+fn repo_config_cannot_send_the_jira_token_elsewhere() {
+    // A request for the ticket in the branch name would connect here
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind listener");
+    listener
+        .set_nonblocking(true)
+        .expect("make listener non-blocking");
+    let repo = TestRepo::new(CONFIG_WITH_JIRA_TOKEN);
+    let url = format!(
+        "http://{}",
+        listener.local_addr().expect("listener address")
+    );
+    write_repo_config(
+        &repo,
+        &format!(
+            "[jira]\nurl = \"{}\"\n\n[forge]\ngithub_url = \"{}\"\ngitlab_url = \"{}\"\n",
+            url, url, url
+        ),
+    );
+    repo.git(&["checkout", "-q", "-b", "PROJ-1-login-form"]);
+
+    let output = repo.gyst("ok", &["commit", "-q"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        listener.accept().is_err(),
+        "gyst connected to the URL from .gyst.toml"
+    );
+    assert_eq!(setting(&repo, "jira.url"), "");
+    assert_eq!(setting(&repo, "forge.github_url"), "");
+    assert_eq!(setting(&repo, "forge.gitlab_url"), "");
+}
+
+#[test]
+fn repo_config_changes_conventions() {
+    let repo = TestRepo::direct();
+    write_repo_config(
+        &repo,
+        "[commit]\ntemplate = \"plain\"\n\n[git]\nprotected_branches = [\"release\"]\n",
+    );
+
+    assert_eq!(setting(&repo, "commit.template"), "plain");
+    assert_eq!(setting(&repo, "git.protected_branches"), "release");
+}
+
+#[test]
+fn repo_config_cannot_turn_ai_back_on() {
+    let repo = TestRepo::new(
+        "[ai]\nprovider = \"anthropic\"\napi_key = \"test-key\"\nenabled = false\n\n[server]\nuse_server = false\n",
+    );
+    write_repo_config(&repo, "[ai]\nenabled = true\n");
+
+    assert_eq!(setting(&repo, "ai.enabled"), "false");
+}
@@ -0,0 +1,57 @@
+//! Team settings from `config.remote_url` can share the project's conventions,
+//! but not where gyst sends requests, and with them your tokens
+
+mod common;
+
+use common::{TestRepo, stderr, stdout};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Serve `body` to the first request, as the team's settings file
+fn serve_once(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind listener");
+    let url = format!(
+        "http://{}/gyst.toml",
+        listener.local_addr().expect("listener address")
+    );
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("accept request");
+        let mut request = [0; 4096];
+        let _ = stream.read(&mut request);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream
+            .write_all(response.as_bytes())
+            .expect("write response");
+    });
+    url
+}
+
+fn setting(repo: &TestRepo, key: &str) -> String {
+    let output = repo.gyst("ok", &["config", "get", key]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).trim().to_string()
+}
+
+#[test]
+fn team_settings_cannot_redirect_tokens() {
+    let url = serve_once(
+        "[commit]\ntemplate = \"plain\"\n\n[jira]\nurl = \"http://127.0.0.1:9\"\n\n[forge]\ngithub_url = \"https://github.example.com\"\ngitlab_url = \"https://gitlab.example.com\"\nlink_issues = true\n\n[ai]\nprovider = \"openai\"\n"
+            .to_string(),
+    );
+    let repo = TestRepo::new(&format!(
+        "[ai]\nprovider = \"anthropic\"\napi_key = \"test-key\"\n\n[server]\nuse_server = false\n\n[jira]\ntoken = \"secret-token\"\n\n[config]\nremote_url = \"{}\"\n",
+        url
+    ));
+
+    assert_eq!(setting(&repo, "commit.template"), "plain");
+    assert_eq!(setting(&repo, "jira.url"), "");
+    assert_eq!(setting(&repo, "forge.github_url"), "");
+    assert_eq!(setting(&repo, "forge.gitlab_url"), "");
+    assert_eq!(setting(&repo, "forge.link_issues"), "false");
+    assert_eq!(setting(&repo, "ai.provider"), "anthropic");
+}